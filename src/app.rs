@@ -1,384 +1,3292 @@
-use sysinfo::System;
-use tokio::sync::mpsc;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use sysinfo::{Disks, Pid, ProcessesToUpdate, System};
+use tokio::sync::{mpsc, watch};
+use tokio_util::sync::CancellationToken;
 
-use crate::config::Config;
-use crate::events::{AppEvent, DownloadPhase, FormatPopupState, Job, JobStatus, SettingsState, StatusCounts, WorkerCommand};
+use crate::bookmarks;
+use crate::channels::ChannelMemory;
+use crate::config::{self, Config};
+use crate::paths;
+use crate::clipboard;
+use crate::events::{short_id, AppEvent, CompletedAction, CompletedMenuState, ConflictPromptState, OverwritePolicy, Credentials, JobId, LoginField, RemediationState, RemediationStep, COOKIE_BROWSERS, DownloadPhase, DownloadRecord, DownloadRequest, FormatPopupState, PartialProgress, PresetTally, GroupAction, GroupId, GroupMenuState, ImportPickerState, Job, DefaultFormatState, LargeDownloadState, DependentsPromptState, SectionPromptState, OutputPromptState, ClashAction, ClashPromptState, PlaylistRangeState, PlaylistChoiceState, PlaylistAmbiguity, QueueFilterState, YtdlpUpdateState, playlist_items, SessionPromptState, SessionLoadState, name_clashes, suffixed_template, JobGroup, JobStatus, Notice, NoticeLevel, SettingsField, SettingsState, StatusCounts, TriageState, WorkerCommand, WorkerEvent, HaltAction, HaltPromptState, RemoveFilter, RemoveMenuState, TrashedJob, TrashViewState, SpeedStats, ChannelFormats, ChannelListState, DownloadError, Failure, Conversion};
+use crate::format;
+use crate::fuzzy;
+use crate::diagnose::{self, DiagnosisScreen};
+use crate::health::{self, HealthScreen};
+use crate::history::{self, HistoryEntry};
+use crate::metrics::{Gauges, Metrics};
+use crate::notify::{self, CompletionBatch};
+use crate::persist::{Autosave, ConfigMessage, QueueSnapshot};
+use crate::preview::LinkPreview;
+use crate::power::{PowerPolicy, PowerState};
+use crate::ratecap::RateCapMonitor;
+use crate::session::{LoadChoice, Session};
+use crate::status::StatusReport;
+use crate::ui::hints::AppContext;
+use crate::worker;
+
+// Removed jobs kept for restoring; the oldest go first
+const TRASH_CAPACITY: usize = 200;
+
+// Counts frames drawn in the trailing second for the debug overlay
+#[derive(Debug, Default)]
+pub struct FrameCounter {
+    frames: VecDeque<Instant>,
+}
+
+impl FrameCounter {
+    pub fn record(&mut self, now: Instant) {
+        self.frames.push_back(now);
+        while self.frames.front().is_some_and(|t| now.duration_since(*t) > Duration::from_secs(1)) {
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn fps(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueueRow {
+    Group(GroupId),
+    Job(usize),
+}
+
+// What the cursor is on, by id, so background inserts and removals can't
+// slide a different job under it
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RowKey {
+    Group(GroupId),
+    Job(JobId),
+}
+
+// The two lists of the split queue layout: everything still to do, and the
+// completed, failed and cancelled items
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueuePane {
+    Active,
+    Finished,
+}
+
+impl QueuePane {
+    pub fn other(self) -> Self {
+        match self {
+            QueuePane::Active => QueuePane::Finished,
+            QueuePane::Finished => QueuePane::Active,
+        }
+    }
+}
+
+// The cursor of the pane that isn't focused, picked up again when it is
+#[derive(Debug, Clone, Copy, Default)]
+struct PaneCursor {
+    index: usize,
+    key: Option<RowKey>,
+    offset: usize,
+}
 
 pub struct App {
     pub jobs: Vec<Job>,
+    pub groups: Vec<JobGroup>,
+    // Row of `selected` as of the last event; kept in step by sync_selection
     pub selected_index: usize,
+    selected: Option<RowKey>,
+    // First queue row on screen, and how many rows fit as of the last draw
+    pub queue_offset: usize,
+    pub queue_height: Cell<usize>,
+    // Split queue layout; selection, offset and every action follow `pane`
+    pub split_queue: bool,
+    pub pane: QueuePane,
+    parked: PaneCursor,
     pub input_buffer: String,
+    pub input_sanitized: bool,
     pub input_mode: bool,
+    pub preview: LinkPreview,
     pub show_help: bool,
     pub show_sysinfo: bool,
+    pub show_fps: bool,
+    pub verbose_rows: bool,
+    // The details list each warning instead of just counting them
+    pub show_warnings: bool,
+    // The details panel shows the selected job's timeline instead
+    pub show_timeline: bool,
+    pub frame_counter: FrameCounter,
     pub should_quit: bool,
+    pub should_suspend: bool,
     pub confirm_quit: bool,
-    pub loading_playlists: usize,
     pub spinner_frame: usize,
     pub format_popup: Option<FormatPopupState>,
     pub settings_popup: Option<SettingsState>,
+    pub group_menu: Option<GroupMenuState>,
+    pub triage: Option<TriageState>,
+    pub import_prompt: Option<String>,
+    // The job whose note is being edited, and the text so far
+    pub note_prompt: Option<(JobId, String)>,
+    pub section_prompt: Option<SectionPromptState>,
+    pub output_prompt: Option<OutputPromptState>,
+    pub playlist_range: Option<PlaylistRangeState>,
+    pub playlist_choice: Option<PlaylistChoiceState>,
+    pub queue_filter: Option<QueueFilterState>,
+    pub session_prompt: Option<SessionPromptState>,
+    pub session_load: Option<SessionLoadState>,
+    // A pending playlist entry Enter was pressed on, to open the format popup for
+    pub awaiting_formats: Option<JobId>,
+    // The last audio quality confirmed in the format popup, offered first next time
+    pub audio_quality: Option<String>,
+    // Unfinished jobs that would write the same file as an earlier job, to that job
+    pub name_clashes: HashMap<JobId, JobId>,
+    // How many handle_event calls are on the stack; handlers that raise another
+    // event leave the timeline and the clash check to the outermost one
+    event_depth: usize,
+    names_may_change: bool,
+    pub clash_prompt: Option<ClashPromptState>,
+    pub import_picker: Option<ImportPickerState>,
+    pub remediation: Option<RemediationState>,
+    pub completed_menu: Option<CompletedMenuState>,
+    pub conflict_prompt: Option<ConflictPromptState>,
+    pub halt_prompt: Option<HaltPromptState>,
+    pub dependents_prompt: Option<DependentsPromptState>,
+    // Picked with `>`; the next `>` makes the selected job wait on it
+    pub dependency_mark: Option<JobId>,
+    pub remove_menu: Option<RemoveMenuState>,
+    pub health: Option<HealthScreen>,
+    pub diagnosis: Option<DiagnosisScreen>,
+    pub ytdlp_update: Option<YtdlpUpdateState>,
+    pub channels: ChannelMemory,
+    pub channel_list: Option<ChannelListState>,
+    // Why the config file couldn't be loaded at startup, for the health check
+    pub config_error: Option<String>,
+    // Removed jobs, oldest first; `u` restores the last removal from here
+    pub trash: Vec<TrashedJob>,
+    next_batch: u64,
+    pub trash_view: Option<TrashViewState>,
+    // The error that made the worker halt the queue, while it is halted
+    pub queue_halted: Option<String>,
+    // Why new downloads are held back by the power settings, while they are
+    pub power_paused: Option<String>,
+    // Resumed by hand while the condition still held; not paused again until it clears
+    power_overridden: bool,
+    // Running downloads cancelled by the pause, resumed with it
+    power_stopped: Vec<JobId>,
+    // An "always" answer to the overwrite prompt, for the rest of the session
+    pub conflict_always: Option<OverwritePolicy>,
+    pub default_format_prompt: Option<DefaultFormatState>,
+    pub large_downloads: Option<LargeDownloadState>,
+    pub rate_cap: RateCapMonitor,
+    pub last_completed: Option<String>,
+    pub metrics: Metrics,
+    pub completions: CompletionBatch,
+    // Direction of the next upload date sort; each sort flips it
+    pub sort_newest_first: bool,
+    pub preset_tally: PresetTally,
+    pub command_popup: Option<String>,
+    pub notice: Option<Notice>,
+    pub throttled_limit: Option<(usize, usize)>,
+    // Links turned away since the queue last had room, for the notice
+    dropped_adds: usize,
+    pub autosave: Autosave,
     pub config: Config,
+    pub ytdlp_version: String,
+    // As of the last health check; video-only formats can't be merged without it
+    pub ffmpeg_missing: bool,
     pub sysinfo: System,
     worker_tx: mpsc::Sender<WorkerCommand>,
+    // For results of work the app starts itself
+    event_tx: Option<mpsc::Sender<AppEvent>>,
+    // Every config change goes through persist::run_config_writer, which
+    // publishes the settings in effect on config_rx
+    config_tx: Option<mpsc::UnboundedSender<ConfigMessage>>,
+    config_rx: Option<watch::Receiver<Arc<Config>>>,
 }
 
 impl App {
     pub fn new(config: Config, worker_tx: mpsc::Sender<WorkerCommand>) -> Self {
         Self {
             jobs: Vec::new(),
+            groups: Vec::new(),
             selected_index: 0,
+            selected: None,
+            queue_offset: 0,
+            queue_height: Cell::new(0),
+            split_queue: config.split_queue,
+            pane: QueuePane::Active,
+            parked: PaneCursor::default(),
             input_buffer: String::new(),
+            input_sanitized: false,
             input_mode: true,
+            preview: LinkPreview::default(),
             show_help: false,
             show_sysinfo: true,
+            show_fps: false,
+            verbose_rows: false,
+            show_warnings: false,
+            show_timeline: false,
+            frame_counter: FrameCounter::default(),
             should_quit: false,
+            should_suspend: false,
             confirm_quit: false,
-            loading_playlists: 0,
             spinner_frame: 0,
             format_popup: None,
             settings_popup: None,
+            group_menu: None,
+            triage: None,
+            import_prompt: None,
+            note_prompt: None,
+            section_prompt: None,
+            output_prompt: None,
+            playlist_range: None,
+            playlist_choice: None,
+            queue_filter: None,
+            session_prompt: None,
+            session_load: None,
+            awaiting_formats: None,
+            audio_quality: None,
+            name_clashes: HashMap::new(),
+            event_depth: 0,
+            names_may_change: false,
+            clash_prompt: None,
+            import_picker: None,
+            remediation: None,
+            completed_menu: None,
+            conflict_prompt: None,
+            halt_prompt: None,
+            dependents_prompt: None,
+            dependency_mark: None,
+            remove_menu: None,
+            health: None,
+            diagnosis: None,
+            ytdlp_update: None,
+            channels: ChannelMemory::default(),
+            channel_list: None,
+            config_error: None,
+            trash: Vec::new(),
+            next_batch: 0,
+            trash_view: None,
+            queue_halted: None,
+            power_paused: None,
+            power_overridden: false,
+            power_stopped: Vec::new(),
+            conflict_always: None,
+            default_format_prompt: None,
+            large_downloads: None,
+            rate_cap: RateCapMonitor::default(),
+            last_completed: None,
+            metrics: Metrics::default(),
+            completions: CompletionBatch::default(),
+            sort_newest_first: false,
+            preset_tally: PresetTally::default(),
+            command_popup: None,
+            notice: None,
+            throttled_limit: None,
+            dropped_adds: 0,
+            autosave: Autosave::new(Duration::from_secs(config.autosave_interval_secs)),
             config,
+            ytdlp_version: String::new(),
+            ffmpeg_missing: false,
             sysinfo: System::new(),
             worker_tx,
+            event_tx: None,
+            config_tx: None,
+            config_rx: None,
+        }
+    }
+
+    pub fn snapshot(&self) -> QueueSnapshot {
+        QueueSnapshot {
+            jobs: self.jobs.clone(),
+            groups: self.groups.clone(),
+            trash: if self.config.persist_trash { self.trash.clone() } else { Vec::new() },
+        }
+    }
+
+    // Returns the fetches the restored jobs need. A big queue has more of them than the
+    // worker channel holds, so they go out through send_restored rather than try_send
+    pub fn restore(&mut self, snapshot: QueueSnapshot) -> Vec<WorkerCommand> {
+        if !snapshot.jobs.is_empty() {
+            self.set_notice(format!("Restored {} jobs from last session", snapshot.jobs.len()), NoticeLevel::Info);
+        }
+        self.groups.extend(snapshot.groups);
+        // Trashed jobs come back through restore_trashed, which starts any fetch they need
+        self.trash.extend(snapshot.trash.into_iter().map(|t| TrashedJob { job: t.job.restored(), ..t }));
+        self.next_batch = self.trash.iter().map(|t| t.batch + 1).max().unwrap_or(self.next_batch);
+        let mut commands = Vec::new();
+        let mut hydrate = Vec::new();
+        for job in snapshot.jobs {
+            let job = job.restored();
+            let command = if matches!(job.status, JobStatus::ExpandingPlaylist { .. }) {
+                Some(job.fetch_playlist_command())
+            } else if job.status == JobStatus::FetchingFormats {
+                Some(WorkerCommand::FetchFormats { job_id: job.id, url: job.url.clone(), credentials: None })
+            } else if job.status == JobStatus::MetadataPending {
+                hydrate.push((job.id, job.url.clone()));
+                None
+            } else if job.title.is_none() {
+                Some(WorkerCommand::ResolveTitle { job_id: job.id, url: job.url.clone() })
+            } else {
+                None
+            };
+            commands.extend(command);
+            self.jobs.push(job);
+        }
+        if !hydrate.is_empty() {
+            commands.push(WorkerCommand::HydrateFormats(hydrate));
+        }
+        self.refresh_name_clashes();
+        commands
+    }
+
+    // Waits for room in the worker channel for each command, off the UI thread
+    pub fn send_restored(&self, commands: Vec<WorkerCommand>) {
+        if commands.is_empty() {
+            return;
         }
+        let worker_tx = self.worker_tx.clone();
+        tokio::spawn(async move {
+            for command in commands {
+                if worker_tx.send(command).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    // Pauses or resumes the queue as the power policy says; called every half minute
+    pub fn check_power(&mut self, state: PowerState) {
+        match PowerPolicy::of(&self.config).pause_reason(&state) {
+            Some(reason) if self.power_overridden => {
+                tracing::debug!("Power pause overridden ({})", reason);
+            }
+            Some(reason) => {
+                if self.power_paused.is_none() {
+                    tracing::info!("Pausing the queue: {}", reason);
+                    if self.worker_tx.try_send(WorkerCommand::SetPaused(true)).is_err() {
+                        tracing::warn!("Worker channel full: SetPaused dropped");
+                    }
+                    if self.config.power_pause_active {
+                        let running: Vec<JobId> = self.jobs.iter().filter(|j| matches!(j.status, JobStatus::Downloading { .. })).map(|j| j.id).collect();
+                        for &id in &running {
+                            self.handle_event(AppEvent::CancelJob(id));
+                        }
+                        self.power_stopped = running;
+                    }
+                    self.set_notice(format!("Queue paused: {}", reason), NoticeLevel::Info);
+                }
+                self.power_paused = Some(reason);
+            }
+            None => {
+                self.power_overridden = false;
+                if self.power_paused.is_some() && self.config.power_auto_resume {
+                    self.resume_power_pause();
+                }
+            }
+        }
+    }
+
+    fn resume_power_pause(&mut self) {
+        self.power_paused = None;
+        if self.worker_tx.try_send(WorkerCommand::SetPaused(false)).is_err() {
+            tracing::warn!("Worker channel full: SetPaused dropped");
+        }
+        for id in std::mem::take(&mut self.power_stopped) {
+            self.handle_event(AppEvent::RestartCancelled { id, resume: true });
+        }
+        self.set_notice("Queue resumed", NoticeLevel::Info);
+    }
+
+    pub fn set_notice(&mut self, text: impl Into<String>, level: NoticeLevel) {
+        self.notice = Some(Notice::new(text, level));
+    }
+
+    pub fn connect(&mut self, event_tx: mpsc::Sender<AppEvent>, config_tx: mpsc::UnboundedSender<ConfigMessage>, config_rx: watch::Receiver<Arc<Config>>) {
+        self.preview.connect(event_tx.clone());
+        self.event_tx = Some(event_tx);
+        self.config_tx = Some(config_tx);
+        self.config_rx = Some(config_rx);
+    }
+
+    // Opens the checklist and runs every check again
+    pub fn run_health_check(&mut self, dismiss_if_ok: bool) {
+        self.health = Some(HealthScreen { report: None, dismiss_if_ok });
+        let Some(event_tx) = self.event_tx.clone() else {
+            return;
+        };
+        let (config, config_error) = (self.config.clone(), self.config_error.clone());
+        tokio::spawn(async move {
+            let report = health::run(config, config_error).await;
+            let _ = event_tx.send(AppEvent::HealthChecked(report)).await;
+        });
+    }
+
+    // Called before each wait, so the preview follows every edit to the input
+    pub fn update_preview(&mut self, now: Instant) {
+        let input = if self.config.url_preview && self.input_mode { self.input_buffer.as_str() } else { "" };
+        self.preview.observe(input, now);
     }
 
     pub fn handle_event(&mut self, event: AppEvent) {
+        self.names_may_change |= marks_queue_dirty(&event) || matches!(event, AppEvent::SaveSettings | AppEvent::ConfigChanged | AppEvent::LoadSession(_));
+        let outermost = self.event_depth == 0;
+        let stages = if outermost { self.stages_before(&event) } else { None };
+        self.event_depth += 1;
+        self.dispatch_event(event);
+        self.event_depth -= 1;
+        if outermost {
+            if let Some((stages, whole_queue)) = stages {
+                self.record_timeline(&stages, whole_queue);
+                self.settle_dependents(&stages);
+            }
+            if std::mem::take(&mut self.names_may_change) {
+                self.warn_new_clashes();
+            }
+        }
+        self.sync_selection();
+    }
+
+    // Each job's stage before the event, and whether that is the whole queue. A
+    // progress tick can only move its own job along, and moving about the
+    // screen moves no job at all
+    fn stages_before(&self, event: &AppEvent) -> Option<(HashMap<JobId, &'static str>, bool)> {
         match event {
-            AppEvent::AddUrl(url) => {
-                if !url.trim().is_empty() {
-                    let url = url.trim();
-                    if is_playlist_url(url) {
-                        self.loading_playlists += 1;
-                        if self.worker_tx.try_send(WorkerCommand::FetchPlaylist { url: url.to_string() }).is_err() {
-                            tracing::warn!("Worker channel full: FetchPlaylist dropped");
-                        }
-                    } else {
-                        let job = Job::new(url);
-                        let job_id = job.id;
-                        let job_url = job.url.clone();
-                        self.jobs.push(job);
-                        if self.worker_tx.try_send(WorkerCommand::FetchFormats { job_id, url: job_url }).is_err() {
-                            tracing::warn!("Worker channel full: FetchFormats dropped");
-                        }
+            AppEvent::Worker(WorkerEvent::JobProgress { id, .. }) => {
+                let job = self.jobs.iter().find(|j| j.id == *id)?;
+                Some((HashMap::from([(job.id, job.status.stage())]), false))
+            }
+            AppEvent::SelectNext
+            | AppEvent::SelectPrev
+            | AppEvent::FormatSelectNext
+            | AppEvent::FormatSelectPrev
+            | AppEvent::FormatPageDown
+            | AppEvent::FormatPageUp
+            | AppEvent::FormatSelectFirst
+            | AppEvent::FormatSelectLast
+            | AppEvent::FormatExpandTier
+            | AppEvent::FormatCollapseTier
+            | AppEvent::SettingsNext
+            | AppEvent::SettingsPrev
+            | AppEvent::ToggleHelp
+            | AppEvent::ToggleSysInfo
+            | AppEvent::ToggleFpsOverlay
+            | AppEvent::ToggleVerboseRows
+            | AppEvent::ToggleWarnings
+            | AppEvent::ToggleTimeline => None,
+            _ => Some((self.jobs.iter().map(|j| (j.id, j.status.stage())).collect(), true)),
+        }
+    }
+
+    // Keyed off the same check as the [V] tag, so what the popup marks for
+    // merging is what gets warned about
+    fn warn_unmergeable(&mut self) {
+        if !self.ffmpeg_missing {
+            return;
+        }
+        let count = self
+            .jobs
+            .iter()
+            .filter(|j| !j.is_finished() && !j.extract_audio && j.selected_format.as_ref().is_some_and(|f| f.needs_merge()))
+            .count();
+        if count > 0 {
+            let text = format!("⚠ ffmpeg not found: {} video-only {} can't be merged with audio", count, if count == 1 { "format" } else { "formats" });
+            self.set_notice(text, NoticeLevel::Error);
+        }
+    }
+
+    fn refresh_name_clashes(&mut self) {
+        self.name_clashes = name_clashes(&self.jobs, &self.config);
+    }
+
+    // Says so once when a job starts clashing, not on every later event
+    fn warn_new_clashes(&mut self) {
+        let before = std::mem::take(&mut self.name_clashes);
+        self.refresh_name_clashes();
+        let new = self.name_clashes.iter().find(|(id, _)| !before.contains_key(*id)).map(|(&id, &earlier)| (id, earlier));
+        if let Some((id, earlier)) = new {
+            let text = format!(
+                "⚠ Item {} would be saved under the same name as item {} (n to resolve)",
+                self.job_number(id),
+                self.job_number(earlier)
+            );
+            self.set_notice(text, NoticeLevel::Info);
+        }
+    }
+
+    // 1-based position in the queue, as the rows are numbered
+    fn job_number(&self, id: JobId) -> usize {
+        self.jobs.iter().position(|j| j.id == id).map_or(0, |i| i + 1)
+    }
+
+    // Whichever handler finished, failed, cancelled or removed a job, the jobs
+    // waiting on it find out here
+    fn settle_dependents(&mut self, before: &HashMap<JobId, &'static str>) {
+        let prerequisites: HashSet<JobId> = self.jobs.iter().filter_map(|j| j.after).collect();
+        for id in prerequisites {
+            let Some(&old) = before.get(&id) else {
+                continue;
+            };
+            let waiting: Vec<JobId> = self
+                .jobs
+                .iter()
+                .filter(|j| j.after == Some(id) && matches!(j.status, JobStatus::Queued | JobStatus::Starting | JobStatus::WaitingForSlot { .. }))
+                .map(|j| j.id)
+                .collect();
+            if waiting.is_empty() {
+                continue;
+            }
+            let stage = self.jobs.iter().find(|j| j.id == id).map(|j| j.status.stage());
+            match stage {
+                Some(stage) if stage == old => {}
+                // Removed from the queue: nothing left to wait for
+                None => {
+                    for job in self.jobs.iter_mut().filter(|j| j.after == Some(id)) {
+                        job.after = None;
                     }
+                    self.release_dependents(id);
+                }
+                Some("completed") => self.release_dependents(id),
+                Some(outcome @ ("failed" | "cancelled")) if self.dependents_prompt.is_none() => {
+                    self.dependents_prompt = Some(DependentsPromptState {
+                        prerequisite: id,
+                        dependents: waiting,
+                        outcome: if outcome == "failed" { "failed" } else { "was cancelled" },
+                    });
                 }
+                Some(_) => {}
             }
+        }
+    }
 
-            AppEvent::ToggleInputMode => {
-                self.input_mode = !self.input_mode;
+    fn release_dependents(&mut self, id: JobId) {
+        if self.worker_tx.try_send(WorkerCommand::ReleaseDependents(id)).is_err() {
+            tracing::warn!("Worker channel full: ReleaseDependents dropped");
+        }
+    }
+
+    // Whether `id` waits on `on`, directly or further down the chain
+    fn waits_on(&self, id: JobId, on: JobId) -> bool {
+        let mut next = Some(id);
+        for _ in 0..=self.jobs.len() {
+            match next.and_then(|id| self.jobs.iter().find(|j| j.id == id)?.after) {
+                Some(after) if after == on => return true,
+                Some(after) => next = Some(after),
+                None => return false,
             }
+        }
+        false
+    }
 
-            AppEvent::SelectNext => {
-                if !self.jobs.is_empty() {
-                    self.selected_index = (self.selected_index + 1) % self.jobs.len();
+    // Compares every job with its stage before the event, so each status change
+    // lands on the timeline whichever handler made it
+    fn record_timeline(&mut self, before: &HashMap<JobId, &'static str>, whole_queue: bool) {
+        for job in &mut self.jobs {
+            let stage = job.status.stage();
+            let event = match before.get(&job.id) {
+                None if !whole_queue => continue,
+                None => {
+                    self.metrics.jobs_added += 1;
+                    job.log_event("added");
+                    stage.to_string()
+                }
+                Some(&old) if old == stage => continue,
+                Some(&old) if old == "failed" || old == "cancelled" => {
+                    self.metrics.retries += 1;
+                    format!("retried: {}", stage)
                 }
+                Some(_) => stage.to_string(),
+            };
+            match stage {
+                "completed" => {
+                    self.metrics.jobs_completed += 1;
+                    self.metrics.downloaded_bytes += job.output_size.unwrap_or(0);
+                }
+                "failed" => self.metrics.jobs_failed += 1,
+                _ => {}
+            }
+            match &job.status {
+                JobStatus::Failed(error) => {
+                    let reason = error.message.lines().next().unwrap_or_default().to_string();
+                    job.log_event(format!("{} ({}): {}", event, error.kind.label(), reason));
+                }
+                _ => job.log_event(event),
             }
+        }
+    }
 
-            AppEvent::SelectPrev => {
-                if !self.jobs.is_empty() {
-                    self.selected_index = if self.selected_index == 0 {
-                        self.jobs.len() - 1
+    fn dispatch_event(&mut self, event: AppEvent) {
+        let _span = event.job_id().map(|id| {
+            let title = self.jobs.iter().find(|j| j.id == id).and_then(|j| j.title.as_deref());
+            tracing::debug_span!("job", id = %short_id(id), title = title.unwrap_or_default()).entered()
+        });
+
+        if marks_queue_dirty(&event) {
+            self.autosave.mark_dirty();
+        }
+
+        match event {
+            AppEvent::PreviewFetched { id, preview } => {
+                self.preview.finish(id, preview);
+            }
+
+            AppEvent::AddUrl(url) => {
+                if !url.trim().is_empty() && self.admit_link() {
+                    let url = url.trim();
+                    if !is_ambiguous_playlist_url(url) {
+                        if is_playlist_url(url) {
+                            self.ask_playlist_range(url);
+                        } else {
+                            self.add_video(Job::new(url));
+                        }
                     } else {
-                        self.selected_index - 1
-                    };
+                        match self.config.playlist_ambiguity {
+                            PlaylistAmbiguity::Ask => self.playlist_choice.get_or_insert_with(Default::default).urls.push_back(url.to_string()),
+                            PlaylistAmbiguity::Single => self.add_video(single_video_job(url)),
+                            PlaylistAmbiguity::Playlist => self.ask_playlist_range(url),
+                        }
+                    }
                 }
             }
 
-            AppEvent::OpenFormatPopup => {
-                if let Some(job) = self.jobs.get(self.selected_index) {
-                    if job.can_select_format() {
-                        self.format_popup = Some(FormatPopupState::new(
-                            self.selected_index,
-                            job.formats.clone(),
-                        ));
-                    }
+            AppEvent::SaveSession(path) => {
+                self.session_prompt = None;
+                let session = Session::new(self.snapshot(), &self.config);
+                match session.save(&path) {
+                    Ok(()) => self.set_notice(format!("Saved {} items to {}", session.jobs.len(), path.display()), NoticeLevel::Info),
+                    Err(e) => self.set_notice(format!("Could not save {}: {}", path.display(), e), NoticeLevel::Error),
                 }
             }
 
-            AppEvent::CloseFormatPopup => {
-                self.format_popup = None;
+            AppEvent::OpenSession(path) => {
+                self.session_prompt = None;
+                match Session::load(&path) {
+                    Ok(session) if self.jobs.is_empty() => self.load_session(&path, session, LoadChoice::Merge),
+                    Ok(session) => self.session_load = Some(SessionLoadState { path, session, selected: 0 }),
+                    Err(e) => self.set_notice(format!("Could not open session: {}", e), NoticeLevel::Error),
+                }
             }
 
-            AppEvent::FormatSelectNext => {
-                if let Some(popup) = &mut self.format_popup {
-                    let filtered_len = popup.filtered_formats().len();
-                    if filtered_len > 0 {
-                        popup.selected = (popup.selected + 1) % filtered_len;
-                        let visible_height = 10; 
-                        if popup.selected >= popup.scroll_offset + visible_height {
-                            popup.scroll_offset = popup.selected.saturating_sub(visible_height - 1);
-                        } else if popup.selected < popup.scroll_offset {
-                            popup.scroll_offset = popup.selected;
-                        }
-                    }
+            AppEvent::SessionLoadNext => {
+                if let Some(prompt) = &mut self.session_load {
+                    prompt.selected = (prompt.selected + 1) % LoadChoice::ALL.len();
                 }
             }
 
-            AppEvent::FormatSelectPrev => {
-                if let Some(popup) = &mut self.format_popup {
-                    let filtered_len = popup.filtered_formats().len();
-                    if filtered_len > 0 {
-                        popup.selected = if popup.selected == 0 {
-                            filtered_len - 1
-                        } else {
-                            popup.selected - 1
-                        };
-                        if popup.selected < popup.scroll_offset {
-                            popup.scroll_offset = popup.selected;
-                        }
-                    }
+            AppEvent::SessionLoadPrev => {
+                if let Some(prompt) = &mut self.session_load {
+                    prompt.selected = prompt.selected.checked_sub(1).unwrap_or(LoadChoice::ALL.len() - 1);
                 }
             }
 
-            AppEvent::ToggleAudioOnly => {
-                if let Some(popup) = &mut self.format_popup {
-                    popup.audio_only = !popup.audio_only;
-                    popup.selected = 0;
-                    popup.scroll_offset = 0;
+            AppEvent::ConfirmSessionLoad => {
+                if let Some(prompt) = &self.session_load {
+                    let choice = LoadChoice::ALL[prompt.selected];
+                    self.handle_event(AppEvent::LoadSession(choice));
                 }
             }
 
-            AppEvent::ToggleApplyToAll => {
-                if let Some(popup) = &mut self.format_popup {
-                    popup.apply_to_all = !popup.apply_to_all;
+            AppEvent::LoadSession(choice) => {
+                if let Some(prompt) = self.session_load.take() {
+                    self.load_session(&prompt.path, prompt.session, choice);
                 }
             }
 
-            AppEvent::ConfirmFormat => {
-                let Some(popup) = self.format_popup.take() else {
+            AppEvent::CloseSessionLoad => {
+                self.session_load = None;
+            }
+
+            AppEvent::ChooseSingleVideo | AppEvent::ChooseWholePlaylist | AppEvent::SkipPlaylistChoice => {
+                let Some(url) = self.playlist_choice.as_mut().and_then(|prompt| prompt.urls.pop_front()) else {
                     return;
                 };
+                if self.playlist_choice.as_ref().is_some_and(|prompt| prompt.urls.is_empty()) {
+                    self.playlist_choice = None;
+                }
+                match event {
+                    AppEvent::ChooseSingleVideo => self.add_video(single_video_job(&url)),
+                    AppEvent::ChooseWholePlaylist => self.ask_playlist_range(&url),
+                    _ => {}
+                }
+            }
 
-                let Some(format) = popup.selected_format().cloned() else {
+            AppEvent::ConfirmPlaylistRange => {
+                let Some(prompt) = &mut self.playlist_range else {
                     return;
                 };
-
-                if popup.apply_to_all {
-                    for job in &mut self.jobs {
-                        if job.can_select_format() {
-                            job.selected_format = Some(format.clone());
-                            job.status = JobStatus::Queued;
-                        }
+                let items = match playlist_items(&prompt.input) {
+                    Ok(items) => items,
+                    Err(error) => {
+                        prompt.error = Some(error);
+                        return;
                     }
-                } else if let Some(job) = self.jobs.get_mut(popup.job_index) {
-                    job.selected_format = Some(format);
-                    job.status = JobStatus::Queued;
+                };
+                if let Some(url) = prompt.urls.pop_front() {
+                    self.add_playlist(&url, items);
                 }
+                self.next_playlist_range();
             }
 
-            AppEvent::StartDownloads => {
-                for job in &self.jobs {
-                    if job.status == JobStatus::Queued {
-                        if let Some(fmt) = &job.selected_format {
-                            if self.worker_tx.try_send(WorkerCommand::StartJob {
-                                job_id: job.id,
-                                url: job.url.clone(),
-                                format_id: fmt.format_id.clone(),
-                            }).is_err() {
-                                tracing::warn!("Worker channel full: StartJob dropped");
-                            }
-                        }
-                    }
+            AppEvent::SkipPlaylistRange => {
+                if let Some(prompt) = &mut self.playlist_range {
+                    prompt.urls.pop_front();
                 }
+                self.next_playlist_range();
             }
 
-            AppEvent::CancelJob(id) => {
-                if self.worker_tx.try_send(WorkerCommand::CancelJob(id)).is_err() {
-                    tracing::warn!("Worker channel full: CancelJob dropped");
-                }
-                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
-                    job.status = JobStatus::Cancelled;
+            AppEvent::ToggleInputMode => {
+                self.input_mode = !self.input_mode;
+            }
+
+            AppEvent::SwitchQueuePane => {
+                if self.split_queue {
+                    self.switch_pane();
                 }
             }
 
-            AppEvent::RemoveJob(id) => {
-                self.jobs.retain(|j| j.id != id);
-                if self.selected_index >= self.jobs.len() && !self.jobs.is_empty() {
-                    self.selected_index = self.jobs.len() - 1;
+            AppEvent::OpenQueueFilter => {
+                self.queue_filter.get_or_insert_with(Default::default).editing = true;
+            }
+
+            AppEvent::SetQueueFilter(query) => {
+                if let Some(filter) = &mut self.queue_filter {
+                    filter.query = query;
                 }
             }
 
-            AppEvent::ToggleHelp => {
-                self.show_help = !self.show_help;
+            AppEvent::KeepQueueFilter => {
+                // Nothing typed leaves nothing to keep
+                self.queue_filter = self.queue_filter.take().filter(|f| !f.query.trim().is_empty()).map(|f| QueueFilterState { editing: false, ..f });
             }
 
-            AppEvent::ToggleSysInfo => {
-                self.show_sysinfo = !self.show_sysinfo;
+            AppEvent::ClearQueueFilter => {
+                self.queue_filter = None;
             }
 
-            AppEvent::Quit => {
-                if !self.confirm_quit {
-                    self.confirm_quit = true;
-                } else {
-                    let _ = self.worker_tx.try_send(WorkerCommand::Shutdown);
-                    self.should_quit = true;
+            AppEvent::ToggleQueueLayout => {
+                if self.pane == QueuePane::Finished {
+                    self.switch_pane();
                 }
+                self.split_queue = !self.split_queue;
+                self.parked = PaneCursor::default();
             }
 
-            AppEvent::CancelQuit => {
-                self.confirm_quit = false;
+            AppEvent::SelectNext => {
+                let row_count = self.queue_rows().len();
+                if row_count > 0 {
+                    self.select_row((self.selected_index + 1) % row_count);
+                }
             }
 
-            AppEvent::ConfirmQuit => {
-                let _ = self.worker_tx.try_send(WorkerCommand::Shutdown);
-                self.should_quit = true;
+            AppEvent::SelectPrev => {
+                let row_count = self.queue_rows().len();
+                if row_count > 0 {
+                    self.select_row(self.selected_index.checked_sub(1).unwrap_or(row_count - 1));
+                }
             }
 
-            AppEvent::JobStarted { id } => {
-                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
-                    job.status = JobStatus::Downloading {
-                        percent: 0.0,
-                        speed: "--".into(),
-                        eta: "--".into(),
-                        phase: DownloadPhase::Video,
-                    };
+            AppEvent::OpenFormatPopup => {
+                if let Some(index) = self.selected_job_index() {
+                    let job = &self.jobs[index];
+                    if job.status == JobStatus::MetadataPending {
+                        // The popup opens by itself when the formats land, if the entry is still selected
+                        if self.worker_tx.try_send(WorkerCommand::PromoteFetch { job_id: job.id, url: job.url.clone() }).is_err() {
+                            tracing::warn!("Worker channel full: PromoteFetch dropped");
+                        }
+                        self.awaiting_formats = Some(job.id);
+                        self.set_notice("Fetching formats for this item next", NoticeLevel::Info);
+                    } else if job.can_select_format() {
+                        let mut popup = FormatPopupState::new(index, job.formats.clone());
+                        if let Some(format) = &job.selected_format {
+                            popup.focus(&format.format_id);
+                        }
+                        popup.live_from_start = job.live_from_start;
+                        popup.extract_audio = job.extract_audio;
+                        popup.container = job.container;
+                        popup.audio_quality = job
+                            .audio_quality
+                            .clone()
+                            .or_else(|| self.audio_quality.clone())
+                            .unwrap_or_else(|| self.config.audio_quality.clone());
+                        self.format_popup = Some(popup);
+                    }
                 }
             }
 
-            AppEvent::FormatsReady { id, title, formats } => {
-                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
-                    job.title = Some(title);
-                    if formats.is_empty() {
-                        job.status = JobStatus::Failed("No formats found".into());
-                    } else {
-                        job.formats = formats.clone();
-                        job.status = JobStatus::Ready { formats };
-                    }
+            AppEvent::CloseFormatPopup => {
+                self.format_popup = None;
+            }
+
+            AppEvent::FormatSelectNext => {
+                let wrap = self.config.wrap_format_list;
+                if let Some(popup) = &mut self.format_popup {
+                    popup.select_next(wrap);
                 }
             }
 
-            AppEvent::JobProgress { id, percent, speed, eta, phase } => {
-                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
-                    job.status = JobStatus::Downloading { percent, speed, eta, phase };
+            AppEvent::FormatSelectPrev => {
+                let wrap = self.config.wrap_format_list;
+                if let Some(popup) = &mut self.format_popup {
+                    popup.select_prev(wrap);
                 }
             }
 
-            AppEvent::JobCompleted { id, path } => {
-                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
-                    job.status = JobStatus::Completed;
-                    job.output_path = Some(path);
+            AppEvent::FormatPageDown => {
+                if let Some(popup) = &mut self.format_popup {
+                    popup.page_down();
                 }
             }
 
-            AppEvent::JobFailed { id, error } => {
-                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
-                    job.status = JobStatus::Failed(error);
+            AppEvent::FormatPageUp => {
+                if let Some(popup) = &mut self.format_popup {
+                    popup.page_up();
                 }
             }
 
-            AppEvent::ToggleSettings => {
-                if self.settings_popup.is_some() {
-                    self.settings_popup = None;
-                } else {
-                    self.settings_popup = Some(SettingsState::new(
-                        self.config.max_concurrent_downloads,
-                        self.config.output_dir.clone(),
-                    ));
+            AppEvent::FormatSelectFirst => {
+                if let Some(popup) = &mut self.format_popup {
+                    popup.select_first();
                 }
             }
 
-            AppEvent::CloseSettings => {
-                self.settings_popup = None;
+            AppEvent::FormatSelectLast => {
+                if let Some(popup) = &mut self.format_popup {
+                    popup.select_last();
+                }
             }
 
-            AppEvent::SettingsNext => {
-                if let Some(ref mut settings) = self.settings_popup {
-                    settings.selected_field = (settings.selected_field + 1).min(1);
-                    settings.editing_path = false;
+            AppEvent::FormatToggleAudioTier => {
+                if let Some(popup) = &mut self.format_popup {
+                    popup.toggle_audio_tier();
                 }
             }
 
-            AppEvent::SettingsPrev => {
-                if let Some(ref mut settings) = self.settings_popup {
-                    settings.selected_field = settings.selected_field.saturating_sub(1);
-                    settings.editing_path = false;
+            AppEvent::FormatToggleExtractAudio => {
+                if let Some(popup) = &mut self.format_popup {
+                    popup.extract_audio = !popup.extract_audio;
                 }
             }
 
-            AppEvent::SettingsIncrement => {
-                if let Some(ref mut settings) = self.settings_popup {
-                    if settings.selected_field == 0 {
-                        settings.concurrent_downloads = (settings.concurrent_downloads + 1).min(10);
-                    }
+            AppEvent::FormatStepAudioQuality(forward) => {
+                if let Some(popup) = self.format_popup.as_mut().filter(|p| p.extract_audio) {
+                    popup.step_audio_quality(forward);
                 }
             }
 
-            AppEvent::SettingsDecrement => {
-                if let Some(ref mut settings) = self.settings_popup {
-                    if settings.selected_field == 0 {
-                        settings.concurrent_downloads = settings.concurrent_downloads.saturating_sub(1).max(1);
-                    }
+            AppEvent::FormatCycleContainer => {
+                if let Some(popup) = &mut self.format_popup {
+                    popup.container = popup.container.next();
                 }
             }
 
-            AppEvent::SettingsToggleEdit => {
-                if let Some(ref mut settings) = self.settings_popup {
-                    if settings.selected_field == 1 {
-                        settings.editing_path = !settings.editing_path;
+            AppEvent::ToggleLiveFromStart => {
+                if let Some(popup) = &mut self.format_popup {
+                    if self.jobs.get(popup.job_index).is_some_and(|j| j.live) {
+                        popup.live_from_start = !popup.live_from_start;
                     }
                 }
             }
 
-            AppEvent::SettingsCharInput(c) => {
-                if let Some(ref mut settings) = self.settings_popup {
-                    if settings.editing_path {
-                        settings.output_dir.push(c);
-                    }
+            AppEvent::FormatToggleShowAll => {
+                if let Some(popup) = &mut self.format_popup {
+                    popup.toggle_show_all();
                 }
             }
 
-            AppEvent::SettingsBackspace => {
-                if let Some(ref mut settings) = self.settings_popup {
-                    if settings.editing_path {
-                        settings.output_dir.pop();
-                    }
+            AppEvent::FormatExpandTier => {
+                if let Some(popup) = &mut self.format_popup {
+                    popup.expand_selected();
                 }
             }
 
-            AppEvent::SaveSettings => {
-                if let Some(settings) = self.settings_popup.take() {
-                    self.config.max_concurrent_downloads = settings.concurrent_downloads;
-                    self.config.output_dir = std::path::PathBuf::from(&settings.output_dir);
+            AppEvent::FormatCollapseTier => {
+                if let Some(popup) = &mut self.format_popup {
+                    popup.collapse_selected();
+                }
+            }
+
+            AppEvent::ToggleApplyToAll => {
+                if let Some(popup) = &mut self.format_popup {
+                    popup.apply_to_all = !popup.apply_to_all;
+                }
+            }
+
+            AppEvent::ConfirmFormat => {
+                let Some(popup) = self.format_popup.take() else {
+                    return;
+                };
+
+                let Some(format) = popup.selected_format().cloned() else {
+                    return;
+                };
+
+                // Kept as the starting point for the next audio download this session
+                let audio_quality = popup.extract_audio.then(|| popup.audio_quality.clone());
+                if audio_quality.is_some() {
+                    self.audio_quality = audio_quality.clone();
+                }
+
+                let mut remembered = false;
+                if popup.apply_to_all || popup.group.is_some() {
+                    for job in &mut self.jobs {
+                        let in_scope = popup.apply_to_all || job.group == popup.group;
+                        if !in_scope || !job.can_select_format() {
+                            continue;
+                        }
+                        if let Some(resolved) = job.equivalent_format(&format).cloned() {
+                            job.extract_audio = popup.extract_audio;
+                            job.audio_quality = audio_quality.clone();
+                            job.container = popup.container;
+                            if self.config.channel_formats != ChannelFormats::Off {
+                                remembered |= self.channels.remember(&job.metadata, &resolved, job.extract_audio);
+                            }
+                            job.selected_format = Some(resolved);
+                            job.status = JobStatus::Queued;
+                            job.from_channel = false;
+                            job.default_format = false;
+                            job.live_from_start = job.live && popup.live_from_start;
+                        }
+                    }
+                } else if let Some(job) = self.jobs.get_mut(popup.job_index) {
+                    job.extract_audio = popup.extract_audio;
+                    job.audio_quality = audio_quality;
+                    job.container = popup.container;
+                    if self.config.channel_formats != ChannelFormats::Off {
+                        remembered = self.channels.remember(&job.metadata, &format, job.extract_audio);
+                    }
+                    job.selected_format = Some(format);
+                    job.status = JobStatus::Queued;
+                    job.from_channel = false;
+                    job.default_format = false;
+                    job.live_from_start = job.live && popup.live_from_start;
+                }
+                if remembered {
+                    self.save_channels();
+                }
+                self.warn_unmergeable();
+            }
+
+            AppEvent::OpenGroupMenu(group_id) => {
+                self.group_menu = Some(GroupMenuState::new(group_id));
+            }
+
+            AppEvent::CloseGroupMenu => {
+                self.group_menu = None;
+            }
+
+            AppEvent::GroupMenuNext => {
+                if let Some(menu) = &mut self.group_menu {
+                    menu.selected = (menu.selected + 1) % GroupAction::ALL.len();
+                }
+            }
+
+            AppEvent::GroupMenuPrev => {
+                if let Some(menu) = &mut self.group_menu {
+                    menu.selected = menu.selected.checked_sub(1).unwrap_or(GroupAction::ALL.len() - 1);
+                }
+            }
+
+            AppEvent::ConfirmGroupAction => {
+                if let Some(menu) = self.group_menu.take() {
+                    self.run_group_action(menu.group_id, menu.action());
+                }
+            }
+
+            AppEvent::StartDownloads => {
+                self.request_start(None);
+            }
+
+            AppEvent::ConfirmDefaultFormat => {
+                if let Some(state) = self.default_format_prompt.take() {
+                    for job in self.jobs.iter_mut().filter(|j| state.jobs.contains(&j.id) && j.status == JobStatus::Ready && j.selected_format.is_none()) {
+                        job.default_format = true;
+                        job.status = JobStatus::Queued;
+                    }
+                    self.check_large_downloads(state.group);
+                }
+            }
+
+            AppEvent::SkipDefaultFormat => {
+                if let Some(state) = self.default_format_prompt.take() {
+                    self.check_large_downloads(state.group);
+                }
+            }
+
+            AppEvent::CloseDefaultFormat => {
+                self.default_format_prompt = None;
+            }
+
+            AppEvent::ConfirmLargeDownloads => {
+                if let Some(state) = self.large_downloads.take() {
+                    self.start_queued(state.group, &[]);
+                }
+            }
+
+            AppEvent::SkipLargeDownloads => {
+                if let Some(state) = self.large_downloads.take() {
+                    let skip: Vec<JobId> = state.jobs.iter().map(|(id, _)| *id).collect();
+                    self.start_queued(state.group, &skip);
+                }
+            }
+
+            AppEvent::DownsizeLargeDownload => {
+                let Some(state) = self.large_downloads.take() else {
+                    return;
+                };
+                let first = state.jobs.first().and_then(|(id, _)| self.jobs.iter().position(|j| j.id == *id));
+                if let Some(index) = first {
+                    self.format_popup = Some(FormatPopupState::new(index, self.jobs[index].formats.clone()));
+                }
+            }
+
+            AppEvent::CloseLargeDownloads => {
+                self.large_downloads = None;
+            }
+
+            AppEvent::LinkAfter => {
+                let Some(job) = self.selected_job() else {
+                    return;
+                };
+                let (id, name) = (job.id, job.display_name().to_string());
+                let linkable = matches!(job.status, JobStatus::FetchingFormats | JobStatus::MetadataPending | JobStatus::Ready | JobStatus::Queued | JobStatus::Failed(_) | JobStatus::Cancelled);
+                let Some(mark) = self.dependency_mark.take() else {
+                    self.dependency_mark = Some(id);
+                    self.set_notice(format!("Marked {}: select another item and press > to run it after this one", name), NoticeLevel::Info);
+                    return;
+                };
+                let Some(before) = self.jobs.iter().find(|j| j.id == mark).map(|j| j.display_name().to_string()) else {
+                    return;
+                };
+                if mark == id {
+                    self.set_notice("Mark cleared", NoticeLevel::Info);
+                } else if !linkable {
+                    self.set_notice(format!("{} has already started", name), NoticeLevel::Error);
+                } else if self.waits_on(mark, id) {
+                    self.set_notice(format!("Can't link: {} already waits on {}", before, name), NoticeLevel::Error);
+                } else {
+                    if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                        job.after = Some(mark);
+                    }
+                    self.set_notice(format!("{} will download after {}", name, before), NoticeLevel::Info);
+                }
+            }
+
+            AppEvent::UnlinkAfter => {
+                let Some(index) = self.selected_job_index() else {
+                    return;
+                };
+                let job = &mut self.jobs[index];
+                if job.after.take().is_none() {
+                    return;
+                }
+                // The worker may already be holding it back
+                let waiting = matches!(job.status, JobStatus::Starting | JobStatus::WaitingForSlot { .. });
+                let id = job.id;
+                if waiting {
+                    self.handle_event(AppEvent::CancelJob(id));
+                    self.handle_event(AppEvent::RestartCancelled { id, resume: true });
+                }
+                self.set_notice("No longer waits on another item", NoticeLevel::Info);
+            }
+
+            AppEvent::RunDependentsAnyway => {
+                let Some(prompt) = self.dependents_prompt.take() else {
+                    return;
+                };
+                for job in self.jobs.iter_mut().filter(|j| prompt.dependents.contains(&j.id)) {
+                    job.after = None;
+                }
+                self.release_dependents(prompt.prerequisite);
+            }
+
+            AppEvent::CancelDependents => {
+                let Some(prompt) = self.dependents_prompt.take() else {
+                    return;
+                };
+                for id in prompt.dependents {
+                    self.handle_event(AppEvent::CancelJob(id));
+                }
+            }
+
+            AppEvent::CloseDependentsPrompt => {
+                self.dependents_prompt = None;
+            }
+
+            AppEvent::OpenChannelList => {
+                self.channel_list = Some(ChannelListState::default());
+            }
+
+            AppEvent::ChannelListNext => {
+                let count = self.channels.len();
+                if let Some(list) = &mut self.channel_list {
+                    list.selected = (list.selected + 1).min(count.saturating_sub(1));
+                }
+            }
+
+            AppEvent::ChannelListPrev => {
+                if let Some(list) = &mut self.channel_list {
+                    list.selected = list.selected.saturating_sub(1);
+                }
+            }
+
+            AppEvent::ForgetChannel => {
+                let Some(list) = &mut self.channel_list else {
+                    return;
+                };
+                let Some(id) = self.channels.entries().get(list.selected).map(|(id, _)| id.to_string()) else {
+                    return;
+                };
+                if let Some(choice) = self.channels.forget(&id) {
+                    list.selected = list.selected.min(self.channels.len().saturating_sub(1));
+                    self.set_notice(format!("Forgot the format for {}", choice.name), NoticeLevel::Info);
+                    self.save_channels();
+                }
+            }
+
+            AppEvent::CloseChannelList => {
+                self.channel_list = None;
+            }
+
+            AppEvent::SetNote { id, note } => {
+                self.note_prompt = None;
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    let note = note.trim();
+                    job.note = (!note.is_empty()).then(|| note.to_string());
+                }
+            }
+
+            AppEvent::OpenClashPrompt => {
+                let Some(job) = self.selected_job() else {
+                    return;
+                };
+                if let Some(&earlier) = self.name_clashes.get(&job.id) {
+                    self.clash_prompt = Some(ClashPromptState { job_id: job.id, earlier, selected: 0 });
+                }
+            }
+
+            AppEvent::ClashPromptNext => {
+                if let Some(prompt) = &mut self.clash_prompt {
+                    prompt.selected = (prompt.selected + 1) % ClashAction::ALL.len();
+                }
+            }
+
+            AppEvent::ClashPromptPrev => {
+                if let Some(prompt) = &mut self.clash_prompt {
+                    prompt.selected = prompt.selected.checked_sub(1).unwrap_or(ClashAction::ALL.len() - 1);
+                }
+            }
+
+            AppEvent::ConfirmClashAction => {
+                if let Some(prompt) = &self.clash_prompt {
+                    let action = ClashAction::ALL[prompt.selected];
+                    self.handle_event(AppEvent::RunClashAction(action));
+                }
+            }
+
+            AppEvent::RunClashAction(action) => {
+                let Some(prompt) = self.clash_prompt.take() else {
+                    return;
+                };
+                let Some(index) = self.jobs.iter().position(|j| j.id == prompt.job_id) else {
+                    return;
+                };
+                match action {
+                    ClashAction::Suffix => {
+                        let template = suffixed_template(&self.jobs[index], &self.jobs, &self.config);
+                        self.jobs[index].output_template = Some(template);
+                        self.refresh_name_clashes();
+                        self.autosave.mark_dirty();
+                    }
+                    ClashAction::Rename => {
+                        let mut output = OutputPromptState::new(&self.jobs[index]);
+                        output.editing_template = true;
+                        self.output_prompt = Some(output);
+                    }
+                }
+            }
+
+            AppEvent::CloseClashPrompt => {
+                self.clash_prompt = None;
+            }
+
+            AppEvent::ConfirmOutput => {
+                let Some(prompt) = &mut self.output_prompt else {
+                    return;
+                };
+                let (dir, template) = (prompt.dir.trim(), prompt.template.trim());
+                let dir = (!dir.is_empty()).then(|| paths::expand_home(dir));
+                if let Some(dir) = dir.as_ref().filter(|d| d.exists() && !d.is_dir()) {
+                    prompt.error = Some(format!("{} is a file, not a directory", dir.display()));
+                    return;
+                }
+                if template.starts_with('/') || template.ends_with('/') {
+                    prompt.error = Some("The template is a file name, optionally under subdirectories".into());
+                    return;
+                }
+                let template = (!template.is_empty()).then(|| template.to_string());
+                let id = prompt.job_id;
+                self.output_prompt = None;
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    job.output_dir = dir;
+                    job.output_template = template;
+                }
+            }
+
+            AppEvent::ConfirmSections => {
+                let Some(prompt) = &mut self.section_prompt else {
+                    return;
+                };
+                let (start, end) = (prompt.start.trim().to_string(), prompt.end.trim().to_string());
+                let duration = self.jobs.iter().find(|j| j.id == prompt.job_id).and_then(|j| j.metadata.duration);
+                let sections = if start.is_empty() && end.is_empty() {
+                    None
+                } else {
+                    match (format::parse_timestamp(&start), format::parse_timestamp(&end)) {
+                        (None, _) | (_, None) => {
+                            prompt.error = Some("Use times like 90, 5:00 or 1:02:03".into());
+                            return;
+                        }
+                        (Some(from), Some(to)) if to <= from => {
+                            prompt.error = Some("The end has to come after the start".into());
+                            return;
+                        }
+                        (_, Some(to)) if duration.is_some_and(|d| to > d + 1.0) => {
+                            prompt.error = Some(format!("The video is only {} long", format::duration(duration.unwrap_or_default())));
+                            return;
+                        }
+                        _ => Some((start, end)),
+                    }
+                };
+                let id = prompt.job_id;
+                self.section_prompt = None;
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    job.sections = sections;
+                }
+            }
+
+            AppEvent::ImportBookmarks(path) => {
+                self.import_prompt = None;
+                match std::fs::read(&path) {
+                    Ok(content) => {
+                        let found = bookmarks::parse(&String::from_utf8_lossy(&content));
+                        if found.is_empty() {
+                            self.set_notice(format!("No video links found in {}", path.display()), NoticeLevel::Info);
+                        } else {
+                            self.import_picker = Some(ImportPickerState::new(found));
+                        }
+                    }
+                    Err(e) => self.set_notice(format!("Could not read {}: {}", path.display(), e), NoticeLevel::Error),
+                }
+            }
+
+            AppEvent::ImportPickerNext => {
+                if let Some(picker) = &mut self.import_picker {
+                    picker.selected = (picker.selected + 1) % picker.bookmarks.len();
+                }
+            }
+
+            AppEvent::ImportPickerPrev => {
+                if let Some(picker) = &mut self.import_picker {
+                    picker.selected = picker.selected.checked_sub(1).unwrap_or(picker.bookmarks.len() - 1);
+                }
+            }
+
+            AppEvent::ImportPickerToggle => {
+                if let Some(picker) = &mut self.import_picker {
+                    picker.chosen[picker.selected] = !picker.chosen[picker.selected];
+                }
+            }
+
+            AppEvent::ImportPickerToggleAll => {
+                if let Some(picker) = &mut self.import_picker {
+                    let choose = picker.chosen_count() < picker.bookmarks.len();
+                    picker.chosen.fill(choose);
+                }
+            }
+
+            AppEvent::ImportPickerPreset(preset) => {
+                if let Some(picker) = &mut self.import_picker {
+                    picker.preset = preset;
+                }
+            }
+
+            AppEvent::ConfirmImport => {
+                if let Some(picker) = self.import_picker.take() {
+                    let urls: Vec<String> = picker.chosen_urls().map(String::from).collect();
+                    self.set_notice(format!("Imported {} links", urls.len()), NoticeLevel::Info);
+                    let first_new = self.jobs.len();
+                    // Picked from the export already, so playlists load whole without asking
+                    for url in urls {
+                        if is_playlist_url(&url) {
+                            self.add_playlist(&url, None);
+                        } else {
+                            self.handle_event(AppEvent::AddUrl(url));
+                        }
+                    }
+                    // New jobs are appended; playlist placeholders pass the preset on when expanded
+                    if let Some(preset) = picker.preset {
+                        for job in &mut self.jobs[first_new..] {
+                            job.pending_preset = Some(preset);
+                        }
+                    }
+                }
+            }
+
+            AppEvent::CloseImportPicker => {
+                self.import_picker = None;
+            }
+
+            AppEvent::OpenRemediation(id) => {
+                let Some(JobStatus::Failed(err)) = self.jobs.iter().find(|j| j.id == id).map(|j| &j.status) else {
+                    return;
+                };
+                let kind = err.kind;
+                // A password won't get past a membership check, so skip straight to cookies
+                if kind == DownloadError::MembersOnly {
+                    let mut state = RemediationState::at_step(id, RemediationStep::Browser);
+                    state.selected = self.cookie_browser_index();
+                    self.remediation = Some(state);
+                } else if kind.is_login_wall() {
+                    self.remediation = Some(RemediationState::new(id));
+                } else if kind.is_transient() || kind == DownloadError::Cancelled {
+                    self.retry_job(id);
+                } else {
+                    let reason = kind.hint().unwrap_or(kind.label());
+                    self.set_notice(format!("Retrying won't help: {}", reason), NoticeLevel::Error);
+                }
+            }
+
+            AppEvent::RestartCancelled { id, resume } => {
+                let Some(index) = self.jobs.iter().position(|j| j.id == id) else {
+                    return;
+                };
+                let job = &mut self.jobs[index];
+                if job.status != JobStatus::Cancelled || !job.has_format() {
+                    return;
+                }
+                job.resume = resume && job.partial.is_some();
+                job.status = JobStatus::Queued;
+                self.start_job(index);
+            }
+
+            AppEvent::RetryMembersOnly => {
+                if self.config.cookies_from_browser.is_none() {
+                    return;
+                }
+                let ids: Vec<JobId> = self.members_only_jobs().map(|j| j.id).collect();
+                for id in &ids {
+                    self.retry_job(*id);
+                }
+                if !ids.is_empty() {
+                    self.set_notice(format!("Retrying {} members-only item(s)", ids.len()), NoticeLevel::Info);
+                }
+            }
+
+            AppEvent::RemediationNext => {
+                if let Some(state) = &mut self.remediation {
+                    let count = state.option_count();
+                    if count > 0 {
+                        state.selected = (state.selected + 1) % count;
+                    }
+                }
+            }
+
+            AppEvent::RemediationPrev => {
+                if let Some(state) = &mut self.remediation {
+                    let count = state.option_count();
+                    if count > 0 {
+                        state.selected = state.selected.checked_sub(1).unwrap_or(count - 1);
+                    }
+                }
+            }
+
+            AppEvent::RemediationConfirm => {
+                let browser_index = self.cookie_browser_index();
+                let Some(state) = &mut self.remediation else {
+                    return;
+                };
+                match (state.step, state.selected) {
+                    (RemediationStep::Menu, 0) => {
+                        state.step = RemediationStep::Browser;
+                        state.selected = browser_index;
+                    }
+                    (RemediationStep::Menu, 1) => {
+                        state.step = RemediationStep::Login;
+                    }
+                    (RemediationStep::Menu, _) => {
+                        self.remediation = None;
+                    }
+                    (RemediationStep::Browser, selected) => {
+                        let id = state.job_id;
+                        self.remediation = None;
+                        self.config.cookies_from_browser = Some(COOKIE_BROWSERS[selected].to_string());
+                        self.apply_config();
+                        self.retry_job(id);
+                    }
+                    (RemediationStep::Login, _) => {
+                        if state.username.is_empty() {
+                            return;
+                        }
+                        let Some(state) = self.remediation.take() else {
+                            return;
+                        };
+                        if let Some(job) = self.jobs.iter_mut().find(|j| j.id == state.job_id) {
+                            job.credentials = Some(Credentials {
+                                username: state.username,
+                                password: state.password,
+                            });
+                        }
+                        self.retry_job(state.job_id);
+                    }
+                }
+            }
+
+            AppEvent::RemediationInput(c) => {
+                if let Some(state) = &mut self.remediation {
+                    state.field_mut().push(c);
+                }
+            }
+
+            AppEvent::RemediationBackspace => {
+                if let Some(state) = &mut self.remediation {
+                    state.field_mut().pop();
+                }
+            }
+
+            AppEvent::RemediationSwitchField => {
+                if let Some(state) = &mut self.remediation {
+                    state.field = match state.field {
+                        LoginField::Username => LoginField::Password,
+                        LoginField::Password => LoginField::Username,
+                    };
+                }
+            }
+
+            AppEvent::CloseRemediation => {
+                self.remediation = None;
+            }
+
+            AppEvent::OpenCompletedMenu(id) => {
+                self.completed_menu = Some(CompletedMenuState { job_id: id, selected: 0 });
+            }
+
+            AppEvent::CompletedMenuNext => {
+                if let Some(menu) = &mut self.completed_menu {
+                    menu.selected = (menu.selected + 1) % CompletedAction::ALL.len();
+                }
+            }
+
+            AppEvent::CompletedMenuPrev => {
+                if let Some(menu) = &mut self.completed_menu {
+                    menu.selected = menu.selected.checked_sub(1).unwrap_or(CompletedAction::ALL.len() - 1);
+                }
+            }
+
+            AppEvent::ConfirmCompletedAction => {
+                if let Some(menu) = &self.completed_menu {
+                    let event = AppEvent::RunCompletedAction(menu.job_id, CompletedAction::ALL[menu.selected]);
+                    self.handle_event(event);
+                }
+            }
+
+            AppEvent::CloseCompletedMenu => {
+                self.completed_menu = None;
+            }
+
+            AppEvent::OpenConflictPrompt(id) => {
+                if let Some(JobStatus::Blocked { path }) = self.jobs.iter().find(|j| j.id == id).map(|j| &j.status) {
+                    self.conflict_prompt = Some(ConflictPromptState::new(id, path.clone()));
+                }
+            }
+
+            AppEvent::ConflictPromptNext => {
+                if let Some(prompt) = &mut self.conflict_prompt {
+                    prompt.selected = (prompt.selected + 1) % ConflictPromptState::CHOICES.len();
+                }
+            }
+
+            AppEvent::ConflictPromptPrev => {
+                if let Some(prompt) = &mut self.conflict_prompt {
+                    prompt.selected = prompt.selected.checked_sub(1).unwrap_or(ConflictPromptState::CHOICES.len() - 1);
+                }
+            }
+
+            AppEvent::ConfirmConflictChoice => {
+                if let Some(prompt) = &self.conflict_prompt {
+                    let (policy, always) = prompt.choice();
+                    self.resolve_conflict(prompt.job_id, policy, always);
+                }
+            }
+
+            AppEvent::ResolveConflict(id, policy, always) => {
+                self.resolve_conflict(id, policy, always);
+            }
+
+            AppEvent::CloseConflictPrompt => {
+                self.conflict_prompt = None;
+            }
+
+            AppEvent::OpenRemoveMenu => {
+                self.remove_menu = Some(RemoveMenuState { selected: 0 });
+            }
+
+            AppEvent::RemoveMenuNext => {
+                if let Some(menu) = &mut self.remove_menu {
+                    menu.selected = (menu.selected + 1) % RemoveFilter::ALL.len();
+                }
+            }
+
+            AppEvent::RemoveMenuPrev => {
+                if let Some(menu) = &mut self.remove_menu {
+                    menu.selected = menu.selected.checked_sub(1).unwrap_or(RemoveFilter::ALL.len() - 1);
+                }
+            }
+
+            AppEvent::ConfirmRemoveMenu => {
+                if let Some(menu) = &self.remove_menu {
+                    let filter = RemoveFilter::ALL[menu.selected];
+                    self.handle_event(AppEvent::RemoveByStatus(filter));
+                }
+            }
+
+            AppEvent::RemoveByStatus(filter) => {
+                self.remove_menu = None;
+                self.remove_by_status(filter);
+            }
+
+            AppEvent::CloseRemoveMenu => {
+                self.remove_menu = None;
+            }
+
+            AppEvent::OpenTrash => {
+                self.settings_popup = None;
+                self.trash_view = Some(TrashViewState::default());
+            }
+
+            AppEvent::TrashNext => {
+                let count = self.trash.len();
+                if let Some(view) = &mut self.trash_view {
+                    view.selected = (view.selected + 1).min(count.saturating_sub(1));
+                }
+            }
+
+            AppEvent::TrashPrev => {
+                if let Some(view) = &mut self.trash_view {
+                    view.selected = view.selected.saturating_sub(1);
+                }
+            }
+
+            AppEvent::RestoreTrashed => {
+                let Some(index) = self.trash_view.as_ref().and_then(|v| self.trash_index(v.selected)) else {
+                    return;
+                };
+                self.restore_trashed(&[index]);
+                self.set_notice("Restored 1 item", NoticeLevel::Info);
+                self.clamp_trash_view();
+            }
+
+            AppEvent::PurgeTrashed => {
+                let Some(index) = self.trash_view.as_ref().and_then(|v| self.trash_index(v.selected)) else {
+                    return;
+                };
+                self.trash.remove(index);
+                self.autosave.mark_dirty();
+                self.clamp_trash_view();
+            }
+
+            AppEvent::EmptyTrash => {
+                let count = self.trash.len();
+                self.trash.clear();
+                self.autosave.mark_dirty();
+                self.clamp_trash_view();
+                self.set_notice(format!("Purged {} item(s) from the trash", count), NoticeLevel::Info);
+            }
+
+            AppEvent::CloseTrash => {
+                self.trash_view = None;
+            }
+
+            AppEvent::UndoRemove => {
+                self.undo_remove();
+            }
+
+            AppEvent::ResumePowerPause => {
+                if self.power_paused.is_some() {
+                    self.power_overridden = true;
+                    self.resume_power_pause();
+                }
+            }
+
+            AppEvent::OpenHaltPrompt => {
+                if let Some(reason) = &self.queue_halted {
+                    self.halt_prompt = Some(HaltPromptState { reason: reason.clone(), selected: 0 });
+                }
+            }
+
+            AppEvent::HaltPromptNext => {
+                if let Some(prompt) = &mut self.halt_prompt {
+                    prompt.selected = (prompt.selected + 1) % HaltAction::ALL.len();
+                }
+            }
+
+            AppEvent::HaltPromptPrev => {
+                if let Some(prompt) = &mut self.halt_prompt {
+                    prompt.selected = prompt.selected.checked_sub(1).unwrap_or(HaltAction::ALL.len() - 1);
+                }
+            }
+
+            AppEvent::ConfirmHaltAction => {
+                if let Some(prompt) = &self.halt_prompt {
+                    let action = HaltAction::ALL[prompt.selected];
+                    self.handle_event(AppEvent::RunHaltAction(action));
+                }
+            }
+
+            AppEvent::RunHaltAction(action) => {
+                self.run_halt_action(action);
+            }
+
+            AppEvent::CloseHaltPrompt => {
+                self.halt_prompt = None;
+            }
+
+            AppEvent::Worker(WorkerEvent::QueueHalted { reason }) => {
+                self.set_notice("Queue paused: downloads keep failing the same way", NoticeLevel::Error);
+                self.halt_prompt = Some(HaltPromptState { reason: reason.clone(), selected: 0 });
+                self.queue_halted = Some(reason);
+            }
+
+            AppEvent::Worker(WorkerEvent::QueueResumed) => {
+                self.queue_halted = None;
+                self.halt_prompt = None;
+                self.set_notice("Test download succeeded, queue resumed", NoticeLevel::Info);
+            }
+
+            AppEvent::Worker(WorkerEvent::YtdlpUpdateOutput { line }) => {
+                if let Some(screen) = &mut self.ytdlp_update {
+                    screen.output.push(line);
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::YtdlpUpdated { success, message }) => {
+                if success {
+                    self.set_notice(format!("yt-dlp: {}", message), NoticeLevel::Info);
+                    // The notice is replaced by the new version once yt-dlp answers
+                    let config = self.config.clone();
+                    if let Some(event_tx) = self.event_tx.clone() {
+                        tokio::spawn(async move {
+                            let version = config::check_ytdlp(&config).await.ok();
+                            let _ = event_tx.send(AppEvent::YtdlpVersionChecked(version)).await;
+                        });
+                    }
+                } else {
+                    self.set_notice(format!("yt-dlp update failed: {}", message), NoticeLevel::Error);
+                }
+                if let Some(screen) = &mut self.ytdlp_update {
+                    screen.result = Some((success, message));
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::ConflictDetected { id, path }) => {
+                let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) else {
+                    return;
+                };
+                if job.status == JobStatus::Cancelled {
+                    return;
+                }
+                tracing::info!("{} already exists, asking", path.display());
+                job.status = JobStatus::Blocked { path: path.clone() };
+                // An "always" answer given while this job was still checking applies to it too
+                if let Some(policy) = self.conflict_always {
+                    self.resolve_conflict(id, policy, false);
+                } else if self.conflict_prompt.is_none() {
+                    self.conflict_prompt = Some(ConflictPromptState::new(id, path));
+                }
+            }
+
+            AppEvent::RunCompletedAction(id, action) => {
+                self.run_completed_action(id, action);
+            }
+
+            AppEvent::StartTriage(group) => {
+                let mut triage = TriageState::new(group);
+                if triage.advance(&self.jobs) {
+                    self.triage = Some(triage);
+                } else {
+                    self.set_notice("No jobs waiting for a format", NoticeLevel::Info);
+                }
+            }
+
+            AppEvent::TriageAssign(preset) => {
+                let Some(id) = self.triage.as_ref().and_then(|t| t.current) else {
+                    return;
+                };
+                let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) else {
+                    return;
+                };
+                if job.apply_preset(preset) {
+                    if let Some(triage) = &mut self.triage {
+                        triage.assigned += 1;
+                    }
+                } else {
+                    self.set_notice("No matching format; skipped", NoticeLevel::Error);
+                    if let Some(triage) = &mut self.triage {
+                        triage.skip();
+                    }
+                }
+                self.advance_triage();
+            }
+
+            AppEvent::TriageSkip => {
+                if let Some(triage) = &mut self.triage {
+                    triage.skip();
+                }
+                self.advance_triage();
+            }
+
+            AppEvent::TriageRemove => {
+                if let Some(id) = self.triage.as_ref().and_then(|t| t.current) {
+                    self.trash_where(|j| j.id == id);
+                }
+                self.advance_triage();
+            }
+
+            AppEvent::CloseTriage => {
+                if let Some(triage) = self.triage.take() {
+                    self.set_notice(format!("Assigned formats to {} jobs", triage.assigned), NoticeLevel::Info);
+                }
+            }
+
+            AppEvent::CancelJob(id) => {
+                if self.worker_tx.try_send(WorkerCommand::CancelJob(id)).is_err() {
+                    tracing::warn!("Worker channel full: CancelJob dropped");
+                }
+                // A live recording ends as a finished file once yt-dlp has closed it
+                let recording = self.jobs.iter().any(|j| j.id == id && j.live && matches!(j.status, JobStatus::Downloading { .. }));
+                if recording {
+                    self.set_notice("Stopping the recording; what was recorded is kept", NoticeLevel::Info);
+                    return;
+                }
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    if let Some(partial) = PartialProgress::of(&job.status) {
+                        job.partial = Some(partial);
+                    }
+                    job.status = JobStatus::Cancelled;
+                    job.pending_preset = None;
+                }
+                self.report_presets();
+                if self.conflict_prompt.as_ref().is_some_and(|p| p.job_id == id) {
+                    self.conflict_prompt = None;
+                }
+            }
+
+            AppEvent::RemoveJob(id) => {
+                if self.trash_where(|j| j.id == id) > 0 {
+                    self.set_notice("Moved to trash, press 'u' to undo", NoticeLevel::Info);
+                }
+                self.report_presets();
+                if self.conflict_prompt.as_ref().is_some_and(|p| p.job_id == id) {
+                    self.conflict_prompt = None;
+                }
+            }
+
+            AppEvent::SortByUploadDate => {
+                self.sort_by_upload_date();
+            }
+
+            AppEvent::ToggleHelp => {
+                self.show_help = !self.show_help;
+            }
+
+            AppEvent::ToggleSysInfo => {
+                self.show_sysinfo = !self.show_sysinfo;
+            }
+
+            AppEvent::ToggleWarnings => {
+                self.show_warnings = !self.show_warnings;
+            }
+
+            AppEvent::ToggleTimeline => {
+                self.show_timeline = !self.show_timeline;
+            }
+
+            AppEvent::CopyErrorReport => {
+                let Some(report) = self.selected_job().and_then(Job::error_report) else {
+                    return;
+                };
+                match clipboard::copy(&report) {
+                    Ok(()) => self.set_notice("Copied error report to clipboard", NoticeLevel::Info),
+                    Err(e) => self.set_notice(format!("Clipboard copy failed: {}", e), NoticeLevel::Error),
+                }
+            }
+
+            AppEvent::ToggleVerboseRows => {
+                self.verbose_rows = !self.verbose_rows;
+            }
+
+            AppEvent::ToggleDoNotDisturb => {
+                self.config.do_not_disturb = !self.config.do_not_disturb;
+                if self.config.do_not_disturb {
+                    self.completions = CompletionBatch::default();
+                }
+                self.apply_config();
+                let state = if self.config.do_not_disturb { "on" } else { "off" };
+                self.set_notice(format!("Do not disturb {}", state), NoticeLevel::Info);
+            }
+
+            AppEvent::ToggleFpsOverlay => {
+                self.show_fps = !self.show_fps;
+            }
+
+            AppEvent::Quit => {
+                if !self.confirm_quit {
+                    self.confirm_quit = true;
+                } else {
+                    let _ = self.worker_tx.try_send(WorkerCommand::Shutdown);
+                    self.should_quit = true;
+                }
+            }
+
+            AppEvent::OpenHealthCheck => {
+                self.run_health_check(false);
+            }
+
+            AppEvent::ConfigSaveFailed(error) => {
+                self.set_notice(format!("Settings not saved: {}", error), NoticeLevel::Error);
+                self.sync_config();
+            }
+
+            AppEvent::ConfigChanged => {
+                self.sync_config();
+            }
+
+            AppEvent::ReloadConfig => {
+                self.settings_popup = None;
+                if let Some(config_tx) = &self.config_tx {
+                    if config_tx.send(ConfigMessage::Reload).is_err() {
+                        tracing::warn!("Config writer stopped: config not reloaded");
+                    }
+                }
+            }
+
+            AppEvent::ConfigReloaded { changed } => {
+                let notice = if changed { "Reloaded config.toml" } else { "config.toml is unchanged" };
+                self.set_notice(notice, NoticeLevel::Info);
+            }
+
+            AppEvent::ConfigReloadFailed(error) => {
+                self.set_notice(format!("config.toml not reloaded: {}", error), NoticeLevel::Error);
+            }
+
+            AppEvent::HealthChecked(report) => {
+                if !report.ytdlp_version.is_empty() {
+                    self.ytdlp_version = report.ytdlp_version.clone();
+                }
+                self.ffmpeg_missing = report.ffmpeg_missing();
+                self.warn_unmergeable();
+                if let Some(screen) = &mut self.health {
+                    if screen.dismiss_if_ok && report.all_ok() {
+                        self.health = None;
+                    } else {
+                        screen.report = Some(report);
+                    }
+                }
+            }
+
+            AppEvent::DiagnoseJob(id) => {
+                let Some(job) = self.jobs.iter().find(|j| j.id == id) else {
+                    return;
+                };
+                let (JobStatus::Failed(failure), Some(event_tx)) = (&job.status, self.event_tx.clone()) else {
+                    return;
+                };
+                if let Some(screen) = self.diagnosis.take() {
+                    screen.cancel.cancel();
+                }
+                let cancel = CancellationToken::new();
+                let (config, url, failure) = (self.config.clone(), job.url.clone(), failure.clone());
+                let token = cancel.clone();
+                tokio::spawn(async move {
+                    if let Some(report) = diagnose::run(config, url, failure, token).await {
+                        let _ = event_tx.send(AppEvent::Diagnosed(id, report)).await;
+                    }
+                });
+                self.diagnosis = Some(DiagnosisScreen { id, report: None, cancel });
+            }
+
+            AppEvent::Diagnosed(id, report) => {
+                if let Some(screen) = self.diagnosis.as_mut().filter(|s| s.id == id) {
+                    screen.report = Some(report);
+                }
+            }
+
+            AppEvent::CopyDiagnosis => {
+                let Some(report) = self.diagnosis.as_ref().and_then(|s| s.report.as_ref()) else {
+                    return;
+                };
+                match clipboard::copy(&report.report()) {
+                    Ok(()) => self.set_notice("Copied diagnosis to clipboard", NoticeLevel::Info),
+                    Err(e) => self.set_notice(format!("Clipboard copy failed: {}", e), NoticeLevel::Error),
+                }
+            }
+
+            AppEvent::CloseDiagnosis => {
+                if let Some(screen) = self.diagnosis.take() {
+                    screen.cancel.cancel();
+                }
+            }
+
+            AppEvent::UpdateYtdlp => {
+                self.update_ytdlp();
+            }
+
+            AppEvent::YtdlpVersionChecked(version) => {
+                match &version {
+                    Some(version) => {
+                        self.ytdlp_version = version.clone();
+                        self.set_notice(format!("yt-dlp is now {}", version), NoticeLevel::Info);
+                    }
+                    None => self.set_notice("yt-dlp doesn't run after the update, press 'H' to check", NoticeLevel::Error),
+                }
+                if let Some(screen) = &mut self.ytdlp_update {
+                    screen.version = version;
+                }
+            }
+
+            // The update carries on in the worker; its result still shows as a notice
+            AppEvent::CloseYtdlpUpdate => {
+                self.ytdlp_update = None;
+            }
+
+            AppEvent::CloseHealthCheck => {
+                // Nothing works without yt-dlp, so that one can't be waved away
+                let blocked = self.health.as_ref().is_none_or(|s| s.report.as_ref().is_none_or(|r| r.is_blocked()));
+                if !blocked {
+                    self.health = None;
+                }
+            }
+
+            AppEvent::Suspend => {
+                self.should_suspend = true;
+            }
+
+            AppEvent::CancelQuit => {
+                self.confirm_quit = false;
+            }
+
+            AppEvent::ConfirmQuit => {
+                let _ = self.worker_tx.try_send(WorkerCommand::Shutdown);
+                self.should_quit = true;
+            }
+
+            AppEvent::Worker(WorkerEvent::SlotQueueChanged { waiting }) => {
+                for (i, id) in waiting.iter().enumerate() {
+                    if let Some(job) = self.jobs.iter_mut().find(|j| j.id == *id) {
+                        if matches!(job.status, JobStatus::Starting | JobStatus::WaitingForSlot { .. }) {
+                            job.status = JobStatus::WaitingForSlot { position: i + 1 };
+                        }
+                    }
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::TitleResolved { id, title }) => {
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id && j.title.is_none()) {
+                    job.title = Some(title);
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::StartAccepted { id }) => {
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    job.start_sent = None;
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::JobStarted { id }) => {
+                tracing::debug!("Download started");
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    job.status = JobStatus::Downloading {
+                        percent: 0.0,
+                        speed: "--".into(),
+                        speed_bps: None,
+                        eta: "--".into(),
+                        phase: DownloadPhase::Video,
+                        total: None,
+                    };
+                    job.thumbnail_issue = None;
+                    job.thumbnail_path = None;
+                    job.sidecars.clear();
+                    job.speed = SpeedStats::default();
+                    job.warnings.clear();
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::JobSpawned { id, pid }) => {
+                tracing::debug!("yt-dlp running as pid {}", pid);
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    job.pid = Some(pid);
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::FormatsReady { id, title, formats, metadata }) => {
+                // A fetch can finish just as it is cancelled; the cancel wins
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id && j.status != JobStatus::Cancelled) {
+                    job.title = Some(title);
+                    job.live = metadata.is_live == Some(true);
+                    job.metadata = metadata;
+                    if formats.is_empty() {
+                        job.status = JobStatus::Failed(Failure::new("No formats found"));
+                    } else {
+                        job.formats = formats.into();
+                        job.status = JobStatus::Ready;
+                        if let Some(preset) = job.pending_preset.take() {
+                            let applied = job.apply_preset(preset);
+                            self.preset_tally.record(applied);
+                        } else if self.config.channel_formats != ChannelFormats::Off {
+                            if let Some((format, extract_audio)) = self.channels.choice_for(job) {
+                                let format = format.clone();
+                                job.selected_format = Some(format);
+                                job.extract_audio = extract_audio;
+                                job.from_channel = true;
+                                if self.config.channel_formats == ChannelFormats::Apply {
+                                    job.status = JobStatus::Queued;
+                                }
+                            }
+                        }
+                    }
+                }
+                self.report_presets();
+                self.resume_triage();
+                if self.awaiting_formats == Some(id) {
+                    self.awaiting_formats = None;
+                    let selected = self.selected_job().is_some_and(|j| j.id == id);
+                    if selected && AppContext::from_app(self).overlay.is_none() {
+                        self.handle_event(AppEvent::OpenFormatPopup);
+                    }
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::JobProgress { id, percent, speed, speed_bps, eta, phase, total }) => {
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    // A tick sent before the cancel reached yt-dlp must not bring the job back
+                    if job.status == JobStatus::Cancelled {
+                        match &mut job.partial {
+                            Some(partial) => partial.observe(percent, total),
+                            None => job.partial = PartialProgress::of(&JobStatus::Downloading { percent, speed, speed_bps, eta, phase, total }),
+                        }
+                    } else {
+                        job.speed.observe(Instant::now(), phase, speed_bps);
+                        job.status = JobStatus::Downloading { percent, speed, speed_bps, eta, phase, total };
+                    }
+                }
+                let fastest = self
+                    .jobs
+                    .iter()
+                    .filter_map(|j| match j.status {
+                        JobStatus::Downloading { speed_bps, .. } => speed_bps,
+                        _ => None,
+                    })
+                    .max();
+                self.rate_cap.observe(Instant::now(), fastest, self.config.rate_limit_bytes());
+            }
+
+            AppEvent::Worker(WorkerEvent::JobCompleted { id, path }) => {
+                tracing::info!("Download completed: {}", path.display());
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    job.status = JobStatus::Completed;
+                    job.output_size = std::fs::metadata(&path).ok().map(|m| m.len());
+                    job.speed.finish(job.output_size);
+                    job.output_path = Some(path.clone());
+                    job.pid = None;
+                    job.formats = Arc::default();
+                    self.last_completed = Some(job.display_name().to_string());
+                    if self.config.notifications && !self.config.do_not_disturb {
+                        self.completions.push(Instant::now(), job.display_name().to_string());
+                    }
+
+                    let entry = HistoryEntry {
+                        url: job.url.clone(),
+                        title: job.title.clone(),
+                        path,
+                        completed_at: chrono::Local::now(),
+                        record: job.record.clone(),
+                        media_kind: job.media_kind(),
+                        note: job.note.clone(),
+                        speed: job.speed.avg_bps.map(|_| job.speed),
+                    };
+                    tokio::spawn(async move {
+                        if let Err(e) = history::append(&entry).await {
+                            tracing::warn!("Failed to write history: {}", e);
+                        }
+                    });
+                } else if let Some(job) = self.trashed_job_mut(id) {
+                    job.status = JobStatus::Completed;
+                    job.output_size = std::fs::metadata(&path).ok().map(|m| m.len());
+                    job.output_path = Some(path);
+                    job.pid = None;
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::Coalesced { id, with }) => {
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    job.linked_to = Some(with);
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::JobWarning { id, warning }) => {
+                const MAX_WARNINGS: usize = 20;
+                tracing::warn!("yt-dlp: {}", warning);
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    if job.warnings.len() < MAX_WARNINGS && !job.warnings.contains(&warning) {
+                        job.warnings.push(warning);
+                    }
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::ThumbnailNotEmbedded { id, reason }) => {
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    job.thumbnail_issue = Some(reason);
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::ThumbnailSaved { id, path }) => {
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    job.thumbnail_path = Some(path);
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::SidecarsSaved { id, paths }) => {
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    job.sidecars = paths;
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::JobFailed { id, error }) => {
+                tracing::warn!("Job failed: {}", error);
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    job.pid = None;
+                    job.pending_preset = None;
+                    // Failures are never batched; each one may need attention
+                    if self.config.notifications && !self.config.do_not_disturb {
+                        notify::send(&format!("Download failed ({}): {}", error.kind.label(), job.display_name()), &error.message);
+                    }
+                    job.status = JobStatus::Failed(error);
+                    if job.title.is_none() && !job.is_playlist {
+                        let command = WorkerCommand::ResolveTitle { job_id: id, url: job.url.clone() };
+                        if self.worker_tx.try_send(command).is_err() {
+                            tracing::warn!("Worker channel full: ResolveTitle dropped");
+                        }
+                    }
+                } else if let Some(job) = self.trashed_job_mut(id) {
+                    job.pid = None;
+                    job.status = JobStatus::Failed(error);
+                }
+                self.report_presets();
+                self.resume_triage();
+            }
+
+            AppEvent::ToggleSettings => {
+                if self.settings_popup.is_some() {
+                    self.settings_popup = None;
+                } else {
+                    self.settings_popup = Some(SettingsState::new(&self.config));
+                }
+            }
+
+            AppEvent::OpenLogFile => {
+                if let Err(e) = open::that_detached(config::current_log_file()) {
+                    self.set_notice(format!("Could not open log file: {}", e), NoticeLevel::Error);
+                }
+            }
+
+            AppEvent::ClearOldLogs => {
+                match config::clear_old_logs(self.config.log_retention_days) {
+                    Ok(removed) => self.set_notice(
+                        format!("Removed {} log files older than {} days", removed, self.config.log_retention_days),
+                        NoticeLevel::Info,
+                    ),
+                    Err(e) => self.set_notice(format!("Could not clear logs: {}", e), NoticeLevel::Error),
+                }
+            }
+
+            AppEvent::CloseSettings => {
+                self.settings_popup = None;
+            }
+
+            AppEvent::SettingsNext => {
+                if let Some(ref mut settings) = self.settings_popup {
+                    settings.selected_field = (settings.selected_field + 1).min(SettingsField::ALL.len() - 1);
+                    settings.editing = false;
+                }
+            }
+
+            AppEvent::SettingsPrev => {
+                if let Some(ref mut settings) = self.settings_popup {
+                    settings.selected_field = settings.selected_field.saturating_sub(1);
+                    settings.editing = false;
+                }
+            }
+
+            AppEvent::SettingsIncrement => {
+                if let Some(ref mut settings) = self.settings_popup {
+                    match settings.field() {
+                        SettingsField::ConcurrentDownloads => {
+                            settings.concurrent_downloads = (settings.concurrent_downloads + 1).min(10);
+                        }
+                        SettingsField::EmbedSubs => settings.embed_subs = !settings.embed_subs,
+                        SettingsField::EmbedMetadata => settings.embed_metadata = !settings.embed_metadata,
+                        SettingsField::UseNetrc => settings.use_netrc = !settings.use_netrc,
+                        SettingsField::CookiesBrowser => settings.cycle_browser(1),
+                        SettingsField::RateLimit => settings.step_rate_limit(true),
+                        _ => {}
+                    }
+                }
+            }
+
+            AppEvent::SettingsDecrement => {
+                if let Some(ref mut settings) = self.settings_popup {
+                    match settings.field() {
+                        SettingsField::ConcurrentDownloads => {
+                            settings.concurrent_downloads = settings.concurrent_downloads.saturating_sub(1).max(1);
+                        }
+                        SettingsField::EmbedSubs => settings.embed_subs = !settings.embed_subs,
+                        SettingsField::EmbedMetadata => settings.embed_metadata = !settings.embed_metadata,
+                        SettingsField::UseNetrc => settings.use_netrc = !settings.use_netrc,
+                        SettingsField::CookiesBrowser => settings.cycle_browser(-1),
+                        SettingsField::RateLimit => settings.step_rate_limit(false),
+                        _ => {}
+                    }
+                }
+            }
+
+            AppEvent::SettingsToggleEdit => {
+                if let Some(ref mut settings) = self.settings_popup {
+                    if settings.field().is_text() {
+                        settings.editing = !settings.editing;
+                    }
+                }
+            }
+
+            AppEvent::SettingsCharInput(c) => {
+                if let Some(ref mut settings) = self.settings_popup {
+                    if let Some(text) = settings.editing_text_mut() {
+                        text.push(c);
+                    }
+                }
+            }
+
+            AppEvent::SettingsBackspace => {
+                if let Some(ref mut settings) = self.settings_popup {
+                    if let Some(text) = settings.editing_text_mut() {
+                        text.pop();
+                    }
+                }
+            }
+
+            AppEvent::ReviewSettings => {
+                let Some(settings) = &mut self.settings_popup else {
+                    return;
+                };
+                settings.editing = false;
+                if settings.changes(&self.config).is_empty() {
+                    self.settings_popup = None;
+                    self.set_notice("No settings changed", NoticeLevel::Info);
+                } else {
+                    settings.reviewing = true;
+                }
+            }
+
+            AppEvent::CancelSettingsReview => {
+                if let Some(settings) = &mut self.settings_popup {
+                    settings.reviewing = false;
+                }
+            }
+
+            AppEvent::SaveSettings => {
+                if let Some(settings) = self.settings_popup.take() {
+                    self.config.max_concurrent_downloads = settings.concurrent_downloads;
+                    self.config.rate_limit = (!settings.rate_limit.is_empty()).then(|| settings.rate_limit.clone());
+                    self.config.output_dir = std::path::PathBuf::from(&settings.output_dir);
+                    let cookies_file = settings.cookies_file.trim();
+                    self.config.cookies_file = (!cookies_file.is_empty()).then(|| std::path::PathBuf::from(cookies_file));
+                    self.config.cookies_from_browser = (!settings.cookies_from_browser.is_empty()).then(|| settings.cookies_from_browser.clone());
+                    let username = settings.username.trim();
+                    self.config.username = (!username.is_empty()).then(|| username.to_string());
+                    self.config.password = (!settings.password.0.is_empty()).then(|| settings.password.clone());
+                    self.config.use_netrc = settings.use_netrc;
+                    self.config.embed_subs = settings.embed_subs;
+                    self.config.embed_metadata = settings.embed_metadata;
+                    let ytdlp_command: Vec<String> = settings.ytdlp_command.split_whitespace().map(String::from).collect();
+                    if !ytdlp_command.is_empty() {
+                        self.config.ytdlp_command = ytdlp_command;
+                    }
                     
                     if self.worker_tx.try_send(WorkerCommand::UpdateConcurrent(settings.concurrent_downloads)).is_err() {
                         tracing::warn!("Failed to send UpdateConcurrent command");
                     }
-                    
-                    let config = self.config.clone();
-                    tokio::spawn(async move {
-                        if let Err(e) = config.save().await {
-                            tracing::warn!("Failed to save config: {}", e);
+                    self.apply_config();
+                }
+            }
+
+            AppEvent::ShowCommand => {
+                if let Some(request) = self.selected_job().and_then(|j| j.start_request(&self.config)) {
+                    let args = worker::download_args(&request, &self.config);
+                    self.command_popup = Some(worker::command_line(&self.config, &args));
+                }
+            }
+
+            AppEvent::CloseCommandPopup => {
+                self.command_popup = None;
+            }
+
+            AppEvent::CopyCommand => {
+                let Some(record) = self.selected_job().and_then(|j| j.record.clone()) else {
+                    return;
+                };
+                match clipboard::copy(&record.command) {
+                    // Recorded before secrets were left out instead of masked
+                    Ok(()) if record.command.contains("********") => self.set_notice("Copied yt-dlp command; replace the ******** secrets to run it", NoticeLevel::Info),
+                    Ok(()) if record.left_out.is_empty() => self.set_notice("Copied yt-dlp command to clipboard", NoticeLevel::Info),
+                    Ok(()) => self.set_notice(format!("Copied yt-dlp command without {}; add them back to run it", record.left_out.join(", ")), NoticeLevel::Info),
+                    Err(e) => self.set_notice(format!("Clipboard copy failed: {}", e), NoticeLevel::Error),
+                }
+            }
+
+            AppEvent::AutosaveFailed { error } => {
+                self.set_notice(format!("Queue autosave failed: {}", error), NoticeLevel::Error);
+            }
+
+            AppEvent::Worker(WorkerEvent::ConcurrencyChanged { limit, max }) => {
+                self.throttled_limit = (limit < max).then_some((limit, max));
+            }
+
+            AppEvent::Worker(WorkerEvent::PlaylistProgress { id, found }) => {
+                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                    if let JobStatus::ExpandingPlaylist { found: count } = &mut job.status {
+                        *count = found;
+                    }
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::PlaylistFailed { id, url, error }) => {
+                tracing::warn!("Playlist expansion failed: {}", error);
+                let placeholder = self
+                    .jobs
+                    .iter_mut()
+                    .find(|j| j.id == id && matches!(j.status, JobStatus::ExpandingPlaylist { .. }));
+                if let Some(job) = placeholder {
+                    job.status = JobStatus::Failed(Failure::new(error.clone()));
+                    self.set_notice(format!("Could not load playlist {}: {}", url, error), NoticeLevel::Error);
+                }
+            }
+
+            AppEvent::Worker(WorkerEvent::PlaylistExpanded { id, url: playlist_url, title: playlist_title, urls }) => {
+                // Gone or cancelled while loading
+                let Some(index) = self
+                    .jobs
+                    .iter()
+                    .position(|j| j.id == id && matches!(j.status, JobStatus::ExpandingPlaylist { .. }))
+                else {
+                    return;
+                };
+                if urls.is_empty() {
+                    self.jobs[index].status = JobStatus::Failed(Failure::new("No videos found in playlist"));
+                    return;
+                }
+
+                // The placeholder's own slot goes to the first entry
+                let room = self.queue_room().saturating_add(1);
+                let found = urls.len();
+                let mut urls = urls;
+                urls.truncate(room);
+                if urls.len() < found {
+                    self.set_notice(
+                        format!("Playlist cut to {} of {} videos: the queue holds at most {} items", urls.len(), found, self.config.max_queue_size),
+                        NoticeLevel::Error,
+                    );
+                }
+
+                let group = JobGroup::new(playlist_title.unwrap_or_else(|| playlist_url.clone()), playlist_url);
+                let group_id = group.id;
+                self.groups.push(group);
+
+                let mut expanded = Vec::with_capacity(urls.len());
+                let mut hydrate = Vec::with_capacity(urls.len());
+                for (url, title) in urls {
+                    let mut job = Job::new(&url);
+                    job.title = title;
+                    job.group = Some(group_id);
+                    job.pending_preset = self.jobs[index].pending_preset;
+                    job.status = JobStatus::MetadataPending;
+                    hydrate.push((job.id, url));
+                    expanded.push(job);
+                }
+                // Should even this one be lost, Enter on an entry still asks for it with PromoteFetch
+                if self.worker_tx.try_send(WorkerCommand::HydrateFormats(hydrate)).is_err() {
+                    tracing::warn!("Worker channel full: HydrateFormats dropped");
+                }
+                // Replace the placeholder where it stands so the queue order matches the pastes
+                self.jobs.splice(index..=index, expanded);
+            }
+        }
+    }
+
+    // The rows of the focused list, which is the whole queue unless it is split
+    pub fn queue_rows(&self) -> Vec<QueueRow> {
+        self.pane_rows(self.pane)
+    }
+
+    // Jobs of a group are contiguous, so a header row is emitted whenever a new group
+    // starts. The finished pane is kept condensed: just its jobs, without headers.
+    // A filter lists the matching jobs alone, best match first
+    pub fn pane_rows(&self, pane: QueuePane) -> Vec<QueueRow> {
+        let in_pane = |job: &Job| match (self.split_queue, pane) {
+            (false, _) => true,
+            (true, QueuePane::Active) => !job.is_finished(),
+            (true, QueuePane::Finished) => job.is_finished(),
+        };
+        let jobs = self.jobs.iter().enumerate().filter(|(_, job)| in_pane(job));
+        if let Some(query) = self.filter_query() {
+            let mut matches: Vec<(i32, usize)> = jobs.filter_map(|(i, job)| Some((fuzzy::find(query, job.display_name())?.score, i))).collect();
+            // Equal scores keep their queue order
+            matches.sort_by_key(|&(score, i)| (std::cmp::Reverse(score), i));
+            return matches.into_iter().map(|(_, i)| QueueRow::Job(i)).collect();
+        }
+        if self.split_queue && pane == QueuePane::Finished {
+            return jobs.map(|(i, _)| QueueRow::Job(i)).collect();
+        }
+        let mut rows = Vec::with_capacity(self.jobs.len() + self.groups.len());
+        let mut current_group = None;
+        for (i, job) in jobs {
+            if let Some(group_id) = job.group.filter(|_| job.group != current_group) {
+                rows.push(QueueRow::Group(group_id));
+            }
+            current_group = job.group;
+            rows.push(QueueRow::Job(i));
+        }
+        rows
+    }
+
+    // None until something beyond the exact-match prefix is typed
+    pub fn filter_query(&self) -> Option<&str> {
+        let query = self.queue_filter.as_ref()?.query.trim();
+        (!query.is_empty() && query != "'").then_some(query)
+    }
+
+    // Which characters of the job's title the filter matched, for highlighting
+    pub fn filter_positions(&self, job: &Job) -> Vec<usize> {
+        self.filter_query().and_then(|query| fuzzy::find(query, job.display_name())).map(|m| m.positions).unwrap_or_default()
+    }
+
+    // Selected row and first row on screen of the pane that isn't focused
+    pub fn parked_cursor(&self) -> (usize, usize) {
+        let rows = self.pane_rows(self.pane.other());
+        let found = self.parked.key.and_then(|key| rows.iter().position(|row| self.row_key(*row) == key));
+        let index = found.unwrap_or(self.parked.index).min(rows.len().saturating_sub(1));
+        (index, self.parked.offset.min(index))
+    }
+
+    // sync_selection, run after every event, settles the cursor in the new pane
+    fn switch_pane(&mut self) {
+        let (index, offset) = self.parked_cursor();
+        self.parked = PaneCursor { index: self.selected_index, key: self.selected, offset: self.queue_offset };
+        self.selected_index = index;
+        self.queue_offset = offset;
+        self.selected = self.pane_rows(self.pane.other()).get(index).map(|row| self.row_key(*row));
+        self.pane = self.pane.other();
+    }
+
+    fn select_row(&mut self, index: usize) {
+        self.selected_index = index;
+        self.selected = self.queue_rows().get(index).map(|row| self.row_key(*row));
+    }
+
+    fn row_key(&self, row: QueueRow) -> RowKey {
+        match row {
+            QueueRow::Group(id) => RowKey::Group(id),
+            QueueRow::Job(index) => RowKey::Job(self.jobs[index].id),
+        }
+    }
+
+    // Finds the selected row again after the queue changed. If rows were added or
+    // removed above it, the scroll offset moves by the same amount so it keeps its
+    // place on screen; if it is gone, whatever now sits at its old position takes over
+    fn sync_selection(&mut self) {
+        let rows = self.queue_rows();
+        if rows.is_empty() {
+            self.selected_index = 0;
+            self.selected = None;
+            self.queue_offset = 0;
+            return;
+        }
+
+        let found = self.selected.and_then(|key| rows.iter().position(|row| self.row_key(*row) == key));
+        let index = found.unwrap_or(self.selected_index.min(rows.len() - 1));
+        if found.is_some() {
+            self.queue_offset = (self.queue_offset + index).saturating_sub(self.selected_index);
+        }
+        self.selected_index = index;
+        self.selected = Some(self.row_key(rows[index]));
+
+        let height = self.queue_height.get().max(1);
+        if index < self.queue_offset {
+            self.queue_offset = index;
+        } else if index >= self.queue_offset + height {
+            self.queue_offset = index + 1 - height;
+        }
+        self.queue_offset = self.queue_offset.min(rows.len().saturating_sub(height));
+    }
+
+    pub fn selected_row(&self) -> Option<QueueRow> {
+        self.queue_rows().get(self.selected_index).copied()
+    }
+
+    pub fn selected_job_index(&self) -> Option<usize> {
+        match self.selected_row()? {
+            QueueRow::Job(i) => Some(i),
+            QueueRow::Group(_) => None,
+        }
+    }
+
+    // Once no job is waiting on a preset any more, sums up how they went
+    fn report_presets(&mut self) {
+        if self.preset_tally.is_empty() || self.jobs.iter().any(|j| j.pending_preset.is_some()) {
+            return;
+        }
+        let tally = std::mem::take(&mut self.preset_tally);
+        let text = match tally.missed {
+            0 => format!("Queued {} jobs with the chosen preset", tally.applied),
+            missed => format!(
+                "Queued {} jobs with the chosen preset; {} had no matching format and are Ready",
+                tally.applied, missed
+            ),
+        };
+        let level = if tally.missed > 0 { NoticeLevel::Error } else { NoticeLevel::Info };
+        self.set_notice(text, level);
+    }
+
+    // Sorts within each playlist and among the loose jobs, each keeping the slots
+    // it already had, so playlists stay together. Jobs without a date go last
+    fn sort_by_upload_date(&mut self) {
+        let newest_first = self.sort_newest_first;
+        self.sort_newest_first = !newest_first;
+
+        let mut scopes: Vec<Option<GroupId>> = Vec::new();
+        for job in &self.jobs {
+            if !scopes.contains(&job.group) {
+                scopes.push(job.group);
+            }
+        }
+        for scope in scopes {
+            let slots: Vec<usize> = (0..self.jobs.len()).filter(|&i| self.jobs[i].group == scope).collect();
+            let mut jobs: Vec<Job> = slots.iter().map(|&i| self.jobs[i].clone()).collect();
+            jobs.sort_by(|a, b| match (&a.metadata.upload_date, &b.metadata.upload_date) {
+                (Some(a), Some(b)) if newest_first => b.cmp(a),
+                (Some(a), Some(b)) => a.cmp(b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            });
+            for (slot, job) in slots.into_iter().zip(jobs) {
+                self.jobs[slot] = job;
+            }
+        }
+
+        let order = if newest_first { "newest" } else { "oldest" };
+        self.set_notice(format!("Sorted by upload date, {} first", order), NoticeLevel::Info);
+    }
+
+    // A plain-words reason for a job sitting in Queued, Starting or Waiting
+    pub fn start_diagnosis(&self, job: &Job) -> Option<String> {
+        let prerequisite = job.after.and_then(|id| self.jobs.iter().enumerate().find(|(_, j)| j.id == id && j.status != JobStatus::Completed));
+        if let (Some((position, before)), JobStatus::WaitingForSlot { .. }) = (prerequisite, &job.status) {
+            return Some(format!("Waiting for item {} ({}) to finish", position + 1, before.display_name()));
+        }
+        match &job.status {
+            JobStatus::Queued => match &job.start_issue {
+                Some(issue) => Some(format!("Not started: {}", issue)),
+                None if prerequisite.is_some() => prerequisite.map(|(position, before)| {
+                    format!("Will download after item {} ({})", position + 1, before.display_name())
+                }),
+                None if self.large_downloads.as_ref().is_some_and(|s| s.jobs.iter().any(|(id, _)| *id == job.id)) => {
+                    Some("Not started: waiting for the large download prompt to be answered".into())
+                }
+                None => None,
+            },
+            JobStatus::Starting => Some(match job.start_sent {
+                Some(sent) => format!("Start command not yet acknowledged ({}s)", sent.elapsed().as_secs()),
+                None => "Accepted by the worker, launching yt-dlp".into(),
+            }),
+            JobStatus::WaitingForSlot { .. } if self.queue_halted.is_some() => {
+                Some("Queue paused after repeated failures: press 'P' for options".into())
+            }
+            JobStatus::WaitingForSlot { .. } => {
+                let active = self.jobs.iter().filter(|j| matches!(j.status, JobStatus::Downloading { .. })).count();
+                let limit = match self.throttled_limit {
+                    Some((limit, _)) => format!("{} (reduced after throttling)", limit),
+                    None => self.config.max_concurrent_downloads.to_string(),
+                };
+                Some(format!("Waiting for a download slot ({} active / limit {})", active, limit))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn flush_notifications(&mut self, now: Instant) {
+        if let Some((summary, body)) = self.completions.flush(now) {
+            notify::send(&summary, &body);
+        }
+    }
+
+    pub fn has_active_downloads(&self) -> bool {
+        self.jobs.iter().any(|j| matches!(j.status, JobStatus::Starting | JobStatus::Downloading { .. }))
+    }
+
+    // How many more jobs fit under max_queue_size
+    pub fn queue_room(&self) -> usize {
+        self.config.max_queue_size().map_or(usize::MAX, |max| max.saturating_sub(self.jobs.len()))
+    }
+
+    // False, with a notice counting every link dropped so far, once the queue is full
+    fn admit_link(&mut self) -> bool {
+        if self.queue_room() > 0 {
+            self.dropped_adds = 0;
+            return true;
+        }
+        self.dropped_adds += 1;
+        self.set_notice(
+            format!("Queue is full at {} items: dropped {} link(s)", self.jobs.len(), self.dropped_adds),
+            NoticeLevel::Error,
+        );
+        false
+    }
+
+    pub fn loading_playlists(&self) -> usize {
+        self.jobs.iter().filter(|j| matches!(j.status, JobStatus::ExpandingPlaylist { .. })).count()
+    }
+
+    pub fn is_recoding(&self) -> bool {
+        self.jobs.iter().any(|j| matches!(j.status, JobStatus::Downloading { phase: DownloadPhase::Recoding, .. }))
+    }
+
+    // Whatever draws the spinner: loading playlists, recoding, a yt-dlp update
+    pub fn spinner_running(&self) -> bool {
+        self.loading_playlists() > 0 || self.is_recoding() || self.ytdlp_update.as_ref().is_some_and(|u| u.result.is_none())
+    }
+
+    // The details panel shows "added 5m ago" for the selected job until it is a
+    // day old, then a plain date that needs no redraw
+    pub fn shows_age(&self) -> bool {
+        self.selected_job().is_some_and(|job| (chrono::Local::now() - job.added_at).num_days() < 1)
+    }
+
+    pub fn needs_animation(&self) -> bool {
+        self.spinner_running() || self.notice.is_some() || self.has_active_downloads()
+    }
+
+    pub fn ytdlp_pids(&self) -> impl Iterator<Item = Pid> + '_ {
+        self.jobs.iter().filter_map(|j| j.pid).map(Pid::from_u32)
+    }
+
+    // Only our own process and the yt-dlp children we spawned; walking every
+    // process on the system is what made the panel expensive
+    pub fn refresh_sysinfo(&mut self) {
+        let pids: Vec<Pid> = std::iter::once(Pid::from_u32(std::process::id()))
+            .chain(self.ytdlp_pids())
+            .collect();
+        self.sysinfo.refresh_processes(ProcessesToUpdate::Some(&pids), true);
+    }
+
+    pub fn selected_job(&self) -> Option<&Job> {
+        self.selected_job_index().and_then(|i| self.jobs.get(i))
+    }
+
+    pub fn group(&self, id: GroupId) -> Option<&JobGroup> {
+        self.groups.iter().find(|g| g.id == id)
+    }
+
+    pub fn group_jobs(&self, id: GroupId) -> impl Iterator<Item = &Job> {
+        self.jobs.iter().filter(move |j| j.group == Some(id))
+    }
+
+    // Moving the job out of Queued as soon as the command is sent keeps a repeated
+    // `s` from dispatching the same job twice; the worker reports WaitingForSlot
+    // positions if it has to hold the job back
+    fn start_job(&mut self, index: usize) {
+        let Some(job) = self.jobs.get(index) else {
+            return;
+        };
+        if job.status != JobStatus::Queued {
+            return;
+        }
+        // Checked again here since titles and settings may have changed since it was queued
+        if let Some(&earlier) = name_clashes(&self.jobs, &self.config).get(&job.id) {
+            let template = suffixed_template(job, &self.jobs, &self.config);
+            let text = format!("{} would overwrite item {}, saving it as {}", job.display_name(), self.job_number(earlier), template);
+            self.jobs[index].output_template = Some(template);
+            self.set_notice(text, NoticeLevel::Info);
+            self.refresh_name_clashes();
+        }
+        let job = &self.jobs[index];
+        let target = job.planned_target(&self.config);
+        if let (Some(needed), Some(free)) = (job.required_space(&self.config), available_space(&target.dir)) {
+            if needed > free {
+                let text = format!(
+                    "Not enough disk space for {}: needs {}, {} free",
+                    job.display_name(),
+                    format::size(needed),
+                    format::size(free)
+                );
+                self.set_notice(text, NoticeLevel::Error);
+                self.jobs[index].start_issue = Some(format!("not enough disk space: needs {}, {} free", format::size(needed), format::size(free)));
+                return;
+            }
+        }
+        if let Some(error) = self.config.cookie_browser_error() {
+            self.set_notice(format!("{} (change it in settings, g)", error), NoticeLevel::Error);
+            self.jobs[index].start_issue = Some(error);
+            return;
+        }
+
+        // Nothing to wait for once the prerequisite is done or gone
+        let unfinished = |id: &JobId| self.jobs.iter().any(|j| j.id == *id && j.status != JobStatus::Completed);
+        let after = self.jobs[index].after.filter(unfinished);
+
+        let job = &mut self.jobs[index];
+        job.output_target = Some(target);
+        let Some(mut request) = job.start_request(&self.config) else {
+            return;
+        };
+        request.after = after;
+        if request.on_conflict.is_none() {
+            request.on_conflict = self.conflict_always;
+        }
+        if self.worker_tx.try_send(WorkerCommand::StartJob(request.clone())).is_err() {
+            tracing::warn!("Worker channel full: StartJob dropped");
+            job.start_issue = Some("the worker was busy and the start was dropped; press 's' again".into());
+            return;
+        }
+        job.status = JobStatus::Starting;
+        job.partial = None;
+        job.resume = false;
+        job.start_issue = None;
+        job.linked_to = None;
+        job.start_sent = Some(Instant::now());
+        let (command, left_out) = worker::runnable_command_line(&self.config, &worker::standalone_args(&request, &self.config));
+        job.record = Some(DownloadRecord {
+            format_spec: request.format_spec.clone(),
+            options: option_summary(&request, &self.config),
+            ytdlp_version: self.ytdlp_version.clone(),
+            command,
+            left_out,
+        });
+    }
+
+    // Bulk starts first look for jobs over the size threshold and, if there are
+    // any, ask once about all of them instead of starting anything
+    // Ready jobs nobody picked a format for can go on the default one, once confirmed
+    fn request_start(&mut self, group: Option<GroupId>) {
+        let unselected: Vec<JobId> = self
+            .jobs
+            .iter()
+            .filter(|j| group.is_none_or(|g| j.group == Some(g)) && j.status == JobStatus::Ready && j.selected_format.is_none())
+            .map(|j| j.id)
+            .collect();
+        if unselected.is_empty() {
+            self.check_large_downloads(group);
+        } else {
+            self.default_format_prompt = Some(DefaultFormatState { jobs: unselected, group });
+        }
+    }
+
+    fn check_large_downloads(&mut self, group: Option<GroupId>) {
+        let in_scope = |j: &Job| group.is_none_or(|g| j.group == Some(g));
+        let large: Vec<(JobId, u64)> = match self.config.large_download_threshold() {
+            Some(threshold) => self
+                .jobs
+                .iter()
+                .filter(|j| in_scope(j) && j.status == JobStatus::Queued)
+                .filter_map(|j| Some((j.id, j.required_space(&self.config)?)))
+                .filter(|(_, size)| *size > threshold)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        if large.is_empty() {
+            self.start_queued(group, &[]);
+        } else {
+            self.large_downloads = Some(LargeDownloadState { jobs: large, group });
+        }
+    }
+
+    fn start_queued(&mut self, group: Option<GroupId>, skip: &[JobId]) {
+        for index in 0..self.jobs.len() {
+            let job = &self.jobs[index];
+            if group.is_none_or(|g| job.group == Some(g)) && !skip.contains(&job.id) {
+                self.start_job(index);
+            }
+        }
+    }
+
+    // An "always" answer settles every blocked job at once. The prompt then moves
+    // on to the next job still waiting, if any
+    fn resolve_conflict(&mut self, id: JobId, policy: OverwritePolicy, always: bool) {
+        let ids: Vec<JobId> = if always {
+            self.conflict_always = Some(policy);
+            self.jobs.iter().filter(|j| matches!(j.status, JobStatus::Blocked { .. })).map(|j| j.id).collect()
+        } else {
+            vec![id]
+        };
+
+        for id in ids {
+            let Some(index) = self.jobs.iter().position(|j| j.id == id) else {
+                continue;
+            };
+            let job = &mut self.jobs[index];
+            let JobStatus::Blocked { path } = &job.status else {
+                continue;
+            };
+            if policy == OverwritePolicy::Skip {
+                job.output_size = std::fs::metadata(path).ok().map(|m| m.len());
+                job.output_path = Some(path.clone());
+                job.status = JobStatus::Completed;
+                job.pid = None;
+                job.formats = Arc::default();
+            } else {
+                job.conflict_choice = Some(policy);
+                job.status = JobStatus::Queued;
+                self.start_job(index);
+            }
+        }
+
+        self.conflict_prompt = self.jobs.iter().find_map(|j| match &j.status {
+            JobStatus::Blocked { path } => Some(ConflictPromptState::new(j.id, path.clone())),
+            _ => None,
+        });
+    }
+
+    fn run_completed_action(&mut self, id: JobId, action: CompletedAction) {
+        let Some(job) = self.jobs.iter().find(|j| j.id == id) else {
+            return;
+        };
+        if !action.is_available(job) {
+            return;
+        }
+        self.completed_menu = None;
+        let path = job.output_path.clone().unwrap_or_default();
+
+        let result: std::io::Result<&str> = match action {
+            CompletedAction::Play => open::that_detached(&path).map(|_| "Opened file"),
+            CompletedAction::OpenFolder => {
+                open::that_detached(path.parent().unwrap_or(&path)).map(|_| "Opened folder")
+            }
+            CompletedAction::CopyPath => clipboard::copy(&path.to_string_lossy()).map(|_| "Copied path to clipboard"),
+            CompletedAction::CopyUrl => clipboard::copy(&job.url).map(|_| "Copied URL to clipboard"),
+            CompletedAction::Redownload => {
+                if let Some(index) = self.jobs.iter().position(|j| j.id == id) {
+                    self.jobs[index].status = JobStatus::Queued;
+                    self.start_job(index);
+                }
+                return;
+            }
+            CompletedAction::DeleteFile => std::fs::remove_file(&path).map(|_| "Deleted file"),
+            CompletedAction::Remove => {
+                self.handle_event(AppEvent::RemoveJob(id));
+                return;
+            }
+        };
+
+        match result {
+            Ok(message) => self.set_notice(message, NoticeLevel::Info),
+            Err(e) => self.set_notice(format!("{} failed: {}", action.label(), e), NoticeLevel::Error),
+        }
+    }
+
+    pub fn count_matching(&self, filter: RemoveFilter) -> usize {
+        self.jobs.iter().filter(|j| filter.matches(&j.status)).count()
+    }
+
+    fn remove_by_status(&mut self, filter: RemoveFilter) {
+        let count = self.trash_where(|j| filter.matches(&j.status));
+        if count == 0 {
+            self.set_notice(format!("No {} items to remove", filter.label()), NoticeLevel::Info);
+            return;
+        }
+        self.report_presets();
+        self.set_notice(
+            format!("Removed {} {} item(s), press 'u' to undo", count, filter.label()),
+            NoticeLevel::Info,
+        );
+    }
+
+    // Moves the matching jobs to the trash as one removal and returns how many went
+    fn trash_where(&mut self, matches: impl Fn(&Job) -> bool) -> usize {
+        let mut removed = Vec::new();
+        let mut kept = Vec::with_capacity(self.jobs.len());
+        for (index, job) in std::mem::take(&mut self.jobs).into_iter().enumerate() {
+            if matches(&job) {
+                removed.push((index, job));
+            } else {
+                kept.push(job);
+            }
+        }
+        self.jobs = kept;
+        if removed.is_empty() {
+            return 0;
+        }
+
+        // Fetches still running for removed jobs would only be ignored; stop them
+        for (_, job) in &removed {
+            if job.is_fetching()
+                && self.worker_tx.try_send(WorkerCommand::CancelJob(job.id)).is_err()
+            {
+                tracing::warn!("Worker channel full: CancelJob dropped");
+            }
+        }
+        let jobs = &self.jobs;
+        let emptied: Vec<JobGroup> = self.groups.iter().filter(|g| !jobs.iter().any(|j| j.group == Some(g.id))).cloned().collect();
+        self.prune_groups();
+        if self.conflict_prompt.as_ref().is_some_and(|p| removed.iter().any(|(_, j)| j.id == p.job_id)) {
+            self.conflict_prompt = None;
+        }
+
+        let count = removed.len();
+        let batch = self.next_batch;
+        self.next_batch += 1;
+        for (index, job) in removed {
+            let group = job.group.and_then(|id| emptied.iter().find(|g| g.id == id)).cloned();
+            self.trash.push(TrashedJob { index, job, group, batch });
+        }
+        if self.trash.len() > TRASH_CAPACITY {
+            let excess = self.trash.len() - TRASH_CAPACITY;
+            self.trash.drain(..excess);
+        }
+        count
+    }
+
+    // Takes back the last removal still in the trash
+    fn undo_remove(&mut self) {
+        let Some(batch) = self.trash.last().map(|t| t.batch) else {
+            self.set_notice("Nothing to undo", NoticeLevel::Info);
+            return;
+        };
+        let entries: Vec<usize> = (0..self.trash.len()).filter(|&i| self.trash[i].batch == batch).collect();
+        let count = self.restore_trashed(&entries);
+        self.set_notice(format!("Restored {} item(s)", count), NoticeLevel::Info);
+    }
+
+    // Puts trash entries back at the positions they had, as far as the queue still
+    // has them. Jobs that were mid-fetch lost their fetch on removal and get a new one
+    fn restore_trashed(&mut self, entries: &[usize]) -> usize {
+        let mut taken = Vec::new();
+        for &i in entries.iter().rev() {
+            if i < self.trash.len() {
+                taken.push(self.trash.remove(i));
+            }
+        }
+        taken.sort_by_key(|t| t.index);
+
+        let count = taken.len();
+        let mut refetch = Vec::new();
+        for entry in taken {
+            if let Some(group) = entry.group {
+                if !self.groups.iter().any(|g| g.id == group.id) {
+                    self.groups.push(group);
+                }
+            }
+            if entry.job.is_fetching() {
+                refetch.push(entry.job.id);
+            }
+            let at = entry.index.min(self.jobs.len());
+            self.jobs.insert(at, entry.job);
+        }
+        for id in refetch {
+            self.retry_job(id);
+        }
+        self.report_presets();
+        self.autosave.mark_dirty();
+        count
+    }
+
+    fn clamp_trash_view(&mut self) {
+        let count = self.trash.len();
+        if let Some(view) = &mut self.trash_view {
+            view.selected = view.selected.min(count.saturating_sub(1));
+        }
+    }
+
+    // The trash index of the entry shown at `selected`; the view lists newest first
+    fn trash_index(&self, selected: usize) -> Option<usize> {
+        self.trash.len().checked_sub(selected + 1)
+    }
+
+    // A download or fetch that outlived its job's removal still finishes; the trashed copy keeps the result
+    fn trashed_job_mut(&mut self, id: JobId) -> Option<&mut Job> {
+        self.trash.iter_mut().map(|t| &mut t.job).find(|j| j.id == id)
+    }
+
+    fn run_halt_action(&mut self, action: HaltAction) {
+        let command = match action {
+            HaltAction::Resume => {
+                self.queue_halted = None;
+                self.set_notice("Queue resumed", NoticeLevel::Info);
+                WorkerCommand::ResumeQueue
+            }
+            HaltAction::Probe => {
+                if !self.jobs.iter().any(|j| matches!(j.status, JobStatus::WaitingForSlot { .. })) {
+                    self.set_notice("No waiting download to try", NoticeLevel::Error);
+                    return;
+                }
+                self.set_notice("Trying one download; the queue resumes if it works", NoticeLevel::Info);
+                WorkerCommand::ProbeQueue
+            }
+            HaltAction::UpdateYtdlp => {
+                self.halt_prompt = None;
+                self.update_ytdlp();
+                return;
+            }
+        };
+        self.halt_prompt = None;
+        if self.worker_tx.try_send(command).is_err() {
+            tracing::warn!("Worker channel full: {:?} dropped", action);
+        }
+    }
+
+    // The worker holds back new downloads until the update has finished
+    fn update_ytdlp(&mut self) {
+        if self.ytdlp_update.as_ref().is_some_and(|u| u.result.is_none()) {
+            return;
+        }
+        if self.worker_tx.try_send(WorkerCommand::UpdateYtdlp).is_err() {
+            tracing::warn!("Worker channel full: UpdateYtdlp dropped");
+            return;
+        }
+        self.ytdlp_update = Some(YtdlpUpdateState {
+            command: self.config.ytdlp_update_words().join(" "),
+            ..Default::default()
+        });
+    }
+
+    fn save_channels(&self) {
+        let channels = self.channels.clone();
+        tokio::spawn(async move {
+            if let Err(e) = channels.save().await {
+                tracing::warn!("Failed to save remembered channels: {}", e);
+            }
+        });
+    }
+
+    // Hands the current config to the worker and saves it
+    // The writer passes it on to the worker too, so jobs started from now on use it
+    fn apply_config(&mut self) {
+        let Some(config_tx) = &self.config_tx else { return };
+        if config_tx.send(ConfigMessage::Save(Box::new(self.config.clone()))).is_err() {
+            tracing::warn!("Config writer stopped: settings not saved");
+        }
+    }
+
+    // Takes on the settings in effect. Edits the writer turned down are dropped
+    // here, and a reload or a save still on its way is picked up when it lands
+    fn sync_config(&mut self) {
+        let Some(config_rx) = &self.config_rx else { return };
+        let config = Config::clone(&config_rx.borrow());
+        if config.max_concurrent_downloads != self.config.max_concurrent_downloads
+            && self.worker_tx.try_send(WorkerCommand::UpdateConcurrent(config.max_concurrent_downloads)).is_err()
+        {
+            tracing::warn!("Failed to send UpdateConcurrent command");
+        }
+        self.config = config;
+    }
+
+    // Runs a job again with its existing format selection, or refetches formats
+    // if it failed before there was one
+    fn cookie_browser_index(&self) -> usize {
+        self.config
+            .cookies_from_browser
+            .as_deref()
+            .and_then(|b| COOKIE_BROWSERS.iter().position(|known| *known == b))
+            .unwrap_or(0)
+    }
+
+    pub fn members_only_jobs(&self) -> impl Iterator<Item = &Job> {
+        self.jobs
+            .iter()
+            .filter(|j| matches!(&j.status, JobStatus::Failed(e) if e.kind == DownloadError::MembersOnly))
+    }
+
+    fn load_session(&mut self, path: &std::path::Path, session: Session, choice: LoadChoice) {
+        if choice != LoadChoice::Merge {
+            // Anything the worker is busy with for the old queue would only report into the void
+            for job in self.jobs.iter().filter(|j| j.is_fetching() || (j.is_started() && j.status != JobStatus::Completed)) {
+                if self.worker_tx.try_send(WorkerCommand::CancelJob(job.id)).is_err() {
+                    tracing::warn!("Worker channel full: CancelJob dropped");
+                }
+            }
+            self.jobs.clear();
+            self.groups.clear();
+        }
+        if choice == LoadChoice::ReplaceWithSettings {
+            session.settings.apply(&mut self.config);
+            self.apply_config();
+        }
+
+        let (incoming, skipped) = session.incoming(&self.jobs);
+        let count = incoming.jobs.len();
+        let commands = self.restore(incoming);
+        self.send_restored(commands);
+        let mut notice = format!("Loaded {} items from {}", count, path.display());
+        if skipped > 0 {
+            notice.push_str(&format!(", skipped {} already in the queue", skipped));
+        }
+        self.set_notice(notice, NoticeLevel::Info);
+        self.autosave.mark_dirty();
+    }
+
+    fn add_playlist(&mut self, url: &str, items: Option<String>) {
+        if !self.admit_link() {
+            return;
+        }
+        let mut job = Job::playlist_placeholder(url, items);
+        // Left as is, the placeholder would spin forever
+        if self.worker_tx.try_send(job.fetch_playlist_command()).is_err() {
+            tracing::warn!("Worker channel full: FetchPlaylist dropped");
+            job.status = JobStatus::Failed(Failure::new("Too busy to load the playlist, try again"));
+        }
+        self.jobs.push(job);
+    }
+
+    fn add_video(&mut self, job: Job) {
+        let job_id = job.id;
+        let job_url = job.url.clone();
+        self.jobs.push(job);
+        if self.worker_tx.try_send(WorkerCommand::FetchFormats { job_id, url: job_url, credentials: None }).is_err() {
+            tracing::warn!("Worker channel full: FetchFormats dropped");
+        }
+    }
+
+    // Asked first, so a long playlist doesn't flood the queue
+    fn ask_playlist_range(&mut self, url: &str) {
+        self.playlist_range.get_or_insert_with(Default::default).urls.push_back(url.to_string());
+    }
+
+    // Moves the range prompt on to the next pasted playlist, or closes it
+    fn next_playlist_range(&mut self) {
+        if let Some(prompt) = &mut self.playlist_range {
+            prompt.input.clear();
+            prompt.error = None;
+            if prompt.urls.is_empty() {
+                self.playlist_range = None;
+            }
+        }
+    }
+
+    fn retry_job(&mut self, id: JobId) {
+        let Some(index) = self.jobs.iter().position(|j| j.id == id) else {
+            return;
+        };
+        let job = &mut self.jobs[index];
+        if job.has_format() {
+            job.status = JobStatus::Queued;
+            self.start_job(index);
+            return;
+        }
+
+        if job.is_playlist {
+            job.status = JobStatus::ExpandingPlaylist { found: 0 };
+            let command = job.fetch_playlist_command();
+            if self.worker_tx.try_send(command).is_err() {
+                tracing::warn!("Worker channel full: FetchPlaylist dropped");
+            }
+            return;
+        }
+
+        if let Some(error) = self.config.cookie_browser_error() {
+            self.set_notice(format!("{} (change it in settings, g)", error), NoticeLevel::Error);
+            return;
+        }
+        job.status = JobStatus::FetchingFormats;
+        let command = WorkerCommand::FetchFormats {
+            job_id: job.id,
+            url: job.url.clone(),
+            credentials: job.credentials.clone(),
+        };
+        if self.worker_tx.try_send(command).is_err() {
+            tracing::warn!("Worker channel full: FetchFormats dropped");
+        }
+    }
+
+    fn advance_triage(&mut self) {
+        let Some(triage) = &mut self.triage else {
+            return;
+        };
+        if !triage.advance(&self.jobs) {
+            self.handle_event(AppEvent::CloseTriage);
+        }
+    }
+
+    // Picks up jobs whose formats arrived while triage was waiting for them
+    fn resume_triage(&mut self) {
+        if self.triage.as_ref().is_some_and(|t| t.current.is_none()) {
+            self.advance_triage();
+        }
+    }
+
+    fn prune_groups(&mut self) {
+        let jobs = &self.jobs;
+        self.groups.retain(|g| jobs.iter().any(|j| j.group == Some(g.id)));
+    }
+
+    fn run_group_action(&mut self, group_id: GroupId, action: GroupAction) {
+        self.autosave.mark_dirty();
+
+        // Group actions never touch jobs that are already downloading or done
+        let in_group = |j: &Job| j.group == Some(group_id) && !j.is_started();
+
+        match action {
+            GroupAction::SelectFormat => {
+                if let Some(index) = self.jobs.iter().position(|j| in_group(j) && j.can_select_format()) {
+                    let formats = self.jobs[index].formats.clone();
+                    self.format_popup = Some(FormatPopupState::for_group(index, formats, group_id));
+                }
+            }
+            GroupAction::Triage => self.handle_event(AppEvent::StartTriage(Some(group_id))),
+            GroupAction::ToggleAudioExtraction => {
+                let enable = !self.jobs.iter().filter(|j| in_group(j)).all(|j| j.extract_audio);
+                for job in self.jobs.iter_mut().filter(|j| in_group(j)) {
+                    job.extract_audio = enable;
+                    if enable && job.selected_format.is_none() {
+                        if let Some(audio) = job.best_audio_format().cloned() {
+                            job.selected_format = Some(audio);
+                            job.status = JobStatus::Queued;
                         }
-                    });
+                    }
                 }
+                let state = if enable { "on" } else { "off" };
+                self.set_notice(format!("Audio extraction {} for playlist", state), NoticeLevel::Info);
             }
-
-            AppEvent::PlaylistExpanded { urls } => {
-                self.loading_playlists = self.loading_playlists.saturating_sub(1);
-                for (url, title) in urls {
-                    let mut job = Job::new(&url);
-                    job.title = title;
-                    let job_id = job.id;
-                    self.jobs.push(job);
-                    if self.worker_tx.try_send(WorkerCommand::FetchFormats { job_id, url }).is_err() {
-                        tracing::warn!("Worker channel full: FetchFormats dropped");
+            GroupAction::StartAll => self.request_start(Some(group_id)),
+            GroupAction::CancelAll => {
+                for job in self.jobs.iter_mut().filter(|j| in_group(j)) {
+                    if self.worker_tx.try_send(WorkerCommand::CancelJob(job.id)).is_err() {
+                        tracing::warn!("Worker channel full: CancelJob dropped");
+                    }
+                    if let Some(partial) = PartialProgress::of(&job.status) {
+                        job.partial = Some(partial);
                     }
+                    job.status = JobStatus::Cancelled;
                 }
             }
+            GroupAction::RemoveAll => {
+                let count = self.trash_where(in_group);
+                self.set_notice(format!("Moved {} item(s) to trash, press 'u' to undo", count), NoticeLevel::Info);
+            }
         }
     }
 
-    pub fn selected_job(&self) -> Option<&Job> {
-        self.jobs.get(self.selected_index)
-    }
-
     pub fn status_counts(&self) -> StatusCounts {
         self.jobs.iter().fold(StatusCounts::default(), |mut c, j| {
             match &j.status {
-                JobStatus::FetchingFormats => c.fetching += 1,
-                JobStatus::Ready { .. } => c.ready += 1,
-                JobStatus::Queued => c.queued += 1,
+                JobStatus::ExpandingPlaylist { .. } | JobStatus::FetchingFormats | JobStatus::MetadataPending => c.fetching += 1,
+                JobStatus::Ready => c.ready += 1,
+                JobStatus::Queued | JobStatus::Starting | JobStatus::WaitingForSlot { .. } | JobStatus::Blocked { .. } => c.queued += 1,
                 JobStatus::Downloading { .. } => c.active += 1,
                 JobStatus::Completed => c.completed += 1,
                 JobStatus::Failed(_) => c.failed += 1,
@@ -388,6 +3296,38 @@ impl App {
         })
     }
 
+    pub fn status_report(&self) -> StatusReport {
+        let counts = self.status_counts();
+        let downloading = self.jobs.iter().filter_map(|j| match &j.status {
+            JobStatus::Downloading { percent, speed_bps, .. } => Some((*percent, speed_bps.unwrap_or(0))),
+            _ => None,
+        });
+        let (percent_sum, speed_bps) = downloading.fold((0.0, 0), |(p, s), (percent, speed)| (p + percent, s + speed));
+        StatusReport {
+            pid: std::process::id(),
+            active: counts.active,
+            queued: counts.queued,
+            completed: counts.completed,
+            failed: counts.failed,
+            percent: (counts.active > 0).then(|| percent_sum / counts.active as f32),
+            speed_bps,
+            last_completed: self.last_completed.clone(),
+        }
+    }
+
+    pub fn metrics_gauges(&self) -> Gauges {
+        let counts = self.status_counts();
+        Gauges { active: counts.active, queued: counts.queued }
+    }
+
+    pub fn completed_size(&self) -> u64 {
+        self.jobs
+            .iter()
+            .filter(|j| j.status == JobStatus::Completed)
+            .filter_map(|j| j.output_size)
+            .sum()
+    }
+
     pub fn aggregate_progress(&self) -> Option<(f32, String, String)> {
         let downloading: Vec<_> = self.jobs.iter()
             .filter(|j| matches!(j.status, JobStatus::Downloading { .. }))
@@ -406,6 +3346,7 @@ impl App {
                         DownloadPhase::Audio => 50.0 + percent * 0.4,   // 50-90%
                         DownloadPhase::Merging => 90.0 + percent * 0.1, // 90-100%
                         DownloadPhase::Single => *percent,              // 0-100%
+                        DownloadPhase::Recoding => 95.0,                // no progress to go on
                     }
                 } else {
                     0.0
@@ -418,7 +3359,7 @@ impl App {
         // Use most recent job's speed and ETA
         let (speed, eta) = downloading.last()
             .and_then(|j| match &j.status {
-                JobStatus::Downloading { speed, eta, .. } => Some((speed.clone(), eta.clone())),
+                JobStatus::Downloading { speed, speed_bps, eta, .. } => Some((format::speed(*speed_bps, speed), eta.clone())),
                 _ => None,
             })
             .unwrap_or_default();
@@ -427,9 +3368,1032 @@ impl App {
     }
 }
 
+fn option_summary(request: &DownloadRequest, config: &Config) -> Vec<String> {
+    let mut options = Vec::new();
+    if request.extract_audio {
+        options.push(format!("extract audio: {} {}", config.audio_format, request.audio_quality.as_deref().unwrap_or(&config.audio_quality)));
+    }
+    if request.resume == Some(true) {
+        options.push("resumed a partial download".into());
+    }
+    if let Some((start, end)) = &request.sections {
+        options.push(format!("section: {} – {}", start, end));
+    }
+    if request.no_playlist {
+        options.push("playlist: just this video (--no-playlist)".into());
+    }
+    match request.convert.filter(|_| !request.extract_audio) {
+        Some((container, Conversion::Remux)) => options.push(format!("container: remuxed to {}", container.label())),
+        Some((container, Conversion::Recode)) => options.push(format!("container: recoded to {} (slow)", container.label())),
+        None => {}
+    }
+    if request.live {
+        options.push(if request.live_from_start { "live: recorded from the start" } else { "live: recorded from joining" }.into());
+    }
+    if config.embed_subs {
+        options.push(if request.subs_embeddable {
+            "subtitles: embedded".into()
+        } else {
+            "subtitles: separate files (the container can't hold them)".into()
+        });
+    }
+    if config.embed_metadata {
+        options.push("metadata and chapters: embedded".into());
+    }
+    if config.embed_thumbnail {
+        options.push("thumbnail: embedded".into());
+    }
+    if config.write_thumbnail {
+        options.push("thumbnail: image file".into());
+    }
+    if config.write_info_json {
+        options.push("info JSON: written".into());
+    }
+    if config.write_description {
+        options.push("description: written".into());
+    }
+    options
+}
+
+// Progress ticks and pure UI navigation don't change anything worth persisting
+fn marks_queue_dirty(event: &AppEvent) -> bool {
+    matches!(
+        event,
+        AppEvent::AddUrl(_)
+            | AppEvent::ConfirmFormat
+            | AppEvent::CancelJob(_)
+            | AppEvent::RestartCancelled { .. }
+            | AppEvent::RemoveJob(_)
+            | AppEvent::RemoveByStatus(_)
+            | AppEvent::UndoRemove
+            | AppEvent::RestoreTrashed
+            | AppEvent::TriageAssign(_)
+            | AppEvent::TriageRemove
+            | AppEvent::Worker(WorkerEvent::JobStarted { .. })
+            | AppEvent::Worker(WorkerEvent::FormatsReady { .. })
+            | AppEvent::Worker(WorkerEvent::JobCompleted { .. })
+            | AppEvent::Worker(WorkerEvent::JobFailed { .. })
+            | AppEvent::Worker(WorkerEvent::PlaylistExpanded { .. })
+            | AppEvent::Worker(WorkerEvent::PlaylistFailed { .. })
+            | AppEvent::Worker(WorkerEvent::TitleResolved { .. })
+            | AppEvent::RunCompletedAction(..)
+            | AppEvent::Worker(WorkerEvent::ConflictDetected { .. })
+            | AppEvent::ResolveConflict(..)
+            | AppEvent::ConfirmConflictChoice
+            | AppEvent::RetryMembersOnly
+            | AppEvent::SortByUploadDate
+            | AppEvent::SetNote { .. }
+            | AppEvent::ConfirmSections
+            | AppEvent::ConfirmOutput
+            | AppEvent::ConfirmPlaylistRange
+            | AppEvent::LinkAfter
+            | AppEvent::UnlinkAfter
+            | AppEvent::RunDependentsAnyway
+    )
+}
+
+// Free space on the disk holding `dir`, found by the longest matching mount point.
+// The directory may not exist yet, so its nearest existing ancestor is used
+fn available_space(dir: &std::path::Path) -> Option<u64> {
+    let existing = dir.ancestors().find(|p| p.exists())?;
+    let dir = existing.canonicalize().ok()?;
+    let disks = Disks::new_with_refreshed_list();
+    disks
+        .iter()
+        .filter(|d| dir.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| d.available_space())
+}
+
+// A link to one video that also says which playlist it was opened from:
+// watch?v=ID&list=… with the parameters in any order, or youtu.be/ID?list=…
+fn is_ambiguous_playlist_url(url: &str) -> bool {
+    let video = (url.contains("youtube.com/watch") && has_param(url, "v")) || youtu_be_id(url).is_some();
+    video && has_param(url, "list")
+}
+
+// A query parameter that is there with a value
+fn has_param(url: &str, name: &str) -> bool {
+    let url = url.split('#').next().unwrap_or_default();
+    let Some((_, query)) = url.split_once('?') else { return false };
+    query.split('&').any(|param| param.split_once('=').is_some_and(|(key, value)| key == name && !value.is_empty()))
+}
+
+// The video id in the path of a youtu.be link
+fn youtu_be_id(url: &str) -> Option<&str> {
+    let (_, path) = url.split_once("youtu.be/")?;
+    let id = path.split(['?', '#', '/']).next()?;
+    (!id.is_empty() && id != "playlist").then_some(id)
+}
+
+// The video alone, without its playlist; the list is stripped before anything
+// compares URLs so it matches other links to the same video
+fn single_video_job(url: &str) -> Job {
+    let mut job = Job::new(strip_playlist_params(url));
+    job.playlist_skipped = url
+        .split(['?', '&', '#'])
+        .find_map(|param| param.strip_prefix("list="))
+        .map(String::from);
+    job
+}
+
+// Drops the playlist and the position in it, keeping the video and anything else
+fn strip_playlist_params(url: &str) -> String {
+    let (url, fragment) = url.split_once('#').map_or((url, None), |(url, fragment)| (url, Some(fragment)));
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|param| !["list", "index", "start_radio", "pp"].contains(&param.split('=').next().unwrap_or_default()))
+        .collect();
+    let mut stripped = base.to_string();
+    if !kept.is_empty() {
+        stripped.push('?');
+        stripped.push_str(&kept.join("&"));
+    }
+    if let Some(fragment) = fragment {
+        stripped.push('#');
+        stripped.push_str(fragment);
+    }
+    stripped
+}
+
 fn is_playlist_url(url: &str) -> bool {
-    url.contains("youtube.com/playlist") 
+    url.contains("youtube.com/playlist")
         || url.contains("youtu.be/playlist")
-        || (url.contains("youtube.com/watch") && url.contains("&list="))
-        || (url.contains("youtu.be/") && url.contains("?list="))
+        // A list with no video in it is the playlist itself
+        || (url.contains("youtube.com/watch") && has_param(url, "list") && !has_param(url, "v"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{Format, Metadata, OutputTarget, TriagePreset};
+
+    fn app() -> (App, mpsc::Receiver<WorkerCommand>) {
+        let (worker_tx, worker_rx) = mpsc::channel(32);
+        (App::new(Config::default(), worker_tx), worker_rx)
+    }
+
+    fn commands(worker_rx: &mut mpsc::Receiver<WorkerCommand>) -> Vec<WorkerCommand> {
+        std::iter::from_fn(|| worker_rx.try_recv().ok()).collect()
+    }
+
+    // A playlist already expanded into entries whose formats have not come in
+    fn expanded(app: &mut App, worker_rx: &mut mpsc::Receiver<WorkerCommand>, entries: usize) -> Vec<WorkerCommand> {
+        let placeholder = Job::playlist_placeholder("https://www.youtube.com/playlist?list=PLx", None);
+        let id = placeholder.id;
+        app.jobs.push(placeholder);
+        let urls = (0..entries).map(|i| (format!("https://www.youtube.com/watch?v=entry{}", i), None)).collect();
+        app.handle_event(AppEvent::Worker(WorkerEvent::PlaylistExpanded { id, url: "https://www.youtube.com/playlist?list=PLx".into(), title: None, urls }));
+        commands(worker_rx)
+    }
+
+    fn select(app: &mut App, id: JobId) {
+        let row = app.queue_rows().iter().position(|row| matches!(row, QueueRow::Job(i) if app.jobs[*i].id == id));
+        app.select_row(row.expect("a row for the job"));
+    }
+
+    #[tokio::test]
+    async fn a_restored_queue_bigger_than_the_channel_loses_no_fetch() {
+        let (mut app, mut worker_rx) = app();
+        let jobs: Vec<Job> = (0..100)
+            .map(|i| Job { status: JobStatus::FetchingFormats, ..Job::new(format!("https://example.com/v/{}", i)) })
+            .chain((0..50).map(|i| Job { status: JobStatus::MetadataPending, ..Job::new(format!("https://example.com/e/{}", i)) }))
+            .collect();
+
+        let restored = app.restore(QueueSnapshot { jobs, ..Default::default() });
+        assert!(restored.len() > 32);
+        assert!(commands(&mut worker_rx).is_empty(), "restore sends nothing itself");
+
+        app.send_restored(restored);
+        let mut fetches = 0;
+        let mut hydrated = 0;
+        while fetches + hydrated < 150 {
+            match worker_rx.recv().await {
+                Some(WorkerCommand::FetchFormats { .. }) => fetches += 1,
+                Some(WorkerCommand::HydrateFormats(entries)) => hydrated += entries.len(),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert_eq!((fetches, hydrated), (100, 50));
+    }
+
+    #[tokio::test]
+    async fn the_app_follows_the_settings_in_effect() {
+        let (mut app, mut worker_rx) = app();
+        let (event_tx, _event_rx) = mpsc::channel(8);
+        let (config_tx, _config_rx) = mpsc::unbounded_channel();
+        let (current, current_rx) = watch::channel(Arc::new(Config { output_dir: "/old".into(), ..Config::default() }));
+        app.connect(event_tx, config_tx, current_rx);
+
+        // The writer turned these down, so the app goes back to what is in effect
+        app.config.output_dir = "/rejected".into();
+        app.handle_event(AppEvent::ConfigSaveFailed("geo_bypass_country".into()));
+        assert_eq!(app.config.output_dir, std::path::PathBuf::from("/old"));
+
+        current.send_replace(Arc::new(Config { output_dir: "/reloaded".into(), max_concurrent_downloads: 7, ..Config::default() }));
+        app.handle_event(AppEvent::ConfigChanged);
+        assert_eq!(app.config.output_dir, std::path::PathBuf::from("/reloaded"));
+        assert!(matches!(commands(&mut worker_rx).as_slice(), [WorkerCommand::UpdateConcurrent(7)]));
+    }
+
+    #[test]
+    fn a_long_playlist_is_hydrated_in_one_command() {
+        let (mut app, mut worker_rx) = app();
+        let sent = expanded(&mut app, &mut worker_rx, 40);
+
+        assert_eq!(app.jobs.len(), 40);
+        assert!(app.jobs.iter().all(|j| j.status == JobStatus::MetadataPending));
+        match sent.as_slice() {
+            [WorkerCommand::HydrateFormats(entries)] => {
+                let ids: Vec<JobId> = entries.iter().map(|(id, _)| *id).collect();
+                assert_eq!(ids, app.jobs.iter().map(|j| j.id).collect::<Vec<_>>());
+            }
+            other => panic!("expected one HydrateFormats, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn enter_on_a_pending_entry_asks_for_it_next() {
+        let (mut app, mut worker_rx) = app();
+        expanded(&mut app, &mut worker_rx, 5);
+        let entry = (app.jobs[3].id, app.jobs[3].url.clone());
+        select(&mut app, entry.0);
+
+        app.handle_event(AppEvent::OpenFormatPopup);
+
+        assert!(app.format_popup.is_none());
+        assert_eq!(app.awaiting_formats, Some(entry.0));
+        match commands(&mut worker_rx).as_slice() {
+            [WorkerCommand::PromoteFetch { job_id, url }] => assert_eq!((*job_id, url.clone()), entry),
+            other => panic!("expected PromoteFetch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn formats_landing_for_the_awaited_entry_end_the_wait() {
+        let (mut app, mut worker_rx) = app();
+        expanded(&mut app, &mut worker_rx, 3);
+        let id = app.jobs[1].id;
+        select(&mut app, id);
+        app.handle_event(AppEvent::OpenFormatPopup);
+
+        let formats_ready = |id| AppEvent::Worker(WorkerEvent::FormatsReady { id, title: "Entry".into(), formats: Vec::new(), metadata: Metadata::default() });
+        // Another entry finishing first changes nothing for the one being waited on
+        app.handle_event(formats_ready(app.jobs[0].id));
+        assert_eq!(app.awaiting_formats, Some(id));
+        assert_eq!(app.jobs[1].status, JobStatus::MetadataPending);
+
+        app.handle_event(formats_ready(id));
+        assert_eq!(app.awaiting_formats, None);
+        assert_ne!(app.jobs[1].status, JobStatus::MetadataPending);
+        assert_eq!(app.jobs[2].status, JobStatus::MetadataPending);
+    }
+
+    #[test]
+    fn playlist_links_are_told_apart_from_videos_in_a_playlist() {
+        // (url, one video from a playlist, the playlist itself)
+        let cases = [
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PLx", true, false),
+            ("https://www.youtube.com/watch?list=PLx&v=dQw4w9WgXcQ", true, false),
+            ("https://www.youtube.com/watch?list=PLx&index=3&v=dQw4w9WgXcQ", true, false),
+            ("https://m.youtube.com/watch?v=dQw4w9WgXcQ&list=PLx#t=30", true, false),
+            ("https://youtu.be/dQw4w9WgXcQ?list=PLx", true, false),
+            ("https://youtu.be/dQw4w9WgXcQ?si=abc&list=PLx", true, false),
+            ("https://www.youtube.com/playlist?list=PLx", false, true),
+            ("https://www.youtube.com/watch?list=PLx", false, true),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ", false, false),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=", false, false),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ#list=PLx", false, false),
+            ("https://youtu.be/dQw4w9WgXcQ?si=abc", false, false),
+            ("https://example.com/watch?v=1&list=2", false, false),
+        ];
+        for (url, ambiguous, playlist) in cases {
+            assert_eq!(is_ambiguous_playlist_url(url), ambiguous, "{}", url);
+            assert_eq!(is_playlist_url(url), playlist, "{}", url);
+        }
+    }
+
+    #[test]
+    fn only_the_playlist_is_stripped_from_a_single_video() {
+        let job = single_video_job("https://www.youtube.com/watch?list=PLx&v=dQw4w9WgXcQ&index=2&t=30#c");
+        assert_eq!(job.url, "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=30#c");
+        assert_eq!(job.playlist_skipped.as_deref(), Some("PLx"));
+
+        let job = single_video_job("https://youtu.be/dQw4w9WgXcQ?si=abc&list=PLx");
+        assert_eq!(job.url, "https://youtu.be/dQw4w9WgXcQ?si=abc");
+        assert_eq!(job.playlist_skipped.as_deref(), Some("PLx"));
+    }
+
+    #[test]
+    fn a_video_in_a_playlist_follows_playlist_ambiguity() {
+        let url = "https://youtu.be/dQw4w9WgXcQ?si=abc&list=PLx";
+
+        let (mut added, _worker_rx) = app();
+        added.handle_event(AppEvent::AddUrl(url.into()));
+        assert!(added.jobs.is_empty());
+        assert_eq!(added.playlist_choice.as_ref().map(|c| c.urls.len()), Some(1));
+
+        let (mut added, _worker_rx) = app();
+        added.config.playlist_ambiguity = PlaylistAmbiguity::Single;
+        added.handle_event(AppEvent::AddUrl(url.into()));
+        assert_eq!(added.jobs.len(), 1);
+        assert_eq!(added.jobs[0].url, "https://youtu.be/dQw4w9WgXcQ?si=abc");
+        assert!(added.playlist_choice.is_none());
+
+        let (mut added, _worker_rx) = app();
+        added.config.playlist_ambiguity = PlaylistAmbiguity::Playlist;
+        added.handle_event(AppEvent::AddUrl(url.into()));
+        assert!(added.jobs.is_empty());
+        assert!(added.playlist_range.is_some());
+    }
+
+    #[test]
+    fn stage_changes_bump_the_metrics() {
+        let (mut app, _worker_rx) = app();
+        let stages = |app: &App| app.jobs.iter().map(|j| (j.id, j.status.stage())).collect::<HashMap<_, _>>();
+
+        let before = stages(&app);
+        app.jobs.push(Job::new("https://example.com/v/1"));
+        app.record_timeline(&before, true);
+        assert_eq!(app.metrics.jobs_added, 1);
+
+        let before = stages(&app);
+        app.jobs[0].status = JobStatus::Failed(Failure::new("HTTP Error 404"));
+        app.record_timeline(&before, true);
+        // Nothing moved, so nothing is counted twice
+        let before = stages(&app);
+        app.record_timeline(&before, true);
+        assert_eq!(app.metrics.jobs_failed, 1);
+
+        let before = stages(&app);
+        app.jobs[0].status = JobStatus::Queued;
+        app.record_timeline(&before, true);
+        assert_eq!(app.metrics.retries, 1);
+
+        let before = stages(&app);
+        app.jobs[0].status = JobStatus::Completed;
+        app.jobs[0].output_size = Some(1 << 20);
+        app.record_timeline(&before, true);
+        assert_eq!(app.metrics, Metrics { jobs_added: 1, jobs_completed: 1, jobs_failed: 1, retries: 1, downloaded_bytes: 1 << 20 });
+    }
+
+    fn format(id: &str, height: Option<u32>, vcodec: &str, acodec: &str, size: u64) -> Format {
+        Format {
+            format_id: id.into(),
+            resolution: None,
+            ext: "mp4".into(),
+            vcodec: Some(vcodec.into()),
+            acodec: Some(acodec.into()),
+            filesize: Some(size),
+            filesize_approx: None,
+            tbr: None,
+            width: None,
+            height,
+        }
+    }
+
+    // One item with a format picked and queued, one fetched but left without
+    fn mixed_queue(app: &mut App) -> (JobId, JobId) {
+        let picked_format = format("22", Some(720), "avc1", "mp4a", 5 << 20);
+        let picked = Job { status: JobStatus::Queued, selected_format: Some(picked_format.clone()), formats: vec![picked_format].into(), ..Job::new("https://example.com/v/picked") };
+        let formats = vec![
+            format("137", Some(1080), "avc1", "none", 30 << 20),
+            format("136", Some(720), "avc1", "none", 10 << 20),
+            format("140", None, "none", "mp4a", 1 << 20),
+        ];
+        let unpicked = Job { status: JobStatus::Ready, formats: formats.into(), ..Job::new("https://example.com/v/unpicked") };
+        let ids = (picked.id, unpicked.id);
+        app.jobs.extend([picked, unpicked]);
+        ids
+    }
+
+    fn started(worker_rx: &mut mpsc::Receiver<WorkerCommand>) -> Vec<(JobId, String)> {
+        commands(worker_rx)
+            .into_iter()
+            .filter_map(|c| match c {
+                WorkerCommand::StartJob(request) => Some((request.job_id, request.format_spec)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn start_all_asks_before_using_the_default_format() {
+        let (mut app, mut worker_rx) = app();
+        app.config.large_download_threshold_mib = 0;
+        let (picked, unpicked) = mixed_queue(&mut app);
+
+        app.handle_event(AppEvent::StartDownloads);
+        assert_eq!(app.default_format_prompt.as_ref().map(|p| p.jobs.clone()), Some(vec![unpicked]));
+        assert!(started(&mut worker_rx).is_empty(), "nothing starts while the prompt is open");
+
+        app.handle_event(AppEvent::ConfirmDefaultFormat);
+        assert_eq!(started(&mut worker_rx), vec![(picked, "22".into()), (unpicked, app.config.default_format())]);
+        assert!(app.jobs[1].default_format);
+    }
+
+    #[test]
+    fn skipping_the_default_format_starts_only_the_picked_items() {
+        let (mut app, mut worker_rx) = app();
+        app.config.large_download_threshold_mib = 0;
+        let (picked, _) = mixed_queue(&mut app);
+
+        app.handle_event(AppEvent::StartDownloads);
+        app.handle_event(AppEvent::SkipDefaultFormat);
+
+        assert_eq!(started(&mut worker_rx), vec![(picked, "22".into())]);
+        assert_eq!(app.jobs[1].status, JobStatus::Ready);
+        assert!(!app.jobs[1].default_format);
+    }
+
+    #[test]
+    fn a_large_default_format_download_is_held_to_the_threshold() {
+        let (mut app, mut worker_rx) = app();
+        app.config.large_download_threshold_mib = 16;
+        let (picked, unpicked) = mixed_queue(&mut app);
+
+        app.handle_event(AppEvent::StartDownloads);
+        app.handle_event(AppEvent::ConfirmDefaultFormat);
+
+        // The tallest video and the best audio, as bestvideo+bestaudio would pick
+        let large = app.large_downloads.as_ref().map(|l| l.jobs.clone());
+        assert_eq!(large, Some(vec![(unpicked, (30 << 20) + (1 << 20))]));
+        assert!(started(&mut worker_rx).is_empty());
+
+        app.handle_event(AppEvent::SkipLargeDownloads);
+        assert_eq!(started(&mut worker_rx), vec![(picked, "22".into())]);
+    }
+
+    #[test]
+    fn the_size_guess_keeps_to_max_height() {
+        let (mut app, _worker_rx) = app();
+        app.config.default_quality.max_height = Some(720);
+        let (_, unpicked) = mixed_queue(&mut app);
+        let job = app.jobs.iter_mut().find(|j| j.id == unpicked).unwrap();
+        job.default_format = true;
+
+        assert_eq!(job.required_space(&app.config), Some((10 << 20) + (1 << 20)));
+    }
+
+    #[test]
+    fn pressing_start_twice_sends_one_start() {
+        let (mut app, mut worker_rx) = app();
+        app.config.large_download_threshold_mib = 0;
+        let (picked, _) = mixed_queue(&mut app);
+        app.jobs.retain(|j| j.id == picked);
+
+        app.handle_event(AppEvent::StartDownloads);
+        app.handle_event(AppEvent::StartDownloads);
+
+        assert_eq!(started(&mut worker_rx), vec![(picked, "22".into())]);
+        assert_eq!(app.jobs[0].status, JobStatus::Starting);
+    }
+
+    #[test]
+    fn a_start_the_worker_had_no_room_for_can_be_sent_again() {
+        let (worker_tx, mut worker_rx) = mpsc::channel(1);
+        let mut app = App::new(Config { large_download_threshold_mib: 0, ..Config::default() }, worker_tx);
+        let (picked, _) = mixed_queue(&mut app);
+        app.jobs.retain(|j| j.id == picked);
+        worker_rx.close();
+
+        app.handle_event(AppEvent::StartDownloads);
+        assert_eq!(app.jobs[0].status, JobStatus::Queued);
+        assert!(app.jobs[0].start_issue.is_some());
+    }
+
+    fn triage_queue(app: &mut App) -> Vec<JobId> {
+        let formats = || -> Arc<[Format]> {
+            vec![
+                format("137", Some(1080), "avc1", "none", 30 << 20),
+                format("22", Some(720), "avc1", "mp4a", 10 << 20),
+                format("140", None, "none", "mp4a", 1 << 20),
+            ]
+            .into()
+        };
+        let jobs = [
+            Job { status: JobStatus::Ready, formats: formats(), ..Job::new("https://example.com/v/music") },
+            Job { status: JobStatus::Ready, ..Job::new("https://example.com/v/no-formats") },
+            Job { status: JobStatus::Ready, formats: formats(), ..Job::new("https://example.com/v/unsure") },
+            Job { status: JobStatus::Ready, formats: formats(), ..Job::new("https://example.com/v/unwanted") },
+            Job { status: JobStatus::FetchingFormats, ..Job::new("https://example.com/v/late") },
+        ];
+        let ids = jobs.iter().map(|j| j.id).collect();
+        app.jobs.extend(jobs);
+        ids
+    }
+
+    #[test]
+    fn triage_steps_through_ready_jobs_and_waits_for_late_ones() {
+        let (mut app, _worker_rx) = app();
+        let ids = triage_queue(&mut app);
+        let status = |app: &App, id: JobId| app.jobs.iter().find(|j| j.id == id).map(|j| j.status.clone());
+
+        app.handle_event(AppEvent::StartTriage(None));
+        assert_eq!(app.triage.as_ref().and_then(|t| t.current), Some(ids[0]));
+
+        app.handle_event(AppEvent::TriageAssign(TriagePreset::Video720));
+        assert_eq!(status(&app, ids[0]), Some(JobStatus::Queued));
+        assert_eq!(app.jobs[0].selected_format.as_ref().map(|f| f.format_id.as_str()), Some("22"));
+        assert_eq!(app.triage.as_ref().and_then(|t| t.current), Some(ids[1]));
+
+        // Nothing fits, so the job is passed over rather than assigned
+        app.handle_event(AppEvent::TriageAssign(TriagePreset::Audio));
+        assert_eq!(app.notice.as_ref().map(|n| n.level), Some(NoticeLevel::Error));
+        assert_eq!(app.triage.as_ref().and_then(|t| t.current), Some(ids[2]));
+
+        app.handle_event(AppEvent::TriageSkip);
+        assert_eq!(app.triage.as_ref().and_then(|t| t.current), Some(ids[3]));
+
+        app.handle_event(AppEvent::TriageRemove);
+        assert_eq!(status(&app, ids[3]), None);
+        let triage = app.triage.as_ref().expect("still waiting on the late job");
+        assert_eq!(triage.current, None);
+        assert_eq!(triage.remaining(&app.jobs), 1);
+
+        let formats = vec![format("251", None, "none", "opus", 1 << 20)];
+        app.handle_event(AppEvent::Worker(WorkerEvent::FormatsReady { id: ids[4], title: "Late".into(), formats, metadata: Metadata::default() }));
+        assert_eq!(app.triage.as_ref().and_then(|t| t.current), Some(ids[4]));
+
+        app.handle_event(AppEvent::TriageAssign(TriagePreset::Audio));
+        assert!(app.triage.is_none());
+        assert_eq!(app.notice.as_ref().map(|n| n.text.as_str()), Some("Assigned formats to 2 jobs"));
+        assert!(app.jobs.iter().find(|j| j.id == ids[4]).is_some_and(|j| j.extract_audio && j.status == JobStatus::Queued));
+        // Skipped jobs keep waiting for a format
+        assert_eq!(status(&app, ids[1]), Some(JobStatus::Ready));
+        assert_eq!(status(&app, ids[2]), Some(JobStatus::Ready));
+    }
+
+    #[test]
+    fn triage_without_ready_jobs_does_not_open() {
+        let (mut app, _worker_rx) = app();
+        mixed_queue(&mut app);
+        app.jobs.retain(|j| j.status == JobStatus::Queued);
+
+        app.handle_event(AppEvent::StartTriage(None));
+        assert!(app.triage.is_none());
+        assert_eq!(app.notice.as_ref().map(|n| n.text.as_str()), Some("No jobs waiting for a format"));
+    }
+
+    #[test]
+    fn triage_for_a_group_leaves_other_jobs_alone() {
+        let (mut app, _worker_rx) = app();
+        let ids = triage_queue(&mut app);
+        let group = GroupId::new_v4();
+        app.jobs[2].group = Some(group);
+
+        app.handle_event(AppEvent::StartTriage(Some(group)));
+        assert_eq!(app.triage.as_ref().and_then(|t| t.current), Some(ids[2]));
+        app.handle_event(AppEvent::CloseTriage);
+        assert!(app.triage.is_none());
+        assert!(app.jobs.iter().all(|j| j.status != JobStatus::Queued));
+    }
+
+    #[test]
+    fn a_failed_expansion_stops_the_spinner_and_says_why() {
+        let (mut app, _worker_rx) = app();
+        let url = "https://www.youtube.com/playlist?list=PLtypo";
+        let placeholder = Job::playlist_placeholder(url, None);
+        let id = placeholder.id;
+        app.jobs.push(placeholder);
+        assert_eq!(app.loading_playlists(), 1);
+
+        let error = "ERROR: [youtube:tab] PLtypo: The playlist does not exist.";
+        app.handle_event(AppEvent::Worker(WorkerEvent::PlaylistFailed { id, url: url.into(), error: error.into() }));
+        assert_eq!(app.loading_playlists(), 0);
+        assert!(matches!(&app.jobs[0].status, JobStatus::Failed(failure) if failure.message.contains("does not exist")));
+        let notice = app.notice.as_ref().expect("a notice");
+        assert_eq!(notice.level, NoticeLevel::Error);
+        assert!(notice.text.contains(url), "{}", notice.text);
+
+        // A late report for a placeholder that is already gone changes nothing
+        app.notice = None;
+        app.jobs.clear();
+        app.handle_event(AppEvent::Worker(WorkerEvent::PlaylistFailed { id, url: url.into(), error: error.into() }));
+        assert!(app.notice.is_none());
+    }
+
+    fn start_targets(worker_rx: &mut mpsc::Receiver<WorkerCommand>) -> Vec<Option<OutputTarget>> {
+        commands(worker_rx)
+            .into_iter()
+            .filter_map(|c| match c {
+                WorkerCommand::StartJob(request) => Some(request.output),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_retry_keeps_the_output_the_job_first_started_with() {
+        let (mut app, mut worker_rx) = app();
+        app.config.large_download_threshold_mib = 0;
+        app.config.output_dir = "/old".into();
+        app.config.output_template = "%(title)s.%(ext)s".into();
+        let (picked, _) = mixed_queue(&mut app);
+        app.jobs.retain(|j| j.id == picked);
+
+        app.handle_event(AppEvent::StartDownloads);
+        let first = OutputTarget { dir: "/old".into(), template: "%(title)s.%(ext)s".into() };
+        assert_eq!(start_targets(&mut worker_rx), vec![Some(first.clone())]);
+        assert_eq!(app.jobs[0].planned_target(&app.config), first);
+
+        // Settings changed while it ran, then it failed on a network error
+        app.config.output_dir = "/new".into();
+        app.config.output_template = "%(uploader)s/%(title)s.%(ext)s".into();
+        app.jobs[0].status = JobStatus::Failed(Failure::new("ERROR: Unable to download: Connection reset by peer"));
+        assert_eq!(app.jobs[0].planned_target(&app.config), first, "the details still show the snapshot");
+
+        app.handle_event(AppEvent::OpenRemediation(picked));
+        assert_eq!(start_targets(&mut worker_rx), vec![Some(first)]);
+        assert_eq!(app.jobs[0].status, JobStatus::Starting);
+    }
+
+    #[test]
+    fn jobs_not_yet_started_follow_the_settings() {
+        let (mut app, _worker_rx) = app();
+        let (_, unpicked) = mixed_queue(&mut app);
+        let job = app.jobs.iter().find(|j| j.id == unpicked).unwrap().clone();
+        app.config.output_dir = "/new".into();
+        assert_eq!(job.planned_target(&app.config).dir, std::path::PathBuf::from("/new"));
+    }
+
+    #[test]
+    fn formats_arriving_after_a_cancel_leave_the_job_cancelled() {
+        let (mut app, mut worker_rx) = app();
+        let job = Job { status: JobStatus::FetchingFormats, ..Job::new("https://example.com/v/slow") };
+        let id = job.id;
+        app.jobs.push(job);
+
+        app.handle_event(AppEvent::CancelJob(id));
+        assert!(matches!(commands(&mut worker_rx).as_slice(), [WorkerCommand::CancelJob(cancelled)] if *cancelled == id));
+        assert_eq!(app.jobs[0].status, JobStatus::Cancelled);
+
+        let formats = vec![format("22", Some(720), "avc1", "mp4a", 5 << 20)];
+        app.handle_event(AppEvent::Worker(WorkerEvent::FormatsReady { id, title: "Late".into(), formats, metadata: Metadata::default() }));
+        assert_eq!(app.jobs[0].status, JobStatus::Cancelled);
+        assert!(app.jobs[0].formats.is_empty());
+        assert!(app.format_popup.is_none());
+    }
+
+    #[test]
+    fn sorting_by_upload_date_stays_within_groups() {
+        let (mut app, _worker_rx) = app();
+        let group = GroupId::new_v4();
+        let dated = |name: &str, date: Option<&str>, group: Option<GroupId>| {
+            let mut job = Job { title: Some(name.into()), group, ..Job::new(format!("https://example.com/v/{}", name)) };
+            job.metadata.upload_date = date.map(String::from);
+            job
+        };
+        app.jobs = vec![
+            dated("loose-old", Some("20200101"), None),
+            dated("a", Some("20230101"), Some(group)),
+            dated("undated", None, Some(group)),
+            dated("loose-new", Some("20240101"), None),
+            dated("b", Some("20240601"), Some(group)),
+        ];
+        let titles = |app: &App| app.jobs.iter().map(|j| j.display_name().to_string()).collect::<Vec<_>>();
+
+        // Each scope is sorted in the rows it already had; undated jobs go last
+        app.handle_event(AppEvent::SortByUploadDate);
+        assert_eq!(titles(&app), ["loose-old", "a", "b", "loose-new", "undated"]);
+        assert_eq!(app.notice.as_ref().map(|n| n.text.as_str()), Some("Sorted by upload date, oldest first"));
+
+        app.handle_event(AppEvent::SortByUploadDate);
+        assert_eq!(titles(&app), ["loose-new", "b", "a", "loose-old", "undated"]);
+        assert_eq!(app.notice.as_ref().map(|n| n.text.as_str()), Some("Sorted by upload date, newest first"));
+    }
+
+    fn progress(id: JobId, percent: f32) -> AppEvent {
+        AppEvent::Worker(WorkerEvent::JobProgress {
+            id,
+            percent,
+            speed: "1.00MiB/s".into(),
+            speed_bps: Some(1 << 20),
+            eta: "00:05".into(),
+            phase: DownloadPhase::Single,
+            total: Some(10 << 20),
+        })
+    }
+
+    fn start_requests(worker_rx: &mut mpsc::Receiver<WorkerCommand>) -> Vec<DownloadRequest> {
+        commands(worker_rx)
+            .into_iter()
+            .filter_map(|c| match c {
+                WorkerCommand::StartJob(request) => Some(request),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // A playlist of three with its middle entry done, then two lone jobs, one failed
+    fn split(app: &mut App) -> Vec<JobId> {
+        let group = JobGroup::new("Mix", "https://www.youtube.com/playlist?list=PLmix");
+        let statuses = [JobStatus::Ready, JobStatus::Completed, JobStatus::Queued, JobStatus::Ready, JobStatus::Failed(Failure::new("ERROR: Private video"))];
+        for (i, status) in statuses.into_iter().enumerate() {
+            let group = (i < 3).then_some(group.id);
+            app.jobs.push(Job { status, group, ..Job::new(format!("https://example.com/v/{}", i)) });
+        }
+        app.groups.push(group);
+        app.split_queue = true;
+        app.jobs.iter().map(|j| j.id).collect()
+    }
+
+    fn job_rows(app: &App, pane: QueuePane) -> Vec<Option<JobId>> {
+        app.pane_rows(pane)
+            .into_iter()
+            .map(|row| match row {
+                QueueRow::Job(i) => Some(app.jobs[i].id),
+                QueueRow::Group(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_split_queue_lists_finished_jobs_apart() {
+        let (mut app, _worker_rx) = app();
+        let ids = split(&mut app);
+        // Headers stay with the active list; the finished one is just its jobs
+        assert_eq!(job_rows(&app, QueuePane::Active), [None, Some(ids[0]), Some(ids[2]), Some(ids[3])]);
+        assert_eq!(job_rows(&app, QueuePane::Finished), [Some(ids[1]), Some(ids[4])]);
+
+        app.handle_event(AppEvent::ToggleQueueLayout);
+        assert_eq!(job_rows(&app, QueuePane::Active), [None, Some(ids[0]), Some(ids[1]), Some(ids[2]), Some(ids[3]), Some(ids[4])]);
+        assert_eq!(job_rows(&app, QueuePane::Finished), job_rows(&app, QueuePane::Active));
+        // The counts cover the whole queue whichever way it is laid out
+        assert_eq!(app.status_counts().completed, 1);
+    }
+
+    #[test]
+    fn each_pane_keeps_its_own_cursor() {
+        let (mut app, _worker_rx) = app();
+        let ids = split(&mut app);
+        app.handle_event(AppEvent::SelectNext);
+        app.handle_event(AppEvent::SelectNext);
+        assert_eq!(app.selected_job().map(|j| j.id), Some(ids[2]));
+
+        app.handle_event(AppEvent::SwitchQueuePane);
+        assert_eq!(app.pane, QueuePane::Finished);
+        assert_eq!(app.selected_job().map(|j| j.id), Some(ids[1]));
+        app.handle_event(AppEvent::SelectNext);
+        assert_eq!(app.selected_job().map(|j| j.id), Some(ids[4]));
+        assert_eq!(app.parked_cursor().0, 2, "the active list's cursor waits on its row");
+
+        app.handle_event(AppEvent::SwitchQueuePane);
+        assert_eq!(app.selected_job().map(|j| j.id), Some(ids[2]));
+        app.handle_event(AppEvent::SwitchQueuePane);
+        assert_eq!(app.selected_job().map(|j| j.id), Some(ids[4]));
+
+        // Turning the split off goes back to the active list's cursor
+        app.handle_event(AppEvent::ToggleQueueLayout);
+        assert_eq!((app.pane, app.split_queue), (QueuePane::Active, false));
+        assert_eq!(app.selected_job().map(|j| j.id), Some(ids[2]));
+        app.handle_event(AppEvent::SwitchQueuePane);
+        assert_eq!(app.pane, QueuePane::Active, "nothing to switch to without the split");
+    }
+
+    #[tokio::test]
+    async fn a_job_finishing_moves_to_the_other_list() {
+        let (mut app, _worker_rx) = app();
+        let ids = split(&mut app);
+        select(&mut app, ids[2]);
+        app.handle_event(AppEvent::Worker(WorkerEvent::JobCompleted { id: ids[2], path: "/downloads/2.mp4".into() }));
+        assert_eq!(job_rows(&app, QueuePane::Finished), [Some(ids[1]), Some(ids[2]), Some(ids[4])]);
+        // The row it left is taken by the next job down
+        assert_eq!(app.selected_job().map(|j| j.id), Some(ids[3]));
+    }
+
+    #[test]
+    fn filter_matches_are_ordered_by_score_and_keep_the_selection() {
+        let (mut app, _worker_rx) = app();
+        for (i, title) in ["Paragon Live", "Gonna Fly Now", "Never Gonna Give You Up", "g o n n a", "Unrelated"].into_iter().enumerate() {
+            app.jobs.push(Job { title: Some(title.into()), status: JobStatus::Ready, ..Job::new(format!("https://example.com/v/{}", i)) });
+        }
+        let ids: Vec<JobId> = app.jobs.iter().map(|j| j.id).collect();
+        select(&mut app, ids[3]);
+
+        app.handle_event(AppEvent::OpenQueueFilter);
+        app.handle_event(AppEvent::SetQueueFilter("gon".into()));
+        // Word starts first, equal scores in queue order, the middle of a word last
+        assert_eq!(job_rows(&app, QueuePane::Active), [Some(ids[1]), Some(ids[2]), Some(ids[3]), Some(ids[0])]);
+        assert_eq!(app.selected_job().map(|j| j.id), Some(ids[3]));
+
+        // Exact text ranks by where it turns up
+        app.handle_event(AppEvent::SetQueueFilter("'gon".into()));
+        assert_eq!(job_rows(&app, QueuePane::Active), [Some(ids[1]), Some(ids[0]), Some(ids[2])]);
+        app.handle_event(AppEvent::ClearQueueFilter);
+        assert_eq!(app.queue_rows().len(), 5);
+    }
+
+    fn notice(app: &App) -> Option<&str> {
+        app.notice.as_ref().map(|n| n.text.as_str())
+    }
+
+    #[test]
+    fn merges_without_ffmpeg_are_warned_about() {
+        let (mut app, _worker_rx) = app();
+        app.handle_event(AppEvent::AddUrl("https://example.com/v/merge".into()));
+        let id = app.jobs[0].id;
+        let formats = vec![format("137", Some(1080), "avc1", "none", 10 << 20), format("18", Some(360), "avc1", "mp4a", 1 << 20)];
+        app.handle_event(AppEvent::Worker(WorkerEvent::FormatsReady { id, title: "Merge".into(), formats, metadata: Metadata::default() }));
+        select(&mut app, id);
+        app.ffmpeg_missing = true;
+
+        app.notice = None;
+        app.handle_event(AppEvent::OpenFormatPopup);
+        if let Some(popup) = &mut app.format_popup {
+            popup.focus("18");
+        }
+        app.handle_event(AppEvent::ConfirmFormat);
+        assert_eq!(notice(&app), None);
+
+        app.handle_event(AppEvent::OpenFormatPopup);
+        if let Some(popup) = &mut app.format_popup {
+            popup.focus("137");
+        }
+        app.handle_event(AppEvent::ConfirmFormat);
+        assert_eq!(notice(&app), Some("⚠ ffmpeg not found: 1 video-only format can't be merged with audio"));
+
+        // The health check finding it gone says so for what is already queued
+        app.notice = None;
+        app.ffmpeg_missing = false;
+        let report = health::HealthReport { checks: vec![health::Check::warning("ffmpeg", "not found in PATH", "install it")], ytdlp_version: String::new() };
+        app.handle_event(AppEvent::HealthChecked(report));
+        assert!(app.ffmpeg_missing);
+        assert_eq!(notice(&app), Some("⚠ ffmpeg not found: 1 video-only format can't be merged with audio"));
+    }
+
+    #[test]
+    fn links_past_the_cap_are_dropped_and_counted() {
+        let (mut app, _worker_rx) = app();
+        app.config.max_queue_size = 0;
+        assert_eq!(app.queue_room(), usize::MAX, "no cap");
+
+        app.config.max_queue_size = 3;
+        for i in 0..5 {
+            app.handle_event(AppEvent::AddUrl(format!("https://example.com/v/{}", i)));
+        }
+        assert_eq!((app.jobs.len(), app.queue_room()), (3, 0));
+        assert_eq!(notice(&app), Some("Queue is full at 3 items: dropped 2 link(s)"));
+
+        // Room again restarts the count
+        app.jobs.pop();
+        app.handle_event(AppEvent::AddUrl("https://example.com/v/5".into()));
+        app.handle_event(AppEvent::AddUrl("https://example.com/v/6".into()));
+        assert_eq!(app.jobs.last().map(|j| j.url.as_str()), Some("https://example.com/v/5"));
+        assert_eq!(notice(&app), Some("Queue is full at 3 items: dropped 1 link(s)"));
+    }
+
+    #[test]
+    fn an_expansion_past_the_cap_is_cut_to_fit() {
+        for (entries, kept, cut) in [(10, 3, true), (3, 3, false), (1, 1, false)] {
+            let (mut app, mut worker_rx) = app();
+            app.config.max_queue_size = 5;
+            app.jobs.extend((0..2).map(|i| Job::new(format!("https://example.com/v/{}", i))));
+            expanded(&mut app, &mut worker_rx, entries);
+            // The placeholder's slot goes to the first entry
+            assert_eq!(app.jobs.len(), 2 + kept, "{} entries", entries);
+            assert_eq!(app.jobs.iter().filter(|j| j.group.is_some()).count(), kept);
+            let expected = format!("Playlist cut to {} of {} videos: the queue holds at most 5 items", kept, entries);
+            assert_eq!(notice(&app) == Some(expected.as_str()), cut, "{:?}", notice(&app));
+        }
+    }
+
+    fn timeline(job: &Job) -> Vec<&str> {
+        job.timeline.iter().map(|e| e.event.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn a_jobs_life_lands_on_its_timeline() {
+        let (mut app, mut worker_rx) = app();
+        app.config.large_download_threshold_mib = 0;
+        app.handle_event(AppEvent::AddUrl("https://example.com/v/life".into()));
+        let id = app.jobs[0].id;
+        let formats = vec![format("22", Some(720), "avc1", "mp4a", 10 << 20)];
+        app.handle_event(AppEvent::Worker(WorkerEvent::FormatsReady { id, title: "Life".into(), formats, metadata: Metadata::default() }));
+        select(&mut app, id);
+        app.handle_event(AppEvent::OpenFormatPopup);
+        app.handle_event(AppEvent::ConfirmFormat);
+        app.handle_event(AppEvent::StartDownloads);
+        app.handle_event(AppEvent::Worker(WorkerEvent::JobStarted { id }));
+        // Ticks within a phase are not steps of their own
+        for percent in [10.0, 50.0, 90.0] {
+            app.handle_event(progress(id, percent));
+        }
+        app.handle_event(AppEvent::Worker(WorkerEvent::JobFailed { id, error: Failure::new("ERROR: Unable to download: Connection reset by peer\nmore detail") }));
+        let report = app.jobs[0].error_report().expect("a report for the failed job");
+        let steps: Vec<&str> = report.lines().skip_while(|l| *l != "Timeline:").skip(1).collect();
+        assert_eq!(steps.len(), 8, "{}", report);
+        assert!(steps.iter().all(|l| l.starts_with("  +")), "{}", report);
+        assert!(steps[7].ends_with("  failed (network error): ERROR: Unable to download: Connection reset by peer"), "{}", report);
+
+        app.handle_event(AppEvent::OpenRemediation(id));
+        app.handle_event(AppEvent::Worker(WorkerEvent::JobStarted { id }));
+        app.handle_event(AppEvent::Worker(WorkerEvent::JobCompleted { id, path: "/downloads/Life.mp4".into() }));
+        commands(&mut worker_rx);
+
+        assert_eq!(
+            timeline(&app.jobs[0]),
+            [
+                "added",
+                "fetching formats",
+                "formats ready",
+                "queued",
+                "start sent",
+                "downloading video",
+                "downloading",
+                "failed (network error): ERROR: Unable to download: Connection reset by peer",
+                "retried: start sent",
+                "downloading video",
+                "completed",
+            ]
+        );
+        assert!(app.jobs[0].error_report().is_none());
+
+        // Handlers that raise events of their own still record each step once
+        app.import_picker = Some(ImportPickerState::new(vec![bookmarks::Bookmark { url: "https://example.com/v/imported".into(), title: None }]));
+        app.handle_event(AppEvent::ConfirmImport);
+        let imported = app.jobs[1].id;
+        assert_eq!(timeline(&app.jobs[1]), ["added", "fetching formats"]);
+        assert_eq!(app.metrics.jobs_added, 2);
+
+        app.jobs[1].after = Some(id);
+        app.dependents_prompt = Some(DependentsPromptState { prerequisite: id, dependents: vec![imported], outcome: "failed" });
+        app.handle_event(AppEvent::CancelDependents);
+        assert_eq!(timeline(&app.jobs[1]), ["added", "fetching formats", "cancelled"]);
+    }
+
+    #[test]
+    fn the_age_only_ticks_while_it_is_on_screen() {
+        let (mut app, _worker_rx) = app();
+        assert!(!app.shows_age());
+        app.handle_event(AppEvent::AddUrl("https://example.com/v/new".into()));
+        assert!(app.shows_age());
+        app.jobs[0].added_at = chrono::Local::now() - chrono::Duration::days(2);
+        assert!(!app.shows_age());
+    }
+
+    #[test]
+    fn the_spinner_keeps_turning_through_a_ytdlp_update() {
+        let (mut app, _worker_rx) = app();
+        assert!(!app.needs_animation());
+        app.handle_event(AppEvent::UpdateYtdlp);
+        app.notice = None;
+        assert!(app.spinner_running() && app.needs_animation());
+        if let Some(update) = &mut app.ytdlp_update {
+            update.result = Some((true, "Updated yt-dlp to 2026.09.01".into()));
+        }
+        assert!(!app.spinner_running() && !app.needs_animation());
+    }
+
+    #[test]
+    fn progress_racing_a_cancel_only_moves_the_partial_forward() {
+        let (mut app, mut worker_rx) = app();
+        app.config.large_download_threshold_mib = 0;
+        let (picked, _) = mixed_queue(&mut app);
+        app.jobs.retain(|j| j.id == picked);
+        app.handle_event(AppEvent::StartDownloads);
+        let first = start_requests(&mut worker_rx).remove(0);
+
+        app.handle_event(progress(picked, 70.0));
+        app.handle_event(AppEvent::CancelJob(picked));
+        let partial = |app: &App| app.jobs[0].partial.map(|p| (p.percent, p.downloaded));
+        assert_eq!(app.jobs[0].status, JobStatus::Cancelled);
+        assert_eq!(partial(&app), Some((70.0, Some(7 << 20))));
+
+        // Ticks yt-dlp printed before it saw the cancel
+        app.handle_event(progress(picked, 80.0));
+        app.handle_event(progress(picked, 75.0));
+        assert_eq!(app.jobs[0].status, JobStatus::Cancelled);
+        assert_eq!(partial(&app), Some((80.0, Some(8 << 20))));
+
+        app.handle_event(AppEvent::RestartCancelled { id: picked, resume: true });
+        let resumed = start_requests(&mut worker_rx).remove(0);
+        assert_eq!(resumed.resume, Some(true));
+        assert_eq!(resumed.output, first.output);
+        assert_eq!(app.jobs[0].status, JobStatus::Starting);
+        assert_eq!(app.jobs[0].partial, None);
+    }
+
+    #[test]
+    fn a_cancel_before_any_progress_still_records_a_late_tick() {
+        let (mut app, mut worker_rx) = app();
+        app.config.large_download_threshold_mib = 0;
+        let (picked, _) = mixed_queue(&mut app);
+        app.jobs.retain(|j| j.id == picked);
+        app.handle_event(AppEvent::StartDownloads);
+        app.handle_event(AppEvent::CancelJob(picked));
+        assert_eq!(app.jobs[0].partial, None);
+
+        app.handle_event(progress(picked, 12.5));
+        assert_eq!(app.jobs[0].status, JobStatus::Cancelled);
+        assert_eq!(app.jobs[0].partial.map(|p| p.percent), Some(12.5));
+
+        // Starting over throws the part file away
+        app.handle_event(AppEvent::RestartCancelled { id: picked, resume: false });
+        let restarted = start_requests(&mut worker_rx).pop().unwrap();
+        assert_ne!(restarted.resume, Some(true));
+    }
 }
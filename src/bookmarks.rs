@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+
+// Hosts whose links are worth offering; anything else in a bookmarks file is noise
+const VIDEO_HOSTS: &[&str] = &[
+    "youtube.com",
+    "youtu.be",
+    "vimeo.com",
+    "dailymotion.com",
+    "twitch.tv",
+    "soundcloud.com",
+    "bandcamp.com",
+    "tiktok.com",
+];
+
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+// Scans a Netscape bookmarks export (what Chrome, Firefox and friends write)
+// for `<A HREF="...">title</A>` entries. The format is loose HTML that is rarely
+// well-formed, so this looks for anchors directly instead of building a DOM
+pub fn parse(html: &str) -> Vec<Bookmark> {
+    let lower = html.to_ascii_lowercase();
+    let mut seen = HashSet::new();
+    let mut bookmarks = Vec::new();
+    let mut pos = 0;
+
+    while let Some(offset) = lower[pos..].find("<a ") {
+        let tag_start = pos + offset;
+        let Some(tag_len) = lower[tag_start..].find('>') else {
+            break;
+        };
+        let tag_end = tag_start + tag_len;
+        pos = tag_end + 1;
+
+        let Some(href) = attribute(&html[tag_start..tag_end], &lower[tag_start..tag_end], "href") else {
+            continue;
+        };
+        let url = decode_entities(href.trim());
+        if !is_video_link(&url) || !seen.insert(url.clone()) {
+            continue;
+        }
+
+        let title = lower[pos..]
+            .find("</a")
+            .map(|len| decode_entities(html[pos..pos + len].trim()))
+            .filter(|t| !t.is_empty());
+        bookmarks.push(Bookmark { url, title });
+    }
+
+    bookmarks
+}
+
+fn attribute<'a>(tag: &'a str, lower_tag: &str, name: &str) -> Option<&'a str> {
+    let start = lower_tag.find(&format!("{}=", name))? + name.len() + 1;
+    let rest = &tag[start..];
+    match rest.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = rest[1..].find(quote)?;
+            Some(&rest[1..1 + end])
+        }
+        _ => Some(rest.split_whitespace().next().unwrap_or(rest)),
+    }
+}
+
+fn is_video_link(url: &str) -> bool {
+    let Some(rest) = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) else {
+        return false;
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default().to_ascii_lowercase();
+    VIDEO_HOSTS
+        .iter()
+        .any(|known| host == *known || host.ends_with(&format!(".{}", known)))
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHROME: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file.
+     It will be read and overwritten.
+     DO NOT EDIT! -->
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1700000000" LAST_MODIFIED="1700000100" PERSONAL_TOOLBAR_FOLDER="true">Bookmarks bar</H3>
+    <DL><p>
+        <DT><A HREF="https://www.youtube.com/watch?v=dQw4w9WgXcQ" ADD_DATE="1700000001" ICON="data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAABAAAAAQ">Rick Astley - Never Gonna Give You Up (Official Music Video) - YouTube</A>
+        <DT><A HREF="https://news.ycombinator.com/" ADD_DATE="1700000002">Hacker News</A>
+        <DT><H3 ADD_DATE="1700000003" LAST_MODIFIED="1700000004">Watch later</H3>
+        <DL><p>
+            <DT><A HREF="https://youtu.be/9bZkp7q19f0?si=abc&amp;t=30" ADD_DATE="1700000005">PSY - GANGNAM STYLE(&#39;강남스타일&#39;) M/V</A>
+            <DT><A HREF="https://www.youtube.com/watch?v=dQw4w9WgXcQ" ADD_DATE="1700000006">Rick again</A>
+        </DL><p>
+    </DL><p>
+</DL><p>
+"#;
+
+    const FIREFOX: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<meta http-equiv="Content-Security-Policy"
+      content="default-src 'self'; script-src 'none'; img-src data: *; object-src 'none'"></meta>
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks Menu</H1>
+
+<DL><p>
+    <DT><A HREF="place:parent=toolbar_____&amp;sort=12&amp;maxResults=10" ADD_DATE="1700000000">Recent Tags</A>
+    <DT><H3 ADD_DATE="1700000000" LAST_MODIFIED="1700000100">Talks</H3>
+    <DL><p>
+        <DT><A HREF="https://vimeo.com/76979871" ADD_DATE="1700000001" LAST_MODIFIED="1700000002" ICON_URI="https://vimeo.com/favicon.ico" TAGS="talks,video">The New Vimeo Player &amp; You</A>
+        <DD>A short description that is not a title
+        <DT><A HREF="https://m.youtube.com/playlist?list=PLx" ADD_DATE="1700000003"></A>
+        <DT><A HREF="https://example.com/youtube.com/fake" ADD_DATE="1700000004">Not a video host</A>
+        <DT><a href=https://soundcloud.com/artist/track add_date="1700000005">lowercase and unquoted</a>
+    </DL><p>
+</DL>
+"#;
+
+    fn urls(bookmarks: &[Bookmark]) -> Vec<&str> {
+        bookmarks.iter().map(|b| b.url.as_str()).collect()
+    }
+
+    #[test]
+    fn reads_a_chrome_export() {
+        let bookmarks = parse(CHROME);
+        assert_eq!(urls(&bookmarks), ["https://www.youtube.com/watch?v=dQw4w9WgXcQ", "https://youtu.be/9bZkp7q19f0?si=abc&t=30"]);
+        assert_eq!(bookmarks[0].title.as_deref(), Some("Rick Astley - Never Gonna Give You Up (Official Music Video) - YouTube"));
+        assert_eq!(bookmarks[1].title.as_deref(), Some("PSY - GANGNAM STYLE('강남스타일') M/V"));
+    }
+
+    #[test]
+    fn reads_a_firefox_export() {
+        let bookmarks = parse(FIREFOX);
+        assert_eq!(urls(&bookmarks), ["https://vimeo.com/76979871", "https://m.youtube.com/playlist?list=PLx", "https://soundcloud.com/artist/track"]);
+        let titles: Vec<Option<&str>> = bookmarks.iter().map(|b| b.title.as_deref()).collect();
+        assert_eq!(titles, [Some("The New Vimeo Player & You"), None, Some("lowercase and unquoted")]);
+    }
+
+    #[test]
+    fn a_truncated_file_keeps_what_came_before() {
+        let cut = &CHROME[..CHROME.find("<DT><A HREF=\"https://youtu.be").unwrap() + 20];
+        assert_eq!(urls(&parse(cut)), ["https://www.youtube.com/watch?v=dQw4w9WgXcQ"]);
+        assert!(parse("").is_empty());
+    }
+}
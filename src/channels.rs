@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use chrono::{DateTime, Local};
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{Format, Job, Metadata};
+use crate::paths;
+use crate::paths::write_atomic;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelChoice {
+    pub name: String,
+    // Mapped onto each new video's own formats with Job::equivalent_format
+    pub format: Format,
+    pub extract_audio: bool,
+    pub updated_at: DateTime<Local>,
+}
+
+impl ChannelChoice {
+    pub fn label(&self) -> String {
+        if self.extract_audio || self.format.is_audio_only() {
+            "audio".into()
+        } else {
+            format!("{} {}", self.format.display_resolution(), self.format.ext)
+        }
+    }
+}
+
+// The last format confirmed for each channel, keyed by yt-dlp's channel_id
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChannelMemory {
+    channels: BTreeMap<String, ChannelChoice>,
+}
+
+impl ChannelMemory {
+    pub fn load() -> Self {
+        let path = paths::get().channels_file();
+        let Ok(content) = std::fs::read(&path) else {
+            return Self::default();
+        };
+        serde_json::from_slice(&content).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring unreadable {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        write_atomic(&paths::get().channels_file(), &serde_json::to_vec_pretty(self)?).await
+    }
+
+    // Only videos that say which channel they are from can be remembered
+    pub fn remember(&mut self, metadata: &Metadata, format: &Format, extract_audio: bool) -> bool {
+        let Some(id) = &metadata.channel_id else {
+            return false;
+        };
+        let choice = ChannelChoice {
+            name: metadata.channel.clone().unwrap_or_else(|| id.clone()),
+            format: format.clone(),
+            extract_audio,
+            updated_at: Local::now(),
+        };
+        self.channels.insert(id.clone(), choice);
+        true
+    }
+
+    pub fn forget(&mut self, id: &str) -> Option<ChannelChoice> {
+        self.channels.remove(id)
+    }
+
+    // The remembered choice for this job's channel, resolved against its formats
+    pub fn choice_for<'a>(&self, job: &'a Job) -> Option<(&'a Format, bool)> {
+        let choice = self.channels.get(job.metadata.channel_id.as_ref()?)?;
+        Some((job.equivalent_format(&choice.format)?, choice.extract_audio))
+    }
+
+    // By channel name, the order the management popup lists them in
+    pub fn entries(&self) -> Vec<(&String, &ChannelChoice)> {
+        let mut entries: Vec<_> = self.channels.iter().collect();
+        entries.sort_by_key(|(_, choice)| choice.name.to_lowercase());
+        entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.channels.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::format;
+
+    fn from_channel(id: Option<&str>, name: Option<&str>) -> Metadata {
+        Metadata { channel_id: id.map(String::from), channel: name.map(String::from), ..Metadata::default() }
+    }
+
+    fn video(channel: &str, formats: &[&str]) -> Job {
+        let mut job = Job { formats: formats.iter().map(|f| format(f)).collect::<Vec<_>>().into(), ..Job::new("https://example.com/v/new") };
+        job.metadata.channel_id = Some(channel.into());
+        job
+    }
+
+    const P1080: &str = r#"{"format_id":"137","ext":"mp4","vcodec":"avc1","acodec":"none","height":1080}"#;
+    const P720: &str = r#"{"format_id":"136","ext":"mp4","vcodec":"avc1","acodec":"none","width":1280,"height":720}"#;
+    const WEBM720: &str = r#"{"format_id":"247","ext":"webm","vcodec":"vp9","acodec":"none","height":720}"#;
+    const M4A: &str = r#"{"format_id":"140","ext":"m4a","vcodec":"none","acodec":"mp4a","tbr":129}"#;
+    const OPUS: &str = r#"{"format_id":"251","ext":"webm","vcodec":"none","acodec":"opus","tbr":135}"#;
+
+    #[test]
+    fn a_choice_is_mapped_onto_the_new_videos_formats() {
+        let mut memory = ChannelMemory::default();
+        // Chosen on a video whose 1080p stream had another id
+        let chosen = format(r#"{"format_id":"399","ext":"mp4","vcodec":"av01","acodec":"none","height":1080}"#);
+        assert!(memory.remember(&from_channel(Some("UCtech"), Some("Tech")), &chosen, false));
+
+        let job = video("UCtech", &[P1080, P720, M4A]);
+        assert_eq!(memory.choice_for(&job).map(|(f, audio)| (f.format_id.as_str(), audio)), Some(("137", false)));
+
+        // Nothing that tall, so the tallest below it in the same container
+        let job = video("UCtech", &[WEBM720, P720, M4A]);
+        assert_eq!(memory.choice_for(&job).map(|(f, _)| f.format_id.as_str()), Some("136"));
+
+        let stranger = video("UCother", &[P1080]);
+        assert!(memory.choice_for(&stranger).is_none());
+    }
+
+    #[test]
+    fn audio_choices_stay_audio() {
+        let mut memory = ChannelMemory::default();
+        memory.remember(&from_channel(Some("UCmusic"), Some("Music")), &format(OPUS), true);
+
+        let job = video("UCmusic", &[P1080, M4A, OPUS]);
+        assert_eq!(memory.choice_for(&job).map(|(f, audio)| (f.format_id.as_str(), audio)), Some(("251", true)));
+        let job = video("UCmusic", &[P1080, M4A]);
+        assert_eq!(memory.choice_for(&job).map(|(f, _)| f.format_id.as_str()), Some("140"));
+    }
+
+    #[test]
+    fn only_videos_with_a_channel_id_are_remembered() {
+        let mut memory = ChannelMemory::default();
+        assert!(!memory.remember(&from_channel(None, Some("Nameless")), &format(P720), false));
+        assert_eq!(memory.len(), 0);
+
+        // The name falls back to the id, and a later choice replaces the earlier one
+        memory.remember(&from_channel(Some("UCb"), None), &format(P720), false);
+        memory.remember(&from_channel(Some("UCa"), Some("zebra")), &format(P720), false);
+        memory.remember(&from_channel(Some("UCc"), Some("Apple")), &format(P720), false);
+        memory.remember(&from_channel(Some("UCc"), Some("Apple")), &format(M4A), false);
+        assert_eq!(memory.len(), 3);
+        let listed: Vec<(&str, String)> = memory.entries().into_iter().map(|(id, c)| (id.as_str(), c.label())).collect();
+        assert_eq!(listed, [("UCc", "audio".to_string()), ("UCb", "1280x720 mp4".into()), ("UCa", "1280x720 mp4".into())]);
+
+        assert_eq!(memory.forget("UCb").map(|c| c.name), Some("UCb".into()));
+        assert!(memory.forget("UCb").is_none());
+        assert_eq!(memory.len(), 2);
+    }
+
+    #[test]
+    fn the_memory_reads_back_from_its_file_contents() {
+        let mut memory = ChannelMemory::default();
+        memory.remember(&from_channel(Some("UCmusic"), Some("Music")), &format(OPUS), true);
+        let saved = serde_json::to_vec_pretty(&memory).unwrap();
+
+        let read: ChannelMemory = serde_json::from_slice(&saved).unwrap();
+        let job = video("UCmusic", &[OPUS]);
+        assert_eq!(read.choice_for(&job).map(|(f, audio)| (f.format_id.as_str(), audio)), Some(("251", true)));
+        assert_eq!(read.entries()[0].1.name, "Music");
+    }
+}
@@ -0,0 +1,11 @@
+use std::io::Write;
+use base64::Engine;
+
+// OSC 52 asks the terminal itself to set the clipboard, which also works over SSH
+// and needs no display-server integration
+pub fn copy(text: &str) -> std::io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+    stdout.flush()
+}
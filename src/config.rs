@@ -1,14 +1,130 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
-use directories::ProjectDirs;
 use color_eyre::Result;
 
+use crate::core::{ChannelFormats, Credentials, OverwritePolicy, PlaylistAmbiguity, Secret};
+use crate::format::SizeUnits;
+use crate::paths::{self, PathOverrides};
+
+// Bumped with every entry in MIGRATIONS; files without a version are 0
+pub const CONFIG_VERSION: u32 = MIGRATIONS.len() as u32;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
+    pub version: u32,
     pub output_dir: PathBuf,
     pub output_template: String,
     pub max_concurrent_downloads: usize,
-    pub default_format: String,
+    pub adaptive_concurrency: bool,
+    pub default_quality: DefaultQuality,
+    pub autosave_interval_secs: u64,
+    pub audio_format: String,
+    pub audio_quality: String,
+    pub ytdlp_command: Vec<String>,
+    // Run by the update action instead of `ytdlp_command -U`, e.g.
+    // ["pip", "install", "-U", "yt-dlp"] where yt-dlp came from pip
+    pub ytdlp_update_command: Vec<String>,
+    // Extra environment for every yt-dlp run, e.g. ALL_PROXY or a venv's PATH
+    pub ytdlp_env: HashMap<String, String>,
+    // Where yt-dlp runs, so its cache and relative paths land somewhere known
+    pub ytdlp_cwd: Option<PathBuf>,
+    pub log_retention_days: u64,
+    pub cookies_from_browser: Option<String>,
+    // A Netscape-format cookies.txt, e.g. exported with a browser extension
+    pub cookies_file: Option<PathBuf>,
+    // For sites that sign in with an account (--username/--password); a sign-in
+    // entered for one job wins over these
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    // Look the login up in ~/.netrc by extractor name instead (--netrc)
+    pub use_netrc: bool,
+    pub wrap_format_list: bool,
+    pub size_units: SizeUnits,
+    pub date_format: String,
+    // Starting jobs bigger than this asks first; 0 turns the check off
+    pub large_download_threshold_mib: u64,
+    // Links added past this many items are dropped and playlists cut short; 0 turns it off
+    pub max_queue_size: usize,
+    // Passed to yt-dlp's --limit-rate, e.g. "2M" or "500K"
+    pub rate_limit: Option<String>,
+    // Two-letter country code to pose as for region-locked videos (--geo-bypass-country)
+    pub geo_bypass_country: Option<String>,
+    // Local IP to bind to, e.g. to pick an interface or force IPv4 (--source-address)
+    pub source_address: Option<String>,
+    // Sent with every request yt-dlp makes, e.g. a Referer some sites insist on
+    pub http_headers: HashMap<String, String>,
+    // Keep a JSON status file in the runtime dir for status bars
+    pub status_file: bool,
+    // Keep Prometheus counters in the runtime dir while the TUI runs, for long unattended sessions
+    pub metrics_file: bool,
+    pub overwrite_policy: OverwritePolicy,
+    // "20240305 - Title.mp4", for archiving channels in upload order
+    pub prefix_upload_date: bool,
+    // Desktop notifications for finished and failed downloads
+    pub notifications: bool,
+    // Finished items in a list of their own below the queue
+    pub split_queue: bool,
+    // Hold back new downloads on battery, or below this charge (0 turns that off);
+    // power_auto_resume lets them go once on the charger, power_pause_active stops running ones too
+    pub pause_on_battery: bool,
+    pub pause_below_battery_percent: u8,
+    pub power_auto_resume: bool,
+    pub power_pause_active: bool,
+    // Silences desktop notifications only; status bar notices still show
+    pub do_not_disturb: bool,
+    // Soft subtitles inside mp4/mkv/webm files; other outputs get subtitle files beside them
+    pub embed_subs: bool,
+    // Title, uploader, upload date and chapter markers written into the file
+    pub embed_metadata: bool,
+    // Cover art inside the file; some containers need mutagen or AtomicParsley
+    pub embed_thumbnail: bool,
+    // The thumbnail as an image file beside the download
+    pub write_thumbnail: bool,
+    // Sidecar files for archiving: the full metadata as .info.json, and the
+    // video description as .description
+    pub write_info_json: bool,
+    pub write_description: bool,
+    // Reuse the format last confirmed for a channel on its other videos
+    pub channel_formats: ChannelFormats,
+    pub playlist_ambiguity: PlaylistAmbiguity,
+    // Look up the title of a YouTube link in the input box before it is added
+    pub url_preview: bool,
+    // Passed to yt-dlp as is, one array entry per argument: extra_args after the
+    // download options, extra_fetch_args on the format fetch
+    pub extra_args: Vec<String>,
+    pub extra_fetch_args: Vec<String>,
+    // Handed to yt-dlp's --downloader, e.g. "aria2c" for faster fragmented downloads,
+    // with external_downloader_args as its --downloader-args
+    pub external_downloader: Option<String>,
+    pub external_downloader_args: Vec<String>,
+    // Keep removed jobs in the trash across restarts, saved with the queue
+    pub persist_trash: bool,
+    // Where the queue, history, cache and logs go; unset entries use the platform default
+    pub paths: PathOverrides,
+    // What load upgraded in an older file, logged once tracing is up
+    #[serde(skip)]
+    pub migration_notes: Vec<String>,
+}
+
+// What downloads use when nothing else picks a format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DefaultQuality {
+    // A yt-dlp format expression
+    pub format: String,
+    // Prefer nothing taller than this, settling for `format` as is when nothing is
+    pub max_height: Option<u32>,
+}
+
+impl Default for DefaultQuality {
+    fn default() -> Self {
+        Self {
+            format: "bestvideo+bestaudio/best".into(),
+            max_height: None,
+        }
+    }
 }
 
 impl Default for Config {
@@ -18,59 +134,584 @@ impl Default for Config {
             .unwrap_or_else(|| PathBuf::from("."));
 
         Self {
+            version: CONFIG_VERSION,
             output_dir: video_dir,
             output_template: "%(title)s.%(ext)s".into(),
             max_concurrent_downloads: 3,
-            default_format: "bestvideo+bestaudio/best".into(),
+            adaptive_concurrency: false,
+            default_quality: DefaultQuality::default(),
+            autosave_interval_secs: 5,
+            audio_format: "mp3".into(),
+            audio_quality: "192K".into(),
+            ytdlp_command: vec!["yt-dlp".into()],
+            ytdlp_update_command: Vec::new(),
+            ytdlp_env: HashMap::new(),
+            ytdlp_cwd: None,
+            log_retention_days: 14,
+            cookies_from_browser: None,
+            cookies_file: None,
+            username: None,
+            password: None,
+            use_netrc: false,
+            wrap_format_list: true,
+            size_units: SizeUnits::Binary,
+            date_format: "%Y-%m-%d %H:%M".into(),
+            large_download_threshold_mib: 2048,
+            max_queue_size: 5000,
+            rate_limit: None,
+            geo_bypass_country: None,
+            source_address: None,
+            http_headers: HashMap::new(),
+            status_file: false,
+            metrics_file: false,
+            overwrite_policy: OverwritePolicy::default(),
+            prefix_upload_date: false,
+            notifications: false,
+            split_queue: false,
+            pause_on_battery: false,
+            pause_below_battery_percent: 0,
+            power_auto_resume: true,
+            power_pause_active: false,
+            do_not_disturb: false,
+            embed_subs: false,
+            embed_metadata: false,
+            embed_thumbnail: false,
+            write_thumbnail: false,
+            write_info_json: false,
+            write_description: false,
+            channel_formats: ChannelFormats::default(),
+            playlist_ambiguity: PlaylistAmbiguity::default(),
+            url_preview: true,
+            extra_args: Vec::new(),
+            extra_fetch_args: Vec::new(),
+            external_downloader: None,
+            external_downloader_args: Vec::new(),
+            persist_trash: false,
+            paths: PathOverrides::default(),
+            migration_notes: Vec::new(),
         }
     }
 }
 
 impl Config {
+    // yt-dlp may be a multi-word invocation such as `python3 -m yt_dlp`
+    pub fn ytdlp_program(&self) -> (&str, &[String]) {
+        match self.ytdlp_command.split_first() {
+            Some((program, args)) => (program.as_str(), args),
+            None => ("yt-dlp", &[]),
+        }
+    }
+
+    pub fn ytdlp_command(&self) -> tokio::process::Command {
+        let (program, args) = self.ytdlp_program();
+        let mut command = tokio::process::Command::new(program);
+        command.args(args).envs(&self.ytdlp_env);
+        if let Some(dir) = &self.ytdlp_cwd {
+            command.current_dir(dir);
+        }
+        command
+    }
+
+    // The words of the update command, for showing what is being run
+    pub fn ytdlp_update_words(&self) -> Vec<String> {
+        if !self.ytdlp_update_command.is_empty() {
+            return self.ytdlp_update_command.clone();
+        }
+        let (program, args) = self.ytdlp_program();
+        std::iter::once(program).chain(args.iter().map(String::as_str)).chain(["-U"]).map(String::from).collect()
+    }
+
+    // Same environment and directory as yt-dlp itself, so a venv's pip is found
+    pub fn ytdlp_update_command(&self) -> tokio::process::Command {
+        let words = self.ytdlp_update_words();
+        let mut command = tokio::process::Command::new(&words[0]);
+        command.args(&words[1..]).envs(&self.ytdlp_env);
+        if let Some(dir) = &self.ytdlp_cwd {
+            command.current_dir(dir);
+        }
+        command
+    }
+
+    // The account from the config file; a missing password is sent empty, since
+    // yt-dlp would otherwise wait for one on a terminal it doesn't have
+    pub fn credentials(&self) -> Option<Credentials> {
+        let username = self.username.as_deref().map(str::trim).filter(|u| !u.is_empty())?;
+        Some(Credentials {
+            username: username.to_string(),
+            password: self.password.clone().unwrap_or_default(),
+        })
+    }
+
+    // Spawning in a missing directory fails with a bare "No such file or directory"
+    pub fn ytdlp_cwd_error(&self) -> Option<String> {
+        let dir = self.ytdlp_cwd.as_ref()?;
+        (!dir.is_dir()).then(|| format!("ytdlp_cwd {} is not a directory", dir.display()))
+    }
+
+    // default_quality as one expression for -f
+    pub fn default_format(&self) -> String {
+        let format = &self.default_quality.format;
+        match self.default_quality.max_height {
+            Some(height) => format!("({})[height<={}]/{}", format, height, format),
+            None => format.clone(),
+        }
+    }
+
+    pub fn large_download_threshold(&self) -> Option<u64> {
+        (self.large_download_threshold_mib > 0).then(|| self.large_download_threshold_mib * 1024 * 1024)
+    }
+
+    pub fn max_queue_size(&self) -> Option<usize> {
+        (self.max_queue_size > 0).then_some(self.max_queue_size)
+    }
+
+    pub fn rate_limit_bytes(&self) -> Option<u64> {
+        parse_rate(self.rate_limit.as_deref()?)
+    }
+
+    // Why yt-dlp would reject cookies_from_browser, which takes
+    // BROWSER[+KEYRING][:PROFILE][::CONTAINER]
+    pub fn cookie_browser_error(&self) -> Option<String> {
+        let spec = self.cookies_from_browser.as_deref()?.trim();
+        let name = spec.split(':').next().unwrap_or_default();
+        let (browser, keyring) = match name.split_once('+') {
+            Some((browser, keyring)) => (browser, Some(keyring)),
+            None => (name, None),
+        };
+        if !SUPPORTED_BROWSERS.contains(&browser.to_lowercase().as_str()) {
+            return Some(format!("cookies_from_browser: unknown browser {:?}, expected one of {}", browser, SUPPORTED_BROWSERS.join(", ")));
+        }
+        match keyring {
+            Some(keyring) if !SUPPORTED_KEYRINGS.contains(&keyring.to_lowercase().as_str()) => {
+                Some(format!("cookies_from_browser: unknown keyring {:?}, expected one of {}", keyring, SUPPORTED_KEYRINGS.join(", ")))
+            }
+            _ => None,
+        }
+    }
+
     pub fn config_path() -> Option<PathBuf> {
-        ProjectDirs::from("com", "oxidlp", "oxidlp")
-            .map(|dirs| dirs.config_dir().join("config.toml"))
+        paths::config_file()
     }
 
     pub async fn load() -> Result<Self> {
-        let Some(path) = Self::config_path() else {
-            return Ok(Self::default());
-        };
+        match Self::config_path() {
+            Some(path) => Self::load_from(&path).await,
+            None => Ok(Self::default()),
+        }
+    }
 
+    pub async fn load_from(path: &Path) -> Result<Self> {
         if !path.exists() {
             return Ok(Self::default());
         }
 
-        let content = tokio::fs::read_to_string(&path).await?;
-        let config: Config = toml::from_str(&content)?;
-        Ok(config)
+        let content = tokio::fs::read_to_string(path).await?;
+        let parsed = toml::from_str::<toml::Table>(&content).map_err(color_eyre::Report::from).and_then(|mut table| {
+            let notes = migrate(&mut table);
+            let mut config: Config = toml::Value::Table(table).try_into()?;
+            if let Some(error) = config.extra_args_error().or_else(|| config.http_headers_error()) {
+                color_eyre::eyre::bail!("{}", error);
+            }
+            config.migration_notes = notes;
+            Ok(config)
+        });
+        // The defaults get saved over it sooner or later, so keep what was there
+        if parsed.is_err() {
+            let broken = paths::sibling(path, ".broken");
+            if tokio::fs::copy(path, &broken).await.is_ok() {
+                return parsed.map_err(|e| e.wrap_err(format!("kept as {}", broken.display())));
+            }
+        }
+        parsed
+    }
+
+    // Flags oxidlp sets itself or reads the output of; passing them again would
+    // send the file elsewhere or break progress and format parsing
+    pub fn extra_args_error(&self) -> Option<String> {
+        let lists = [("extra_args", &self.extra_args), ("extra_fetch_args", &self.extra_fetch_args)];
+        lists.into_iter().find_map(|(name, args)| {
+            let flag = args.iter().find(|arg| is_reserved_flag(arg))?;
+            Some(format!("{} can't contain {}: oxidlp sets the output and reads yt-dlp's own output", name, flag))
+        })
+    }
+
+    // A colon would move part of the name into the value, and a line break
+    // would smuggle in a header of its own
+    pub fn http_headers_error(&self) -> Option<String> {
+        self.http_headers.iter().find_map(|(name, value)| {
+            if name.trim().is_empty() || name.contains([':', '\n', '\r']) {
+                Some(format!("http_headers has an invalid header name {:?}", name))
+            } else if value.contains(['\n', '\r']) {
+                Some(format!("http_headers value for {} contains a line break", name))
+            } else {
+                None
+            }
+        })
+    }
+
+    // Sorted so commands come out the same every time
+    pub fn sorted_headers(&self) -> Vec<(&String, &String)> {
+        let mut headers: Vec<_> = self.http_headers.iter().collect();
+        headers.sort();
+        headers
     }
 
     pub async fn save(&self) -> Result<()> {
         let Some(path) = Self::config_path() else {
             return Ok(());
         };
+        self.validate().await?;
+        self.write_to(&path).await
+    }
 
-        if let Some(parent) = path.parent() {
-            tokio::fs::create_dir_all(parent).await?;
+    // Every download would fail on these, so they're caught before the config is
+    // used or saved rather than job by job
+    pub async fn validate(&self) -> Result<()> {
+        check_external_downloader(self).await?;
+        check_geo_bypass_country(self)?;
+        if let Some(error) = self.ytdlp_cwd_error() {
+            color_eyre::eyre::bail!("{}", error);
         }
-
-        let content = toml::to_string_pretty(self)?;
-        tokio::fs::write(&path, content).await?;
         Ok(())
     }
+
+    pub async fn write_to(&self, path: &Path) -> Result<()> {
+        paths::write_atomic(path, self.to_toml()?.as_bytes()).await
+    }
+
+    // The file as it would be written, so two configs can be compared setting by setting
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(&Config { version: CONFIG_VERSION, ..self.clone() })?)
+    }
+}
+
+const RESERVED_FLAGS: &[&str] = &[
+    "-o", "--output", "-P", "--paths", "--print", "-O", "--print-to-file", "-j", "--dump-json", "-J",
+    "--dump-single-json", "-s", "--simulate", "--skip-download", "-q", "--quiet", "--no-progress",
+    "--progress-template", "--flat-playlist", "--newline", "--no-newline",
+];
+
+// Also catches `--output=x` and a short flag with its value attached, like `-ofile`
+fn is_reserved_flag(arg: &str) -> bool {
+    let name = arg.split_once('=').map_or(arg, |(name, _)| name);
+    RESERVED_FLAGS.contains(&name) || (!arg.starts_with("--") && ["-o", "-P", "-O"].iter().any(|f| arg.starts_with(f)))
+}
+
+// Environment variables and headers whose values stay out of logs and shown commands
+pub fn is_secret_key(key: &str) -> bool {
+    const MARKERS: &[&str] = &["TOKEN", "SECRET", "PASSWORD", "PASSWD", "KEY", "AUTH", "CREDENTIAL", "COOKIE"];
+    let key = key.to_uppercase();
+    MARKERS.iter().any(|m| key.contains(m))
 }
 
-pub async fn check_ytdlp() -> Result<String> {
-    let output = tokio::process::Command::new("yt-dlp")
+type Migration = fn(&mut toml::Table) -> Vec<String>;
+
+// MIGRATIONS[n] takes a version n file to n + 1, returning what it changed
+const MIGRATIONS: &[Migration] = &[quality_table];
+
+// Runs every step the file is behind on; a file from a newer oxidlp is left alone
+fn migrate(table: &mut toml::Table) -> Vec<String> {
+    let version = table.get("version").and_then(toml::Value::as_integer).unwrap_or(0).max(0) as usize;
+    let mut notes = Vec::new();
+    for (from, step) in MIGRATIONS.iter().enumerate().skip(version) {
+        notes.extend(step(table).into_iter().map(|note| format!("config v{} → v{}: {}", from, from + 1, note)));
+    }
+    if version > MIGRATIONS.len() {
+        notes.push(format!("config version {} is newer than this oxidlp knows ({}); unknown settings are ignored", version, CONFIG_VERSION));
+    } else {
+        table.insert("version".into(), toml::Value::Integer(CONFIG_VERSION as i64));
+    }
+    notes
+}
+
+// v0 → v1: `default_format = "..."` becomes a [default_quality] table, with a
+// `[height<=N]` written into every part of the expression pulled out as max_height
+fn quality_table(table: &mut toml::Table) -> Vec<String> {
+    let Some(toml::Value::String(format)) = table.remove("default_format") else {
+        return Vec::new();
+    };
+    let heights: Vec<&str> = format.split("[height<=").skip(1).filter_map(|rest| rest.split_once(']')).map(|(n, _)| n).collect();
+    let max_height = match heights.split_first() {
+        Some((first, rest)) if rest.iter().all(|h| h == first) => first.parse::<u32>().ok(),
+        _ => None,
+    };
+    let mut quality = toml::Table::new();
+    let note = match max_height {
+        Some(height) => {
+            let plain = format.replace(&format!("[height<={}]", height), "");
+            quality.insert("max_height".into(), toml::Value::Integer(height as i64));
+            quality.insert("format".into(), toml::Value::String(plain.clone()));
+            format!("default_format {:?} moved to [default_quality] as format {:?}, max_height {}", format, plain, height)
+        }
+        None => {
+            quality.insert("format".into(), toml::Value::String(format.clone()));
+            format!("default_format {:?} moved to [default_quality]", format)
+        }
+    };
+    table.insert("default_quality".into(), toml::Value::Table(quality));
+    vec![note]
+}
+
+// The names yt-dlp's --cookies-from-browser knows
+pub const SUPPORTED_BROWSERS: [&str; 9] = ["brave", "chrome", "chromium", "edge", "firefox", "opera", "safari", "vivaldi", "whale"];
+const SUPPORTED_KEYRINGS: [&str; 5] = ["basictext", "gnomekeyring", "kwallet", "kwallet5", "kwallet6"];
+
+// Browsers with a profile directory on this machine. Covers the usual Linux,
+// macOS and Windows locations; a browser installed elsewhere can still be set by hand
+pub fn detect_browsers() -> Vec<&'static str> {
+    let Some(base) = directories::BaseDirs::new() else {
+        return Vec::new();
+    };
+    let (home, config, local) = (base.home_dir(), base.config_dir(), base.data_local_dir());
+    let candidates: [(&str, Vec<PathBuf>); 9] = [
+        ("firefox", vec![home.join(".mozilla/firefox"), home.join("snap/firefox"), config.join("Firefox"), config.join("Mozilla/Firefox")]),
+        ("chrome", vec![config.join("google-chrome"), config.join("Google/Chrome"), local.join("Google/Chrome")]),
+        ("chromium", vec![config.join("chromium"), config.join("Chromium"), local.join("Chromium")]),
+        ("edge", vec![config.join("microsoft-edge"), config.join("Microsoft Edge"), local.join("Microsoft/Edge")]),
+        ("brave", vec![config.join("BraveSoftware/Brave-Browser"), local.join("BraveSoftware/Brave-Browser")]),
+        ("opera", vec![config.join("opera"), config.join("com.operasoftware.Opera"), config.join("Opera Software/Opera Stable")]),
+        ("vivaldi", vec![config.join("vivaldi"), config.join("Vivaldi"), local.join("Vivaldi")]),
+        ("whale", vec![config.join("naver-whale"), local.join("Naver/Naver Whale")]),
+        ("safari", vec![home.join("Library/Safari")]),
+    ];
+    candidates
+        .into_iter()
+        .filter(|(_, dirs)| dirs.iter().any(|d| d.is_dir()))
+        .map(|(browser, _)| browser)
+        .collect()
+}
+
+// yt-dlp reads the suffixes as binary multiples
+pub fn parse_rate(limit: &str) -> Option<u64> {
+    let limit = limit.trim();
+    let split = limit.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(limit.len());
+    let (number, suffix) = limit.split_at(split);
+    let multiplier = match suffix.to_ascii_uppercase().as_str() {
+        "" | "B" => 1.0,
+        "K" | "KB" | "KIB" => 1024.0,
+        "M" | "MB" | "MIB" => 1024.0 * 1024.0,
+        "G" | "GB" | "GIB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number.trim().parse::<f64>().ok()? * multiplier) as u64)
+}
+
+pub const LOG_FILE_PREFIX: &str = "oxidlp.log";
+
+pub fn log_dir() -> PathBuf {
+    paths::get().log_dir.clone()
+}
+
+// The daily appender names files by UTC date
+pub fn current_log_file() -> PathBuf {
+    log_dir().join(format!("{}.{}", LOG_FILE_PREFIX, chrono::Utc::now().format("%Y-%m-%d")))
+}
+
+// Deletes rolled log files last written more than `max_age_days` ago and
+// returns how many were removed; the file currently being written is kept
+pub fn clear_old_logs(max_age_days: u64) -> Result<usize> {
+    let max_age = std::time::Duration::from_secs(max_age_days * 24 * 60 * 60);
+    let current = current_log_file();
+    let mut removed = 0;
+
+    for entry in std::fs::read_dir(log_dir())? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_log = entry.file_name().to_string_lossy().starts_with(LOG_FILE_PREFIX);
+        if !is_log || path == current {
+            continue;
+        }
+        let age = entry.metadata()?.modified()?.elapsed().unwrap_or_default();
+        if age > max_age {
+            std::fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+// yt-dlp's own downloaders need nothing installed; anything else is a program on PATH
+const BUILTIN_DOWNLOADERS: &[&str] = &["native", "ffmpeg", "avconv"];
+
+// yt-dlp takes any string here and only fails once a download tries to use it
+pub fn check_geo_bypass_country(config: &Config) -> Result<()> {
+    match config.geo_bypass_country.as_deref() {
+        Some(code) if code.len() != 2 || !code.chars().all(|c| c.is_ascii_alphabetic()) => {
+            color_eyre::eyre::bail!("geo_bypass_country {:?} is not a two-letter country code such as \"US\"", code)
+        }
+        _ => Ok(()),
+    }
+}
+
+// The external downloader's version line, or None when none is set
+pub async fn check_external_downloader(config: &Config) -> Result<Option<String>> {
+    let Some(name) = config.external_downloader.as_deref().filter(|n| !BUILTIN_DOWNLOADERS.contains(n)) else {
+        return Ok(None);
+    };
+    let output = tokio::process::Command::new(name)
         .arg("--version")
+        .kill_on_drop(true)
         .output()
-        .await?;
+        .await
+        .map_err(|_| color_eyre::eyre::eyre!("external_downloader {:?} is not installed or not in PATH", name))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(Some(stdout.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or(name).to_string()))
+}
+
+pub async fn check_ytdlp(config: &Config) -> Result<String> {
+    let output = config
+        .ytdlp_command()
+        .arg("--version")
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| color_eyre::eyre::eyre!("Could not run `{}`: {}", config.ytdlp_command.join(" "), e))?;
 
     if !output.status.success() {
         color_eyre::eyre::bail!("yt-dlp is not installed or not in PATH");
     }
 
-    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    Ok(version)
+    // Module invocations can print interpreter warnings before the version
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = stdout.lines().rev().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default();
+    Ok(version.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    async fn load(contents: &str) -> (TempDir, Result<Config>) {
+        let dir = TempDir::new("config");
+        let path = dir.join("config.toml");
+        tokio::fs::write(&path, contents).await.unwrap();
+        let loaded = Config::load_from(&path).await;
+        (dir, loaded)
+    }
+
+    #[tokio::test]
+    async fn a_v0_file_is_upgraded_and_keeps_its_settings() {
+        let (_dir, loaded) = load("max_concurrent_downloads = 5\ndefault_format = \"bestvideo[height<=720]+bestaudio/best[height<=720]\"\n").await;
+        let config = loaded.unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.max_concurrent_downloads, 5);
+        assert_eq!(config.default_quality, DefaultQuality { format: "bestvideo+bestaudio/best".into(), max_height: Some(720) });
+        assert_eq!(config.migration_notes.len(), 1);
+        assert!(config.migration_notes[0].starts_with("config v0 → v1: default_format"), "{:?}", config.migration_notes);
+
+        // Heights that differ can't be pulled out, so the expression moves over whole
+        let (_dir, loaded) = load("default_format = \"best[height<=720]/best[height<=1080]\"\n").await;
+        assert_eq!(loaded.unwrap().default_quality, DefaultQuality { format: "best[height<=720]/best[height<=1080]".into(), max_height: None });
+
+        let (_dir, loaded) = load("max_concurrent_downloads = 2\n").await;
+        let config = loaded.unwrap();
+        assert_eq!((config.version, config.default_quality), (CONFIG_VERSION, DefaultQuality::default()));
+        assert!(config.migration_notes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_current_file_loads_as_written() {
+        let dir = TempDir::new("config");
+        let path = dir.join("config.toml");
+        let config = Config { max_concurrent_downloads: 6, default_quality: DefaultQuality { format: "best".into(), max_height: Some(480) }, ..Config::default() };
+        config.write_to(&path).await.unwrap();
+
+        let loaded = Config::load_from(&path).await.unwrap();
+        assert!(loaded.migration_notes.is_empty());
+        assert_eq!(loaded.to_toml().unwrap(), config.to_toml().unwrap());
+
+        // The next save keeps one backup of what it replaced and leaves no temp file behind
+        Config { max_concurrent_downloads: 1, ..loaded }.write_to(&path).await.unwrap();
+        let backup = Config::load_from(&paths::sibling(&path, ".bak")).await.unwrap();
+        assert_eq!(backup.max_concurrent_downloads, 6);
+        assert_eq!(Config::load_from(&path).await.unwrap().max_concurrent_downloads, 1);
+        assert!(!paths::sibling(&path, ".tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn a_file_from_a_newer_version_is_read_but_not_downgraded() {
+        let (_dir, loaded) = load("version = 99\nmax_concurrent_downloads = 4\nsome_future_setting = true\n").await;
+        let config = loaded.unwrap();
+        assert_eq!((config.version, config.max_concurrent_downloads), (99, 4));
+        assert!(config.migration_notes[0].contains("newer than this oxidlp knows"), "{:?}", config.migration_notes);
+    }
+
+    #[tokio::test]
+    async fn a_damaged_file_is_kept_as_broken() {
+        for contents in ["max_concurrent_downloads = [\n", "max_concurrent_downloads = \"many\"\n", "extra_args = [\"-o\", \"x\"]\n"] {
+            let (dir, loaded) = load(contents).await;
+            let error = loaded.expect_err(contents);
+            let broken = dir.join("config.toml.broken");
+            assert!(format!("{:#}", error).contains(&broken.display().to_string()), "{:#}", error);
+            assert_eq!(tokio::fs::read_to_string(&broken).await.unwrap(), contents);
+            assert_eq!(tokio::fs::read_to_string(dir.join("config.toml")).await.unwrap(), contents, "the original stays put");
+            }
+    }
+
+    #[test]
+    fn every_ytdlp_command_gets_the_env_and_directory() {
+        let dir = std::env::temp_dir();
+        let config = Config {
+            ytdlp_command: vec!["python3".into(), "-m".into(), "yt_dlp".into()],
+            ytdlp_env: HashMap::from([("ALL_PROXY".into(), "socks5://127.0.0.1:9050".into())]),
+            ytdlp_cwd: Some(dir.clone()),
+            ..Config::default()
+        };
+        for command in [config.ytdlp_command(), config.ytdlp_update_command()] {
+            let command = command.as_std();
+            assert_eq!(command.get_program(), "python3");
+            assert_eq!(command.get_args().take(2).collect::<Vec<_>>(), ["-m", "yt_dlp"]);
+            let envs: Vec<_> = command.get_envs().collect();
+            assert_eq!(envs, [(std::ffi::OsStr::new("ALL_PROXY"), Some(std::ffi::OsStr::new("socks5://127.0.0.1:9050")))]);
+            assert_eq!(command.get_current_dir(), Some(dir.as_path()));
+        }
+
+        let plain = Config::default().ytdlp_command();
+        assert_eq!(plain.as_std().get_envs().count(), 0);
+        assert_eq!(plain.as_std().get_current_dir(), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn the_spawned_process_sees_them() {
+        let dir = TempDir::new("config");
+        let config = Config {
+            ytdlp_command: vec!["sh".into(), "-c".into(), "echo \"$OXIDLP_PROBE\"; pwd -P".into()],
+            ytdlp_env: HashMap::from([("OXIDLP_PROBE".into(), "from the config".into())]),
+            ytdlp_cwd: Some(dir.to_path_buf()),
+            ..Config::default()
+        };
+        let output = config.ytdlp_command().output().await.unwrap();
+        let expected = format!("from the config\n{}\n", dir.canonicalize().unwrap().display());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    }
+
+    #[test]
+    fn the_working_directory_must_exist() {
+        let dir = TempDir::new("config");
+        let missing = dir.join("missing");
+        let config = Config { ytdlp_cwd: Some(missing.clone()), ..Config::default() };
+        assert_eq!(config.ytdlp_cwd_error(), Some(format!("ytdlp_cwd {} is not a directory", missing.display())));
+        let config = Config { ytdlp_cwd: Some(std::env::temp_dir()), ..Config::default() };
+        assert_eq!(config.ytdlp_cwd_error(), None);
+        assert_eq!(Config::default().ytdlp_cwd_error(), None);
+    }
+
+    #[test]
+    fn secret_keys_are_spotted_by_name() {
+        for (key, secret) in [
+            ("API_TOKEN", true),
+            ("github_token", true),
+            ("AWS_SECRET_ACCESS_KEY", true),
+            ("Authorization", true),
+            ("Cookie", true),
+            ("MY_PASSWD", true),
+            ("ALL_PROXY", false),
+            ("PATH", false),
+            ("Referer", false),
+        ] {
+            assert_eq!(is_secret_key(key), secret, "{}", key);
+        }
+    }
 }
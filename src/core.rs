@@ -0,0 +1,1761 @@
+//! The download engine without the TUI: the job and format types, the worker
+//! pool that runs yt-dlp, and the events it reports back.
+//!
+//! Add a URL, follow its events and get the file path:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use oxidlp::core::{Config, Job, WorkerCommand, WorkerEvent, WorkerPool};
+//! use tokio::sync::mpsc;
+//!
+//! # async fn example() {
+//! let (command_tx, command_rx) = mpsc::channel(32);
+//! let (event_tx, mut event_rx) = mpsc::channel(32);
+//! tokio::spawn(WorkerPool::new(Arc::new(Config::default()), command_rx, event_tx).run());
+//!
+//! let job = Job::new("https://www.youtube.com/watch?v=dQw4w9WgXcQ");
+//! let request = job.request_with_format("bestvideo+bestaudio/best".into());
+//! command_tx.send(WorkerCommand::StartJob(request)).await.unwrap();
+//!
+//! while let Some(event) = event_rx.recv().await {
+//!     match event {
+//!         WorkerEvent::JobProgress { percent, .. } => println!("{:.1}%", percent),
+//!         WorkerEvent::JobCompleted { path, .. } => {
+//!             println!("saved to {}", path.display());
+//!             break;
+//!         }
+//!         WorkerEvent::JobFailed { error, .. } => {
+//!             eprintln!("failed ({}): {}", error.kind.label(), error);
+//!             break;
+//!         }
+//!         _ => {}
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! To let the user pick a format first, send `WorkerCommand::FetchFormats` and
+//! choose from the list in `WorkerEvent::FormatsReady`.
+//!
+//! Every yt-dlp run goes through the `YtdlpRunner` trait. `WorkerPool::new`
+//! uses the real binary (`Ytdlp`); `WorkerPool::with_runner` takes any other
+//! implementation, such as one that replays scripted progress without a network.
+//!
+//! A config that changes while the pool runs can be sent with
+//! `WorkerCommand::UpdateConfig`, or followed through a `tokio::sync::watch`
+//! channel with `WorkerPool::watch_config`.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+use uuid::Uuid;
+
+pub use crate::config::Config;
+pub use crate::worker::{WorkerPool, Ytdlp, YtdlpRunner};
+use crate::format;
+
+pub type JobId = Uuid;
+pub type GroupId = Uuid;
+
+// First eight hex digits of a job id, enough to grep one job out of the log
+pub fn short_id(id: JobId) -> String {
+    let mut short = id.simple().to_string();
+    short.truncate(8);
+    short
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Format {
+    pub format_id: String,
+    #[serde(default)]
+    pub resolution: Option<String>,
+    #[serde(default)]
+    pub ext: String,
+    #[serde(default)]
+    pub vcodec: Option<String>,
+    #[serde(default)]
+    pub acodec: Option<String>,
+    #[serde(default)]
+    pub filesize: Option<u64>,
+    #[serde(default)]
+    pub filesize_approx: Option<u64>,
+    #[serde(default)]
+    pub tbr: Option<f64>,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+}
+
+impl Format {
+    pub fn display_resolution(&self) -> Cow<'_, str> {
+        if let (Some(w), Some(h)) = (self.width, self.height) {
+            Cow::Owned(format!("{}x{}", w, h))
+        } else if let Some(res) = &self.resolution {
+            Cow::Borrowed(res)
+        } else {
+            Cow::Borrowed("audio")
+        }
+    }
+
+    pub fn size(&self) -> Option<u64> {
+        self.filesize.or(self.filesize_approx)
+    }
+
+    pub fn display_size(&self) -> Cow<'_, str> {
+        match self.size() {
+            Some(b) => Cow::Owned(format::size(b)),
+            None => Cow::Borrowed("~"),
+        }
+    }
+
+    // Codec family without the profile suffix, e.g. "avc1" from "avc1.640028"
+    pub fn display_codec(&self) -> &str {
+        let codec = if self.is_video() { &self.vcodec } else { &self.acodec };
+        codec
+            .as_deref()
+            .and_then(|c| c.split('.').next())
+            .unwrap_or("?")
+    }
+
+    pub fn display_bitrate(&self) -> Cow<'_, str> {
+        match self.tbr {
+            Some(br) => Cow::Owned(format!("{:.0} kbps", br)),
+            None => Cow::Borrowed("~"),
+        }
+    }
+
+    pub fn is_video(&self) -> bool {
+        self.vcodec.as_ref().map(|v| v != "none").unwrap_or(false)
+    }
+
+    pub fn has_audio(&self) -> bool {
+        self.acodec.as_ref().map(|a| a != "none").unwrap_or(false)
+    }
+
+    pub fn is_audio_only(&self) -> bool {
+        !self.is_video() && self.has_audio()
+    }
+
+    // Video-only streams get merged with the best audio by ffmpeg
+    pub fn needs_merge(&self) -> bool {
+        self.is_video() && !self.has_audio()
+    }
+
+    // Soft subtitles fit in mp4, mkv and webm video; merges always end up in one of those
+    pub fn holds_subtitles(&self) -> bool {
+        self.is_video() && (self.needs_merge() || matches!(self.ext.as_str(), "mp4" | "mkv" | "webm"))
+    }
+
+    pub fn download_spec(&self) -> String {
+        if self.needs_merge() {
+            format!("{}+bestaudio/best", self.format_id)
+        } else {
+            self.format_id.clone()
+        }
+    }
+
+    pub fn stream_tag(&self) -> &'static str {
+        match (self.is_video(), self.has_audio()) {
+            (true, true) => "[V+A]",
+            (true, false) => "[V]",
+            (false, true) => "[A]",
+            (false, false) => "[?]",
+        }
+    }
+}
+
+// Target bitrate for yt-dlp's --audio-quality: either an explicit rate like
+// "192K" or a 0 (best) to 10 (worst) VBR level, mapped to LAME's typical rates
+pub fn audio_quality_kbps(quality: &str) -> Option<f64> {
+    const VBR_KBPS: [f64; 11] = [245.0, 225.0, 190.0, 175.0, 165.0, 130.0, 115.0, 100.0, 85.0, 65.0, 45.0];
+
+    let quality = quality.trim();
+    if let Some(rate) = quality.strip_suffix(['K', 'k']) {
+        return rate.parse().ok();
+    }
+    let level: usize = quality.parse().ok()?;
+    VBR_KBPS.get(level).copied()
+}
+
+// The steps the format popup offers for --audio-quality, best first within each kind
+pub const AUDIO_QUALITIES: [&str; 12] = ["0", "2", "4", "6", "9", "320K", "256K", "192K", "160K", "128K", "96K", "64K"];
+
+// Lossless targets have no fixed bitrate to estimate from
+pub fn estimate_audio_size(duration_secs: f64, audio_format: &str, quality: &str) -> Option<u64> {
+    if matches!(audio_format, "flac" | "wav" | "alac") {
+        return None;
+    }
+    let kbps = audio_quality_kbps(quality)?;
+    Some((kbps * 1000.0 / 8.0 * duration_secs) as u64)
+}
+
+// The file a video download should end up in; Keep takes whatever yt-dlp produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Container {
+    #[default]
+    Keep,
+    Mp4,
+    Mkv,
+    Webm,
+}
+
+// Remuxing copies the streams into the new container in seconds; recoding
+// re-encodes them with ffmpeg and can take longer than the download
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Conversion {
+    Remux,
+    Recode,
+}
+
+impl Container {
+    pub const ALL: [Container; 4] = [Container::Keep, Container::Mp4, Container::Mkv, Container::Webm];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Container::Keep => "keep",
+            Container::Mp4 => "mp4",
+            Container::Mkv => "mkv",
+            Container::Webm => "webm",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|c| *c == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    // Codecs as yt-dlp names them, e.g. "avc1.64001F" or "mp4a.40.2"
+    fn holds(self, codec: &str) -> bool {
+        let accepted: &[&str] = match self {
+            Container::Keep | Container::Mkv => return true,
+            Container::Mp4 => &["avc1", "avc3", "h264", "hev1", "hvc1", "h265", "av01", "vp09", "vp9", "mp4a", "aac", "mp3", "opus", "ac-3", "ec-3", "flac", "alac"],
+            Container::Webm => &["vp8", "vp09", "vp9", "av01", "opus", "vorbis"],
+        };
+        let codec = codec.to_ascii_lowercase();
+        accepted.iter().any(|prefix| codec.starts_with(prefix))
+    }
+
+    // What getting `format` into this container takes. A video-only format is
+    // merged with the best audio, so that stream's codec counts too; a codec
+    // yt-dlp didn't report is assumed to fit rather than recoded for nothing
+    pub fn conversion(self, format: &Format, formats: &[Format]) -> Option<Conversion> {
+        if self == Container::Keep || !format.is_video() {
+            return None;
+        }
+        let audio = format.needs_merge().then(|| best_audio(formats)).flatten();
+        let fits = std::iter::once(format)
+            .chain(audio)
+            .flat_map(|f| [f.vcodec.as_deref(), f.acodec.as_deref()])
+            .flatten()
+            .filter(|codec| *codec != "none")
+            .all(|codec| self.holds(codec));
+        Some(if fits { Conversion::Remux } else { Conversion::Recode })
+    }
+}
+
+// Roughly what yt-dlp's "bestaudio" picks
+pub fn best_audio(formats: &[Format]) -> Option<&Format> {
+    formats
+        .iter()
+        .filter(|f| f.is_audio_only())
+        .max_by(|a, b| a.tbr.unwrap_or(0.0).total_cmp(&b.tbr.unwrap_or(0.0)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QualityTier {
+    P2160,
+    P1440,
+    P1080,
+    P720,
+    Lower,
+    Audio,
+}
+
+impl QualityTier {
+    pub const ALL: [QualityTier; 6] = [
+        QualityTier::P2160,
+        QualityTier::P1440,
+        QualityTier::P1080,
+        QualityTier::P720,
+        QualityTier::Lower,
+        QualityTier::Audio,
+    ];
+
+    // Anything above 2160p shares the top tier; formats with neither stream fit nowhere
+    pub fn of(format: &Format) -> Option<Self> {
+        if format.is_audio_only() {
+            return Some(QualityTier::Audio);
+        }
+        if !format.is_video() {
+            return None;
+        }
+        Some(match format.height.unwrap_or(0) {
+            h if h >= 2160 => QualityTier::P2160,
+            h if h >= 1440 => QualityTier::P1440,
+            h if h >= 1080 => QualityTier::P1080,
+            h if h >= 720 => QualityTier::P720,
+            _ => QualityTier::Lower,
+        })
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            QualityTier::P2160 => "2160p",
+            QualityTier::P1440 => "1440p",
+            QualityTier::P1080 => "1080p",
+            QualityTier::P720 => "720p",
+            QualityTier::Lower => "Lower",
+            QualityTier::Audio => "Audio",
+        }
+    }
+
+    // Indexes into `formats` of every format in this tier, best first
+    pub fn members(self, formats: &[Format]) -> Vec<usize> {
+        let mut members: Vec<usize> = (0..formats.len())
+            .filter(|&i| QualityTier::of(&formats[i]) == Some(self))
+            .collect();
+        // Stable, so equally ranked formats keep yt-dlp's order
+        members.sort_by(|&a, &b| rank(&formats[b]).total_cmp(&rank(&formats[a])));
+        members
+    }
+}
+
+// Height first, then bitrate, then a muxed stream over one that needs merging
+fn rank(format: &Format) -> f64 {
+    let height = format.height.unwrap_or(0) as f64;
+    let bitrate = format.tbr.unwrap_or(0.0).min(999_999.0);
+    let muxed = if format.has_audio() { 0.5 } else { 0.0 };
+    height * 1_000_000.0 + bitrate + muxed
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobGroup {
+    pub id: GroupId,
+    pub title: String,
+    pub url: String,
+}
+
+impl JobGroup {
+    pub fn new(title: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            title: title.into(),
+            url: url.into(),
+        }
+    }
+}
+
+// What to do when the file a job would write already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    // yt-dlp's own behaviour: leave the file alone and report it as downloaded
+    #[default]
+    Skip,
+    Overwrite,
+    // Download next to it with a " (1)" style suffix
+    KeepBoth,
+    Ask,
+}
+
+impl OverwritePolicy {
+    pub fn label(self) -> &'static str {
+        match self {
+            OverwritePolicy::Skip => "Skip",
+            OverwritePolicy::Overwrite => "Overwrite",
+            OverwritePolicy::KeepBoth => "Keep both",
+            OverwritePolicy::Ask => "Ask",
+        }
+    }
+}
+
+// What to do with a channel's remembered format when another of its videos is ready
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelFormats {
+    Off,
+    // Select it, but leave the job Ready for a look first
+    #[default]
+    Preselect,
+    // Select it and queue the job
+    Apply,
+}
+
+// What a watch link that also names a playlist (`watch?v=…&list=…`) adds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaylistAmbiguity {
+    #[default]
+    Ask,
+    Single,
+    Playlist,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TriagePreset {
+    Video1080,
+    Video720,
+    Audio,
+}
+
+impl TriagePreset {
+    pub fn max_height(&self) -> Option<u32> {
+        match self {
+            TriagePreset::Video1080 => Some(1080),
+            TriagePreset::Video720 => Some(720),
+            TriagePreset::Audio => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TriagePreset::Video1080 => "1080p",
+            TriagePreset::Video720 => "720p",
+            TriagePreset::Audio => "audio",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PartialProgress {
+    pub percent: f32,
+    pub downloaded: Option<u64>,
+}
+
+impl PartialProgress {
+    pub fn of(status: &JobStatus) -> Option<Self> {
+        match status {
+            JobStatus::Downloading { percent, total, .. } => Some(Self {
+                percent: *percent,
+                downloaded: total.map(|t| (t as f64 * *percent as f64 / 100.0) as u64),
+            }),
+            _ => None,
+        }
+    }
+
+    // Progress lines still in the pipe when the cancel lands can only move it forward
+    pub fn observe(&mut self, percent: f32, total: Option<u64>) {
+        if percent >= self.percent {
+            self.percent = percent;
+            if let Some(total) = total {
+                self.downloaded = Some((total as f64 * percent as f64 / 100.0) as u64);
+            }
+        }
+    }
+}
+
+// Running figures from a download's progress ticks, rather than every sample
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpeedStats {
+    pub peak_bps: u64,
+    // Time between ticks while transferring; merging and post-processing don't count
+    pub active_secs: f64,
+    // The finished file's size over active_secs, set on completion
+    pub avg_bps: Option<u64>,
+    #[serde(skip)]
+    last_tick: Option<Instant>,
+}
+
+impl SpeedStats {
+    pub fn observe(&mut self, now: Instant, phase: DownloadPhase, speed_bps: Option<u64>) {
+        if matches!(phase, DownloadPhase::Merging | DownloadPhase::Recoding) {
+            self.last_tick = None;
+            return;
+        }
+        if let Some(last) = self.last_tick {
+            self.active_secs += now.duration_since(last).as_secs_f64();
+        }
+        self.last_tick = Some(now);
+        self.peak_bps = self.peak_bps.max(speed_bps.unwrap_or(0));
+    }
+
+    pub fn finish(&mut self, bytes: Option<u64>) {
+        self.last_tick = None;
+        // A single tick or two says nothing about the average
+        if self.active_secs >= 1.0 {
+            self.avg_bps = bytes.map(|b| (b as f64 / self.active_secs) as u64);
+        }
+    }
+
+    // e.g. "avg 8.40 MiB/s, peak 12.10 MiB/s, 4:02"
+    pub fn summary(&self) -> Option<String> {
+        let avg = self.avg_bps?;
+        let mut parts = vec![format!("avg {}", format::rate(avg))];
+        if self.peak_bps > 0 {
+            parts.push(format!("peak {}", format::rate(self.peak_bps)));
+        }
+        parts.push(format::duration(self.active_secs));
+        Some(parts.join(", "))
+    }
+}
+
+// How many timeline entries a job keeps; the oldest go first
+pub const TIMELINE_CAP: usize = 50;
+
+// One state change in a job's life, for working out where the time went
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    pub at: DateTime<Local>,
+    pub event: String,
+}
+
+// Keeps passwords out of Debug output and therefore out of logs
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(pub String);
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("********")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: Secret,
+}
+
+// Everything the worker pool reports back, in the order it happened per job
+#[derive(Debug, Clone)]
+pub enum WorkerEvent {
+    // Jobs the worker holds back for a free slot, in the order they will start
+    SlotQueueChanged { waiting: Vec<JobId> },
+    TitleResolved { id: JobId, title: String },
+    // The worker has taken a StartJob off its channel
+    StartAccepted { id: JobId },
+    JobStarted { id: JobId },
+    JobSpawned { id: JobId, pid: u32 },
+    FormatsReady { id: JobId, title: String, formats: Vec<Format>, metadata: Metadata },
+    JobProgress { id: JobId, percent: f32, speed: String, speed_bps: Option<u64>, eta: String, phase: DownloadPhase, total: Option<u64> },
+    JobCompleted { id: JobId, path: PathBuf },
+    // A `WARNING:` line from yt-dlp while downloading
+    JobWarning { id: JobId, warning: String },
+    // Sent before JobCompleted; the download itself still succeeded
+    ThumbnailNotEmbedded { id: JobId, reason: String },
+    ThumbnailSaved { id: JobId, path: PathBuf },
+    // Also sent before JobCompleted: the info JSON, description and such
+    SidecarsSaved { id: JobId, paths: Vec<PathBuf> },
+    JobFailed { id: JobId, error: Failure },
+    // The destination exists and the policy is to ask
+    ConflictDetected { id: JobId, path: PathBuf },
+    ConcurrencyChanged { limit: usize, max: usize },
+    PlaylistProgress { id: JobId, found: usize },
+    PlaylistExpanded { id: JobId, url: String, title: Option<String>, urls: Vec<(String, Option<String>)> },
+    PlaylistFailed { id: JobId, url: String, error: String },
+    // Several downloads in a row failed at once with this same error; nothing
+    // more is started until ResumeQueue, or a probe succeeds
+    QueueHalted { reason: String },
+    QueueResumed,
+    // A line the update command printed, as it comes
+    YtdlpUpdateOutput { line: String },
+    YtdlpUpdated { success: bool, message: String },
+    // Same video as a job already being fetched or downloaded; `id` follows
+    // that run's events instead of starting its own
+    Coalesced { id: JobId, with: JobId },
+}
+
+impl WorkerEvent {
+    pub fn job_id(&self) -> Option<JobId> {
+        match self {
+            WorkerEvent::JobStarted { id }
+            | WorkerEvent::StartAccepted { id }
+            | WorkerEvent::JobSpawned { id, .. }
+            | WorkerEvent::FormatsReady { id, .. }
+            | WorkerEvent::PlaylistProgress { id, .. }
+            | WorkerEvent::PlaylistExpanded { id, .. }
+            | WorkerEvent::PlaylistFailed { id, .. }
+            | WorkerEvent::JobProgress { id, .. }
+            | WorkerEvent::JobCompleted { id, .. }
+            | WorkerEvent::ThumbnailNotEmbedded { id, .. }
+            | WorkerEvent::ThumbnailSaved { id, .. }
+            | WorkerEvent::SidecarsSaved { id, .. }
+            | WorkerEvent::JobWarning { id, .. }
+            | WorkerEvent::JobFailed { id, .. }
+            | WorkerEvent::ConflictDetected { id, .. }
+            | WorkerEvent::Coalesced { id, .. } => Some(*id),
+            _ => None,
+        }
+    }
+
+    // The same event for another job sharing this one's yt-dlp run
+    pub fn for_job(&self, job: JobId) -> WorkerEvent {
+        let mut event = self.clone();
+        match &mut event {
+            WorkerEvent::JobStarted { id }
+            | WorkerEvent::StartAccepted { id }
+            | WorkerEvent::JobSpawned { id, .. }
+            | WorkerEvent::FormatsReady { id, .. }
+            | WorkerEvent::PlaylistProgress { id, .. }
+            | WorkerEvent::PlaylistExpanded { id, .. }
+            | WorkerEvent::PlaylistFailed { id, .. }
+            | WorkerEvent::JobProgress { id, .. }
+            | WorkerEvent::JobCompleted { id, .. }
+            | WorkerEvent::ThumbnailNotEmbedded { id, .. }
+            | WorkerEvent::ThumbnailSaved { id, .. }
+            | WorkerEvent::SidecarsSaved { id, .. }
+            | WorkerEvent::JobWarning { id, .. }
+            | WorkerEvent::JobFailed { id, .. }
+            | WorkerEvent::ConflictDetected { id, .. }
+            | WorkerEvent::Coalesced { id, .. } => *id = job,
+            _ => {}
+        }
+        event
+    }
+
+    // The last event of a fetch or download run
+    pub fn ends_run(&self) -> bool {
+        matches!(
+            self,
+            WorkerEvent::FormatsReady { .. }
+                | WorkerEvent::JobCompleted { .. }
+                | WorkerEvent::JobFailed { .. }
+                | WorkerEvent::ConflictDetected { .. }
+        )
+    }
+}
+
+// How a completed file was produced, kept for reproducibility
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadRecord {
+    pub format_spec: String,
+    pub options: Vec<String>,
+    pub ytdlp_version: String,
+    // Runnable as copied; secrets are left out, never masked
+    pub command: String,
+    // What was left out of the command, to add back before running it
+    #[serde(default)]
+    pub left_out: Vec<String>,
+}
+
+// Everything the worker needs to run one download, captured when the job is started
+#[derive(Debug, Clone)]
+pub struct DownloadRequest {
+    pub job_id: JobId,
+    pub url: String,
+    pub title: Option<String>,
+    pub format_spec: String,
+    pub extract_audio: bool,
+    // Overrides the config's audio_quality for this download
+    pub audio_quality: Option<String>,
+    pub credentials: Option<Credentials>,
+    // None only for jobs that were never started, e.g. in --dry-run
+    pub output: Option<OutputTarget>,
+    // None follows the configured overwrite_policy
+    pub on_conflict: Option<OverwritePolicy>,
+    // Set when a cancelled attempt left a part file: true continues it, false starts over
+    pub resume: Option<bool>,
+    // Whether the finished file can carry embed_subs; otherwise subtitles are written beside it
+    pub subs_embeddable: bool,
+    // Held in the worker's queue until this job is released by ReleaseDependents
+    pub after: Option<JobId>,
+    // Start and end timestamps when only part of the video is wanted
+    pub sections: Option<(String, String)>,
+    // A live stream: stopping it finishes the file instead of failing
+    pub live: bool,
+    pub live_from_start: bool,
+    // The link came from a playlist but only this video is wanted
+    pub no_playlist: bool,
+    // --remux-video or --recode-video into this container after the download
+    pub convert: Option<(Container, Conversion)>,
+}
+
+// Where a job writes, fixed when it first starts so later settings changes
+// only affect jobs started afterwards
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputTarget {
+    pub dir: PathBuf,
+    pub template: String,
+}
+
+impl OutputTarget {
+    pub fn from_config(config: &Config) -> Self {
+        Self::with(config, &config.output_dir, &config.output_template)
+    }
+
+    // The configured target with a job's own directory or template in place of the settings
+    pub fn with(config: &Config, dir: &Path, template: &str) -> Self {
+        let template = if config.prefix_upload_date {
+            // Only the file name gets the date, not any directories in the template
+            match template.rsplit_once('/') {
+                Some((dirs, name)) => format!("{}/%(upload_date)s - {}", dirs, name),
+                None => format!("%(upload_date)s - {}", template),
+            }
+        } else {
+            template.to_string()
+        };
+        Self {
+            dir: dir.to_path_buf(),
+            template,
+        }
+    }
+
+    // PathBuf::join uses the native separator; yt-dlp accepts `/` inside the
+    // template on every platform, so only the directory part is OS-specific
+    pub fn path_template(&self) -> PathBuf {
+        self.dir.join(&self.template)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum WorkerCommand {
+    FetchFormats { job_id: JobId, url: String, credentials: Option<Credentials> },
+    // `items` is a `--playlist-items` selection such as "1-25"; None loads every entry
+    FetchPlaylist { job_id: JobId, url: String, items: Option<String> },
+    // Format fetches for playlist entries, run a few at a time behind the others;
+    // a whole playlist goes in one command so none of it is lost to a full channel
+    HydrateFormats(Vec<(JobId, String)>),
+    // Moves a waiting HydrateFormats to the front of the line, or puts the entry
+    // there if the worker never got it
+    PromoteFetch { job_id: JobId, url: String },
+    // Low-priority lookup for jobs that would otherwise only show their URL
+    ResolveTitle { job_id: JobId, url: String },
+    StartJob(DownloadRequest),
+    CancelJob(JobId),
+    UpdateConcurrent(usize),
+    // Lift a halt after repeated failures, or let a single waiting job through to test the waters
+    ResumeQueue,
+    ProbeQueue,
+    // Holds back new downloads while on battery, apart from any failure halt
+    SetPaused(bool),
+    // Runs `yt-dlp -U`
+    UpdateYtdlp,
+    // Settings that apply to the next yt-dlp run, such as the output dir or cookies
+    UpdateConfig(Box<Config>),
+    // Lets downloads waiting on this job go, once it finished or the user said so
+    ReleaseDependents(JobId),
+    Shutdown,
+}
+
+// What kind of file a job will produce, as far as its selection says so far
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    Video,
+    Audio,
+    #[default]
+    Unknown,
+}
+
+impl MediaKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MediaKind::Video => "video",
+            MediaKind::Audio => "audio",
+            MediaKind::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum DownloadPhase {
+    #[default]
+    Video,
+    Audio,
+    Merging,
+    Single,
+    // ffmpeg re-encoding into another container; yt-dlp reports no progress for it
+    Recoding,
+}
+
+// `Ready` is written as `{"Ready": null}` so queue files from before it became a
+// unit variant, which still carry `{"Ready": {"formats": [...]}}`, load too
+fn serialize_unit<S: serde::Serializer>(serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_unit()
+}
+
+fn deserialize_ready<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<(), D::Error> {
+    serde::de::IgnoredAny::deserialize(deserializer).map(|_| ())
+}
+
+impl JobStatus {
+    // The status as a timeline step; progress within a step doesn't count as a change
+    pub fn stage(&self) -> &'static str {
+        match self {
+            JobStatus::ExpandingPlaylist { .. } => "expanding playlist",
+            JobStatus::FetchingFormats => "fetching formats",
+            JobStatus::MetadataPending => "waiting for metadata",
+            JobStatus::Ready => "formats ready",
+            JobStatus::Queued => "queued",
+            JobStatus::Starting => "start sent",
+            JobStatus::WaitingForSlot { .. } => "waiting for a slot",
+            JobStatus::Downloading { phase: DownloadPhase::Video, .. } => "downloading video",
+            JobStatus::Downloading { phase: DownloadPhase::Audio, .. } => "downloading audio",
+            JobStatus::Downloading { phase: DownloadPhase::Merging, .. } => "merging",
+            JobStatus::Downloading { phase: DownloadPhase::Single, .. } => "downloading",
+            JobStatus::Downloading { phase: DownloadPhase::Recoding, .. } => "recoding",
+            JobStatus::Blocked { .. } => "blocked on an existing file",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed(_) => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum JobStatus {
+    // Placeholder for a pasted playlist until its entries replace it
+    ExpandingPlaylist { found: usize },
+    FetchingFormats,
+    // A playlist entry with only the title from the flat listing, formats fetched in the background
+    MetadataPending,
+    // The formats themselves live on the job
+    #[serde(serialize_with = "serialize_unit", deserialize_with = "deserialize_ready")]
+    Ready,
+    Queued,
+    Starting,
+    // Dispatched to the worker but behind others for a download slot; 1-based
+    WaitingForSlot { position: usize },
+    Downloading { percent: f32, speed: String, speed_bps: Option<u64>, eta: String, phase: DownloadPhase, total: Option<u64> },
+    // Waiting on the overwrite prompt for a file that already exists
+    Blocked { path: PathBuf },
+    Completed,
+    Failed(Failure),
+    Cancelled,
+}
+
+// What went wrong with a job, sorted out once in the worker so nothing else has
+// to pick through yt-dlp's stderr
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadError {
+    NotFound,
+    Private,
+    GeoBlocked,
+    AgeRestricted,
+    // Sign-in or bot check; a password or cookies get past it
+    LoginRequired,
+    // Channel memberships and Premium; only cookies from a subscribed account work
+    MembersOnly,
+    Throttled,
+    Network,
+    DiskFull,
+    FfmpegMissing,
+    Cancelled,
+    #[default]
+    Unknown,
+}
+
+impl DownloadError {
+    pub fn label(self) -> &'static str {
+        match self {
+            DownloadError::NotFound => "not found",
+            DownloadError::Private => "private video",
+            DownloadError::GeoBlocked => "blocked in this country",
+            DownloadError::AgeRestricted => "age-restricted",
+            DownloadError::LoginRequired => "sign-in required",
+            DownloadError::MembersOnly => "members only",
+            DownloadError::Throttled => "rate-limited",
+            DownloadError::Network => "network error",
+            DownloadError::DiskFull => "disk full",
+            DownloadError::FfmpegMissing => "ffmpeg missing",
+            DownloadError::Cancelled => "cancelled",
+            DownloadError::Unknown => "error",
+        }
+    }
+
+    pub fn badge(self) -> &'static str {
+        match self {
+            DownloadError::NotFound => "[GONE]",
+            DownloadError::Private => "[PRIVATE]",
+            DownloadError::GeoBlocked => "[GEO]",
+            DownloadError::AgeRestricted => "[AGE]",
+            DownloadError::MembersOnly => "[MEMBERS]",
+            DownloadError::DiskFull => "[NO SPACE]",
+            _ => "[FAILED]",
+        }
+    }
+
+    // Signing in, one way or another, might get past it
+    pub fn is_login_wall(self) -> bool {
+        matches!(self, DownloadError::AgeRestricted | DownloadError::LoginRequired | DownloadError::MembersOnly)
+    }
+
+    // Worth simply trying again later
+    pub fn is_transient(self) -> bool {
+        matches!(self, DownloadError::Throttled | DownloadError::Network | DownloadError::Unknown)
+    }
+
+    pub fn hint(self) -> Option<&'static str> {
+        match self {
+            DownloadError::NotFound => Some("The video was removed or the URL is wrong"),
+            DownloadError::Private => Some("Only the uploader can make this video visible again"),
+            DownloadError::GeoBlocked => Some("Set geo_bypass_country in the config, e.g. \"US\", to pose as another country"),
+            DownloadError::Throttled => Some("The site is rate-limiting; lower the concurrency or wait a while"),
+            DownloadError::Network => Some("Check the network connection"),
+            DownloadError::DiskFull => Some("Free up space in the download folder, then retry"),
+            DownloadError::FfmpegMissing => Some("Install ffmpeg, it is needed to merge and convert"),
+            _ => None,
+        }
+    }
+}
+
+// A failed job: the kind of failure and yt-dlp's own words for it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "FailureRepr")]
+pub struct Failure {
+    pub kind: DownloadError,
+    pub message: String,
+}
+
+// Queue files from before failures were classified hold just the message
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FailureRepr {
+    Message(String),
+    Classified { kind: DownloadError, message: String },
+}
+
+impl From<FailureRepr> for Failure {
+    fn from(repr: FailureRepr) -> Self {
+        match repr {
+            FailureRepr::Message(message) => Failure::new(message),
+            FailureRepr::Classified { kind, message } => Failure { kind, message },
+        }
+    }
+}
+
+impl Failure {
+    pub fn new(message: impl Into<String>) -> Self {
+        let message = message.into();
+        Self { kind: crate::worker::classify(&message), message }
+    }
+
+    pub fn cancelled(message: impl Into<String>) -> Self {
+        Self { kind: DownloadError::Cancelled, message: message.into() }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+// Video details from the format fetch beyond the formats themselves
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    #[serde(default)]
+    pub duration: Option<f64>,
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+    #[serde(default)]
+    pub view_count: Option<u64>,
+    // yt-dlp's raw YYYYMMDD
+    #[serde(default)]
+    pub upload_date: Option<String>,
+    #[serde(default)]
+    pub channel_id: Option<String>,
+    #[serde(default)]
+    pub channel: Option<String>,
+    // Null rather than false for most videos
+    #[serde(default)]
+    pub is_live: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: JobId,
+    pub url: String,
+    pub title: Option<String>,
+    pub status: JobStatus,
+    // Shared with the format popup rather than copied into it; emptied once
+    // the job has completed since nothing needs the list after that
+    pub formats: Arc<[Format]>,
+    pub selected_format: Option<Format>,
+    pub output_path: Option<PathBuf>,
+    #[serde(default)]
+    pub group: Option<GroupId>,
+    #[serde(default)]
+    pub extract_audio: bool,
+    // Picked in the format popup; None uses audio_quality from the config
+    #[serde(default)]
+    pub audio_quality: Option<String>,
+    // Picked in the format popup
+    #[serde(default)]
+    pub container: Container,
+    #[serde(default)]
+    pub record: Option<DownloadRecord>,
+    #[serde(default)]
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub output_size: Option<u64>,
+    #[serde(default = "Local::now")]
+    pub added_at: DateTime<Local>,
+    // The URL is a playlist still to be expanded; retrying loads it again
+    #[serde(default)]
+    pub is_playlist: bool,
+    // The entries of the playlist to load, kept for a retry
+    #[serde(default)]
+    pub playlist_items: Option<String>,
+    #[serde(default)]
+    pub output_target: Option<OutputTarget>,
+    // Set with `o` to send this job somewhere other than the configured output dir or template
+    #[serde(default)]
+    pub output_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub output_template: Option<String>,
+    // Answer to the overwrite prompt for this job
+    #[serde(default)]
+    pub conflict_choice: Option<OverwritePolicy>,
+    // Applied by the FormatsReady handler, which queues the job instead of leaving it Ready
+    #[serde(default)]
+    pub pending_preset: Option<TriagePreset>,
+    // How far a cancelled download got before it was stopped
+    #[serde(default)]
+    pub partial: Option<PartialProgress>,
+    // The user's own reminder, e.g. "for mum"
+    #[serde(default)]
+    pub note: Option<String>,
+    // The selection came from what was last chosen for this channel
+    #[serde(default)]
+    pub from_channel: bool,
+    // Started from `s` without a selection, on the config's default_quality
+    #[serde(default)]
+    pub default_format: bool,
+    // Why embed_thumbnail didn't work out for this file
+    #[serde(default)]
+    pub thumbnail_issue: Option<String>,
+    // The image kept by write_thumbnail
+    #[serde(default)]
+    pub thumbnail_path: Option<PathBuf>,
+    // Every other file the download left beside the media
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sidecars: Vec<PathBuf>,
+    // Of the latest download attempt
+    #[serde(default)]
+    pub speed: SpeedStats,
+    // yt-dlp's warnings from the latest download attempt, without repeats
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    #[serde(default)]
+    pub timeline: Vec<TimelineEntry>,
+    // Only downloads once this job has finished
+    #[serde(default)]
+    pub after: Option<JobId>,
+    // Just this stretch of the video, e.g. ("5:00", "12:30")
+    #[serde(default)]
+    pub sections: Option<(String, String)>,
+    // A stream that was live when its formats were fetched; stopping it keeps the recording
+    #[serde(default)]
+    pub live: bool,
+    // Record from the beginning of the stream rather than from now
+    #[serde(default)]
+    pub live_from_start: bool,
+    // The playlist id dropped from the link when just this video was chosen
+    #[serde(default)]
+    pub playlist_skipped: Option<String>,
+    #[serde(skip)]
+    pub resume: bool,
+    // Entered for a login wall; kept in memory only
+    #[serde(skip)]
+    pub credentials: Option<Credentials>,
+    #[serde(skip)]
+    pub pid: Option<u32>,
+    // Why the last start attempt didn't reach the worker, shown in the details
+    #[serde(skip)]
+    pub start_issue: Option<String>,
+    // When the StartJob went out, until the worker acknowledges it
+    #[serde(skip)]
+    pub start_sent: Option<Instant>,
+    // The job whose fetch or download this one shares, for the same video
+    #[serde(skip)]
+    pub linked_to: Option<JobId>,
+}
+
+impl Job {
+    // What was downloaded may not be the format that was picked
+    pub fn fell_back(&self) -> bool {
+        self.warnings.iter().any(|w| crate::worker::is_format_fallback(w))
+    }
+
+    pub fn log_event(&mut self, event: impl Into<String>) {
+        if self.timeline.len() >= TIMELINE_CAP {
+            self.timeline.remove(0);
+        }
+        self.timeline.push(TimelineEntry { at: Local::now(), event: event.into() });
+    }
+
+    // e.g. "+1:05  downloading video", timed from when the job was added
+    pub fn timeline_lines(&self) -> Vec<String> {
+        self.timeline
+            .iter()
+            .map(|entry| {
+                let secs = (entry.at - self.added_at).num_milliseconds().max(0) as f64 / 1000.0;
+                format!("+{}  {}", format::duration(secs), entry.event)
+            })
+            .collect()
+    }
+
+    // Everything worth pasting into a bug report about a failed job
+    pub fn error_report(&self) -> Option<String> {
+        let JobStatus::Failed(error) = &self.status else {
+            return None;
+        };
+        let mut lines = vec![format!("URL: {}", self.url)];
+        if let Some(title) = &self.title {
+            lines.push(format!("Title: {}", title));
+        }
+        lines.push(format!("Error ({}): {}", error.kind.label(), error));
+        if let Some(record) = &self.record {
+            lines.push(format!("Format: {}", record.format_spec));
+            lines.push(format!("yt-dlp: {}", record.ytdlp_version));
+            lines.push(format!("Command: {}", record.command));
+        }
+        lines.extend(self.warnings.iter().map(|w| format!("Warning: {}", w)));
+        lines.push("Timeline:".into());
+        lines.extend(self.timeline_lines().into_iter().map(|line| format!("  {}", line)));
+        Some(lines.join("\n"))
+    }
+
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            url: url.into(),
+            title: None,
+            status: JobStatus::FetchingFormats,
+            formats: Arc::default(),
+            selected_format: None,
+            output_path: None,
+            group: None,
+            extract_audio: false,
+            audio_quality: None,
+            container: Container::Keep,
+            record: None,
+            metadata: Metadata::default(),
+            output_size: None,
+            added_at: Local::now(),
+            is_playlist: false,
+            playlist_items: None,
+            output_target: None,
+            output_dir: None,
+            output_template: None,
+            conflict_choice: None,
+            pending_preset: None,
+            partial: None,
+            note: None,
+            from_channel: false,
+            default_format: false,
+            thumbnail_issue: None,
+            thumbnail_path: None,
+            sidecars: Vec::new(),
+            speed: SpeedStats::default(),
+            warnings: Vec::new(),
+            timeline: Vec::new(),
+            after: None,
+            sections: None,
+            live: false,
+            live_from_start: false,
+            playlist_skipped: None,
+            resume: false,
+            credentials: None,
+            pid: None,
+            start_issue: None,
+            start_sent: None,
+            linked_to: None,
+        }
+    }
+
+    pub fn playlist_placeholder(url: impl Into<String>, items: Option<String>) -> Self {
+        Self {
+            status: JobStatus::ExpandingPlaylist { found: 0 },
+            is_playlist: true,
+            playlist_items: items,
+            ..Self::new(url)
+        }
+    }
+
+    pub fn fetch_playlist_command(&self) -> WorkerCommand {
+        WorkerCommand::FetchPlaylist { job_id: self.id, url: self.url.clone(), items: self.playlist_items.clone() }
+    }
+
+    pub fn media_kind(&self) -> MediaKind {
+        match &self.selected_format {
+            _ if self.extract_audio => MediaKind::Audio,
+            Some(fmt) if fmt.is_audio_only() => MediaKind::Audio,
+            Some(fmt) if fmt.is_video() => MediaKind::Video,
+            _ => MediaKind::Unknown,
+        }
+    }
+
+    pub fn display_name(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.url)
+    }
+
+    pub fn can_select_format(&self) -> bool {
+        matches!(self.status, JobStatus::Ready | JobStatus::Queued) && !self.formats.is_empty()
+    }
+
+    // Where the job writes: the snapshot once started, otherwise what starting it now would give
+    pub fn planned_target(&self, config: &Config) -> OutputTarget {
+        self.output_target.clone().unwrap_or_else(|| {
+            let dir = self.output_dir.as_deref().unwrap_or(&config.output_dir);
+            let template = self.output_template.as_deref().unwrap_or(&config.output_template);
+            OutputTarget::with(config, dir, template)
+        })
+    }
+
+    // `template` with the keys known before downloading filled in, the rest left for yt-dlp
+    pub fn preview_path(&self, template: &str, config: &Config) -> String {
+        let ext = match &self.selected_format {
+            Some(_) if self.extract_audio && config.audio_format != "best" => Some(config.audio_format.clone()),
+            // Merged downloads may change container, so only trust single streams
+            Some(fmt) if !fmt.needs_merge() => Some(fmt.ext.clone()),
+            _ => None,
+        };
+        let fields = [
+            ("title", self.title.clone()),
+            ("upload_date", self.metadata.upload_date.clone()),
+            ("ext", ext),
+        ];
+        let known: Vec<(&str, &str)> = fields
+            .iter()
+            .filter_map(|(key, value)| Some((*key, value.as_deref()?)))
+            .collect();
+        format::template_preview(template, &known)
+    }
+
+    pub fn planned_path(&self, config: &Config) -> String {
+        self.preview_path(&self.planned_target(config).path_template().to_string_lossy(), config)
+    }
+
+    // What two jobs writing the same file have in common. An unknown extension is
+    // left as the placeholder, so two merges of the same title still match; any
+    // other unknown key means the name can't be told in advance
+    fn clash_key(&self, config: &Config) -> Option<String> {
+        self.title.as_ref()?;
+        let path = self.planned_path(config);
+        if path.replace("%(ext)s", "").contains("%(") {
+            return None;
+        }
+        // Composed and decomposed forms of the same title are the same name on macOS
+        let path: String = path.nfc().collect();
+        Some(if cfg!(any(windows, target_os = "macos")) { path.to_lowercase() } else { path })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status, JobStatus::Completed | JobStatus::Failed(_) | JobStatus::Cancelled)
+    }
+
+    pub fn is_fetching(&self) -> bool {
+        matches!(self.status, JobStatus::ExpandingPlaylist { .. } | JobStatus::FetchingFormats | JobStatus::MetadataPending)
+    }
+
+    pub fn is_started(&self) -> bool {
+        matches!(
+            self.status,
+            JobStatus::Starting | JobStatus::WaitingForSlot { .. } | JobStatus::Downloading { .. } | JobStatus::Completed
+        )
+    }
+
+    pub fn format_spec(&self) -> Option<String> {
+        let fmt = self.selected_format.as_ref()?;
+        if self.extract_audio && !fmt.is_audio_only() {
+            Some("bestaudio/best".into())
+        } else {
+            Some(fmt.download_spec())
+        }
+    }
+
+    pub fn download_request(&self) -> Option<DownloadRequest> {
+        Some(self.request_with_format(self.format_spec()?))
+    }
+
+    // Falls back to the config's default expression for a job started without a
+    // selection; extraction only needs the audio
+    pub fn start_request(&self, config: &Config) -> Option<DownloadRequest> {
+        if self.selected_format.is_some() || !self.default_format {
+            return self.download_request();
+        }
+        let format_spec = if self.extract_audio { "bestaudio/best".into() } else { config.default_format() };
+        Some(self.request_with_format(format_spec))
+    }
+
+    // Whether a start can go ahead without asking for a format again
+    pub fn has_format(&self) -> bool {
+        self.selected_format.is_some() || self.default_format
+    }
+
+    pub fn request_with_format(&self, format_spec: String) -> DownloadRequest {
+        DownloadRequest {
+            job_id: self.id,
+            url: self.url.clone(),
+            title: self.title.clone(),
+            format_spec,
+            extract_audio: self.extract_audio,
+            audio_quality: self.audio_quality.clone(),
+            credentials: self.credentials.clone(),
+            output: self.output_target.clone(),
+            on_conflict: self.conflict_choice,
+            resume: self.partial.as_ref().map(|_| self.resume),
+            // Without a selection yt-dlp's default picks a merged video
+            subs_embeddable: !self.extract_audio && self.selected_format.as_ref().is_none_or(Format::holds_subtitles),
+            after: self.after,
+            sections: self.sections.clone(),
+            live: self.live,
+            live_from_start: self.live_from_start,
+            no_playlist: self.playlist_skipped.is_some(),
+            convert: self.conversion(),
+        }
+    }
+
+    // Format ids and resolutions differ between videos, so a choice made on one job
+    // is mapped onto the closest equivalent in this job's own list
+    pub fn equivalent_format(&self, wanted: &Format) -> Option<&Format> {
+        if let Some(exact) = self.formats.iter().find(|f| f.format_id == wanted.format_id) {
+            return Some(exact);
+        }
+
+        let same_kind = || self.formats.iter().filter(|f| f.is_audio_only() == wanted.is_audio_only());
+
+        if wanted.is_audio_only() {
+            let wanted_tbr = wanted.tbr.unwrap_or(0.0);
+            return same_kind().min_by(|a, b| {
+                let da = (a.tbr.unwrap_or(0.0) - wanted_tbr).abs();
+                let db = (b.tbr.unwrap_or(0.0) - wanted_tbr).abs();
+                (a.ext != wanted.ext).cmp(&(b.ext != wanted.ext)).then(da.total_cmp(&db))
+            });
+        }
+
+        // Tallest format not exceeding the wanted height, preferring the same container
+        // and audio layout; otherwise the smallest available
+        same_kind()
+            .filter(|f| f.height <= wanted.height)
+            .max_by_key(|f| (f.height, f.ext == wanted.ext, f.has_audio() == wanted.has_audio()))
+            .or_else(|| same_kind().min_by_key(|f| f.height))
+    }
+
+    // Selects the preset's format and queues the job; false leaves it as it was
+    pub fn apply_preset(&mut self, preset: TriagePreset) -> bool {
+        let Some(format) = self.preset_format(preset).cloned() else {
+            return false;
+        };
+        self.extract_audio = preset == TriagePreset::Audio;
+        self.selected_format = Some(format);
+        self.status = JobStatus::Queued;
+        true
+    }
+
+    // Tallest video not above the preset's height, preferring formats that carry audio
+    pub fn preset_format(&self, preset: TriagePreset) -> Option<&Format> {
+        let Some(max_height) = preset.max_height() else {
+            return self.best_audio_format();
+        };
+        let videos = || self.formats.iter().filter(|f| f.is_video());
+        videos()
+            .filter(|f| f.height.is_some_and(|h| h <= max_height))
+            .max_by_key(|f| (f.height, f.has_audio()))
+            .or_else(|| videos().min_by_key(|f| f.height))
+    }
+
+    // Bytes yt-dlp has to fetch for the current selection, before any conversion
+    pub fn source_size(&self) -> Option<u64> {
+        self.source_size_of(self.selected_format.as_ref()?)
+    }
+
+    fn source_size_of(&self, fmt: &Format) -> Option<u64> {
+        let best_audio = || self.best_audio_format().and_then(Format::size);
+        if self.extract_audio && !fmt.is_audio_only() {
+            return best_audio().or(fmt.size());
+        }
+        let merged_audio = if fmt.needs_merge() { best_audio().unwrap_or(0) } else { 0 };
+        Some(fmt.size()? + merged_audio)
+    }
+
+    pub fn audio_quality<'a>(&'a self, config: &'a Config) -> &'a str {
+        self.audio_quality.as_deref().unwrap_or(&config.audio_quality)
+    }
+
+    pub fn estimated_audio_size(&self, config: &Config) -> Option<u64> {
+        if !self.extract_audio {
+            return None;
+        }
+        estimate_audio_size(self.metadata.duration?, &config.audio_format, self.audio_quality(config))
+    }
+
+    // The source is downloaded in full before conversion, so the peak disk use is
+    // whichever of the two is larger
+    pub fn required_space(&self, config: &Config) -> Option<u64> {
+        let source = match &self.selected_format {
+            Some(fmt) => self.source_size_of(fmt),
+            None if self.default_format => self.source_size_of(self.default_format_guess(config)?),
+            None => None,
+        };
+        match (source, self.estimated_audio_size(config)) {
+            (Some(source), Some(audio)) => Some(source.max(audio)),
+            (source, audio) => source.or(audio),
+        }
+    }
+
+    // What the default format most likely comes to, so a job on it can still be
+    // held to the large-download threshold: the tallest video within max_height,
+    // merged with the best audio. yt-dlp makes the real choice
+    fn default_format_guess(&self, config: &Config) -> Option<&Format> {
+        let max_height = config.default_quality.max_height;
+        self.formats
+            .iter()
+            .filter(|f| !f.is_audio_only() && f.height.is_some_and(|h| max_height.is_none_or(|max| h <= max)))
+            .max_by_key(|f| (f.height, f.size()))
+    }
+
+    pub fn best_audio_format(&self) -> Option<&Format> {
+        best_audio(&self.formats)
+    }
+
+    // None when the download stays as yt-dlp makes it, audio extraction included
+    pub fn conversion(&self) -> Option<(Container, Conversion)> {
+        let format = self.selected_format.as_ref().filter(|_| !self.extract_audio)?;
+        Some((self.container, self.container.conversion(format, &self.formats)?))
+    }
+
+    // A download that was running when we exited has to be started again
+    pub fn restored(mut self) -> Self {
+        if matches!(self.status, JobStatus::Starting | JobStatus::WaitingForSlot { .. } | JobStatus::Downloading { .. }) {
+            self.status = JobStatus::Queued;
+        }
+        self
+    }
+}
+
+// Checks a playlist range as typed: "1-25", "10,12,30-40" or "50-" for the rest.
+// Blank or "all" means every entry
+pub fn playlist_items(input: &str) -> Result<Option<String>, String> {
+    let input: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    if input.is_empty() || input.eq_ignore_ascii_case("all") {
+        return Ok(None);
+    }
+    let number = |part: &str| match part.parse::<u32>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("\"{}\" is not an item number (they start at 1)", part)),
+    };
+    for part in input.split(',') {
+        match part.split_once('-') {
+            None => {
+                number(part)?;
+            }
+            Some((from, "")) => {
+                number(from)?;
+            }
+            Some((from, to)) => {
+                if number(from)? > number(to)? {
+                    return Err(format!("{} counts backwards", part));
+                }
+            }
+        }
+    }
+    Ok(Some(input))
+}
+
+// Unfinished jobs that would write the same file as an earlier one, mapped to
+// that earlier job. Copies of the same video share their run, so they don't count
+pub fn name_clashes(jobs: &[Job], config: &Config) -> HashMap<JobId, JobId> {
+    let mut first: HashMap<String, &Job> = HashMap::new();
+    let mut clashes = HashMap::new();
+    for job in jobs.iter().filter(|j| !j.is_finished() && !j.is_playlist) {
+        let Some(key) = job.clash_key(config) else {
+            continue;
+        };
+        match first.get(&key) {
+            Some(earlier) if !same_video(earlier, job) => {
+                clashes.insert(job.id, earlier.id);
+            }
+            Some(_) => {}
+            None => {
+                first.insert(key, job);
+            }
+        }
+    }
+    clashes
+}
+
+fn same_video(a: &Job, b: &Job) -> bool {
+    a.url == b.url || video_id(&a.url).is_some_and(|id| video_id(&b.url) == Some(id))
+}
+
+// The job's template with " (n)" before the extension, for the first n from 2 up
+// that no other unfinished job's name uses
+pub fn suffixed_template(job: &Job, jobs: &[Job], config: &Config) -> String {
+    let template = job.output_template.as_deref().unwrap_or(&config.output_template);
+    let (stem, ext) = template.strip_suffix(".%(ext)s").map_or((template, ""), |stem| (stem, ".%(ext)s"));
+    let taken: Vec<String> = jobs.iter().filter(|j| j.id != job.id && !j.is_finished()).filter_map(|j| j.clash_key(config)).collect();
+    let mut candidate = job.clone();
+    (2..)
+        .map(|n| format!("{} ({}){}", stem, n, ext))
+        .find(|suffixed| {
+            candidate.output_template = Some(suffixed.clone());
+            candidate.clash_key(config).is_none_or(|key| !taken.contains(&key))
+        })
+        .unwrap_or_default()
+}
+
+// watch?v=, youtu.be/, /shorts/ and /live/ links; ids are always 11 characters
+pub fn video_id(input: &str) -> Option<String> {
+    let url = input.split_whitespace().last()?;
+    let (_, rest) = url.split_once("://").unwrap_or(("", url));
+    let (host, path) = rest.split_once('/')?;
+    let host = host.trim_start_matches("www.").trim_start_matches("m.").trim_start_matches("music.");
+
+    let id = match host {
+        "youtu.be" => path.split(['?', '&', '#', '/']).next(),
+        "youtube.com" => match path.split_once('?') {
+            Some(("watch", query)) => query.split('&').find_map(|pair| pair.strip_prefix("v=")),
+            _ => path
+                .strip_prefix("shorts/")
+                .or_else(|| path.strip_prefix("live/"))
+                .and_then(|rest| rest.split(['?', '&', '#', '/']).next()),
+        },
+        _ => None,
+    }?;
+    let valid = id.len() == 11 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::format;
+
+    #[test]
+    fn formats_are_tagged_by_their_streams() {
+        let video_only = format(r#"{"format_id":"137","ext":"mp4","vcodec":"avc1.640028","acodec":"none","height":1080}"#);
+        let progressive = format(r#"{"format_id":"18","ext":"mp4","vcodec":"avc1.42001E","acodec":"mp4a.40.2","height":360}"#);
+        let audio_only = format(r#"{"format_id":"140","ext":"m4a","vcodec":"none","acodec":"mp4a.40.2"}"#);
+        let storyboard = format(r#"{"format_id":"sb0","ext":"mhtml","vcodec":"none","acodec":"none"}"#);
+        let bare = format(r#"{"format_id":"hls-1"}"#);
+        // Some extractors write null rather than leaving the codec out
+        let null_audio = format(r#"{"format_id":"dash-1","vcodec":"vp9","acodec":null}"#);
+        let null_video = format(r#"{"format_id":"dash-2","vcodec":null,"acodec":"opus"}"#);
+        let null_both = format(r#"{"format_id":"hls-2","vcodec":null,"acodec":null}"#);
+
+        let all = [&video_only, &progressive, &audio_only, &storyboard, &bare, &null_audio, &null_video, &null_both];
+        let tags: Vec<&str> = all.iter().map(|f| f.stream_tag()).collect();
+        assert_eq!(tags, ["[V]", "[V+A]", "[A]", "[?]", "[?]", "[V]", "[A]", "[?]"]);
+        let merged: Vec<bool> = all.iter().map(|f| f.needs_merge()).collect();
+        assert_eq!(merged, [true, false, false, false, false, true, false, false]);
+        assert!(audio_only.is_audio_only() && !progressive.is_audio_only() && !storyboard.is_audio_only());
+    }
+
+    #[test]
+    fn only_video_only_formats_are_merged_with_audio() {
+        let video_only = format(r#"{"format_id":"137","vcodec":"avc1.640028","acodec":"none"}"#);
+        let progressive = format(r#"{"format_id":"18","vcodec":"avc1.42001E","acodec":"mp4a.40.2"}"#);
+        let audio_only = format(r#"{"format_id":"140","vcodec":"none","acodec":"mp4a.40.2"}"#);
+
+        assert_eq!(video_only.download_spec(), "137+bestaudio/best");
+        assert_eq!(progressive.download_spec(), "18");
+        assert_eq!(audio_only.download_spec(), "140");
+    }
+
+    #[test]
+    fn audio_quality_maps_to_a_bitrate() {
+        for (quality, kbps) in [("0", Some(245.0)), ("5", Some(130.0)), ("10", Some(45.0)), ("11", None), ("192K", Some(192.0)), (" 128k ", Some(128.0)), ("best", None), ("", None)] {
+            assert_eq!(audio_quality_kbps(quality), kbps, "{:?}", quality);
+        }
+    }
+
+    #[test]
+    fn audio_size_is_bitrate_times_duration() {
+        // 192 kbit/s for ten minutes is 24 kB/s × 600 s
+        assert_eq!(estimate_audio_size(600.0, "mp3", "192K"), Some(14_400_000));
+        assert_eq!(estimate_audio_size(0.0, "mp3", "192K"), Some(0));
+        assert_eq!(estimate_audio_size(600.0, "opus", "5"), Some(9_750_000));
+        for lossless in ["flac", "wav", "alac"] {
+            assert_eq!(estimate_audio_size(600.0, lossless, "0"), None);
+        }
+    }
+
+    #[test]
+    fn extraction_needs_room_for_the_larger_of_source_and_result() {
+        let config = Config { audio_format: "mp3".into(), audio_quality: "320K".into(), ..Config::default() };
+        let video = format(r#"{"format_id":"22","vcodec":"avc1","acodec":"mp4a","filesize":100000000}"#);
+        let audio = format(r#"{"format_id":"140","vcodec":"none","acodec":"mp4a","filesize":10000000}"#);
+        let mut job = Job { selected_format: Some(audio.clone()), formats: vec![video, audio].into(), extract_audio: true, ..Job::new("https://example.com/v/1") };
+        job.metadata.duration = Some(600.0);
+
+        // A 10 MB source makes a 24 MB mp3 at 320K, and a 4.9 MB one at VBR 9
+        assert_eq!(job.estimated_audio_size(&config), Some(24_000_000));
+        assert_eq!(job.required_space(&config), Some(24_000_000));
+
+        job.audio_quality = Some("9".into());
+        assert_eq!(job.estimated_audio_size(&config), Some(4_875_000));
+        assert_eq!(job.required_space(&config), Some(10_000_000));
+
+        job.metadata.duration = None;
+        assert_eq!(job.required_space(&config), Some(10_000_000));
+        job.extract_audio = false;
+        assert_eq!(job.estimated_audio_size(&config), None);
+    }
+
+    #[test]
+    fn media_kind_follows_the_selection_and_extraction() {
+        let video = format(r#"{"format_id":"137","vcodec":"avc1","acodec":"none","height":1080}"#);
+        let progressive = format(r#"{"format_id":"18","vcodec":"avc1","acodec":"mp4a","height":360}"#);
+        let audio = format(r#"{"format_id":"140","vcodec":"none","acodec":"mp4a"}"#);
+        let storyboard = format(r#"{"format_id":"sb0","vcodec":"none","acodec":"none"}"#);
+
+        for (selected, extract_audio, kind) in [
+            (None, false, MediaKind::Unknown),
+            (None, true, MediaKind::Audio),
+            (Some(&video), false, MediaKind::Video),
+            (Some(&progressive), false, MediaKind::Video),
+            (Some(&progressive), true, MediaKind::Audio),
+            (Some(&audio), false, MediaKind::Audio),
+            (Some(&storyboard), false, MediaKind::Unknown),
+        ] {
+            let job = Job { selected_format: selected.cloned(), extract_audio, ..Job::new("https://example.com/v/1") };
+            assert_eq!(job.media_kind(), kind, "{:?} extracting {}", selected.map(|f| &f.format_id), extract_audio);
+        }
+    }
+
+    #[test]
+    fn media_kind_is_written_in_lowercase() {
+        assert_eq!(serde_json::to_string(&MediaKind::Audio).unwrap(), r#""audio""#);
+        assert_eq!(serde_json::from_str::<MediaKind>(r#""video""#).unwrap(), MediaKind::Video);
+        assert_eq!(MediaKind::default().label(), "unknown");
+    }
+
+    #[test]
+    fn formats_fall_into_tiers_by_height() {
+        for (json, tier) in [
+            (r#"{"format_id":"a","vcodec":"av01","acodec":"none","height":4320}"#, Some(QualityTier::P2160)),
+            (r#"{"format_id":"b","vcodec":"vp9","acodec":"none","height":2160}"#, Some(QualityTier::P2160)),
+            (r#"{"format_id":"c","vcodec":"vp9","acodec":"none","height":1440}"#, Some(QualityTier::P1440)),
+            (r#"{"format_id":"d","vcodec":"vp9","acodec":"none","height":1439}"#, Some(QualityTier::P1080)),
+            (r#"{"format_id":"e","vcodec":"avc1","acodec":"mp4a","height":720}"#, Some(QualityTier::P720)),
+            (r#"{"format_id":"f","vcodec":"avc1","acodec":"mp4a","height":719}"#, Some(QualityTier::Lower)),
+            (r#"{"format_id":"g","vcodec":"avc1","acodec":"none"}"#, Some(QualityTier::Lower)),
+            (r#"{"format_id":"h","vcodec":"none","acodec":"opus"}"#, Some(QualityTier::Audio)),
+            (r#"{"format_id":"sb0","vcodec":"none","acodec":"none"}"#, None),
+        ] {
+            let format = format(json);
+            assert_eq!(QualityTier::of(&format), tier, "{}", format.format_id);
+        }
+    }
+
+    #[test]
+    fn the_best_in_a_tier_comes_first() {
+        let formats = [
+            r#"{"format_id":"136","vcodec":"avc1","acodec":"none","height":720,"tbr":1500}"#,
+            r#"{"format_id":"22","vcodec":"avc1","acodec":"mp4a","height":720,"tbr":1500}"#,
+            r#"{"format_id":"247","vcodec":"vp9","acodec":"none","height":720,"tbr":1800}"#,
+            r#"{"format_id":"298","vcodec":"avc1","acodec":"none","height":720,"tbr":3000}"#,
+            r#"{"format_id":"302","vcodec":"vp9","acodec":"none","height":720}"#,
+            r#"{"format_id":"303","vcodec":"vp9","acodec":"none","height":720}"#,
+            r#"{"format_id":"137","vcodec":"avc1","acodec":"none","height":1080,"tbr":4000}"#,
+            r#"{"format_id":"140","vcodec":"none","acodec":"mp4a","tbr":129}"#,
+            r#"{"format_id":"251","vcodec":"none","acodec":"opus","tbr":135}"#,
+        ]
+        .map(format);
+        let ids = |tier: QualityTier| tier.members(&formats).into_iter().map(|i| formats[i].format_id.as_str()).collect::<Vec<_>>();
+
+        // Bitrate breaks height ties, a muxed stream breaks bitrate ties, and
+        // equals keep yt-dlp's order
+        assert_eq!(ids(QualityTier::P720), ["298", "247", "22", "136", "302", "303"]);
+        assert_eq!(ids(QualityTier::P1080), ["137"]);
+        assert_eq!(ids(QualityTier::Audio), ["251", "140"]);
+        assert!(ids(QualityTier::P2160).is_empty());
+    }
+
+    #[test]
+    fn the_upload_date_goes_on_the_file_name_only() {
+        let config = Config { prefix_upload_date: true, ..Config::default() };
+        let dir = Path::new("/downloads");
+        assert_eq!(OutputTarget::with(&config, dir, "%(title)s.%(ext)s").template, "%(upload_date)s - %(title)s.%(ext)s");
+        assert_eq!(OutputTarget::with(&config, dir, "%(uploader)s/%(title)s.%(ext)s").template, "%(uploader)s/%(upload_date)s - %(title)s.%(ext)s");
+        let plain = Config { prefix_upload_date: false, ..Config::default() };
+        assert_eq!(OutputTarget::with(&plain, dir, "%(title)s.%(ext)s").template, "%(title)s.%(ext)s");
+    }
+
+    #[test]
+    fn the_preview_fills_in_a_known_upload_date() {
+        let config = Config { prefix_upload_date: true, output_dir: "/downloads".into(), output_template: "%(title)s.%(ext)s".into(), ..Config::default() };
+        let progressive = format(r#"{"format_id":"18","ext":"mp4","vcodec":"avc1","acodec":"mp4a","height":360}"#);
+        let mut job = Job { title: Some("Clip".into()), selected_format: Some(progressive), ..Job::new("https://example.com/v/1") };
+        assert_eq!(job.planned_path(&config), "/downloads/%(upload_date)s - Clip.mp4");
+
+        job.metadata.upload_date = Some("20240305".into());
+        assert_eq!(job.planned_path(&config), "/downloads/20240305 - Clip.mp4");
+    }
+
+    #[test]
+    fn the_timeline_keeps_the_latest_entries_timed_from_the_add() {
+        let mut job = Job::new("https://example.com/v/long");
+        for i in 0..TIMELINE_CAP + 5 {
+            job.log_event(format!("step {}", i));
+        }
+        assert_eq!(job.timeline.len(), TIMELINE_CAP);
+        assert_eq!(job.timeline[0].event, "step 5");
+
+        job.timeline.truncate(2);
+        job.timeline[0].at = job.added_at - chrono::Duration::seconds(3);
+        job.timeline[1].at = job.added_at + chrono::Duration::milliseconds(3_725_400);
+        assert_eq!(job.timeline_lines(), ["+0:00  step 5", "+1:02:05  step 6"]);
+    }
+
+    fn titled(title: &str, url: &str) -> Job {
+        Job { title: Some(title.into()), status: JobStatus::Queued, ..Job::new(url) }
+    }
+
+    #[test]
+    fn name_clashes_match_titles_across_unicode_forms() {
+        let config = Config { output_dir: "/dl".into(), output_template: "%(title)s.%(ext)s".into(), ..Config::default() };
+        let jobs = vec![
+            titled("Caf\u{e9} Tour", "https://example.com/v/1"),
+            titled("Cafe\u{301} Tour", "https://example.com/v/2"),
+            titled("Café Tour", "https://example.com/v/3"),
+            titled("Untitled", "https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+            // The same video again is a duplicate, not a clash
+            titled("Untitled", "https://youtu.be/dQw4w9WgXcQ"),
+            titled("Café Tour (live)", "https://example.com/v/4"),
+            Job { title: None, ..titled("", "https://example.com/v/5") },
+        ];
+        let clashes = name_clashes(&jobs, &config);
+        assert_eq!(clashes, HashMap::from([(jobs[1].id, jobs[0].id), (jobs[2].id, jobs[0].id)]));
+
+        // Finished jobs and ones saved elsewhere don't take a name
+        let mut spread = jobs.clone();
+        spread[0].status = JobStatus::Completed;
+        spread[2].output_template = Some("live/%(title)s.%(ext)s".into());
+        assert_eq!(name_clashes(&spread, &config), HashMap::new());
+
+        // A key only known after the download can't be compared
+        let config = Config { output_template: "%(uploader)s - %(title)s.%(ext)s".into(), ..config };
+        assert_eq!(name_clashes(&jobs, &config), HashMap::new());
+    }
+
+    #[test]
+    fn the_suffix_skips_names_already_taken() {
+        let config = Config { output_dir: "/dl".into(), output_template: "%(title)s.%(ext)s".into(), ..Config::default() };
+        let mut jobs = vec![titled("Untitled", "https://example.com/v/1"), titled("Untitled", "https://example.com/v/2"), titled("Untitled (2)", "https://example.com/v/3")];
+        assert_eq!(suffixed_template(&jobs[1], &jobs, &config), "%(title)s (3).%(ext)s");
+        jobs[1].output_template = Some(suffixed_template(&jobs[1], &jobs, &config));
+        assert!(name_clashes(&jobs, &config).is_empty());
+
+        let odd = Job { output_template: Some("%(title)s".into()), ..titled("Untitled", "https://example.com/v/4") };
+        jobs.push(Job { output_template: Some("%(title)s".into()), ..titled("Untitled", "https://example.com/v/5") });
+        assert_eq!(suffixed_template(&odd, &jobs, &config), "%(title)s (2)");
+    }
+
+    #[test]
+    fn failures_keep_their_kind_and_read_old_plain_messages() {
+        let failure = Failure::new("ERROR: unable to write data: [Errno 28] No space left on device");
+        assert_eq!((failure.kind, failure.to_string().as_str()), (DownloadError::DiskFull, "ERROR: unable to write data: [Errno 28] No space left on device"));
+        let saved = serde_json::to_string(&JobStatus::Failed(failure.clone())).unwrap();
+        assert_eq!(serde_json::from_str::<JobStatus>(&saved).unwrap(), JobStatus::Failed(failure));
+
+        // Queue files from before classification, and a kind that was set rather than read off the text
+        let old: JobStatus = serde_json::from_str(r#"{"Failed":"ERROR: [youtube] x: Private video"}"#).unwrap();
+        assert_eq!(old, JobStatus::Failed(Failure { kind: DownloadError::Private, message: "ERROR: [youtube] x: Private video".into() }));
+        let cancelled = Failure::cancelled("Stopped before it finished");
+        let saved = serde_json::to_string(&cancelled).unwrap();
+        assert_eq!(serde_json::from_str::<Failure>(&saved).unwrap().kind, DownloadError::Cancelled);
+    }
+}
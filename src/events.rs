@@ -1,9 +1,19 @@
-use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::VecDeque;
 use std::path::PathBuf;
-use serde::Deserialize;
-use uuid::Uuid;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-pub type JobId = Uuid;
+use crate::bookmarks::Bookmark;
+use crate::config::Config;
+use crate::diagnose::Diagnosis;
+use crate::health::HealthReport;
+use crate::preview::VideoPreview;
+use crate::session::{LoadChoice, Session};
+use serde::{Deserialize, Serialize};
+
+// The engine's types, re-exported so the UI modules keep one import path
+pub use oxidlp::core::*;
 
 #[derive(Debug, Clone, Default)]
 pub struct StatusCounts {
@@ -15,144 +25,1276 @@ pub struct StatusCounts {
     pub failed: usize,
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
-pub struct Format {
-    pub format_id: String,
-    #[serde(default)]
-    pub resolution: Option<String>,
-    #[serde(default)]
-    pub ext: String,
-    #[serde(default)]
-    pub vcodec: Option<String>,
-    #[serde(default)]
-    pub acodec: Option<String>,
-    #[serde(default)]
-    pub filesize: Option<u64>,
-    #[serde(default)]
-    pub filesize_approx: Option<u64>,
-    #[serde(default)]
-    pub tbr: Option<f64>,
-    #[serde(default)]
-    pub width: Option<u32>,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FormatRow {
+    // A tier header stands for its best format
+    Tier { tier: QualityTier, best: usize, count: usize, expanded: bool },
+    Variant { tier: QualityTier, format: usize },
+}
+
+impl FormatRow {
+    pub fn tier(&self) -> QualityTier {
+        match *self {
+            FormatRow::Tier { tier, .. } | FormatRow::Variant { tier, .. } => tier,
+        }
+    }
+
+    pub fn format_index(&self) -> usize {
+        match *self {
+            FormatRow::Tier { best, .. } => best,
+            FormatRow::Variant { format, .. } => format,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FormatPopupState {
+    pub job_index: usize,
+    pub formats: Arc<[Format]>,
+    pub selected: usize,
+    pub scroll_offset: usize,
+    // Rows the list had at the last draw; paging and scrolling follow it
+    pub viewport_height: Cell<usize>,
+    expanded: Vec<QualityTier>,
+    // Lifts VARIANT_CAP for every expanded tier
+    pub show_all: bool,
+    pub apply_to_all: bool,
+    // Only offered for live streams
+    pub live_from_start: bool,
+    // Convert to audio, and at what --audio-quality
+    pub extract_audio: bool,
+    pub audio_quality: String,
+    // Remuxed or recoded into after the download, see Container::conversion
+    pub container: Container,
+    pub group: Option<GroupId>,
+    // Built when tiers open or close, not per key press or frame
+    rows: Vec<FormatRow>,
+    // Variants of the expanded tiers past the cap, and how many they have in all
+    pub beyond_cap: usize,
+    pub variants: usize,
+}
+
+// Podcasts and long streams can list over a hundred formats in one tier
+const VARIANT_CAP: usize = 20;
+
+impl FormatPopupState {
+    pub fn new(job_index: usize, formats: Arc<[Format]>) -> Self {
+        let mut popup = Self {
+            job_index,
+            formats,
+            selected: 0,
+            scroll_offset: 0,
+            viewport_height: Cell::new(10),
+            expanded: Vec::new(),
+            show_all: false,
+            apply_to_all: false,
+            live_from_start: false,
+            extract_audio: false,
+            audio_quality: String::new(),
+            container: Container::Keep,
+            group: None,
+            rows: Vec::new(),
+            beyond_cap: 0,
+            variants: 0,
+        };
+        popup.rebuild();
+        popup
+    }
+
+    pub fn for_group(job_index: usize, formats: Arc<[Format]>, group: GroupId) -> Self {
+        Self {
+            group: Some(group),
+            ..Self::new(job_index, formats)
+        }
+    }
+
+    // Empty tiers are left out; an expanded tier lists its formats under the
+    // header, the first VARIANT_CAP of them unless show_all is on
+    fn rebuild(&mut self) {
+        self.rows.clear();
+        self.beyond_cap = 0;
+        self.variants = 0;
+        for tier in QualityTier::ALL {
+            let members = tier.members(&self.formats);
+            let Some(&best) = members.first() else {
+                continue;
+            };
+            let expanded = self.expanded.contains(&tier);
+            self.rows.push(FormatRow::Tier { tier, best, count: members.len(), expanded });
+            if expanded {
+                let shown = if self.show_all { members.len() } else { members.len().min(VARIANT_CAP) };
+                self.variants += members.len();
+                self.beyond_cap += members.len().saturating_sub(VARIANT_CAP);
+                self.rows.extend(members.into_iter().take(shown).map(|format| FormatRow::Variant { tier, format }));
+            }
+        }
+    }
+
+    pub fn hidden(&self) -> usize {
+        if self.show_all { 0 } else { self.beyond_cap }
+    }
+
+    pub fn rows(&self) -> &[FormatRow] {
+        &self.rows
+    }
+
+    pub fn selected_row(&self) -> Option<FormatRow> {
+        self.rows.get(self.selected).copied()
+    }
+
+    pub fn toggle_show_all(&mut self) {
+        let format = self.selected_row().map(|r| r.format_index());
+        self.show_all = !self.show_all;
+        self.rebuild();
+        if let Some(row) = format.and_then(|f| self.rows.iter().position(|r| r.format_index() == f)) {
+            self.select(row);
+        } else {
+            self.select(self.selected);
+        }
+    }
+
+    pub fn selected_format(&self) -> Option<&Format> {
+        self.selected_row().map(|row| &self.formats[row.format_index()])
+    }
+
+    // Puts the cursor on a format, opening its tier if it isn't the tier's best
+    pub fn focus(&mut self, format_id: &str) {
+        let Some(index) = self.formats.iter().position(|f| f.format_id == format_id) else {
+            return;
+        };
+        let Some(tier) = QualityTier::of(&self.formats[index]) else {
+            return;
+        };
+        let members = tier.members(&self.formats);
+        if members.first() != Some(&index) && !self.expanded.contains(&tier) {
+            self.expanded.push(tier);
+        }
+        if members.iter().position(|&m| m == index).is_some_and(|pos| pos >= VARIANT_CAP) {
+            self.show_all = true;
+        }
+        self.rebuild();
+        let row = self.rows.iter().position(|r| r.format_index() == index && (r.tier() == tier));
+        if let Some(row) = row {
+            self.select(row);
+        }
+    }
+
+    pub fn expand_selected(&mut self) {
+        if let Some(FormatRow::Tier { tier, expanded: false, .. }) = self.selected_row() {
+            self.expanded.push(tier);
+            self.rebuild();
+        }
+    }
+
+    // From a variant, collapses its tier and moves back up to the header
+    pub fn collapse_selected(&mut self) {
+        let Some(row) = self.selected_row() else {
+            return;
+        };
+        let tier = row.tier();
+        self.expanded.retain(|t| *t != tier);
+        self.rebuild();
+        if let Some(header) = self.rows.iter().position(|r| matches!(r, FormatRow::Tier { tier: t, .. } if *t == tier)) {
+            self.select(header);
+        }
+    }
+
+    // Jumps between the audio tier and the top of the list
+    // Steps through AUDIO_QUALITIES; a value typed into the config starts from the best
+    pub fn step_audio_quality(&mut self, forward: bool) {
+        let count = AUDIO_QUALITIES.len();
+        let next = match AUDIO_QUALITIES.iter().position(|q| *q == self.audio_quality) {
+            Some(i) if forward => (i + 1) % count,
+            Some(i) => (i + count - 1) % count,
+            None => 0,
+        };
+        self.audio_quality = AUDIO_QUALITIES[next].to_string();
+    }
+
+    pub fn toggle_audio_tier(&mut self) {
+        let in_audio = self.selected_row().is_some_and(|r| r.tier() == QualityTier::Audio);
+        let target = if in_audio {
+            0
+        } else {
+            match self.rows.iter().position(|r| r.tier() == QualityTier::Audio) {
+                Some(index) => index,
+                None => return,
+            }
+        };
+        self.select(target);
+    }
+
+    pub fn select_next(&mut self, wrap: bool) {
+        let len = self.rows.len();
+        let target = if wrap && self.selected + 1 >= len { 0 } else { self.selected + 1 };
+        self.select(target);
+    }
+
+    pub fn select_prev(&mut self, wrap: bool) {
+        let target = match self.selected.checked_sub(1) {
+            Some(prev) => prev,
+            None if wrap => self.rows.len().saturating_sub(1),
+            None => 0,
+        };
+        self.select(target);
+    }
+
+    pub fn page_down(&mut self) {
+        self.select(self.selected + self.page_size());
+    }
+
+    pub fn page_up(&mut self) {
+        self.select(self.selected.saturating_sub(self.page_size()));
+    }
+
+    pub fn select_first(&mut self) {
+        self.select(0);
+    }
+
+    pub fn select_last(&mut self) {
+        self.select(usize::MAX);
+    }
+
+    fn page_size(&self) -> usize {
+        self.viewport_height.get().max(1)
+    }
+
+    // Clamps to the list and scrolls just enough to keep the selection visible
+    fn select(&mut self, index: usize) {
+        let len = self.rows.len();
+        let height = self.page_size();
+        self.selected = index.min(len.saturating_sub(1));
+        if self.selected < self.scroll_offset {
+            self.scroll_offset = self.selected;
+        } else if self.selected >= self.scroll_offset + height {
+            self.scroll_offset = self.selected + 1 - height;
+        }
+        self.scroll_offset = self.scroll_offset.min(len.saturating_sub(height));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupAction {
+    SelectFormat,
+    Triage,
+    ToggleAudioExtraction,
+    StartAll,
+    CancelAll,
+    RemoveAll,
+}
+
+impl GroupAction {
+    pub const ALL: [GroupAction; 6] = [
+        GroupAction::SelectFormat,
+        GroupAction::Triage,
+        GroupAction::ToggleAudioExtraction,
+        GroupAction::StartAll,
+        GroupAction::CancelAll,
+        GroupAction::RemoveAll,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GroupAction::SelectFormat => "Select format for all",
+            GroupAction::Triage => "Choose formats one by one",
+            GroupAction::ToggleAudioExtraction => "Toggle audio extraction for all",
+            GroupAction::StartAll => "Start all",
+            GroupAction::CancelAll => "Cancel all",
+            GroupAction::RemoveAll => "Remove all",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupMenuState {
+    pub group_id: GroupId,
+    pub selected: usize,
+}
+
+impl GroupMenuState {
+    pub fn new(group_id: GroupId) -> Self {
+        Self { group_id, selected: 0 }
+    }
+
+    pub fn action(&self) -> GroupAction {
+        GroupAction::ALL[self.selected.min(GroupAction::ALL.len() - 1)]
+    }
+}
+
+// Things to do with a finished download. Both the Enter menu and the direct
+// keys are driven by this table so they can't drift apart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompletedAction {
+    Play,
+    OpenFolder,
+    CopyPath,
+    CopyUrl,
+    Redownload,
+    DeleteFile,
+    Remove,
+}
+
+impl CompletedAction {
+    pub const ALL: [CompletedAction; 7] = [
+        CompletedAction::Play,
+        CompletedAction::OpenFolder,
+        CompletedAction::CopyPath,
+        CompletedAction::CopyUrl,
+        CompletedAction::Redownload,
+        CompletedAction::DeleteFile,
+        CompletedAction::Remove,
+    ];
+
+    pub fn key(&self) -> char {
+        match self {
+            CompletedAction::Play => 'o',
+            CompletedAction::OpenFolder => 'O',
+            CompletedAction::CopyPath => 'y',
+            CompletedAction::CopyUrl => 'u',
+            CompletedAction::Redownload => 'R',
+            CompletedAction::DeleteFile => 'D',
+            CompletedAction::Remove => 'd',
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CompletedAction::Play => "Play",
+            CompletedAction::OpenFolder => "Open folder",
+            CompletedAction::CopyPath => "Copy path",
+            CompletedAction::CopyUrl => "Copy URL",
+            CompletedAction::Redownload => "Download again",
+            CompletedAction::DeleteFile => "Delete file",
+            CompletedAction::Remove => "Remove from queue",
+        }
+    }
+
+    pub fn for_key(key: char) -> Option<CompletedAction> {
+        Self::ALL.into_iter().find(|a| a.key() == key)
+    }
+
+    pub fn is_available(&self, job: &Job) -> bool {
+        let file_exists = job.output_path.as_ref().is_some_and(|p| p.exists());
+        match self {
+            CompletedAction::Play | CompletedAction::CopyPath | CompletedAction::DeleteFile => file_exists,
+            CompletedAction::OpenFolder => {
+                job.output_path.as_ref().and_then(|p| p.parent()).is_some_and(|p| p.exists())
+            }
+            CompletedAction::Redownload => job.has_format(),
+            CompletedAction::CopyUrl | CompletedAction::Remove => true,
+        }
+    }
+}
+
+// Queued jobs over the size threshold, held back until `s` is confirmed
+#[derive(Debug, Clone)]
+pub struct LargeDownloadState {
+    pub jobs: Vec<(JobId, u64)>,
+    // Set when the start came from a playlist's "Start all"
+    pub group: Option<GroupId>,
+}
+
+// Ready jobs without a selection, asked about before `s` starts them on the
+// config's default format
+#[derive(Debug, Clone)]
+pub struct DefaultFormatState {
+    pub jobs: Vec<JobId>,
+    pub group: Option<GroupId>,
+}
+
+// The file name prompt for "save session as" (saving) or "open session"
+#[derive(Debug, Clone, Default)]
+pub struct SessionPromptState {
+    pub saving: bool,
+    pub path: String,
+}
+
+// Asked when an opened session would land on a queue that isn't empty
+#[derive(Debug, Clone)]
+pub struct SessionLoadState {
+    pub path: PathBuf,
+    pub session: Session,
+    pub selected: usize,
+}
+
+// Playlists pasted while asking which of their entries to load; the front one is asked about
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistRangeState {
+    pub urls: VecDeque<String>,
+    pub input: String,
+    pub error: Option<String>,
+}
+
+// Watch links that carry a playlist, waiting for "this video or the playlist?"
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistChoiceState {
+    pub urls: VecDeque<String>,
+}
+
+// The yt-dlp update popup: what ran, what it printed, and how it ended
+#[derive(Debug, Clone, Default)]
+pub struct YtdlpUpdateState {
+    pub command: String,
+    pub output: Vec<String>,
+    // Success and the last line printed, once it has finished
+    pub result: Option<(bool, String)>,
+    // Asked of yt-dlp again after a successful update
+    pub version: Option<String>,
+}
+
+// Narrows the queue to titles matching `query`; `editing` while it is being typed
+#[derive(Debug, Clone, Default)]
+pub struct QueueFilterState {
+    pub query: String,
+    pub editing: bool,
+}
+
+// A job's own output directory and template as typed; empty follows the settings
+#[derive(Debug, Clone)]
+pub struct OutputPromptState {
+    pub job_id: JobId,
+    pub dir: String,
+    pub template: String,
+    pub editing_template: bool,
+    pub error: Option<String>,
+}
+
+impl OutputPromptState {
+    pub fn new(job: &Job) -> Self {
+        Self {
+            job_id: job.id,
+            dir: job.output_dir.as_ref().map(|d| d.display().to_string()).unwrap_or_default(),
+            template: job.output_template.clone().unwrap_or_default(),
+            editing_template: false,
+            error: None,
+        }
+    }
+
+    pub fn field_mut(&mut self) -> &mut String {
+        if self.editing_template { &mut self.template } else { &mut self.dir }
+    }
+}
+
+// Start and end of the part of a video to download, as typed
+#[derive(Debug, Clone)]
+pub struct SectionPromptState {
+    pub job_id: JobId,
+    pub start: String,
+    pub end: String,
+    pub editing_end: bool,
+    // Why the last Enter was refused
+    pub error: Option<String>,
+}
+
+impl SectionPromptState {
+    pub fn new(job: &Job) -> Self {
+        let (start, end) = job.sections.clone().unwrap_or_default();
+        Self { job_id: job.id, start, end, editing_end: false, error: None }
+    }
+
+    pub fn field_mut(&mut self) -> &mut String {
+        if self.editing_end { &mut self.end } else { &mut self.start }
+    }
+}
+
+// Asked when a job others wait on failed or was cancelled
+#[derive(Debug, Clone)]
+pub struct DependentsPromptState {
+    pub prerequisite: JobId,
+    pub dependents: Vec<JobId>,
+    // "failed" or "was cancelled"
+    pub outcome: &'static str,
+}
+
+impl LargeDownloadState {
+    pub fn total(&self) -> u64 {
+        self.jobs.iter().map(|(_, size)| size).sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompletedMenuState {
+    pub job_id: JobId,
+    pub selected: usize,
+}
+
+// Which jobs a bulk removal takes out of the queue
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RemoveFilter {
+    Failed,
+    Cancelled,
+    Completed,
+    NotStarted,
+}
+
+impl RemoveFilter {
+    pub const ALL: [RemoveFilter; 4] = [
+        RemoveFilter::Failed,
+        RemoveFilter::Cancelled,
+        RemoveFilter::Completed,
+        RemoveFilter::NotStarted,
+    ];
+
+    pub fn key(&self) -> char {
+        match self {
+            RemoveFilter::Failed => 'f',
+            RemoveFilter::Cancelled => 'x',
+            RemoveFilter::Completed => 'c',
+            RemoveFilter::NotStarted => 'n',
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RemoveFilter::Failed => "failed",
+            RemoveFilter::Cancelled => "cancelled",
+            RemoveFilter::Completed => "completed",
+            RemoveFilter::NotStarted => "not started",
+        }
+    }
+
+    pub fn for_key(key: char) -> Option<RemoveFilter> {
+        Self::ALL.into_iter().find(|f| f.key() == key)
+    }
+
+    pub fn matches(&self, status: &JobStatus) -> bool {
+        match self {
+            RemoveFilter::Failed => matches!(status, JobStatus::Failed(_)),
+            RemoveFilter::Cancelled => *status == JobStatus::Cancelled,
+            RemoveFilter::Completed => *status == JobStatus::Completed,
+            RemoveFilter::NotStarted => matches!(
+                status,
+                JobStatus::ExpandingPlaylist { .. }
+                    | JobStatus::FetchingFormats
+                    | JobStatus::MetadataPending
+                    | JobStatus::Ready
+                    | JobStatus::Queued
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoveMenuState {
+    pub selected: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChannelListState {
+    pub selected: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TrashViewState {
+    pub selected: usize,
+}
+
+// A removed job, kept so it can be put back: the index it had, the playlist
+// header that went with it, and the removal it was part of so `u` takes back a whole batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedJob {
+    pub index: usize,
+    pub job: Job,
     #[serde(default)]
-    pub height: Option<u32>,
+    pub group: Option<JobGroup>,
+    pub batch: u64,
+}
+
+// What to do once the worker has halted the queue over repeated failures
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HaltAction {
+    Resume,
+    Probe,
+    UpdateYtdlp,
+}
+
+impl HaltAction {
+    pub const ALL: [HaltAction; 3] = [HaltAction::Resume, HaltAction::Probe, HaltAction::UpdateYtdlp];
+
+    pub fn key(&self) -> char {
+        match self {
+            HaltAction::Resume => 'r',
+            HaltAction::Probe => 'p',
+            HaltAction::UpdateYtdlp => 'u',
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HaltAction::Resume => "Resume the queue",
+            HaltAction::Probe => "Try one download first",
+            HaltAction::UpdateYtdlp => "Update yt-dlp (yt-dlp -U)",
+        }
+    }
+
+    pub fn for_key(key: char) -> Option<HaltAction> {
+        Self::ALL.into_iter().find(|a| a.key() == key)
+    }
+}
+
+// Ways out of two jobs writing the same file, offered on the later one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClashAction {
+    Suffix,
+    Rename,
+}
+
+impl ClashAction {
+    pub const ALL: [ClashAction; 2] = [ClashAction::Suffix, ClashAction::Rename];
+
+    pub fn key(&self) -> char {
+        match self {
+            ClashAction::Suffix => 's',
+            ClashAction::Rename => 'r',
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClashAction::Suffix => "Add a number, e.g. \"Title (2)\"",
+            ClashAction::Rename => "Choose a name for this item",
+        }
+    }
+
+    pub fn for_key(key: char) -> Option<ClashAction> {
+        Self::ALL.into_iter().find(|a| a.key() == key)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClashPromptState {
+    pub job_id: JobId,
+    pub earlier: JobId,
+    pub selected: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct HaltPromptState {
+    pub reason: String,
+    pub selected: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConflictPromptState {
+    pub job_id: JobId,
+    pub path: PathBuf,
+    pub selected: usize,
 }
 
-impl Format {
-    pub fn display_resolution(&self) -> Cow<'_, str> {
-        if let (Some(w), Some(h)) = (self.width, self.height) {
-            Cow::Owned(format!("{}x{}", w, h))
-        } else if let Some(res) = &self.resolution {
-            Cow::Borrowed(res)
+impl ConflictPromptState {
+    // Each answer once for this job, then for every conflict this session
+    pub const CHOICES: [(OverwritePolicy, bool); 6] = [
+        (OverwritePolicy::Overwrite, false),
+        (OverwritePolicy::KeepBoth, false),
+        (OverwritePolicy::Skip, false),
+        (OverwritePolicy::Overwrite, true),
+        (OverwritePolicy::KeepBoth, true),
+        (OverwritePolicy::Skip, true),
+    ];
+
+    pub fn new(job_id: JobId, path: PathBuf) -> Self {
+        Self { job_id, path, selected: 0 }
+    }
+
+    pub fn choice(&self) -> (OverwritePolicy, bool) {
+        Self::CHOICES[self.selected]
+    }
+
+    // Lowercase answers for this job only, uppercase for the session
+    pub fn for_key(c: char) -> Option<(OverwritePolicy, bool)> {
+        let policy = match c.to_ascii_lowercase() {
+            'o' => OverwritePolicy::Overwrite,
+            'b' => OverwritePolicy::KeepBoth,
+            's' => OverwritePolicy::Skip,
+            _ => return None,
+        };
+        Some((policy, c.is_ascii_uppercase()))
+    }
+}
+
+// How jobs imported with a preset fared, reported once they have all resolved
+#[derive(Debug, Clone, Default)]
+pub struct PresetTally {
+    pub applied: usize,
+    pub missed: usize,
+}
+
+impl PresetTally {
+    pub fn record(&mut self, applied: bool) {
+        if applied {
+            self.applied += 1;
         } else {
-            Cow::Borrowed("audio")
+            self.missed += 1;
         }
     }
 
-    pub fn display_size(&self) -> Cow<'_, str> {
-        let size = self.filesize.or(self.filesize_approx);
-        match size {
-            Some(b) if b >= 1024 * 1024 * 1024 => Cow::Owned(format!("{:.2} GiB", b as f64 / (1024.0 * 1024.0 * 1024.0))),
-            Some(b) if b >= 1024 * 1024 => Cow::Owned(format!("{:.2} MiB", b as f64 / (1024.0 * 1024.0))),
-            Some(b) if b >= 1024 => Cow::Owned(format!("{:.2} KiB", b as f64 / 1024.0)),
-            Some(b) => Cow::Owned(format!("{} B", b)),
-            None => Cow::Borrowed("~"),
+    pub fn is_empty(&self) -> bool {
+        self.applied + self.missed == 0
+    }
+}
+
+// Steps through Ready jobs one at a time in queue order. Jobs whose formats
+// arrive while triaging are picked up too, so the mode can start right after
+// a playlist is expanded
+#[derive(Debug, Clone)]
+pub struct TriageState {
+    pub group: Option<GroupId>,
+    pub current: Option<JobId>,
+    pub skipped: Vec<JobId>,
+    pub assigned: usize,
+}
+
+impl TriageState {
+    pub fn new(group: Option<GroupId>) -> Self {
+        Self {
+            group,
+            current: None,
+            skipped: Vec::new(),
+            assigned: 0,
         }
     }
 
-    pub fn display_bitrate(&self) -> Cow<'_, str> {
-        match self.tbr {
-            Some(br) => Cow::Owned(format!("{:.0} kbps", br)),
-            None => Cow::Borrowed("~"),
+    fn in_scope(&self, job: &Job) -> bool {
+        self.group.is_none_or(|g| job.group == Some(g))
+    }
+
+    fn is_pending(&self, job: &Job) -> bool {
+        self.in_scope(job) && job.status == JobStatus::Ready && !self.skipped.contains(&job.id)
+    }
+
+    // Moves to the next Ready job; false once there is nothing left to triage
+    pub fn advance(&mut self, jobs: &[Job]) -> bool {
+        self.current = jobs.iter().find(|j| self.is_pending(j)).map(|j| j.id);
+        self.current.is_some() || self.is_waiting(jobs)
+    }
+
+    pub fn skip(&mut self) {
+        if let Some(id) = self.current.take() {
+            self.skipped.push(id);
         }
     }
 
-    pub fn is_video(&self) -> bool {
-        self.vcodec.as_ref().map(|v| v != "none").unwrap_or(false)
+    pub fn is_waiting(&self, jobs: &[Job]) -> bool {
+        jobs.iter().any(|j| self.in_scope(j) && matches!(j.status, JobStatus::FetchingFormats | JobStatus::MetadataPending))
     }
 
-    pub fn is_audio_only(&self) -> bool {
-        !self.is_video() && self.acodec.as_ref().map(|a| a != "none").unwrap_or(false)
+    pub fn remaining(&self, jobs: &[Job]) -> usize {
+        jobs.iter()
+            .filter(|j| self.is_pending(j) || (self.in_scope(j) && matches!(j.status, JobStatus::FetchingFormats | JobStatus::MetadataPending)))
+            .count()
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct FormatPopupState {
-    pub job_index: usize,
-    pub formats: Vec<Format>,
+pub struct ImportPickerState {
+    pub bookmarks: Vec<Bookmark>,
+    pub chosen: Vec<bool>,
     pub selected: usize,
-    pub scroll_offset: usize,
-    pub audio_only: bool,
-    pub apply_to_all: bool,
+    // Queue the imported jobs straight away with this preset once formats arrive
+    pub preset: Option<TriagePreset>,
 }
 
-impl FormatPopupState {
-    pub fn new(job_index: usize, formats: Vec<Format>) -> Self {
+impl ImportPickerState {
+    pub fn new(bookmarks: Vec<Bookmark>) -> Self {
         Self {
-            job_index,
-            formats,
+            chosen: vec![true; bookmarks.len()],
+            bookmarks,
             selected: 0,
-            scroll_offset: 0,
-            audio_only: false,
-            apply_to_all: false,
+            preset: None,
         }
     }
 
-    pub fn filtered_formats(&self) -> Vec<&Format> {
-        self.formats
+    pub fn chosen_count(&self) -> usize {
+        self.chosen.iter().filter(|c| **c).count()
+    }
+
+    pub fn chosen_urls(&self) -> impl Iterator<Item = &str> {
+        self.bookmarks
             .iter()
-            .filter(|f| {
-                if self.audio_only {
-                    f.is_audio_only()
-                } else {
-                    f.is_video()
-                }
-            })
-            .collect()
+            .zip(&self.chosen)
+            .filter(|(_, chosen)| **chosen)
+            .map(|(b, _)| b.url.as_str())
     }
+}
 
-    pub fn selected_format(&self) -> Option<&Format> {
-        self.filtered_formats().get(self.selected).copied()
+pub const COOKIE_BROWSERS: [&str; 8] = ["chrome", "firefox", "edge", "brave", "chromium", "opera", "vivaldi", "safari"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RemediationStep {
+    Menu,
+    Browser,
+    Login,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoginField {
+    Username,
+    Password,
+}
+
+// Offered on a job that failed behind an age, consent or login wall
+#[derive(Debug, Clone)]
+pub struct RemediationState {
+    pub job_id: JobId,
+    pub step: RemediationStep,
+    pub selected: usize,
+    pub username: String,
+    pub password: Secret,
+    pub field: LoginField,
+}
+
+impl RemediationState {
+    pub const OPTIONS: [&'static str; 3] = ["Use cookies from a browser", "Enter username and password", "Skip"];
+
+    pub fn new(job_id: JobId) -> Self {
+        Self::at_step(job_id, RemediationStep::Menu)
+    }
+
+    pub fn at_step(job_id: JobId, step: RemediationStep) -> Self {
+        Self {
+            job_id,
+            step,
+            selected: 0,
+            username: String::new(),
+            password: Secret::default(),
+            field: LoginField::Username,
+        }
+    }
+
+    pub fn option_count(&self) -> usize {
+        match self.step {
+            RemediationStep::Menu => Self::OPTIONS.len(),
+            RemediationStep::Browser => COOKIE_BROWSERS.len(),
+            RemediationStep::Login => 0,
+        }
+    }
+
+    pub fn field_mut(&mut self) -> &mut String {
+        match self.field {
+            LoginField::Username => &mut self.username,
+            LoginField::Password => &mut self.password.0,
+        }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingsField {
+    ConcurrentDownloads,
+    RateLimit,
+    OutputDir,
+    YtdlpCommand,
+    CookiesFile,
+    CookiesBrowser,
+    Username,
+    Password,
+    UseNetrc,
+    EmbedSubs,
+    EmbedMetadata,
+}
+
+impl SettingsField {
+    pub const ALL: [SettingsField; 11] = [
+        SettingsField::ConcurrentDownloads,
+        SettingsField::RateLimit,
+        SettingsField::OutputDir,
+        SettingsField::YtdlpCommand,
+        SettingsField::CookiesFile,
+        SettingsField::CookiesBrowser,
+        SettingsField::Username,
+        SettingsField::Password,
+        SettingsField::UseNetrc,
+        SettingsField::EmbedSubs,
+        SettingsField::EmbedMetadata,
+    ];
+
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self,
+            SettingsField::OutputDir | SettingsField::YtdlpCommand | SettingsField::CookiesFile | SettingsField::Username | SettingsField::Password
+        )
+    }
+
+    // Typed and reviewed as asterisks
+    pub fn is_secret(&self) -> bool {
+        *self == SettingsField::Password
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SettingsField::ConcurrentDownloads => "Concurrent downloads",
+            SettingsField::RateLimit => "Rate limit",
+            SettingsField::OutputDir => "Download location",
+            SettingsField::YtdlpCommand => "yt-dlp command",
+            SettingsField::CookiesFile => "Cookies file",
+            SettingsField::CookiesBrowser => "Cookies from browser",
+            SettingsField::Username => "Username",
+            SettingsField::Password => "Password",
+            SettingsField::UseNetrc => "Use .netrc",
+            SettingsField::EmbedSubs => "Embed subtitles",
+            SettingsField::EmbedMetadata => "Embed metadata and chapters",
+        }
+    }
+
+    // The live value, in the same form the popup edits it
+    pub fn current(&self, config: &Config) -> String {
+        match self {
+            SettingsField::ConcurrentDownloads => config.max_concurrent_downloads.to_string(),
+            SettingsField::RateLimit => config.rate_limit.clone().unwrap_or_default(),
+            SettingsField::OutputDir => config.output_dir.to_string_lossy().into_owned(),
+            SettingsField::YtdlpCommand => config.ytdlp_command.join(" "),
+            SettingsField::CookiesFile => config.cookies_file.as_ref().map(|p| p.to_string_lossy().into_owned()).unwrap_or_default(),
+            SettingsField::CookiesBrowser => config.cookies_from_browser.clone().unwrap_or_default(),
+            SettingsField::Username => config.username.clone().unwrap_or_default(),
+            SettingsField::Password => config.password.clone().unwrap_or_default().0,
+            SettingsField::UseNetrc => on_off(config.use_netrc).into(),
+            SettingsField::EmbedSubs => on_off(config.embed_subs).into(),
+            SettingsField::EmbedMetadata => on_off(config.embed_metadata).into(),
+        }
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value { "on" } else { "off" }
+}
+
+// Steps for the rate limit setting; empty is no limit
+pub const RATE_LIMIT_PRESETS: [&str; 8] = ["", "500K", "1M", "2M", "5M", "10M", "20M", "50M"];
+
+// One line of the review shown before saving; a password is already masked
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingsChange {
+    pub field: SettingsField,
+    pub old: String,
+    pub new: String,
+}
+
+fn mask(value: &str) -> String {
+    if value.is_empty() { String::new() } else { "********".into() }
+}
+
 #[derive(Debug, Clone)]
 pub struct SettingsState {
     pub selected_field: usize,
     pub concurrent_downloads: usize,
+    // Empty for no limit
+    pub rate_limit: String,
     pub output_dir: String,
-    pub editing_path: bool,
+    pub ytdlp_command: String,
+    // Empty for none
+    pub cookies_file: String,
+    // Empty for none; cycles through browser_choices
+    pub cookies_from_browser: String,
+    pub browser_choices: Vec<String>,
+    // Empty for none
+    pub username: String,
+    pub password: Secret,
+    pub use_netrc: bool,
+    pub embed_subs: bool,
+    pub embed_metadata: bool,
+    pub editing: bool,
+    // Showing the changes for a final confirmation before they are applied
+    pub reviewing: bool,
 }
 
 impl SettingsState {
-    pub fn new(concurrent: usize, output_dir: PathBuf) -> Self {
+    pub fn new(config: &Config) -> Self {
+        let cookies_from_browser = SettingsField::CookiesBrowser.current(config);
+        // None first, then what is installed, plus a hand-set value detection missed
+        let mut browser_choices: Vec<String> = std::iter::once(String::new())
+            .chain(crate::config::detect_browsers().into_iter().map(String::from))
+            .collect();
+        if !browser_choices.contains(&cookies_from_browser) {
+            browser_choices.push(cookies_from_browser.clone());
+        }
         Self {
             selected_field: 0,
-            concurrent_downloads: concurrent,
-            output_dir: output_dir.to_string_lossy().into_owned(),
-            editing_path: false,
+            concurrent_downloads: config.max_concurrent_downloads,
+            rate_limit: SettingsField::RateLimit.current(config),
+            output_dir: config.output_dir.to_string_lossy().into_owned(),
+            ytdlp_command: config.ytdlp_command.join(" "),
+            cookies_file: SettingsField::CookiesFile.current(config),
+            cookies_from_browser,
+            browser_choices,
+            username: SettingsField::Username.current(config),
+            password: config.password.clone().unwrap_or_default(),
+            use_netrc: config.use_netrc,
+            embed_subs: config.embed_subs,
+            embed_metadata: config.embed_metadata,
+            editing: false,
+            reviewing: false,
+        }
+    }
+
+    // The edited value as it would be saved: an empty command keeps the old one
+    pub fn value(&self, field: SettingsField, config: &Config) -> String {
+        match field {
+            SettingsField::ConcurrentDownloads => self.concurrent_downloads.to_string(),
+            SettingsField::RateLimit => self.rate_limit.clone(),
+            SettingsField::OutputDir => self.output_dir.clone(),
+            SettingsField::YtdlpCommand => {
+                let words: Vec<&str> = self.ytdlp_command.split_whitespace().collect();
+                if words.is_empty() {
+                    field.current(config)
+                } else {
+                    words.join(" ")
+                }
+            }
+            SettingsField::CookiesFile => self.cookies_file.trim().to_string(),
+            SettingsField::CookiesBrowser => self.cookies_from_browser.clone(),
+            SettingsField::Username => self.username.trim().to_string(),
+            SettingsField::Password => self.password.0.clone(),
+            SettingsField::UseNetrc => on_off(self.use_netrc).into(),
+            SettingsField::EmbedSubs => on_off(self.embed_subs).into(),
+            SettingsField::EmbedMetadata => on_off(self.embed_metadata).into(),
+        }
+    }
+
+    pub fn changes(&self, config: &Config) -> Vec<SettingsChange> {
+        SettingsField::ALL
+            .iter()
+            .map(|&field| SettingsChange {
+                field,
+                old: field.current(config),
+                new: self.value(field, config),
+            })
+            .filter(|c| c.old != c.new)
+            .map(|c| match c.field.is_secret() {
+                // Swapping one password for another still shows as a change
+                true if !c.old.is_empty() && !c.new.is_empty() => SettingsChange { old: mask(&c.old), new: "******** (new)".into(), ..c },
+                true => SettingsChange { old: mask(&c.old), new: mask(&c.new), ..c },
+                false => c,
+            })
+            .collect()
+    }
+
+    // Moves to the next preset above or below the current limit, which may be
+    // a hand-written one from the config file
+    pub fn step_rate_limit(&mut self, up: bool) {
+        let bytes = |limit: &str| crate::config::parse_rate(limit).unwrap_or(0);
+        let current = bytes(&self.rate_limit);
+        let next = if up {
+            RATE_LIMIT_PRESETS.iter().find(|p| bytes(p) > current)
+        } else {
+            RATE_LIMIT_PRESETS.iter().rev().find(|p| bytes(p) < current)
+        };
+        if let Some(preset) = next {
+            self.rate_limit = preset.to_string();
         }
     }
+
+    pub fn cycle_browser(&mut self, step: isize) {
+        let len = self.browser_choices.len() as isize;
+        let at = self.browser_choices.iter().position(|b| *b == self.cookies_from_browser).unwrap_or(0) as isize;
+        self.cookies_from_browser = self.browser_choices[(at + step).rem_euclid(len) as usize].clone();
+    }
+
+    pub fn field(&self) -> SettingsField {
+        SettingsField::ALL[self.selected_field.min(SettingsField::ALL.len() - 1)]
+    }
+
+    pub fn editing_text_mut(&mut self) -> Option<&mut String> {
+        if !self.editing {
+            return None;
+        }
+        match self.field() {
+            SettingsField::OutputDir => Some(&mut self.output_dir),
+            SettingsField::YtdlpCommand => Some(&mut self.ytdlp_command),
+            SettingsField::CookiesFile => Some(&mut self.cookies_file),
+            SettingsField::Username => Some(&mut self.username),
+            SettingsField::Password => Some(&mut self.password.0),
+            SettingsField::ConcurrentDownloads
+            | SettingsField::RateLimit
+            | SettingsField::CookiesBrowser
+            | SettingsField::UseNetrc
+            | SettingsField::EmbedSubs
+            | SettingsField::EmbedMetadata => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoticeLevel {
+    Info,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Notice {
+    pub text: String,
+    pub level: NoticeLevel,
+    pub created: Instant,
+}
+
+impl Notice {
+    const TTL: Duration = Duration::from_secs(5);
+
+    pub fn new(text: impl Into<String>, level: NoticeLevel) -> Self {
+        Self {
+            text: text.into(),
+            level,
+            created: Instant::now(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.created.elapsed() >= Self::TTL
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum AppEvent {
     AddUrl(String),
+    PreviewFetched { id: String, preview: Option<VideoPreview> },
     StartDownloads,
+    ConfirmDefaultFormat,
+    SkipDefaultFormat,
+    CloseDefaultFormat,
+    ConfirmLargeDownloads,
+    SkipLargeDownloads,
+    DownsizeLargeDownload,
+    CloseLargeDownloads,
+    // `>`: mark the selected job, or make it wait on the marked one
+    LinkAfter,
+    UnlinkAfter,
+    RunDependentsAnyway,
+    CancelDependents,
+    CloseDependentsPrompt,
+    SaveSession(PathBuf),
+    OpenSession(PathBuf),
+    SessionLoadNext,
+    SessionLoadPrev,
+    ConfirmSessionLoad,
+    LoadSession(LoadChoice),
+    CloseSessionLoad,
+    // For the front link of the playlist choice prompt
+    ChooseSingleVideo,
+    ChooseWholePlaylist,
+    SkipPlaylistChoice,
+    // Loads the front playlist of the range prompt with the typed range
+    ConfirmPlaylistRange,
+    // Drops the front playlist of the range prompt without loading it
+    SkipPlaylistRange,
+    OpenClashPrompt,
+    ClashPromptNext,
+    ClashPromptPrev,
+    ConfirmClashAction,
+    RunClashAction(ClashAction),
+    CloseClashPrompt,
+    // Sets the job's output overrides from the output prompt
+    ConfirmOutput,
+    // Sets the job's sections from the section prompt, or clears them when both fields are empty
+    ConfirmSections,
     OpenFormatPopup,
     CloseFormatPopup,
     FormatSelectNext,
     FormatSelectPrev,
-    ToggleAudioOnly,
+    FormatPageDown,
+    FormatPageUp,
+    FormatSelectFirst,
+    FormatSelectLast,
+    FormatToggleAudioTier,
+    FormatToggleExtractAudio,
+    // Forward or back through the audio quality steps
+    FormatStepAudioQuality(bool),
+    FormatCycleContainer,
+    FormatToggleShowAll,
+    ToggleLiveFromStart,
+    FormatExpandTier,
+    FormatCollapseTier,
     ToggleApplyToAll,
     ConfirmFormat,
+    OpenGroupMenu(GroupId),
+    CloseGroupMenu,
+    GroupMenuNext,
+    GroupMenuPrev,
+    ConfirmGroupAction,
+    ImportBookmarks(PathBuf),
+    SetNote { id: JobId, note: String },
+    OpenChannelList,
+    ChannelListNext,
+    ChannelListPrev,
+    ForgetChannel,
+    CloseChannelList,
+    ImportPickerNext,
+    ImportPickerPrev,
+    ImportPickerToggle,
+    ImportPickerToggleAll,
+    ImportPickerPreset(Option<TriagePreset>),
+    ConfirmImport,
+    CloseImportPicker,
+    OpenRemediation(JobId),
+    // Restart a cancelled job, continuing its part file or starting over
+    RestartCancelled { id: JobId, resume: bool },
+    RetryMembersOnly,
+    RemediationNext,
+    RemediationPrev,
+    RemediationConfirm,
+    RemediationInput(char),
+    RemediationBackspace,
+    RemediationSwitchField,
+    CloseRemediation,
+    OpenCompletedMenu(JobId),
+    CompletedMenuNext,
+    CompletedMenuPrev,
+    ConfirmCompletedAction,
+    CloseCompletedMenu,
+    RunCompletedAction(JobId, CompletedAction),
+    OpenConflictPrompt(JobId),
+    ConflictPromptNext,
+    ConflictPromptPrev,
+    ConfirmConflictChoice,
+    // The policy to apply, and whether it holds for the rest of the session
+    ResolveConflict(JobId, OverwritePolicy, bool),
+    CloseConflictPrompt,
+    OpenRemoveMenu,
+    RemoveMenuNext,
+    RemoveMenuPrev,
+    ConfirmRemoveMenu,
+    RemoveByStatus(RemoveFilter),
+    CloseRemoveMenu,
+    UndoRemove,
+    OpenTrash,
+    TrashNext,
+    TrashPrev,
+    RestoreTrashed,
+    PurgeTrashed,
+    EmptyTrash,
+    CloseTrash,
+    OpenHaltPrompt,
+    ResumePowerPause,
+    HaltPromptNext,
+    HaltPromptPrev,
+    ConfirmHaltAction,
+    RunHaltAction(HaltAction),
+    CloseHaltPrompt,
+    StartTriage(Option<GroupId>),
+    TriageAssign(TriagePreset),
+    TriageSkip,
+    TriageRemove,
+    CloseTriage,
     CancelJob(JobId),
     RemoveJob(JobId),
     SelectNext,
     SelectPrev,
+    SortByUploadDate,
     ToggleInputMode,
+    // Between the active and finished lists of the split queue
+    SwitchQueuePane,
+    ToggleQueueLayout,
+    OpenQueueFilter,
+    // The filter text after each key press
+    SetQueueFilter(String),
+    // Stop typing and keep the queue filtered
+    KeepQueueFilter,
+    ClearQueueFilter,
     ToggleHelp,
     ToggleSysInfo,
+    ToggleFpsOverlay,
+    ToggleVerboseRows,
+    ToggleWarnings,
+    ToggleTimeline,
+    CopyErrorReport,
+    ToggleDoNotDisturb,
     ToggleSettings,
     SettingsNext,
     SettingsPrev,
@@ -161,80 +1303,245 @@ pub enum AppEvent {
     SettingsToggleEdit,
     SettingsCharInput(char),
     SettingsBackspace,
+    ReviewSettings,
+    CancelSettingsReview,
     SaveSettings,
+    OpenLogFile,
+    ClearOldLogs,
     CloseSettings,
     Quit,
+    Suspend,
+    OpenHealthCheck,
+    HealthChecked(HealthReport),
+    // Troubleshooting steps for a failed job
+    DiagnoseJob(JobId),
+    Diagnosed(JobId, Diagnosis),
+    CopyDiagnosis,
+    CloseDiagnosis,
+    UpdateYtdlp,
+    // yt-dlp's version after an update, None if it no longer runs
+    YtdlpVersionChecked(Option<String>),
+    CloseYtdlpUpdate,
+    ConfigSaveFailed(String),
+    // The config task put new settings in effect; the app takes them from its snapshot
+    ConfigChanged,
+    ReloadConfig,
+    ConfigReloaded { changed: bool },
+    ConfigReloadFailed(String),
+    CloseHealthCheck,
     CancelQuit,
     ConfirmQuit,
+    ShowCommand,
+    CloseCommandPopup,
+    CopyCommand,
+    AutosaveFailed { error: String },
 
-    JobStarted { id: JobId },
-    FormatsReady { id: JobId, title: String, formats: Vec<Format> },
-    JobProgress { id: JobId, percent: f32, speed: String, eta: String, phase: DownloadPhase },
-    JobCompleted { id: JobId, path: PathBuf },
-    JobFailed { id: JobId, error: String },
-    PlaylistExpanded { urls: Vec<(String, Option<String>)> },
+    Worker(WorkerEvent),
 }
 
-#[derive(Debug, Clone)]
-pub enum WorkerCommand {
-    FetchFormats { job_id: JobId, url: String },
-    FetchPlaylist { url: String },
-    StartJob { job_id: JobId, url: String, format_id: String },
-    CancelJob(JobId),
-    UpdateConcurrent(usize),
-    Shutdown,
+impl AppEvent {
+    // The job an event concerns, used to attach log lines to that job's span
+    pub fn job_id(&self) -> Option<JobId> {
+        match self {
+            AppEvent::CancelJob(id) | AppEvent::RemoveJob(id) => Some(*id),
+            AppEvent::Worker(event) => event.job_id(),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
-pub enum DownloadPhase {
-    #[default]
-    Video,
-    Audio,
-    Merging,
-    Single,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::format;
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum JobStatus {
-    FetchingFormats,
-    Ready { formats: Vec<Format> },
-    Queued,
-    Downloading { percent: f32, speed: String, eta: String, phase: DownloadPhase },
-    Completed,
-    Failed(String),
-    Cancelled,
-}
+    // One audio tier opened out to `rows` rows in all, header included
+    fn popup(rows: usize, viewport: usize) -> FormatPopupState {
+        let formats: Vec<Format> = (1..rows)
+            .map(|i| format(&format!(r#"{{"format_id":"a{}","vcodec":"none","acodec":"opus","tbr":{}}}"#, i, 1000 - i)))
+            .collect();
+        let mut popup = FormatPopupState::new(0, formats.into());
+        popup.viewport_height.set(viewport);
+        popup.expand_selected();
+        popup.toggle_show_all();
+        // One row on its own can't be built: an opened tier lists its format under the header
+        assert_eq!(popup.rows().len(), if rows > 1 { rows } else { 0 });
+        popup
+    }
 
-#[derive(Debug, Clone)]
-pub struct Job {
-    pub id: JobId,
-    pub url: String,
-    pub title: Option<String>,
-    pub status: JobStatus,
-    pub formats: Vec<Format>,
-    pub selected_format: Option<Format>,
-    pub output_path: Option<PathBuf>,
-}
+    fn at(popup: &FormatPopupState) -> (usize, usize) {
+        (popup.selected, popup.scroll_offset)
+    }
 
-impl Job {
-    pub fn new(url: impl Into<String>) -> Self {
-        Self {
-            id: Uuid::new_v4(),
-            url: url.into(),
-            title: None,
-            status: JobStatus::FetchingFormats,
-            formats: Vec::new(),
-            selected_format: None,
-            output_path: None,
+    #[test]
+    fn paging_moves_a_viewport_and_clamps() {
+        let mut popup = popup(25, 10);
+        popup.page_down();
+        assert_eq!(at(&popup), (10, 1));
+        popup.page_down();
+        assert_eq!(at(&popup), (20, 11));
+        popup.page_down();
+        assert_eq!(at(&popup), (24, 15));
+        popup.page_up();
+        assert_eq!(at(&popup), (14, 14));
+        popup.page_up();
+        popup.page_up();
+        assert_eq!(at(&popup), (0, 0));
+    }
+
+    #[test]
+    fn home_and_end_across_list_sizes() {
+        for (rows, viewport) in [(0, 10), (2, 10), (10, 10), (11, 10), (60, 10), (60, 1), (5, 0)] {
+            let mut popup = popup(rows, viewport);
+            popup.select_last();
+            let last = popup.rows().len().saturating_sub(1);
+            let page = viewport.max(1);
+            assert_eq!(at(&popup), (last, popup.rows().len().saturating_sub(page)), "{} rows in {}", rows, viewport);
+            popup.select_first();
+            assert_eq!(at(&popup), (0, 0), "{} rows in {}", rows, viewport);
+            popup.page_down();
+            assert_eq!(popup.selected, page.min(last), "{} rows in {}", rows, viewport);
+            assert!(popup.selected < popup.scroll_offset + page || popup.rows().is_empty());
         }
     }
 
-    pub fn display_name(&self) -> &str {
-        self.title.as_deref().unwrap_or(&self.url)
+    #[test]
+    fn wrapping_is_optional() {
+        let mut popup = popup(4, 10);
+        popup.select_prev(false);
+        assert_eq!(popup.selected, 0);
+        popup.select_prev(true);
+        assert_eq!(popup.selected, 3);
+        popup.select_next(false);
+        assert_eq!(popup.selected, 3);
+        popup.select_next(true);
+        assert_eq!(popup.selected, 0);
+
+        let mut empty = FormatPopupState::new(0, Vec::new().into());
+        empty.select_next(true);
+        empty.select_prev(true);
+        assert_eq!(at(&empty), (0, 0));
+    }
+
+    fn tiered() -> FormatPopupState {
+        let formats: Vec<Format> = [
+            r#"{"format_id":"18","vcodec":"avc1","acodec":"mp4a","height":360}"#,
+            r#"{"format_id":"136","vcodec":"avc1","acodec":"none","height":720,"tbr":1500}"#,
+            r#"{"format_id":"22","vcodec":"avc1","acodec":"mp4a","height":720,"tbr":1500}"#,
+            r#"{"format_id":"137","vcodec":"avc1","acodec":"none","height":1080}"#,
+            r#"{"format_id":"140","vcodec":"none","acodec":"mp4a","tbr":129}"#,
+            r#"{"format_id":"sb0","vcodec":"none","acodec":"none"}"#,
+        ]
+        .into_iter()
+        .map(format)
+        .collect();
+        FormatPopupState::new(0, formats.into())
+    }
+
+    fn shown(popup: &FormatPopupState) -> Vec<String> {
+        popup
+            .rows()
+            .iter()
+            .map(|row| match row {
+                FormatRow::Tier { tier, count, expanded, .. } => format!("{}{} ({})", if *expanded { "-" } else { "+" }, tier.label(), count),
+                FormatRow::Variant { format, .. } => format!("  {}", popup.formats[*format].format_id),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tiers_open_collapsed_on_their_best_format() {
+        let mut popup = tiered();
+        assert_eq!(shown(&popup), ["+1080p (1)", "+720p (2)", "+Lower (1)", "+Audio (1)"]);
+
+        popup.select_next(false);
+        assert_eq!(popup.selected_format().map(|f| f.format_id.as_str()), Some("22"));
+
+        popup.expand_selected();
+        assert_eq!(shown(&popup), ["+1080p (1)", "-720p (2)", "  22", "  136", "+Lower (1)", "+Audio (1)"]);
+        popup.select_next(false);
+        popup.select_next(false);
+        assert_eq!(popup.selected_format().map(|f| f.format_id.as_str()), Some("136"));
+
+        // Collapsing from a variant goes back up to its header
+        popup.collapse_selected();
+        assert_eq!(popup.selected, 1);
+        assert_eq!(shown(&popup), ["+1080p (1)", "+720p (2)", "+Lower (1)", "+Audio (1)"]);
+    }
+
+    #[test]
+    fn focusing_a_variant_opens_its_tier() {
+        let mut popup = tiered();
+        popup.focus("136");
+        assert_eq!(popup.selected_format().map(|f| f.format_id.as_str()), Some("136"));
+        assert!(matches!(popup.rows()[1], FormatRow::Tier { expanded: true, .. }));
+
+        // The best of a tier is its header; nothing needs opening
+        let mut popup = tiered();
+        popup.focus("140");
+        assert_eq!(popup.selected, 3);
+        assert_eq!(popup.rows().len(), 4);
+
+        popup.focus("sb0");
+        popup.focus("missing");
+        assert_eq!(popup.selected, 3);
+    }
+
+    #[test]
+    fn the_audio_key_jumps_between_audio_and_the_top() {
+        let mut popup = tiered();
+        popup.toggle_audio_tier();
+        assert_eq!(popup.selected_row().map(|r| r.tier()), Some(QualityTier::Audio));
+        popup.toggle_audio_tier();
+        assert_eq!(popup.selected, 0);
     }
 
-    pub fn can_select_format(&self) -> bool {
-        matches!(self.status, JobStatus::Ready { .. } | JobStatus::Queued) && !self.formats.is_empty()
+    #[test]
+    fn long_tiers_are_capped_until_show_all() {
+        let formats: Vec<Format> = (0..VARIANT_CAP + 5)
+            .map(|i| format(&format!(r#"{{"format_id":"v{}","vcodec":"avc1","acodec":"none","height":720,"tbr":{}}}"#, i, 5000 - i)))
+            .collect();
+        let mut popup = FormatPopupState::new(0, formats.into());
+        popup.expand_selected();
+        assert_eq!(popup.rows().len(), 1 + VARIANT_CAP);
+        assert_eq!((popup.hidden(), popup.variants), (5, VARIANT_CAP + 5));
+
+        popup.focus("v22");
+        assert!(popup.show_all);
+        assert_eq!(popup.hidden(), 0);
+        assert_eq!(popup.selected_format().map(|f| f.format_id.as_str()), Some("v22"));
+
+        popup.toggle_show_all();
+        assert_eq!(popup.rows().len(), 1 + VARIANT_CAP);
+        assert!(popup.selected < popup.rows().len());
     }
 
+    #[test]
+    fn moving_through_a_huge_popup_never_rebuilds_its_rows() {
+        let formats: Vec<Format> = (0..500)
+            .map(|i| format(&format!(r#"{{"format_id":"a{}","vcodec":"none","acodec":"opus","tbr":{}}}"#, i, 1000 - i)))
+            .collect();
+        let mut popup = FormatPopupState::new(0, formats.into());
+        popup.viewport_height.set(12);
+        assert_eq!(popup.rows().len(), 1);
+        popup.expand_selected();
+        assert_eq!((popup.rows().len(), popup.hidden()), (1 + VARIANT_CAP, 500 - VARIANT_CAP));
+        popup.toggle_show_all();
+        assert_eq!(popup.rows().len(), 501);
+
+        // Rows are only built when tiers open or close; moving reuses them
+        let built = popup.rows().as_ptr();
+        for _ in 0..600 {
+            popup.select_next(false);
+            assert!(popup.selected < popup.scroll_offset + 12);
+        }
+        assert_eq!(at(&popup), (500, 489));
+        popup.page_up();
+        popup.select_prev(true);
+        popup.select_first();
+        popup.select_last();
+        popup.toggle_audio_tier();
+        assert_eq!(at(&popup), (0, 0));
+        assert_eq!(popup.rows().as_ptr(), built);
+    }
 }
@@ -0,0 +1,263 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeUnits {
+    #[default]
+    Binary,
+    Decimal,
+}
+
+// Set once from the config at startup so every render site agrees without
+// having the config passed down to it
+static DECIMAL_UNITS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_size_units(units: SizeUnits) {
+    DECIMAL_UNITS.store(units == SizeUnits::Decimal, Ordering::Relaxed);
+}
+
+pub fn size(bytes: u64) -> String {
+    let (base, suffixes) = if DECIMAL_UNITS.load(Ordering::Relaxed) {
+        (1000.0, ["kB", "MB", "GB", "TB"])
+    } else {
+        (1024.0, ["KiB", "MiB", "GiB", "TiB"])
+    };
+
+    if (bytes as f64) < base {
+        return format!("{} B", bytes);
+    }
+    // Moves up a unit as soon as two decimals would round to the base, so
+    // nothing shows as 1024.00 KiB
+    let mut value = bytes as f64 / base;
+    let mut unit = 0;
+    while unit + 1 < suffixes.len() && (value * 100.0).round() >= base * 100.0 {
+        value /= base;
+        unit += 1;
+    }
+    format!("{:.2} {}", value, suffixes[unit])
+}
+
+pub fn rate(bytes_per_sec: u64) -> String {
+    format!("{}/s", size(bytes_per_sec))
+}
+
+// A download's speed in the configured units; yt-dlp's own text, which is
+// always binary, only when it gave no number
+pub fn speed(bytes_per_sec: Option<u64>, ytdlp_text: &str) -> String {
+    bytes_per_sec.map(rate).unwrap_or_else(|| ytdlp_text.to_string())
+}
+
+// View and like counts: 950, 1.2K, 3.4M, 1.1B. The thresholds sit where one
+// decimal would round up to the next unit, so 999,999 is 1.0M and not 1000.0K
+pub fn count(n: u64) -> String {
+    match n {
+        n if n >= 999_950_000 => format!("{:.1}B", n as f64 / 1e9),
+        n if n >= 999_950 => format!("{:.1}M", n as f64 / 1e6),
+        n if n >= 1_000 => format!("{:.1}K", n as f64 / 1e3),
+        n => n.to_string(),
+    }
+}
+
+// m:ss, or h:mm:ss once there are hours
+pub fn duration(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+// "90", "1:30" or "1:02:03.5" as seconds, the forms yt-dlp takes for sections
+pub fn parse_timestamp(text: &str) -> Option<f64> {
+    let parts: Vec<&str> = text.trim().split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+    parts.iter().try_fold(0.0, |total, part| {
+        let value: f64 = part.parse().ok().filter(|v: &f64| *v >= 0.0)?;
+        Some(total * 60.0 + value)
+    })
+}
+
+// Coarse "added 5m ago" style age; anything in the future counts as just now
+pub fn age(then: DateTime<Local>, now: DateTime<Local>) -> String {
+    let secs = (now - then).num_seconds();
+    match secs {
+        s if s < 60 => "just now".into(),
+        s if s < 60 * 60 => format!("{}m ago", s / 60),
+        s if s < 24 * 60 * 60 => format!("{}h ago", s / (60 * 60)),
+        s => format!("{}d ago", s / (24 * 60 * 60)),
+    }
+}
+
+// `pattern` is the configured `date_format`, in strftime syntax
+pub fn date(when: DateTime<Local>, pattern: &str) -> String {
+    use std::fmt::Write;
+
+    // chrono reports a bad pattern as a fmt error rather than at parse time
+    let mut out = String::new();
+    match write!(out, "{}", when.format(pattern)) {
+        Ok(()) => out,
+        Err(_) => when.format("%Y-%m-%d %H:%M").to_string(),
+    }
+}
+
+// yt-dlp's `upload_date` is a bare YYYYMMDD; shown as 2024-03-05
+pub fn upload_date(raw: &str) -> Option<String> {
+    NaiveDate::parse_from_str(raw.trim(), "%Y%m%d")
+        .ok()
+        .map(|d| d.format("%Y-%m-%d").to_string())
+}
+
+// Fills in the `%(field)s` keys that are known before downloading, leaving the
+// rest for yt-dlp; a preview only, so no width or conversion flags
+pub fn template_preview(template: &str, fields: &[(&str, &str)]) -> String {
+    fields.iter().fold(template.to_string(), |out, (key, value)| {
+        out.replace(&format!("%({})s", key), value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeDelta, TimeZone};
+    use std::sync::Mutex;
+
+    // The unit setting is process wide, so tests that change it take turns
+    static UNITS: Mutex<()> = Mutex::new(());
+
+    fn with_units<T>(units: SizeUnits, f: impl FnOnce() -> T) -> T {
+        let _guard = UNITS.lock().unwrap_or_else(|e| e.into_inner());
+        set_size_units(units);
+        let out = f();
+        set_size_units(SizeUnits::Binary);
+        out
+    }
+
+    #[test]
+    fn ages_round_down_to_the_unit() {
+        let now = Local::now();
+        for (secs, expected) in [
+            (-30, "just now"),
+            (0, "just now"),
+            (59, "just now"),
+            (60, "1m ago"),
+            (3599, "59m ago"),
+            (3600, "1h ago"),
+            (86_399, "23h ago"),
+            (86_400, "1d ago"),
+            (12 * 86_400 + 5, "12d ago"),
+        ] {
+            assert_eq!(age(now - TimeDelta::seconds(secs), now), expected, "{}s", secs);
+        }
+    }
+
+    #[test]
+    fn sizes_switch_unit_at_the_base() {
+        with_units(SizeUnits::Binary, || {
+            for (bytes, expected) in [
+                (0, "0 B"),
+                (1023, "1023 B"),
+                (1024, "1.00 KiB"),
+                (1536, "1.50 KiB"),
+                ((1 << 20) - 1, "1.00 MiB"),
+                ((1 << 20) - 6000, "1018.14 KiB"),
+                (1 << 20, "1.00 MiB"),
+                (5 << 30, "5.00 GiB"),
+                (1 << 40, "1.00 TiB"),
+                (u64::MAX, "16777216.00 TiB"),
+            ] {
+                assert_eq!(size(bytes), expected, "{} bytes", bytes);
+            }
+        });
+        with_units(SizeUnits::Decimal, || {
+            for (bytes, expected) in [(999, "999 B"), (1000, "1.00 kB"), (1023, "1.02 kB"), (1024, "1.02 kB"), (999_999, "1.00 MB"), (1_000_000, "1.00 MB"), (2_500_000_000, "2.50 GB")] {
+                assert_eq!(size(bytes), expected, "{} bytes", bytes);
+            }
+            assert_eq!(rate(1_500_000), "1.50 MB/s");
+        });
+    }
+
+    #[test]
+    fn speeds_everywhere_follow_the_setting() {
+        let mut stats = crate::core::SpeedStats::default();
+        (stats.peak_bps, stats.active_secs) = (12_000_000, 242.0);
+        stats.finish(Some(2_420_000_000));
+        let cap = crate::config::Config { rate_limit: Some("2M".into()), ..Default::default() }.rate_limit_bytes().unwrap();
+
+        with_units(SizeUnits::Binary, || {
+            assert_eq!(rate(cap), "2.00 MiB/s");
+            assert_eq!(speed(Some(1 << 20), "1.00MiB/s"), "1.00 MiB/s");
+            assert_eq!(speed(None, "Unknown B/s"), "Unknown B/s");
+            assert_eq!(stats.summary().as_deref(), Some("avg 9.54 MiB/s, peak 11.44 MiB/s, 4:02"));
+        });
+        with_units(SizeUnits::Decimal, || {
+            assert_eq!(rate(cap), "2.10 MB/s");
+            // yt-dlp always prints binary; its number is shown in the chosen units instead
+            assert_eq!(speed(Some(1 << 20), "1.00MiB/s"), "1.05 MB/s");
+            assert_eq!(speed(None, "Unknown B/s"), "Unknown B/s");
+            assert_eq!(rate(999), "999 B/s");
+            assert_eq!(stats.summary().as_deref(), Some("avg 10.00 MB/s, peak 12.00 MB/s, 4:02"));
+        });
+    }
+
+    #[test]
+    fn counts_abbreviate_from_a_thousand() {
+        for (n, expected) in [
+            (0, "0"),
+            (999, "999"),
+            (1000, "1.0K"),
+            (1250, "1.2K"),
+            (999_949, "999.9K"),
+            (999_999, "1.0M"),
+            (1_000_000, "1.0M"),
+            (999_999_999, "1.0B"),
+            (3_400_000_000, "3.4B"),
+        ] {
+            assert_eq!(count(n), expected, "{}", n);
+        }
+    }
+
+    #[test]
+    fn durations_gain_hours_only_when_needed() {
+        for (secs, expected) in [(0.0, "0:00"), (59.0, "0:59"), (59.6, "1:00"), (60.0, "1:00"), (3599.0, "59:59"), (3600.0, "1:00:00"), (36_061.0, "10:01:01")] {
+            assert_eq!(duration(secs), expected, "{}s", secs);
+        }
+    }
+
+    #[test]
+    fn timestamps_parse_in_every_form() {
+        for (text, expected) in [
+            ("90", Some(90.0)),
+            ("1:30", Some(90.0)),
+            (" 1:02:03.5 ", Some(3723.5)),
+            ("0:59", Some(59.0)),
+            ("1:2:3:4", None),
+            ("-5", None),
+            ("1:-5", None),
+            ("", None),
+            ("abc", None),
+        ] {
+            assert_eq!(parse_timestamp(text), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn dates_fall_back_on_a_bad_pattern() {
+        let when = Local.with_ymd_and_hms(2024, 3, 5, 14, 7, 0).unwrap();
+        assert_eq!(date(when, "%d/%m/%Y"), "05/03/2024");
+        assert_eq!(date(when, "%Q"), "2024-03-05 14:07");
+        assert_eq!(upload_date("20240305"), Some("2024-03-05".into()));
+        assert_eq!(upload_date("2024-03-05"), None);
+    }
+
+    #[test]
+    fn template_preview_fills_only_known_fields() {
+        let preview = template_preview("%(uploader)s/%(title)s [%(id)s].%(ext)s", &[("title", "Clip"), ("id", "abc")]);
+        assert_eq!(preview, "%(uploader)s/Clip [abc].%(ext)s");
+    }
+}
@@ -0,0 +1,193 @@
+// Queue filter matching. Each word of the query has to turn up in the title in
+// order, not necessarily side by side, scored the way fzf does: characters at
+// the start of words and runs of consecutive characters count for more, gaps
+// cost a little. A query starting with `'` is matched exactly instead, as one
+// case-insensitive substring
+const SCORE_MATCH: i32 = 16;
+const BONUS_BOUNDARY: i32 = 8;
+const BONUS_CAMEL: i32 = 7;
+const BONUS_CONSECUTIVE: i32 = 4;
+const PENALTY_GAP_START: i32 = 3;
+const PENALTY_GAP_EXTENSION: i32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub score: i32,
+    // Char indices into the text, ascending, for highlighting
+    pub positions: Vec<usize>,
+}
+
+pub fn find(query: &str, text: &str) -> Option<Match> {
+    let text: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = text.iter().map(|c| fold(*c)).collect();
+    if let Some(exact) = query.strip_prefix('\'') {
+        return substring(exact, &lower);
+    }
+
+    let mut total = Match { score: 0, positions: Vec::new() };
+    for term in query.split_whitespace() {
+        let found = score(term, &text, &lower)?;
+        total.score += found.score;
+        total.positions.extend(found.positions);
+    }
+    total.positions.sort_unstable();
+    total.positions.dedup();
+    Some(total)
+}
+
+// One lowercase char, so indices into the folded text stay those of the original
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn substring(needle: &str, lower: &[char]) -> Option<Match> {
+    let needle: Vec<char> = needle.chars().map(fold).collect();
+    if needle.is_empty() {
+        return Some(Match { score: 0, positions: Vec::new() });
+    }
+    let start = lower.windows(needle.len()).position(|w| w == needle.as_slice())?;
+    // Earlier matches rank first, like a plain search would list them
+    let score = SCORE_MATCH * needle.len() as i32 - start as i32;
+    Some(Match { score, positions: (start..start + needle.len()).collect() })
+}
+
+// fzf's v1 algorithm: the first occurrence of the whole subsequence going
+// forward, then walked back from its end to the tightest start
+fn score(term: &str, text: &[char], lower: &[char]) -> Option<Match> {
+    let pattern: Vec<char> = term.chars().map(fold).collect();
+    if pattern.is_empty() {
+        return Some(Match { score: 0, positions: Vec::new() });
+    }
+
+    let mut p = 0;
+    let mut end = None;
+    for (i, c) in lower.iter().enumerate() {
+        if *c == pattern[p] {
+            p += 1;
+            if p == pattern.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    let mut start = end;
+    let mut p = pattern.len();
+    for i in (0..=end).rev() {
+        if lower[i] == pattern[p - 1] {
+            p -= 1;
+            if p == 0 {
+                start = i;
+                break;
+            }
+        }
+    }
+
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut score = 0;
+    let mut p = 0;
+    let mut in_gap = false;
+    let mut consecutive = 0;
+    let mut first_bonus = 0;
+    for (i, c) in lower.iter().enumerate().take(end + 1).skip(start) {
+        if p < pattern.len() && *c == pattern[p] {
+            let mut bonus = bonus_at(text, i);
+            if consecutive == 0 {
+                first_bonus = bonus;
+            } else {
+                // A run is worth as much as the boundary it started on
+                bonus = bonus.max(first_bonus).max(BONUS_CONSECUTIVE);
+            }
+            // The first character of the query matters most
+            score += SCORE_MATCH + if p == 0 { bonus * 2 } else { bonus };
+            positions.push(i);
+            consecutive += 1;
+            in_gap = false;
+            p += 1;
+        } else {
+            score -= if in_gap { PENALTY_GAP_EXTENSION } else { PENALTY_GAP_START };
+            in_gap = true;
+            consecutive = 0;
+            first_bonus = 0;
+        }
+    }
+    Some(Match { score, positions })
+}
+
+fn bonus_at(text: &[char], i: usize) -> i32 {
+    let Some(&prev) = i.checked_sub(1).and_then(|j| text.get(j)) else {
+        return BONUS_BOUNDARY;
+    };
+    let c = text[i];
+    if !prev.is_alphanumeric() && c.is_alphanumeric() {
+        BONUS_BOUNDARY
+    } else if (prev.is_lowercase() && c.is_uppercase()) || (!prev.is_numeric() && c.is_numeric()) {
+        BONUS_CAMEL
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(query: &str, text: &str) -> Option<Vec<usize>> {
+        find(query, text).map(|m| m.positions)
+    }
+
+    fn score_of(query: &str, text: &str) -> i32 {
+        find(query, text).unwrap().score
+    }
+
+    #[test]
+    fn terms_match_in_order_ignoring_case() {
+        for (query, text, expected) in [
+            ("never gonna", "Rick Astley - Never Gonna Give You Up", Some(vec![14, 15, 16, 17, 18, 20, 21, 22, 23, 24])),
+            ("ngg", "Never Gonna Give", Some(vec![0, 6, 12])),
+            ("NGG", "never gonna give", Some(vec![0, 6, 12])),
+            ("give never", "Never Gonna Give", Some(vec![0, 1, 2, 3, 4, 12, 13, 14, 15])),
+            ("vg", "Never Gonna Give", Some(vec![2, 6])),
+            ("an", "Never Gonna Give", None),
+            ("neverx", "Never Gonna Give", None),
+            ("", "anything", Some(vec![])),
+            ("   ", "anything", Some(vec![])),
+            // Indices count chars, not bytes, and survive case folding
+            ("été", "ÉTÉ à Paris", Some(vec![0, 1, 2])),
+            ("par", "ÉTÉ à Paris", Some(vec![6, 7, 8])),
+            ("日本", "東京 日本 ライブ", Some(vec![3, 4])),
+        ] {
+            assert_eq!(positions(query, text), expected, "{:?} in {:?}", query, text);
+        }
+    }
+
+    #[test]
+    fn the_tightest_occurrence_is_picked() {
+        // The forward pass ends at the first "c", then walks back to the closest "a" and "b"
+        assert_eq!(positions("abc", "a_a_ab_c"), Some(vec![4, 5, 7]));
+        assert_eq!(positions("ab", "xaxab"), Some(vec![3, 4]));
+    }
+
+    #[test]
+    fn word_starts_and_runs_score_higher() {
+        // Starting a word beats landing mid-word
+        assert!(score_of("gon", "Never Gonna") > score_of("gon", "Paragon"));
+        // One run beats the same letters scattered
+        assert!(score_of("give", "Give Up") > score_of("give", "Great Ivy Vale East"));
+        // camelCase and digits start words too
+        assert!(score_of("up", "NeverGiveUp") > score_of("up", "Neverupset"));
+        assert!(score_of("2", "Part2") > score_of("2", "Part 12"));
+        // Longer gaps cost more
+        assert!(score_of("ab", "xa_bx") > score_of("ab", "xa___bx"));
+    }
+
+    #[test]
+    fn a_quote_asks_for_the_exact_text() {
+        assert_eq!(positions("'gonna give", "Never Gonna Give"), Some(vec![6, 7, 8, 9, 10, 11, 12, 13, 14, 15]));
+        assert_eq!(positions("'ngg", "Never Gonna Give"), None);
+        assert_eq!(positions("'", "Never Gonna Give"), Some(vec![]));
+        // Earlier occurrences rank first
+        assert!(score_of("'live", "Live at Wembley") > score_of("'live", "Queen Live"));
+    }
+}
@@ -0,0 +1,199 @@
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+use chrono::{Local, NaiveDate};
+
+use crate::config::{self, Config};
+
+// Each check gets this long; a hung yt-dlp shouldn't hold up startup forever
+const TIMEOUT: Duration = Duration::from_secs(8);
+// YouTube changes often enough that an older yt-dlp is probably broken somewhere
+const STALE_AFTER_DAYS: i64 = 90;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    // oxidlp can't do anything useful until this is fixed
+    Blocking,
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    // What to do about it, for anything but Ok
+    pub hint: Option<String>,
+}
+
+impl Check {
+    pub fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Ok, detail: detail.into(), hint: None }
+    }
+
+    pub fn warning(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Warning, detail: detail.into(), hint: Some(hint.into()) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    pub checks: Vec<Check>,
+    // Empty unless yt-dlp answered
+    pub ytdlp_version: String,
+}
+
+impl HealthReport {
+    pub fn is_blocked(&self) -> bool {
+        self.checks.iter().any(|c| c.status == CheckStatus::Blocking)
+    }
+
+    pub fn all_ok(&self) -> bool {
+        self.checks.iter().all(|c| c.status == CheckStatus::Ok)
+    }
+
+    pub fn ffmpeg_missing(&self) -> bool {
+        self.checks.iter().any(|c| c.name == "ffmpeg" && c.status != CheckStatus::Ok)
+    }
+}
+
+// `config_error` is why the config file couldn't be loaded, if it couldn't
+pub async fn run(config: Config, config_error: Option<String>) -> HealthReport {
+    let (ytdlp, ffmpeg, downloader, output, settings) = tokio::join!(
+        timed("yt-dlp", check_ytdlp(&config)),
+        timed("ffmpeg", check_ffmpeg()),
+        async {
+            match config.external_downloader {
+                Some(_) => Some(timed("Downloader", check_downloader(&config)).await),
+                None => None,
+            }
+        },
+        timed("Output directory", check_output_dir(&config.output_dir)),
+        timed("Config", async { check_config(&config, config_error) }),
+    );
+    let ytdlp_version = match &ytdlp {
+        (check, Some(version)) if check.status != CheckStatus::Blocking => version.clone(),
+        _ => String::new(),
+    };
+    let mut checks = vec![ytdlp.0, ffmpeg.0];
+    checks.extend(downloader.map(|(check, _)| check));
+    checks.extend([output.0, settings.0]);
+    HealthReport {
+        checks,
+        ytdlp_version,
+    }
+}
+
+async fn timed<T: Default>(name: &'static str, check: impl Future<Output = (Check, T)>) -> (Check, T) {
+    match tokio::time::timeout(TIMEOUT, check).await {
+        Ok(result) => result,
+        Err(_) => (
+            Check::warning(name, format!("no answer within {}s", TIMEOUT.as_secs()), "press 'r' to check again"),
+            T::default(),
+        ),
+    }
+}
+
+pub async fn check_ytdlp(config: &Config) -> (Check, Option<String>) {
+    let version = match config::check_ytdlp(config).await {
+        Ok(version) => version,
+        Err(e) => {
+            let check = Check {
+                name: "yt-dlp",
+                status: CheckStatus::Blocking,
+                detail: e.to_string(),
+                hint: Some("install yt-dlp (e.g. `pip install yt-dlp`) or set ytdlp_command in the config".into()),
+            };
+            return (check, None);
+        }
+    };
+
+    // Releases are named by date, e.g. 2024.03.10 or 2024.03.10.232 for nightlies
+    let released = version.get(..10).and_then(|date| NaiveDate::parse_from_str(date, "%Y.%m.%d").ok());
+    let age = released.map(|date| (Local::now().date_naive() - date).num_days());
+    let check = match age {
+        Some(days) if days > STALE_AFTER_DAYS => Check::warning(
+            "yt-dlp",
+            format!("{} is {} days old", version, days),
+            "sites change often; update with `yt-dlp -U` or your package manager",
+        ),
+        _ => Check::ok("yt-dlp", version.clone()),
+    };
+    (check, Some(version))
+}
+
+pub async fn check_ffmpeg() -> (Check, ()) {
+    let output = tokio::process::Command::new("ffmpeg").arg("-version").kill_on_drop(true).output().await;
+    let check = match output {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let version = stdout.lines().next().unwrap_or_default().split_whitespace().nth(2).unwrap_or("found");
+            Check::ok("ffmpeg", version.to_string())
+        }
+        _ => Check::warning(
+            "ffmpeg",
+            "not found in PATH",
+            "needed to merge separate video and audio streams and to extract audio",
+        ),
+    };
+    (check, ())
+}
+
+async fn check_downloader(config: &Config) -> (Check, ()) {
+    let check = match config::check_external_downloader(config).await {
+        Ok(version) => Check::ok("Downloader", version.unwrap_or_default()),
+        Err(e) => Check::warning(
+            "Downloader",
+            e.to_string(),
+            "install it or remove external_downloader from the config; downloads fail until then",
+        ),
+    };
+    (check, ())
+}
+
+async fn check_output_dir(dir: &Path) -> (Check, ()) {
+    let probe = dir.join(".oxidlp-write-test");
+    let result = async {
+        tokio::fs::create_dir_all(dir).await?;
+        tokio::fs::write(&probe, b"").await?;
+        tokio::fs::remove_file(&probe).await
+    }
+    .await;
+    let check = match result {
+        Ok(()) => Check::ok("Output directory", dir.display().to_string()),
+        Err(e) => Check::warning(
+            "Output directory",
+            format!("{} is not writable: {}", dir.display(), e),
+            "pick another download location in settings (g)",
+        ),
+    };
+    (check, ())
+}
+
+fn check_config(config: &Config, load_error: Option<String>) -> (Check, ()) {
+    let path = Config::config_path().map(|p| p.display().to_string()).unwrap_or_else(|| "config.toml".into());
+    let problem = if let Some(e) = load_error {
+        Some(format!("could not be read, using defaults: {}", e.lines().next().unwrap_or_default()))
+    } else if let Some(error) = config.cookie_browser_error() {
+        Some(error)
+    } else if config.rate_limit.is_some() && config.rate_limit_bytes().is_none() {
+        Some(format!("rate_limit {:?} isn't a size like \"2M\" or \"500K\"", config.rate_limit.as_deref().unwrap_or_default()))
+    } else {
+        None
+    };
+
+    let check = match problem {
+        Some(problem) => Check::warning("Config", problem, format!("fix {}", path)),
+        None => Check::ok("Config", path),
+    };
+    (check, ())
+}
+
+// The checklist screen; `report` is None while the checks are running
+#[derive(Debug, Clone)]
+pub struct HealthScreen {
+    pub report: Option<HealthReport>,
+    // The startup run goes away by itself when everything passed
+    pub dismiss_if_ok: bool,
+}
@@ -0,0 +1,174 @@
+use std::fs::TryLockError;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Local};
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{DownloadRecord, MediaKind, SpeedStats};
+use crate::paths;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub url: String,
+    pub title: Option<String>,
+    pub path: PathBuf,
+    pub completed_at: DateTime<Local>,
+    pub record: Option<DownloadRecord>,
+    #[serde(default)]
+    pub media_kind: MediaKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<SpeedStats>,
+}
+
+pub fn history_path() -> PathBuf {
+    paths::get().history_file()
+}
+
+pub async fn append(entry: &HistoryEntry) -> Result<()> {
+    let path = history_path();
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+
+    tokio::task::spawn_blocking(move || append_line(&path, line.as_bytes())).await??;
+    Ok(())
+}
+
+// Another oxidlp instance may be appending to the same file. The advisory lock
+// keeps whole entries from interleaving between cooperating writers, and the
+// entry goes out in one write so an O_APPEND file stays line-atomic even when
+// the lock couldn't be had.
+fn append_line(path: &Path, line: &[u8]) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    let locked = lock_with_retry(&file, path);
+
+    let written = file.write(line)?;
+    if written != line.len() {
+        // Short writes only happen on full disks and the like; finish the line
+        file.write_all(&line[written..])?;
+    }
+    if locked {
+        file.unlock()?;
+    }
+    Ok(())
+}
+
+fn lock_with_retry(file: &std::fs::File, path: &Path) -> bool {
+    const LOCK_ATTEMPTS: u32 = 20;
+    const LOCK_RETRY: Duration = Duration::from_millis(25);
+
+    for _ in 0..LOCK_ATTEMPTS {
+        match file.try_lock() {
+            Ok(()) => return true,
+            Err(TryLockError::WouldBlock) => std::thread::sleep(LOCK_RETRY),
+            Err(TryLockError::Error(e)) => {
+                tracing::warn!("Could not lock {}, writing without it: {}", path.display(), e);
+                return false;
+            }
+        }
+    }
+    tracing::warn!("{} is still locked by another process, writing without the lock", path.display());
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn entries_are_appended_one_per_line() {
+        let dir = TempDir::new("history");
+        let path = dir.join("history.jsonl");
+        append_line(&path, b"{\"n\":1}\n").unwrap();
+        append_line(&path, b"{\"n\":2}\n").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{\"n\":1}\n{\"n\":2}\n");
+    }
+
+    #[test]
+    fn two_writers_never_interleave_lines() {
+        const LINES: usize = 200;
+        let dir = TempDir::new("history");
+        let path = dir.join("history.jsonl");
+        // Long enough that a line split across writes would show
+        let writer = |tag: char| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                for i in 0..LINES {
+                    let line = format!("{}{:04}{}\n", tag, i, tag.to_string().repeat(4000));
+                    append_line(&path, line.as_bytes()).unwrap();
+                }
+            })
+        };
+        let (a, b) = (writer('a'), writer('b'));
+        a.join().unwrap();
+        b.join().unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2 * LINES);
+        for line in &lines {
+            let tag = line.chars().next().unwrap();
+            assert_eq!(line.len(), 4005, "a torn line: {:.40}…", line);
+            assert!(line[5..].chars().all(|c| c == tag), "a mixed line: {:.40}…", line);
+        }
+        for tag in ['a', 'b'] {
+            let numbers: Vec<usize> = lines.iter().filter(|l| l.starts_with(tag)).map(|l| l[1..5].parse().unwrap()).collect();
+            assert_eq!(numbers, (0..LINES).collect::<Vec<_>>(), "{}'s entries out of order", tag);
+        }
+    }
+
+    #[test]
+    fn a_held_lock_is_waited_for() {
+        let dir = TempDir::new("history");
+        let path = dir.join("history.jsonl");
+        let holder = std::fs::OpenOptions::new().create(true).append(true).open(&path).unwrap();
+        holder.lock().unwrap();
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            holder.unlock().unwrap();
+        });
+
+        append_line(&path, b"after the lock\n").unwrap();
+        release.join().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "after the lock\n");
+    }
+
+    #[test]
+    fn an_entry_reads_back_from_its_line() {
+        let entry = HistoryEntry {
+            url: "https://example.com/v/1".into(),
+            title: Some("A title".into()),
+            path: PathBuf::from("/downloads/A title.mp4"),
+            completed_at: Local::now(),
+            record: None,
+            media_kind: MediaKind::default(),
+            note: Some("for later".into()),
+            speed: None,
+        };
+        let line = serde_json::to_string(&entry).unwrap();
+        assert!(!line.contains('\n'));
+
+        let read: HistoryEntry = serde_json::from_str(&line).unwrap();
+        assert_eq!((read.url, read.title, read.path, read.note), (entry.url, entry.title, entry.path, entry.note));
+    }
+
+    #[test]
+    fn lines_from_before_media_kind_still_load() {
+        let line = r#"{"url":"https://example.com/v/1","title":null,"path":"/downloads/1.mp3","completed_at":"2024-03-05T14:07:00+00:00","record":null}"#;
+        let read: HistoryEntry = serde_json::from_str(line).unwrap();
+        assert_eq!(read.media_kind, MediaKind::Unknown);
+
+        let with_kind = line.replace("\"record\":null", "\"record\":null,\"media_kind\":\"audio\"");
+        assert_eq!(serde_json::from_str::<HistoryEntry>(&with_kind).unwrap().media_kind, MediaKind::Audio);
+    }
+}
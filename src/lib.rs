@@ -0,0 +1,7 @@
+pub mod config;
+pub mod core;
+pub mod format;
+pub mod paths;
+#[cfg(test)]
+mod testing;
+pub mod worker;
@@ -1,51 +1,122 @@
 mod app;
-mod config;
+mod bookmarks;
+mod channels;
+mod clipboard;
+mod diagnose;
 mod events;
+mod fuzzy;
+mod health;
+mod metrics;
+mod history;
+mod notify;
+mod persist;
+mod power;
+mod preview;
+mod ratecap;
+mod session;
+mod status;
+#[cfg(test)]
+mod testing;
+mod tty;
 mod ui;
-mod worker;
+
+use oxidlp::{config, format, paths, worker};
+// So the shared test fixtures find Format as `crate::core` here too
+#[cfg(test)]
+use oxidlp::core;
 
 use std::io;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use color_eyre::Result;
-use crossterm::{
-    event::{self, Event, KeyEventKind},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
+use crossterm::event::{self, Event, KeyEventKind};
 use ratatui::prelude::*;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use std::sync::Arc;
 
 use app::App;
-use config::{check_ytdlp, Config};
-use events::AppEvent;
+use config::Config;
+use events::{AppEvent, Job};
+use paths::{PathOverrides, Paths};
+use persist::QueueSnapshot;
+use power::PowerPolicy;
 use worker::WorkerPool;
 
 #[derive(Parser)]
 #[command(name = "oxidlp")]
 #[command(about = "A beautiful TUI YouTube downloader", long_about = None)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(value_name = "URL")]
     urls: Vec<String>,
     #[arg(short, long)]
     output: Option<String>,
     #[arg(short = 'j', long, default_value = "3")]
     concurrent: usize,
+    /// Print the yt-dlp command for every pending job and exit without downloading
+    #[arg(long)]
+    dry_run: bool,
+    /// Pick videos to add from a browser bookmarks HTML export
+    #[arg(long, value_name = "FILE")]
+    import_bookmarks: Option<std::path::PathBuf>,
+    /// Write the saved queue and download settings to a session file and exit
+    #[arg(long, value_name = "FILE")]
+    save_session: Option<std::path::PathBuf>,
+    /// Add the items of a session file to the saved queue and exit
+    #[arg(long, value_name = "FILE")]
+    load_session: Option<std::path::PathBuf>,
+    /// With --load-session, replace the saved queue instead of adding to it
+    #[arg(long, requires = "load_session")]
+    replace: bool,
+    /// Where the queue and history are kept (also OXIDLP_DATA_DIR or [paths] in the config)
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<std::path::PathBuf>,
+    /// Where cached data is kept (also OXIDLP_CACHE_DIR)
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<std::path::PathBuf>,
+    /// Where log files are written (also OXIDLP_LOG_DIR)
+    #[arg(long, value_name = "DIR")]
+    log_dir: Option<std::path::PathBuf>,
+    /// Print every file location oxidlp uses and exit
+    #[arg(long)]
+    print_paths: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show what a running instance is doing, from its status file (needs `status_file = true`)
+    Status,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     color_eyre::install()?;
 
-    let file_appender = tracing_appender::rolling::daily(
-        directories::ProjectDirs::from("com", "oxidlp", "oxidlp")
-            .map(|d| d.data_dir().to_path_buf())
-            .unwrap_or_else(|| std::path::PathBuf::from(".")),
-        "oxidlp.log",
-    );
+    let cli = Cli::parse();
+
+    // The config says where the logs go, so it is read before tracing is up
+    // A broken config file shouldn't lock anyone out; the health check reports it
+    let (mut config, config_error) = match Config::load().await {
+        Ok(config) => (config, None),
+        Err(e) => (Config::default(), Some(e.to_string())),
+    };
+    let overrides = PathOverrides {
+        data_dir: cli.data_dir.clone(),
+        cache_dir: cli.cache_dir.clone(),
+        log_dir: cli.log_dir.clone(),
+    };
+    paths::init(Paths::resolve(overrides, PathOverrides::from_env(), config.paths.clone()));
+
+    if cli.print_paths {
+        print_paths();
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(config::log_dir())?;
+    let file_appender = tracing_appender::rolling::daily(config::log_dir(), config::LOG_FILE_PREFIX);
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
     
     tracing_subscriber::registry()
@@ -53,87 +124,302 @@ async fn main() -> Result<()> {
         .with(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
-    let ytdlp_version = check_ytdlp().await?;
-    tracing::info!("Found yt-dlp version: {}", ytdlp_version);
+    // Written back straight away, before any command-line overrides, with the old
+    // file as .bak. A file from a newer oxidlp isn't touched
+    if !config.migration_notes.is_empty() {
+        for note in config.migration_notes.drain(..) {
+            tracing::info!("{}", note);
+        }
+        if config.version == config::CONFIG_VERSION {
+            if let Err(e) = config.save().await {
+                tracing::warn!("Failed to save the upgraded config: {}", e);
+            }
+        }
+    }
 
-    let cli = Cli::parse();
-    
-    let mut config = Config::load().await?;
     if let Some(output) = cli.output {
         config.output_dir = output.into();
     }
     config.max_concurrent_downloads = cli.concurrent;
+    format::set_size_units(config.size_units);
+
+    if let Some(Command::Status) = cli.command {
+        if !status::print().await? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if cli.dry_run {
+        return dry_run(&config, cli.urls).await;
+    }
+
+    if let Some(path) = cli.save_session {
+        let session = session::Session::new(persist::load_queue().await?, &config);
+        session.save(&path)?;
+        println!("Saved {} items to {}", session.jobs.len(), path.display());
+        return Ok(());
+    }
+
+    if let Some(path) = cli.load_session {
+        return load_session(&path, cli.replace).await;
+    }
+
+    if let Some(e) = &config_error {
+        tracing::warn!("Failed to load config, using defaults: {}", e);
+    }
     let config = Arc::new(config);
     let (worker_tx, worker_rx) = mpsc::channel(32);
     let (event_tx, mut event_rx) = mpsc::channel(32);
     let mut app = App::new((*config).clone(), worker_tx);
+    app.config_error = config_error;
+    app.channels = channels::ChannelMemory::load();
+
+    let (config_tx, config_rx) = mpsc::unbounded_channel();
+    let (current_tx, current_rx) = watch::channel(config.clone());
+    // Up before the queue is restored, so nothing restore asks of it can be lost
+    let (worker_event_tx, mut worker_event_rx) = mpsc::channel(32);
+    let worker = WorkerPool::new(config, worker_rx, worker_event_tx).watch_config(current_rx.clone());
+    tokio::spawn(worker.run());
+
+    match persist::load_queue().await {
+        Ok(snapshot) => {
+            let commands = app.restore(snapshot);
+            app.send_restored(commands);
+        }
+        Err(e) => tracing::warn!("Failed to load saved queue: {}", e),
+    }
     
     for url in cli.urls {
         app.handle_event(AppEvent::AddUrl(url));
     }
+    if let Some(path) = cli.import_bookmarks {
+        app.handle_event(AppEvent::ImportBookmarks(path));
+    }
 
-    let worker = WorkerPool::new(config, worker_rx, event_tx);
-    tokio::spawn(worker.run());
+    let (snapshot_tx, snapshot_rx) = mpsc::channel(4);
+    let autosave_task = tokio::spawn(persist::run_autosave(snapshot_rx, event_tx.clone()));
+
+    let config_task = tokio::spawn(persist::run_config_writer(Config::config_path(), config_rx, current_tx, event_tx.clone()));
+
+    app.connect(event_tx.clone(), config_tx, current_rx);
+    app.run_health_check(true);
 
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
+    tokio::spawn(async move {
+        while let Some(event) = worker_event_rx.recv().await {
+            if event_tx.send(AppEvent::Worker(event)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    tty::enter()?;
+    let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
-    let result = run_app(&mut terminal, &mut app, &mut event_rx).await;
+    let result = run_app(&mut terminal, &mut app, &mut event_rx, &snapshot_tx).await;
+    if app.config.status_file {
+        status::remove().await;
+    }
+    if app.config.metrics_file {
+        metrics::remove().await;
+    }
+
+    tty::leave()?;
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    // Always flush the queue on quit, behind any autosave still in flight
+    let _ = snapshot_tx.send(app.snapshot()).await;
+    drop(snapshot_tx);
+    let _ = autosave_task.await;
+    // Dropping the app closes the config writer once its last save is written
+    drop(app);
+    let _ = config_task.await;
 
     result
 }
 
+fn print_paths() {
+    let paths = paths::get();
+    let config_file = Config::config_path().map(|p| p.display().to_string()).unwrap_or_else(|| "(none)".into());
+    println!("Config file:  {}", config_file);
+    println!("Data dir:     {}", paths.data_dir.display());
+    println!("Queue:        {}", paths.queue_file().display());
+    println!("History:      {}", paths.history_file().display());
+    println!("Channels:     {}", paths.channels_file().display());
+    println!("Cache dir:    {}", paths.cache_dir.display());
+    println!("Log dir:      {}", paths.log_dir.display());
+    println!("Status file:  {}", paths.status_file().display());
+    println!("Metrics file: {}", paths.metrics_file().display());
+}
+
+async fn dry_run(config: &Config, urls: Vec<String>) -> Result<()> {
+    let saved = persist::load_queue().await?.jobs;
+    // Unexpanded playlist placeholders have no single download to show
+    let pending = saved.into_iter().filter(|j| !j.is_started() && !j.is_playlist);
+    let added = urls.iter().map(|url| Job::new(url.trim()));
+
+    for job in pending.chain(added) {
+        // Jobs without an explicit selection would need one; show them with the configured default
+        let mut request = job
+            .download_request()
+            .unwrap_or_else(|| job.request_with_format(config.default_format()));
+        request.output = Some(job.planned_target(config));
+        println!("{}", worker::command_line(config, &worker::download_args(&request, config)));
+    }
+    Ok(())
+}
+
+// Works on the queue file directly, so it is meant for when oxidlp isn't running
+async fn load_session(path: &std::path::Path, replace: bool) -> Result<()> {
+    let session = session::Session::load(path)?;
+    let mut queue = if replace { persist::QueueSnapshot::default() } else { persist::load_queue().await? };
+    let (incoming, skipped) = session.incoming(&queue.jobs);
+    let count = incoming.jobs.len();
+    queue.jobs.extend(incoming.jobs.into_iter().map(Job::restored));
+    queue.groups.extend(incoming.groups);
+    persist::save_queue(&queue).await?;
+
+    println!("Loaded {} items from {}", count, path.display());
+    if skipped > 0 {
+        println!("Skipped {} already in the queue", skipped);
+    }
+    Ok(())
+}
+
+// Terminal input is read on a plain thread so the main loop can await it alongside
+// worker events instead of polling
+fn spawn_input_reader() -> mpsc::Receiver<Event> {
+    let (tx, rx) = mpsc::channel(64);
+    std::thread::spawn(move || {
+        while let Ok(ev) = event::read() {
+            if tx.blocking_send(ev).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
 async fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
     event_rx: &mut mpsc::Receiver<AppEvent>,
+    snapshot_tx: &mpsc::Sender<QueueSnapshot>,
 ) -> Result<()> {
-    // Initial CPU refresh - need two calls with delay to establish baseline
-    app.sysinfo.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
-    std::thread::sleep(Duration::from_millis(500));
-    app.sysinfo.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
-    
-    let mut last_sysinfo_refresh = std::time::Instant::now();
-    const SYSINFO_REFRESH_INTERVAL: Duration = Duration::from_millis(1000);
+    let mut input_rx = spawn_input_reader();
+    let mut job_signals = tty::JobSignals::new()?;
+
+    // The tickers are only polled while something needs them, so an idle
+    // session sleeps until the next key press or worker event
+    let mut animation_tick = tokio::time::interval(Duration::from_millis(80));
+    animation_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut sysinfo_tick = tokio::time::interval(Duration::from_millis(1000));
+    sysinfo_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    // Only here to redraw so "added 5m ago" in the details panel keeps up
+    let mut age_tick = tokio::time::interval(Duration::from_secs(30));
+    age_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    // Status bars poll the file, so it is only rewritten when something changed
+    let mut status_tick = tokio::time::interval(Duration::from_secs(1));
+    status_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut last_status = None;
+    let mut last_metrics = None;
+    let mut power_tick = tokio::time::interval(Duration::from_secs(30));
+    power_tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
     
     loop {
-        while event::poll(Duration::from_millis(0))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    if let Some(app_event) = ui::input::handle_key(key, app) {
-                        app.handle_event(app_event);
-                    }
-                }
-            }
-        }
-        
-        while let Ok(worker_event) = event_rx.try_recv() {
-            app.handle_event(worker_event);
-        }
-        
-        if app.show_sysinfo && last_sysinfo_refresh.elapsed() >= SYSINFO_REFRESH_INTERVAL {
-            app.sysinfo.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
-            last_sysinfo_refresh = std::time::Instant::now();
-        }
-        
-        if app.loading_playlists > 0 {
-            app.spinner_frame = app.spinner_frame.wrapping_add(1);
-        }
-        
         terminal.draw(|f| ui::render(f, app))?;
+        app.frame_counter.record(Instant::now());
 
         if app.should_quit {
             break;
         }
-        
-        tokio::time::sleep(Duration::from_millis(16)).await;
+        if app.should_suspend {
+            app.should_suspend = false;
+            tty::suspend()?;
+            // Whatever the shell drew meanwhile is on screen, not in ratatui's buffer
+            terminal.clear()?;
+            continue;
+        }
+
+        app.update_preview(Instant::now());
+        let preview_deadline = app.preview.deadline();
+        let autosave_deadline = app.autosave.deadline();
+        let notify_deadline = app.completions.deadline();
+
+        tokio::select! {
+            Some(ev) = input_rx.recv() => {
+                match ev {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => {
+                        if let Some(app_event) = ui::input::handle_key(key, app) {
+                            app.handle_event(app_event);
+                        }
+                    }
+                    Event::Paste(text) => ui::input::handle_paste(&text, app),
+                    _ => {}
+                }
+            }
+            Some(worker_event) = event_rx.recv() => {
+                app.handle_event(worker_event);
+                while let Ok(worker_event) = event_rx.try_recv() {
+                    app.handle_event(worker_event);
+                }
+            }
+            signal = job_signals.recv() => match signal {
+                tty::JobControl::Stop => app.should_suspend = true,
+                tty::JobControl::Continue => {
+                    tty::enter()?;
+                    terminal.clear()?;
+                }
+            },
+            _ = animation_tick.tick(), if app.needs_animation() => {
+                if app.spinner_running() {
+                    app.spinner_frame = app.spinner_frame.wrapping_add(1);
+                }
+                if app.notice.as_ref().is_some_and(|n| n.is_expired()) {
+                    app.notice = None;
+                }
+            }
+            // The first tick after the panel opens is the baseline, so startup never
+            // waits on it; the CPU figures come from the ticks after. Kept going while
+            // the panel shows, idle or not, so its figures are never stale
+            _ = sysinfo_tick.tick(), if app.show_sysinfo => {
+                app.refresh_sysinfo();
+            }
+            _ = age_tick.tick(), if app.shows_age() => {}
+            _ = power_tick.tick(), if PowerPolicy::of(&app.config).is_enabled() || app.power_paused.is_some() => {
+                app.check_power(power::probe());
+            }
+            _ = status_tick.tick(), if app.config.status_file || app.config.metrics_file => {
+                let report = app.status_report();
+                if app.config.status_file && last_status.as_ref() != Some(&report) {
+                    if let Err(e) = status::write(&report).await {
+                        tracing::warn!("Failed to write status file: {}", e);
+                    }
+                    last_status = Some(report);
+                }
+                let sample = (app.metrics, app.metrics_gauges());
+                if app.config.metrics_file && last_metrics != Some(sample) {
+                    if let Err(e) = metrics::write(&sample.0.render(sample.1)).await {
+                        tracing::warn!("Failed to write metrics file: {}", e);
+                    }
+                    last_metrics = Some(sample);
+                }
+            }
+            _ = tokio::time::sleep_until(preview_deadline.unwrap_or_else(Instant::now).into()), if preview_deadline.is_some() => {
+                app.preview.fetch();
+            }
+            _ = tokio::time::sleep_until(notify_deadline.unwrap_or_else(Instant::now).into()), if notify_deadline.is_some() => {
+                app.flush_notifications(Instant::now());
+            }
+            _ = tokio::time::sleep_until(autosave_deadline.unwrap_or_else(Instant::now).into()), if autosave_deadline.is_some() => {
+                let now = Instant::now();
+                if snapshot_tx.try_send(app.snapshot()).is_ok() {
+                    app.autosave.mark_written(now);
+                } else {
+                    app.autosave.postpone(now);
+                }
+            }
+        }
     }
 
     Ok(())
@@ -0,0 +1,127 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+use color_eyre::Result;
+
+use crate::paths;
+
+// Counters since this instance started, bumped in App::record_timeline where
+// every stage change already passes. Written with `metrics_file = true` in
+// Prometheus' text format, for node_exporter's textfile collector
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Metrics {
+    pub jobs_added: u64,
+    pub jobs_completed: u64,
+    pub jobs_failed: u64,
+    pub retries: u64,
+    pub downloaded_bytes: u64,
+}
+
+// Read off the queue when the page is rendered rather than counted
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Gauges {
+    pub active: usize,
+    pub queued: usize,
+}
+
+impl Metrics {
+    pub fn render(&self, gauges: Gauges) -> String {
+        let samples: [(&str, &str, &str, u64); 7] = [
+            ("oxidlp_jobs_added_total", "counter", "Jobs added to the queue.", self.jobs_added),
+            ("oxidlp_jobs_completed_total", "counter", "Jobs that finished downloading.", self.jobs_completed),
+            ("oxidlp_jobs_failed_total", "counter", "Jobs that failed.", self.jobs_failed),
+            ("oxidlp_retries_total", "counter", "Failed or cancelled jobs started again.", self.retries),
+            ("oxidlp_downloaded_bytes_total", "counter", "Size of the files of completed jobs.", self.downloaded_bytes),
+            ("oxidlp_active_downloads", "gauge", "Jobs downloading right now.", gauges.active as u64),
+            ("oxidlp_queued_downloads", "gauge", "Jobs waiting for a download slot.", gauges.queued as u64),
+        ];
+        let mut page = String::new();
+        for (name, kind, help, value) in samples {
+            let _ = writeln!(page, "# HELP {} {}", name, help);
+            let _ = writeln!(page, "# TYPE {} {}", name, kind);
+            let _ = writeln!(page, "{} {}", name, value);
+        }
+        page
+    }
+}
+
+pub fn metrics_path() -> PathBuf {
+    paths::get().metrics_file()
+}
+
+// The collector may read at any moment, so like status.json it is renamed into place
+pub async fn write(page: &str) -> Result<()> {
+    let path = metrics_path();
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let tmp = path.with_extension("prom.tmp");
+    tokio::fs::write(&tmp, page).await?;
+    tokio::fs::rename(&tmp, &path).await?;
+    Ok(())
+}
+
+pub async fn remove() {
+    let _ = tokio::fs::remove_file(metrics_path()).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> String {
+        let metrics = Metrics { jobs_added: 12, jobs_completed: 9, jobs_failed: 2, retries: 1, downloaded_bytes: 5_368_709_120 };
+        metrics.render(Gauges { active: 3, queued: 4 })
+    }
+
+    #[test]
+    fn every_metric_has_help_type_and_one_sample() {
+        let page = sample();
+        let lines: Vec<&str> = page.lines().collect();
+        assert_eq!(lines.len() % 3, 0);
+        for block in lines.chunks(3) {
+            let name = block[0].strip_prefix("# HELP ").and_then(|rest| rest.split(' ').next()).expect("a HELP line first");
+            let kind = block[1].strip_prefix(&format!("# TYPE {} ", name)).expect("a TYPE line for the same metric");
+            let (sample_name, value) = block[2].split_once(' ').expect("a sample line");
+            assert_eq!(sample_name, name);
+            assert!(value.parse::<u64>().is_ok(), "{}", block[2]);
+            assert!(name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') && name.starts_with("oxidlp_"), "{}", name);
+            match kind {
+                "counter" => assert!(name.ends_with("_total"), "{}", name),
+                "gauge" => assert!(!name.ends_with("_total"), "{}", name),
+                other => panic!("unknown type {}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn the_page_ends_in_a_line_break() {
+        // The textfile collector rejects a file whose last line is cut off
+        assert!(sample().ends_with('\n'));
+        assert!(!sample().contains("\r"));
+    }
+
+    #[test]
+    fn samples_carry_the_counts() {
+        let page = sample();
+        for expected in [
+            "oxidlp_jobs_added_total 12",
+            "oxidlp_jobs_completed_total 9",
+            "oxidlp_jobs_failed_total 2",
+            "oxidlp_retries_total 1",
+            "oxidlp_downloaded_bytes_total 5368709120",
+            "oxidlp_active_downloads 3",
+            "oxidlp_queued_downloads 4",
+        ] {
+            assert!(page.lines().any(|line| line == expected), "no {:?} in\n{}", expected, page);
+        }
+    }
+
+    #[test]
+    fn a_fresh_instance_reports_zeros() {
+        let page = Metrics::default().render(Gauges::default());
+        let samples: Vec<&str> = page.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(samples.len(), 7);
+        assert!(samples.iter().all(|line| line.ends_with(" 0")), "{:?}", samples);
+    }
+}
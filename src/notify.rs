@@ -0,0 +1,139 @@
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+// A batch goes out once completions stop arriving for this long...
+const QUIET: Duration = Duration::from_secs(3);
+// ...or once it has been open this long, so a steady playlist still reports
+const WINDOW: Duration = Duration::from_secs(30);
+
+// Collects completions so a playlist finishing one item after another raises
+// one "5 downloads finished" rather than a popup per item
+#[derive(Debug, Default)]
+pub struct CompletionBatch {
+    titles: Vec<String>,
+    opened: Option<Instant>,
+    last: Option<Instant>,
+}
+
+impl CompletionBatch {
+    pub fn push(&mut self, now: Instant, title: String) {
+        self.titles.push(title);
+        self.opened.get_or_insert(now);
+        self.last = Some(now);
+    }
+
+    // When the batch should be sent, or None while it is empty
+    pub fn deadline(&self) -> Option<Instant> {
+        Some((self.last? + QUIET).min(self.opened? + WINDOW))
+    }
+
+    // The (summary, body) to show once the deadline has passed
+    pub fn flush(&mut self, now: Instant) -> Option<(String, String)> {
+        if self.deadline().is_none_or(|deadline| now < deadline) {
+            return None;
+        }
+        self.opened = None;
+        self.last = None;
+        let mut titles = std::mem::take(&mut self.titles);
+        if titles.len() == 1 {
+            return Some(("Download finished".into(), titles.remove(0)));
+        }
+        let shown: Vec<&str> = titles.iter().take(3).map(String::as_str).collect();
+        let mut body = shown.join("\n");
+        if titles.len() > shown.len() {
+            body.push_str(&format!("\n…and {} more", titles.len() - shown.len()));
+        }
+        Some((format!("{} downloads finished", titles.len()), body))
+    }
+}
+
+// Hands the notification to the desktop's own tool; a missing tool just means
+// no popup, the status bar notice still shows
+pub fn send(summary: &str, body: &str) {
+    let mut command = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {:?} with title {:?}",
+            body,
+            format!("oxidlp: {}", summary)
+        );
+        let mut command = Command::new("osascript");
+        command.args(["-e", &script]);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name=oxidlp", summary, body]);
+        command
+    };
+
+    let spawned = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match spawned {
+        // Reaped off the UI thread so it doesn't linger as a zombie
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => tracing::debug!("Desktop notification not sent: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(start: Instant, secs: u64) -> Instant {
+        start + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn an_empty_batch_never_flushes() {
+        let mut batch = CompletionBatch::default();
+        assert_eq!(batch.deadline(), None);
+        assert_eq!(batch.flush(Instant::now()), None);
+    }
+
+    #[test]
+    fn one_completion_goes_out_after_a_quiet_spell() {
+        let start = Instant::now();
+        let mut batch = CompletionBatch::default();
+        batch.push(start, "Clip".into());
+        assert_eq!(batch.deadline(), Some(at(start, 3)));
+        assert_eq!(batch.flush(at(start, 2)), None);
+        assert_eq!(batch.flush(at(start, 3)), Some(("Download finished".into(), "Clip".into())));
+        assert_eq!(batch.deadline(), None);
+    }
+
+    #[test]
+    fn completions_in_a_row_make_one_summary() {
+        let start = Instant::now();
+        let mut batch = CompletionBatch::default();
+        for (i, title) in ["One", "Two", "Three", "Four", "Five"].into_iter().enumerate() {
+            batch.push(at(start, i as u64 * 2), title.into());
+        }
+        assert_eq!(batch.flush(at(start, 10)), None);
+        let (summary, body) = batch.flush(at(start, 11)).unwrap();
+        assert_eq!(summary, "5 downloads finished");
+        assert_eq!(body, "One\nTwo\nThree\n…and 2 more");
+
+        // The next completion starts a batch of its own
+        batch.push(at(start, 20), "Six".into());
+        assert_eq!(batch.flush(at(start, 23)), Some(("Download finished".into(), "Six".into())));
+    }
+
+    #[test]
+    fn a_steady_stream_still_reports_when_the_window_closes() {
+        let start = Instant::now();
+        let mut batch = CompletionBatch::default();
+        for secs in (0..=30).step_by(2) {
+            batch.push(at(start, secs), format!("Item {}", secs));
+            if secs < 30 {
+                assert_eq!(batch.flush(at(start, secs)), None, "flushed at {}s", secs);
+            }
+        }
+        assert_eq!(batch.deadline(), Some(at(start, 30)));
+        let (summary, _) = batch.flush(at(start, 30)).unwrap();
+        assert_eq!(summary, "16 downloads finished");
+    }
+}
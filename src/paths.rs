@@ -0,0 +1,241 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use color_eyre::Result;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+// The `[paths]` config section, and the same three locations as given on the
+// command line or in the environment. Unset entries fall through to the next source
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathOverrides {
+    pub data_dir: Option<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+    pub log_dir: Option<PathBuf>,
+}
+
+impl PathOverrides {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+        Self {
+            data_dir: var("OXIDLP_DATA_DIR"),
+            cache_dir: var("OXIDLP_CACHE_DIR"),
+            log_dir: var("OXIDLP_LOG_DIR"),
+        }
+    }
+
+    // Keeps what `self` sets and takes the rest from `fallback`
+    pub fn or(self, fallback: PathOverrides) -> Self {
+        Self {
+            data_dir: self.data_dir.or(fallback.data_dir),
+            cache_dir: self.cache_dir.or(fallback.cache_dir),
+            log_dir: self.log_dir.or(fallback.log_dir),
+        }
+    }
+}
+
+// Every file oxidlp keeps is located through this, so the queue, history,
+// logs and status file can't disagree about where things live
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paths {
+    pub data_dir: PathBuf,
+    pub cache_dir: PathBuf,
+    pub log_dir: PathBuf,
+    // Per-login scratch space; only some platforms have one
+    pub runtime_dir: Option<PathBuf>,
+}
+
+impl Paths {
+    // Command line beats environment beats config file beats the platform default
+    pub fn resolve(cli: PathOverrides, env: PathOverrides, config: PathOverrides) -> Self {
+        let chosen = cli.or(env).or(config);
+        let dirs = project_dirs();
+        let default = |pick: fn(&ProjectDirs) -> PathBuf| dirs.as_ref().map(pick).unwrap_or_else(|| PathBuf::from("."));
+
+        let data_dir = chosen.data_dir.unwrap_or_else(|| default(|d| d.data_dir().to_path_buf()));
+        let cache_dir = chosen.cache_dir.unwrap_or_else(|| default(|d| d.cache_dir().to_path_buf()));
+        // Logs are state, not data: $XDG_STATE_HOME on Linux, beside the data elsewhere
+        let log_dir = chosen
+            .log_dir
+            .or_else(|| dirs.as_ref().and_then(|d| d.state_dir()).map(|p| p.to_path_buf()))
+            .unwrap_or_else(|| data_dir.clone());
+        let runtime_dir = dirs.as_ref().and_then(|d| d.runtime_dir()).map(|p| p.to_path_buf());
+
+        Self {
+            data_dir,
+            cache_dir,
+            log_dir,
+            runtime_dir,
+        }
+    }
+
+    pub fn queue_file(&self) -> PathBuf {
+        self.data_dir.join("queue.json")
+    }
+
+    pub fn history_file(&self) -> PathBuf {
+        self.data_dir.join("history.jsonl")
+    }
+
+    pub fn channels_file(&self) -> PathBuf {
+        self.data_dir.join("channels.json")
+    }
+
+    // Status bars poll this, so it prefers the runtime dir, cleared on logout
+    pub fn status_file(&self) -> PathBuf {
+        self.runtime_dir.as_ref().unwrap_or(&self.cache_dir).join("status.json")
+    }
+
+    pub fn metrics_file(&self) -> PathBuf {
+        self.runtime_dir.as_ref().unwrap_or(&self.cache_dir).join("oxidlp.prom")
+    }
+}
+
+// The config file decides the other paths, so it can't be moved by them
+pub fn config_file() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.config_dir().join("config.toml"))
+}
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("com", "oxidlp", "oxidlp")
+}
+
+// Set once at startup, after the config is loaded, like the size units
+static PATHS: OnceLock<Paths> = OnceLock::new();
+
+pub fn init(paths: Paths) {
+    if PATHS.set(paths).is_err() {
+        tracing::warn!("Paths were already resolved; keeping the first resolution");
+    }
+}
+
+// Without init, e.g. when embedded as a library, environment and defaults apply
+pub fn get() -> &'static Paths {
+    PATHS.get_or_init(|| Paths::resolve(PathOverrides::default(), PathOverrides::from_env(), PathOverrides::default()))
+}
+
+// A typed path with a leading `~` meaning the home directory
+pub fn expand_home(input: &str) -> PathBuf {
+    let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    match (input.strip_prefix('~'), home) {
+        (Some(""), Some(home)) => home,
+        (Some(rest), Some(home)) if rest.starts_with(['/', '\\']) => home.join(&rest[1..]),
+        _ => PathBuf::from(input),
+    }
+}
+
+// `queue.json` with a suffix such as ".bak" appended
+pub fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+// Write to a temp file and rename over the target so a crash mid-write never
+// leaves a truncated file; the previous generation is kept as `.bak`
+pub async fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let tmp = sibling(path, ".tmp");
+    let mut file = tokio::fs::File::create(&tmp).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    if tokio::fs::try_exists(path).await.unwrap_or(false) {
+        tokio::fs::rename(path, sibling(path, ".bak")).await?;
+    }
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[tokio::test]
+    async fn a_write_replaces_the_file_and_keeps_the_last_one() {
+        let dir = TempDir::new("paths");
+        let path = dir.join("queue.json");
+
+        write_atomic(&path, b"first").await.unwrap();
+        write_atomic(&path, b"second").await.unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"second");
+        assert_eq!(std::fs::read(sibling(&path, ".bak")).unwrap(), b"first");
+        assert!(!sibling(&path, ".tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn a_failure_after_the_write_leaves_the_old_file_whole() {
+        let dir = TempDir::new("paths");
+        let path = dir.join("queue.json");
+        write_atomic(&path, b"old").await.unwrap();
+        // A directory where the backup goes makes the first rename fail, after
+        // the new contents were already written to the temp file
+        let backup = sibling(&path, ".bak");
+        std::fs::remove_file(&backup).ok();
+        std::fs::create_dir_all(backup.join("blocker")).unwrap();
+
+        assert!(write_atomic(&path, b"new").await.is_err());
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"old");
+        assert_eq!(std::fs::read(sibling(&path, ".tmp")).unwrap(), b"new");
+    }
+
+    fn overrides(tag: &str, data: bool, cache: bool, log: bool) -> PathOverrides {
+        let dir = |set: bool, kind: &str| set.then(|| PathBuf::from(format!("/{}/{}", tag, kind)));
+        PathOverrides { data_dir: dir(data, "data"), cache_dir: dir(cache, "cache"), log_dir: dir(log, "log") }
+    }
+
+    #[test]
+    fn each_path_comes_from_the_first_source_that_sets_it() {
+        let paths = Paths::resolve(overrides("cli", true, false, false), overrides("env", true, true, false), overrides("config", true, true, true));
+        assert_eq!(paths.data_dir, PathBuf::from("/cli/data"));
+        assert_eq!(paths.cache_dir, PathBuf::from("/env/cache"));
+        assert_eq!(paths.log_dir, PathBuf::from("/config/log"));
+
+        let paths = Paths::resolve(PathOverrides::default(), PathOverrides::default(), overrides("config", false, true, false));
+        assert_eq!(paths.cache_dir, PathBuf::from("/config/cache"));
+        assert_ne!(paths.data_dir, PathBuf::from("/config/data"));
+    }
+
+    #[test]
+    fn the_platform_defaults_fill_the_rest() {
+        let defaults = Paths::resolve(PathOverrides::default(), PathOverrides::default(), PathOverrides::default());
+        let dirs = project_dirs().expect("a home directory to test against");
+        assert_eq!(defaults.data_dir, dirs.data_dir());
+        assert_eq!(defaults.cache_dir, dirs.cache_dir());
+        // Logs live in the state dir where there is one, beside the data otherwise
+        assert_eq!(defaults.log_dir, dirs.state_dir().unwrap_or(dirs.data_dir()));
+
+        // Moving only the data doesn't drag the logs along where a state dir exists
+        let moved = Paths::resolve(overrides("cli", true, false, false), PathOverrides::default(), PathOverrides::default());
+        let expected_log = dirs.state_dir().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("/cli/data"));
+        assert_eq!(moved.log_dir, expected_log);
+    }
+
+    #[test]
+    fn files_live_under_their_dirs() {
+        let mut paths = Paths::resolve(overrides("cli", true, true, true), PathOverrides::default(), PathOverrides::default());
+        paths.runtime_dir = None;
+        assert_eq!(paths.queue_file(), PathBuf::from("/cli/data/queue.json"));
+        assert_eq!(paths.history_file(), PathBuf::from("/cli/data/history.jsonl"));
+        assert_eq!(paths.status_file(), PathBuf::from("/cli/cache/status.json"));
+
+        paths.runtime_dir = Some("/run/user/1000".into());
+        assert_eq!(paths.status_file(), PathBuf::from("/run/user/1000/status.json"));
+        assert_eq!(paths.metrics_file(), PathBuf::from("/run/user/1000/oxidlp.prom"));
+    }
+
+    #[test]
+    fn sibling_appends_to_the_whole_name() {
+        assert_eq!(sibling(Path::new("/data/queue.json"), ".bak"), PathBuf::from("/data/queue.json.bak"));
+        assert_eq!(expand_home("/abs/~dir"), PathBuf::from("/abs/~dir"));
+        assert_eq!(expand_home("~user/x"), PathBuf::from("~user/x"));
+    }
+}
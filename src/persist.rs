@@ -0,0 +1,377 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, watch};
+
+use crate::config::Config;
+use crate::events::{AppEvent, Job, JobGroup, TrashedJob};
+use crate::paths::{self, sibling, write_atomic};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueSnapshot {
+    pub jobs: Vec<Job>,
+    #[serde(default)]
+    pub groups: Vec<JobGroup>,
+    // Only written with `persist_trash`; otherwise the trash goes with the session
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trash: Vec<TrashedJob>,
+}
+
+pub fn queue_path() -> PathBuf {
+    paths::get().queue_file()
+}
+
+pub async fn save_queue(snapshot: &QueueSnapshot) -> Result<()> {
+    let path = queue_path();
+
+    let content = serde_json::to_vec_pretty(snapshot)?;
+    write_atomic(&path, &content).await
+}
+
+pub async fn load_queue() -> Result<QueueSnapshot> {
+    read_queue(&queue_path()).await
+}
+
+async fn read_queue(path: &Path) -> Result<QueueSnapshot> {
+    // Fall back to the backup generation if the main file is missing or unreadable
+    for candidate in [path.to_path_buf(), sibling(path, ".bak")] {
+        let Ok(content) = tokio::fs::read(&candidate).await else {
+            continue;
+        };
+        match serde_json::from_slice::<QueueSnapshot>(&content) {
+            Ok(queue) => return Ok(queue),
+            Err(e) => tracing::warn!("Ignoring unreadable queue file {}: {}", candidate.display(), e),
+        }
+    }
+
+    Ok(QueueSnapshot::default())
+}
+
+#[derive(Debug)]
+pub struct Autosave {
+    interval: Duration,
+    dirty: bool,
+    last_write: Option<Instant>,
+}
+
+impl Autosave {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            dirty: false,
+            last_write: None,
+        }
+    }
+
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    // When the next write should happen, or None while there is nothing to save
+    pub fn deadline(&self) -> Option<Instant> {
+        if !self.dirty {
+            return None;
+        }
+        Some(self.last_write.map_or_else(Instant::now, |last| last + self.interval))
+    }
+
+    // The saver is busy; keep the dirty flag and try again one interval later
+    pub fn postpone(&mut self, now: Instant) {
+        self.last_write = Some(now);
+    }
+
+    pub fn mark_written(&mut self, now: Instant) {
+        self.dirty = false;
+        self.last_write = Some(now);
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigMessage {
+    Save(Box<Config>),
+    // Reads config.toml again after it was edited outside oxidlp
+    Reload,
+}
+
+// Owns the settings in effect, which everyone else reads from `current`, and is
+// the one place config.toml is written. Saves and reloads are handled one at a
+// time in the order they were sent, so a reload can't land between a save's
+// check and its write, and saves can't race on the temp file. A config is only
+// published once it checks out; of a burst of saves only the newest is handled
+pub async fn run_config_writer(
+    path: Option<PathBuf>,
+    mut messages: mpsc::UnboundedReceiver<ConfigMessage>,
+    current: watch::Sender<Arc<Config>>,
+    event_tx: mpsc::Sender<AppEvent>,
+) {
+    let mut next = None;
+    loop {
+        let message = match next.take() {
+            Some(message) => message,
+            None => match messages.recv().await {
+                Some(message) => message,
+                None => break,
+            },
+        };
+        match message {
+            ConfigMessage::Save(mut config) => {
+                while let Ok(newer) = messages.try_recv() {
+                    match newer {
+                        ConfigMessage::Save(newer) => config = newer,
+                        reload => {
+                            next = Some(reload);
+                            break;
+                        }
+                    }
+                }
+                save_config(path.as_deref(), *config, &current, &event_tx).await;
+            }
+            ConfigMessage::Reload => reload_config(path.as_deref(), &current, &event_tx).await,
+        }
+    }
+}
+
+// try_send throughout: the last save on quit runs after the UI stopped draining events
+async fn save_config(path: Option<&Path>, config: Config, current: &watch::Sender<Arc<Config>>, event_tx: &mpsc::Sender<AppEvent>) {
+    // Rejected settings never reach the worker; it keeps the ones it has
+    if let Err(e) = config.validate().await {
+        tracing::warn!("Not saving config: {}", e);
+        let _ = event_tx.try_send(AppEvent::ConfigSaveFailed(e.to_string()));
+        return;
+    }
+    let config = Arc::new(config);
+    current.send_replace(config.clone());
+    let _ = event_tx.try_send(AppEvent::ConfigChanged);
+    let Some(path) = path else { return };
+    if let Err(e) = config.write_to(path).await {
+        tracing::warn!("Failed to save config: {}", e);
+        let _ = event_tx.try_send(AppEvent::ConfigSaveFailed(e.to_string()));
+    }
+}
+
+async fn reload_config(path: Option<&Path>, current: &watch::Sender<Arc<Config>>, event_tx: &mpsc::Sender<AppEvent>) {
+    let Some(path) = path else { return };
+    let loaded = match Config::load_from(path).await {
+        Ok(config) => config,
+        Err(e) => {
+            let _ = event_tx.try_send(AppEvent::ConfigReloadFailed(e.to_string()));
+            return;
+        }
+    };
+    // What was saved last reads back the same, and is left alone
+    let changed = loaded.to_toml().ok() != current.borrow().to_toml().ok();
+    if changed {
+        if let Err(e) = loaded.validate().await {
+            let _ = event_tx.try_send(AppEvent::ConfigReloadFailed(e.to_string()));
+            return;
+        }
+        current.send_replace(Arc::new(loaded));
+        let _ = event_tx.try_send(AppEvent::ConfigChanged);
+    }
+    let _ = event_tx.try_send(AppEvent::ConfigReloaded { changed });
+}
+
+pub async fn run_autosave(mut snapshot_rx: mpsc::Receiver<QueueSnapshot>, event_tx: mpsc::Sender<AppEvent>) {
+    while let Some(snapshot) = snapshot_rx.recv().await {
+        if let Err(e) = save_queue(&snapshot).await {
+            tracing::warn!("Failed to save queue: {}", e);
+            // try_send: the final save on quit runs after the UI stopped draining events
+            let _ = event_tx.try_send(AppEvent::AutosaveFailed { error: e.to_string() });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn snapshot(urls: &[&str]) -> Vec<u8> {
+        let snapshot = QueueSnapshot { jobs: urls.iter().map(|url| Job::new(*url)).collect(), ..Default::default() };
+        serde_json::to_vec(&snapshot).unwrap()
+    }
+
+    fn urls(snapshot: &QueueSnapshot) -> Vec<&str> {
+        snapshot.jobs.iter().map(|j| j.url.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn a_crash_between_the_renames_loads_the_backup() {
+        let dir = TempDir::new("persist");
+        let path = dir.join("queue.json");
+        // The old file was moved aside, the new one never moved in
+        std::fs::write(sibling(&path, ".bak"), snapshot(&["https://example.com/old"])).unwrap();
+        std::fs::write(sibling(&path, ".tmp"), snapshot(&["https://example.com/new"])).unwrap();
+
+        let loaded = read_queue(&path).await.unwrap();
+        assert_eq!(urls(&loaded), ["https://example.com/old"]);
+    }
+
+    #[tokio::test]
+    async fn a_truncated_queue_file_falls_back_to_the_backup() {
+        let dir = TempDir::new("persist");
+        let path = dir.join("queue.json");
+        let whole = snapshot(&["https://example.com/a"]);
+        std::fs::write(sibling(&path, ".bak"), &whole).unwrap();
+        std::fs::write(&path, &whole[..whole.len() / 2]).unwrap();
+
+        let loaded = read_queue(&path).await.unwrap();
+        assert_eq!(urls(&loaded), ["https://example.com/a"]);
+    }
+
+    #[tokio::test]
+    async fn nothing_on_disk_is_an_empty_queue() {
+        let dir = TempDir::new("persist");
+        let loaded = read_queue(&dir.join("queue.json")).await.unwrap();
+        assert!(loaded.jobs.is_empty());
+    }
+
+    fn with_output(dir: &str) -> Config {
+        Config { output_dir: PathBuf::from(dir), ..Config::default() }
+    }
+
+    // Runs the writer over messages that are all queued before it starts, the
+    // way a burst from the UI looks to it
+    async fn write_config(path: &Path, in_effect: Config, messages: Vec<ConfigMessage>) -> (Arc<Config>, Vec<AppEvent>) {
+        let (messages_tx, messages_rx) = mpsc::unbounded_channel();
+        for message in messages {
+            messages_tx.send(message).unwrap();
+        }
+        drop(messages_tx);
+        let (current, current_rx) = watch::channel(Arc::new(in_effect));
+        let (event_tx, mut event_rx) = mpsc::channel(64);
+        run_config_writer(Some(path.to_path_buf()), messages_rx, current, event_tx).await;
+        let events = std::iter::from_fn(|| event_rx.try_recv().ok()).collect();
+        let config = current_rx.borrow().clone();
+        (config, events)
+    }
+
+    fn changes(events: &[AppEvent]) -> usize {
+        events.iter().filter(|e| matches!(e, AppEvent::ConfigChanged)).count()
+    }
+
+    #[tokio::test]
+    async fn rapid_saves_write_only_the_newest() {
+        let dir = TempDir::new("persist");
+        let path = dir.join("config.toml");
+        let saves = ["/a", "/b", "/c"].map(|dir| ConfigMessage::Save(Box::new(with_output(dir))));
+
+        let (config, events) = write_config(&path, Config::default(), saves.into()).await;
+
+        assert_eq!(config.output_dir, PathBuf::from("/c"));
+        assert_eq!(Config::load_from(&path).await.unwrap().output_dir, PathBuf::from("/c"));
+        assert_eq!(changes(&events), 1, "{:?}", events);
+    }
+
+    #[tokio::test]
+    async fn a_reload_behind_a_save_reads_that_save_back() {
+        let dir = TempDir::new("persist");
+        let path = dir.join("config.toml");
+        with_output("/on-disk").write_to(&path).await.unwrap();
+
+        let messages = vec![ConfigMessage::Save(Box::new(with_output("/saved"))), ConfigMessage::Reload];
+        let (config, events) = write_config(&path, with_output("/on-disk"), messages).await;
+
+        assert_eq!(config.output_dir, PathBuf::from("/saved"));
+        assert!(events.iter().any(|e| matches!(e, AppEvent::ConfigReloaded { changed: false })), "{:?}", events);
+        assert_eq!(changes(&events), 1);
+    }
+
+    #[tokio::test]
+    async fn a_save_after_a_reload_wins_over_it() {
+        let dir = TempDir::new("persist");
+        let path = dir.join("config.toml");
+        with_output("/edited").write_to(&path).await.unwrap();
+
+        let messages = vec![ConfigMessage::Reload, ConfigMessage::Save(Box::new(with_output("/saved")))];
+        let (config, events) = write_config(&path, with_output("/old"), messages).await;
+
+        assert_eq!(config.output_dir, PathBuf::from("/saved"));
+        assert_eq!(Config::load_from(&path).await.unwrap().output_dir, PathBuf::from("/saved"));
+        assert!(events.iter().any(|e| matches!(e, AppEvent::ConfigReloaded { changed: true })), "{:?}", events);
+    }
+
+    #[tokio::test]
+    async fn a_hand_edit_is_reloaded() {
+        let dir = TempDir::new("persist");
+        let path = dir.join("config.toml");
+        with_output("/edited").write_to(&path).await.unwrap();
+
+        let (config, events) = write_config(&path, with_output("/old"), vec![ConfigMessage::Reload]).await;
+
+        assert_eq!(config.output_dir, PathBuf::from("/edited"));
+        assert_eq!(changes(&events), 1);
+    }
+
+    #[tokio::test]
+    async fn rejected_settings_are_neither_used_nor_written() {
+        let dir = TempDir::new("persist");
+        let path = dir.join("config.toml");
+        with_output("/old").write_to(&path).await.unwrap();
+        let rejected = Config { geo_bypass_country: Some("USA".into()), ..with_output("/new") };
+
+        let (config, events) = write_config(&path, with_output("/old"), vec![ConfigMessage::Save(Box::new(rejected))]).await;
+
+        assert_eq!(config.output_dir, PathBuf::from("/old"));
+        assert_eq!(Config::load_from(&path).await.unwrap().output_dir, PathBuf::from("/old"));
+        assert!(matches!(events.as_slice(), [AppEvent::ConfigSaveFailed(error)] if error.contains("USA")), "{:?}", events);
+    }
+
+    #[tokio::test]
+    async fn a_rejected_hand_edit_keeps_the_settings_in_effect() {
+        let dir = TempDir::new("persist");
+        let path = dir.join("config.toml");
+        Config { geo_bypass_country: Some("USA".into()), ..with_output("/edited") }.write_to(&path).await.unwrap();
+
+        let (config, events) = write_config(&path, with_output("/old"), vec![ConfigMessage::Reload]).await;
+
+        assert_eq!(config.output_dir, PathBuf::from("/old"));
+        assert!(matches!(events.as_slice(), [AppEvent::ConfigReloadFailed(_)]), "{:?}", events);
+    }
+
+    #[test]
+    fn a_clean_queue_has_no_deadline() {
+        let autosave = Autosave::new(Duration::from_secs(2));
+        assert_eq!(autosave.deadline(), None);
+    }
+
+    #[test]
+    fn the_first_change_is_saved_straight_away() {
+        let mut autosave = Autosave::new(Duration::from_secs(2));
+        autosave.mark_dirty();
+        let deadline = autosave.deadline().expect("a deadline once dirty");
+        assert!(deadline <= Instant::now());
+    }
+
+    #[test]
+    fn changes_after_a_write_wait_out_the_interval() {
+        let mut autosave = Autosave::new(Duration::from_secs(2));
+        let written = Instant::now();
+        autosave.mark_dirty();
+        autosave.mark_written(written);
+        assert_eq!(autosave.deadline(), None);
+
+        // However many changes come in, they share the one write
+        for _ in 0..10 {
+            autosave.mark_dirty();
+        }
+        assert_eq!(autosave.deadline(), Some(written + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn a_busy_saver_pushes_the_write_back_an_interval() {
+        let mut autosave = Autosave::new(Duration::from_secs(2));
+        let start = Instant::now();
+        autosave.mark_written(start);
+        autosave.mark_dirty();
+
+        let busy = start + Duration::from_secs(2);
+        autosave.postpone(busy);
+        assert_eq!(autosave.deadline(), Some(busy + Duration::from_secs(2)));
+
+        autosave.mark_written(busy + Duration::from_secs(2));
+        assert_eq!(autosave.deadline(), None);
+    }
+}
@@ -0,0 +1,185 @@
+use std::path::Path;
+
+use crate::config::Config;
+
+// What the machine says about its power supply; None where it can't tell
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PowerState {
+    pub on_battery: Option<bool>,
+    pub percent: Option<u8>,
+}
+
+// When new downloads should wait for the charger
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerPolicy {
+    pub pause_on_battery: bool,
+    pub pause_below_percent: Option<u8>,
+}
+
+impl PowerPolicy {
+    pub fn of(config: &Config) -> Self {
+        Self {
+            pause_on_battery: config.pause_on_battery,
+            pause_below_percent: (config.pause_below_battery_percent > 0).then_some(config.pause_below_battery_percent),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.pause_on_battery || self.pause_below_percent.is_some()
+    }
+
+    // Why the queue should pause right now. Unknown readings never pause it, and
+    // a low battery doesn't count while it is charging
+    pub fn pause_reason(&self, state: &PowerState) -> Option<String> {
+        if self.pause_on_battery && state.on_battery == Some(true) {
+            return Some("on battery".into());
+        }
+        match (self.pause_below_percent, state.percent) {
+            (Some(min), Some(percent)) if percent < min && state.on_battery != Some(false) => {
+                Some(format!("battery at {}%", percent))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Reads /sys/class/power_supply on Linux; elsewhere everything is unknown
+pub fn probe() -> PowerState {
+    if cfg!(target_os = "linux") {
+        probe_sysfs(Path::new("/sys/class/power_supply"))
+    } else {
+        PowerState::default()
+    }
+}
+
+fn probe_sysfs(root: &Path) -> PowerState {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return PowerState::default();
+    };
+    let read = |dir: &Path, name: &str| std::fs::read_to_string(dir.join(name)).ok().map(|s| s.trim().to_string());
+
+    let mut mains_online = None;
+    let mut discharging = None;
+    let mut percent = None;
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        match read(&dir, "type").as_deref() {
+            Some("Mains") | Some("USB") => {
+                let online = read(&dir, "online").as_deref() == Some("1");
+                mains_online = Some(mains_online.unwrap_or(false) || online);
+            }
+            // Peripherals such as mice report as batteries too, but with scope "Device"
+            Some("Battery") if read(&dir, "scope").as_deref() != Some("Device") => {
+                discharging = Some(discharging.unwrap_or(false) || read(&dir, "status").as_deref() == Some("Discharging"));
+                percent = percent.or_else(|| read(&dir, "capacity").and_then(|c| c.parse().ok()));
+            }
+            _ => {}
+        }
+    }
+
+    // A machine without a battery is never on one
+    let on_battery = match (mains_online, discharging) {
+        (_, None) => Some(false),
+        (Some(online), Some(_)) => Some(!online),
+        (None, Some(discharging)) => Some(discharging),
+    };
+    PowerState { on_battery, percent }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn state(on_battery: Option<bool>, percent: Option<u8>) -> PowerState {
+        PowerState { on_battery, percent }
+    }
+
+    #[test]
+    fn pause_reasons() {
+        let on_battery = PowerPolicy { pause_on_battery: true, pause_below_percent: None };
+        let below_20 = PowerPolicy { pause_on_battery: false, pause_below_percent: Some(20) };
+        let cases = [
+            (on_battery, state(Some(true), Some(90)), Some("on battery")),
+            (on_battery, state(Some(false), Some(5)), None),
+            (on_battery, state(None, None), None),
+            (below_20, state(Some(true), Some(15)), Some("battery at 15%")),
+            (below_20, state(Some(true), Some(20)), None),
+            // Charging, so it only gets fuller
+            (below_20, state(Some(false), Some(15)), None),
+            // Can't tell about the charger, but the battery itself is low
+            (below_20, state(None, Some(15)), Some("battery at 15%")),
+            (below_20, state(Some(true), None), None),
+        ];
+        for (policy, state, reason) in cases {
+            assert_eq!(policy.pause_reason(&state).as_deref(), reason, "{:?} {:?}", policy, state);
+        }
+    }
+
+    #[test]
+    fn the_policy_is_off_by_default() {
+        let policy = PowerPolicy::of(&Config::default());
+        assert!(!policy.is_enabled());
+        assert_eq!(policy.pause_reason(&state(Some(true), Some(1))), None);
+    }
+
+    struct Sysfs(TempDir);
+
+    impl Sysfs {
+        fn new() -> Self {
+            Self(TempDir::new("power"))
+        }
+
+        fn supply(self, name: &str, files: &[(&str, &str)]) -> Self {
+            let dir = self.0.join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            for (file, content) in files {
+                std::fs::write(dir.join(file), format!("{}\n", content)).unwrap();
+            }
+            self
+        }
+
+        fn probe(&self) -> PowerState {
+            probe_sysfs(&self.0)
+        }
+    }
+
+    #[test]
+    fn a_laptop_unplugged() {
+        let sysfs = Sysfs::new()
+            .supply("AC", &[("type", "Mains"), ("online", "0")])
+            .supply("BAT0", &[("type", "Battery"), ("status", "Discharging"), ("capacity", "42")]);
+        assert_eq!(sysfs.probe(), state(Some(true), Some(42)));
+    }
+
+    #[test]
+    fn a_laptop_on_the_charger() {
+        let sysfs = Sysfs::new()
+            .supply("AC", &[("type", "Mains"), ("online", "1")])
+            .supply("BAT0", &[("type", "Battery"), ("status", "Charging"), ("capacity", "80")]);
+        assert_eq!(sysfs.probe(), state(Some(false), Some(80)));
+    }
+
+    #[test]
+    fn a_battery_without_a_charger_entry_goes_by_its_status() {
+        let sysfs = Sysfs::new().supply("BAT1", &[("type", "Battery"), ("status", "Discharging"), ("capacity", "30")]);
+        assert_eq!(sysfs.probe(), state(Some(true), Some(30)));
+    }
+
+    #[test]
+    fn a_desktop_is_never_on_battery() {
+        let sysfs = Sysfs::new()
+            .supply("AC", &[("type", "Mains"), ("online", "1")])
+            // A wireless mouse is not the machine's battery
+            .supply("hidpp_battery_0", &[("type", "Battery"), ("scope", "Device"), ("status", "Discharging"), ("capacity", "10")]);
+        assert_eq!(sysfs.probe(), state(Some(false), None));
+        assert_eq!(Sysfs::new().probe(), state(Some(false), None));
+    }
+
+    #[test]
+    fn no_power_supply_class_is_unknown() {
+        let dir = TempDir::new("power");
+        let missing = dir.join("missing");
+        assert_eq!(probe_sysfs(&missing), PowerState::default());
+    }
+}
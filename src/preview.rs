@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use oxidlp::core;
+
+use crate::events::AppEvent;
+use crate::worker;
+
+// Typing pauses at least this long before the link is looked up
+const DEBOUNCE: Duration = Duration::from_millis(400);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoPreview {
+    pub title: String,
+    pub channel: String,
+}
+
+// The title and channel of the YouTube video in the input box, looked up with
+// oEmbed once typing settles. Lookups for a link that has since been edited
+// away are cancelled, and every answer, failures included, is kept per video
+// id so going back to a link never asks twice
+#[derive(Default)]
+pub struct LinkPreview {
+    events: Option<mpsc::Sender<AppEvent>>,
+    // Video id the input currently points at
+    target: Option<String>,
+    due: Option<Instant>,
+    in_flight: Option<CancellationToken>,
+    // None for lookups that failed, which show nothing
+    cache: HashMap<String, Option<VideoPreview>>,
+}
+
+impl LinkPreview {
+    // Results come back as PreviewFetched on the app's event channel
+    pub fn connect(&mut self, events: mpsc::Sender<AppEvent>) {
+        self.events = Some(events);
+    }
+
+    pub fn observe(&mut self, input: &str, now: Instant) {
+        let id = core::video_id(input);
+        if id == self.target {
+            return;
+        }
+        if let Some(token) = self.in_flight.take() {
+            token.cancel();
+        }
+        self.due = id.as_ref().filter(|id| !self.cache.contains_key(*id)).map(|_| now + DEBOUNCE);
+        self.target = id;
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.due
+    }
+
+    pub fn fetch(&mut self) {
+        self.due = None;
+        let (Some(id), Some(events)) = (self.target.clone(), self.events.clone()) else {
+            return;
+        };
+
+        let token = CancellationToken::new();
+        self.in_flight = Some(token.clone());
+        tokio::spawn(async move {
+            let url = format!("https://www.youtube.com/watch?v={}", id);
+            let preview = tokio::select! {
+                _ = token.cancelled() => return,
+                result = worker::oembed(&url) => match result {
+                    Ok(oembed) => Some(VideoPreview { title: oembed.title, channel: oembed.author_name }),
+                    Err(e) => {
+                        tracing::debug!("Link preview for {} failed: {}", id, e);
+                        None
+                    }
+                },
+            };
+            let _ = events.send(AppEvent::PreviewFetched { id, preview }).await;
+        });
+    }
+
+    pub fn finish(&mut self, id: String, preview: Option<VideoPreview>) {
+        if self.target.as_ref() == Some(&id) {
+            self.in_flight = None;
+        }
+        self.cache.insert(id, preview);
+    }
+
+    pub fn current(&self) -> Option<&VideoPreview> {
+        self.cache.get(self.target.as_ref()?)?.as_ref()
+    }
+}
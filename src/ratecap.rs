@@ -0,0 +1,98 @@
+use std::time::{Duration, Instant};
+
+// Progress speeds are noisy, so only this far over the cap counts...
+const TOLERANCE: f64 = 1.10;
+// ...and only once it has lasted this long
+const GRACE: Duration = Duration::from_secs(3);
+
+// Watches whether downloads stay under the configured --limit-rate. yt-dlp
+// applies the limit per download, so the fastest job is what gets compared
+#[derive(Debug, Default)]
+pub struct RateCapMonitor {
+    over_since: Option<Instant>,
+    exceeded: bool,
+}
+
+impl RateCapMonitor {
+    pub fn observe(&mut self, now: Instant, fastest: Option<u64>, cap: Option<u64>) {
+        let over = match (fastest, cap) {
+            (Some(speed), Some(cap)) => speed as f64 > cap as f64 * TOLERANCE,
+            _ => false,
+        };
+
+        if !over {
+            self.over_since = None;
+            self.exceeded = false;
+            return;
+        }
+        let since = *self.over_since.get_or_insert(now);
+        self.exceeded = now.duration_since(since) >= GRACE;
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        self.exceeded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAP: Option<u64> = Some(1_000_000);
+
+    fn after(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn only_a_lasting_overrun_past_the_tolerance_counts() {
+        let start = Instant::now();
+        let mut monitor = RateCapMonitor::default();
+
+        // Ten percent over is still within the noise
+        monitor.observe(start, Some(1_100_000), CAP);
+        monitor.observe(after(start, 5000), Some(1_100_000), CAP);
+        assert!(!monitor.is_exceeded());
+
+        monitor.observe(after(start, 6000), Some(1_100_001), CAP);
+        monitor.observe(after(start, 8999), Some(2_000_000), CAP);
+        assert!(!monitor.is_exceeded());
+        monitor.observe(after(start, 9000), Some(2_000_000), CAP);
+        assert!(monitor.is_exceeded());
+    }
+
+    #[test]
+    fn dropping_under_the_cap_starts_the_grace_over() {
+        let start = Instant::now();
+        let mut monitor = RateCapMonitor::default();
+        monitor.observe(start, Some(2_000_000), CAP);
+        monitor.observe(after(start, 3000), Some(2_000_000), CAP);
+        assert!(monitor.is_exceeded());
+
+        monitor.observe(after(start, 3500), Some(900_000), CAP);
+        assert!(!monitor.is_exceeded());
+        monitor.observe(after(start, 4000), Some(2_000_000), CAP);
+        monitor.observe(after(start, 6000), Some(2_000_000), CAP);
+        assert!(!monitor.is_exceeded());
+        monitor.observe(after(start, 7000), Some(2_000_000), CAP);
+        assert!(monitor.is_exceeded());
+    }
+
+    #[test]
+    fn no_cap_or_no_downloads_is_never_exceeded() {
+        let start = Instant::now();
+        for (fastest, cap) in [(Some(u64::MAX), None), (None, CAP), (None, None)] {
+            let mut monitor = RateCapMonitor::default();
+            monitor.observe(start, fastest, cap);
+            monitor.observe(after(start, 60_000), fastest, cap);
+            assert!(!monitor.is_exceeded(), "{:?} against {:?}", fastest, cap);
+        }
+
+        // Finishing the last download clears a standing warning
+        let mut monitor = RateCapMonitor::default();
+        monitor.observe(start, Some(2_000_000), CAP);
+        monitor.observe(after(start, 3000), Some(2_000_000), CAP);
+        monitor.observe(after(start, 3100), None, CAP);
+        assert!(!monitor.is_exceeded());
+    }
+}
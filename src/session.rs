@@ -0,0 +1,226 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, DefaultQuality};
+use crate::events::{Job, JobGroup};
+use crate::persist::QueueSnapshot;
+
+// Bumped whenever a field changes meaning; a newer file is refused, not half-read
+pub const SESSION_VERSION: u32 = 1;
+
+// A queue written out on purpose, to load later or on another machine. Unlike
+// the autosaved queue it carries the download settings it was made with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    pub saved_at: DateTime<Local>,
+    pub jobs: Vec<Job>,
+    #[serde(default)]
+    pub groups: Vec<JobGroup>,
+    pub settings: SessionSettings,
+}
+
+// Only what shapes the downloads; paths to yt-dlp, cookies and the like belong to the machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSettings {
+    pub output_dir: PathBuf,
+    pub output_template: String,
+    pub default_quality: DefaultQuality,
+    #[serde(default)]
+    pub rate_limit: Option<String>,
+    pub audio_format: String,
+    pub audio_quality: String,
+    pub embed_subs: bool,
+    pub embed_metadata: bool,
+}
+
+impl SessionSettings {
+    pub fn of(config: &Config) -> Self {
+        Self {
+            output_dir: config.output_dir.clone(),
+            output_template: config.output_template.clone(),
+            default_quality: config.default_quality.clone(),
+            rate_limit: config.rate_limit.clone(),
+            audio_format: config.audio_format.clone(),
+            audio_quality: config.audio_quality.clone(),
+            embed_subs: config.embed_subs,
+            embed_metadata: config.embed_metadata,
+        }
+    }
+
+    pub fn apply(&self, config: &mut Config) {
+        config.output_dir = self.output_dir.clone();
+        config.output_template = self.output_template.clone();
+        config.default_quality = self.default_quality.clone();
+        config.rate_limit = self.rate_limit.clone();
+        config.audio_format = self.audio_format.clone();
+        config.audio_quality = self.audio_quality.clone();
+        config.embed_subs = self.embed_subs;
+        config.embed_metadata = self.embed_metadata;
+    }
+}
+
+impl Session {
+    pub fn new(queue: QueueSnapshot, config: &Config) -> Self {
+        Self {
+            version: SESSION_VERSION,
+            saved_at: Local::now(),
+            jobs: queue.jobs,
+            groups: queue.groups,
+            settings: SessionSettings::of(config),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read(path)?;
+        let value: serde_json::Value = serde_json::from_slice(&content)?;
+        match value.get("version").and_then(|v| v.as_u64()) {
+            None => color_eyre::eyre::bail!("{} is not an oxidlp session file", path.display()),
+            Some(version) if version > SESSION_VERSION as u64 => {
+                color_eyre::eyre::bail!("{} was saved by a newer oxidlp (session format {})", path.display(), version)
+            }
+            Some(_) => Ok(serde_json::from_value(value)?),
+        }
+    }
+
+    // The jobs and groups to add to a queue already holding `present`. A job
+    // counts as present when its id or its URL is; the count of those skipped comes back too
+    pub fn incoming(self, present: &[Job]) -> (QueueSnapshot, usize) {
+        let ids: HashSet<_> = present.iter().map(|j| j.id).collect();
+        let urls: HashSet<_> = present.iter().map(|j| j.url.as_str()).collect();
+        let total = self.jobs.len();
+        let jobs: Vec<Job> = self.jobs.into_iter().filter(|j| !ids.contains(&j.id) && !urls.contains(j.url.as_str())).collect();
+        let skipped = total - jobs.len();
+        let groups = self.groups.into_iter().filter(|g| jobs.iter().any(|j| j.group == Some(g.id))).collect();
+        (QueueSnapshot { jobs, groups, trash: Vec::new() }, skipped)
+    }
+}
+
+// What to do with a session when the queue isn't empty
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoadChoice {
+    Merge,
+    Replace,
+    ReplaceWithSettings,
+}
+
+impl LoadChoice {
+    pub const ALL: [LoadChoice; 3] = [LoadChoice::Merge, LoadChoice::Replace, LoadChoice::ReplaceWithSettings];
+
+    pub fn key(&self) -> char {
+        match self {
+            LoadChoice::Merge => 'm',
+            LoadChoice::Replace => 'r',
+            LoadChoice::ReplaceWithSettings => 's',
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LoadChoice::Merge => "Add to the current queue",
+            LoadChoice::Replace => "Replace the current queue",
+            LoadChoice::ReplaceWithSettings => "Replace the queue and use its settings",
+        }
+    }
+
+    pub fn for_key(key: char) -> Option<LoadChoice> {
+        Self::ALL.into_iter().find(|c| c.key() == key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{Failure, JobStatus};
+    use crate::testing::{format, TempDir};
+
+    // A playlist of two, one of them failed, beside a lone job with its format picked
+    fn synthetic() -> Session {
+        let group = JobGroup::new("Mix", "https://www.youtube.com/playlist?list=PLmix");
+        let format = format(r#"{"format_id":"251","ext":"webm","vcodec":"none","acodec":"opus","tbr":135}"#);
+        let jobs = vec![
+            Job { group: Some(group.id), status: JobStatus::Completed, note: Some("keep for the trip".into()), output_path: Some("/music/One.webm".into()), ..Job::new("https://www.youtube.com/watch?v=one") },
+            Job { group: Some(group.id), status: JobStatus::Failed(Failure::new("ERROR: Private video")), ..Job::new("https://www.youtube.com/watch?v=two") },
+            Job {
+                title: Some("Lone".into()),
+                status: JobStatus::Queued,
+                formats: vec![format.clone()].into(),
+                selected_format: Some(format),
+                extract_audio: true,
+                audio_quality: Some("192K".into()),
+                output_template: Some("%(uploader)s/%(title)s.%(ext)s".into()),
+                ..Job::new("https://example.com/v/lone")
+            },
+        ];
+        let config = Config {
+            output_dir: "/music".into(),
+            rate_limit: Some("2M".into()),
+            default_quality: DefaultQuality { format: "bestaudio".into(), max_height: None },
+            embed_metadata: true,
+            ..Config::default()
+        };
+        Session::new(QueueSnapshot { jobs, groups: vec![group], trash: Vec::new() }, &config)
+    }
+
+    #[test]
+    fn a_session_reads_back_as_it_was_saved() {
+        let dir = TempDir::new("session");
+        let path = dir.join("session.json");
+        let session = synthetic();
+        session.save(&path).unwrap();
+        let loaded = Session::load(&path).unwrap();
+
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&session).unwrap());
+        assert_eq!(loaded.jobs.iter().map(|j| j.id).collect::<Vec<_>>(), session.jobs.iter().map(|j| j.id).collect::<Vec<_>>());
+        assert_eq!(loaded.jobs[0].note.as_deref(), Some("keep for the trip"));
+        assert_eq!(loaded.jobs[2].selected_format.as_ref().map(|f| f.format_id.as_str()), Some("251"));
+        assert!(loaded.jobs[..2].iter().all(|j| j.group == Some(loaded.groups[0].id)));
+
+        let mut config = Config::default();
+        loaded.settings.apply(&mut config);
+        assert_eq!(config.output_dir, PathBuf::from("/music"));
+        assert_eq!(config.rate_limit.as_deref(), Some("2M"));
+        assert_eq!(config.default_quality.format, "bestaudio");
+        assert!(config.embed_metadata);
+    }
+
+    #[test]
+    fn only_session_files_this_version_knows_are_loaded() {
+        let dir = TempDir::new("session");
+        let path = dir.join("session.json");
+        let mut newer = serde_json::to_value(synthetic()).unwrap();
+        newer["version"] = (SESSION_VERSION + 1).into();
+        for (contents, error) in [
+            (newer.to_string(), "saved by a newer oxidlp"),
+            (r#"{"jobs":[],"groups":[]}"#.to_string(), "is not an oxidlp session file"),
+        ] {
+            std::fs::write(&path, contents).unwrap();
+            let message = Session::load(&path).unwrap_err().to_string();
+            assert!(message.contains(error), "{}", message);
+        }
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(Session::load(&path).is_err());
+    }
+
+    #[test]
+    fn merging_skips_jobs_already_queued() {
+        let session = synthetic();
+        // One job by id, the other by URL under a fresh id
+        let present = vec![session.jobs[0].clone(), Job::new(session.jobs[1].url.clone())];
+        let (incoming, skipped) = session.clone().incoming(&present);
+        assert_eq!(skipped, 2);
+        assert_eq!(incoming.jobs.iter().map(|j| j.id).collect::<Vec<_>>(), [session.jobs[2].id]);
+        assert!(incoming.groups.is_empty(), "a group with no members left is dropped");
+
+        let (incoming, skipped) = session.clone().incoming(&present[1..]);
+        assert_eq!((incoming.jobs.len(), skipped, incoming.groups.len()), (2, 1, 1));
+    }
+}
@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use sysinfo::{Pid, ProcessesToUpdate, System};
+
+use crate::{format, paths};
+
+// The schema of `status.json`, written while `status_file = true` for status
+// bar integrations. Counts are job counts; `percent` is the average over the
+// active downloads and `speed_bps` their combined rate in bytes per second.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusReport {
+    pub pid: u32,
+    pub active: usize,
+    pub queued: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub percent: Option<f32>,
+    pub speed_bps: u64,
+    pub last_completed: Option<String>,
+}
+
+pub fn status_path() -> PathBuf {
+    paths::get().status_file()
+}
+
+// Readers poll this file, so it is replaced in one rename rather than rewritten
+pub async fn write(report: &StatusReport) -> Result<()> {
+    let path = status_path();
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, serde_json::to_vec_pretty(report)?).await?;
+    tokio::fs::rename(&tmp, &path).await?;
+    Ok(())
+}
+
+pub async fn remove() {
+    let _ = tokio::fs::remove_file(status_path()).await;
+}
+
+// Backs `oxidlp status`. A file left behind by a crashed instance is treated
+// as not running
+pub async fn print() -> Result<bool> {
+    let report = match tokio::fs::read(status_path()).await {
+        Ok(content) => Some(serde_json::from_slice::<StatusReport>(&content)?),
+        Err(_) => None,
+    };
+    let Some(report) = report.filter(|r| is_alive(r.pid)) else {
+        println!("oxidlp is not running");
+        return Ok(false);
+    };
+
+    println!("oxidlp is running (pid {})", report.pid);
+    match report.percent {
+        Some(percent) => println!(
+            "Active:    {} ({:.1}% · {})",
+            report.active,
+            percent,
+            format::rate(report.speed_bps)
+        ),
+        None => println!("Active:    {}", report.active),
+    }
+    println!("Queued:    {}", report.queued);
+    println!("Completed: {}", report.completed);
+    println!("Failed:    {}", report.failed);
+    if let Some(title) = &report.last_completed {
+        println!("Last:      {}", title);
+    }
+    Ok(true)
+}
+
+fn is_alive(pid: u32) -> bool {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    system.process(pid).is_some()
+}
@@ -0,0 +1,38 @@
+//! Fixtures for the unit tests. The library and the binary each compile this
+//! file as their own `testing` module, so both crates share one copy.
+
+use std::path::{Path, PathBuf};
+
+use crate::core::Format;
+
+// A fresh directory under the system temp dir. It is removed with everything
+// in it when dropped, which also happens when a test panics
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    // `name` is the module using it, to tell leftovers apart
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("oxidlp-{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// A row as `yt-dlp -J` lists it
+pub fn format(json: &str) -> Format {
+    serde_json::from_str(json).unwrap()
+}
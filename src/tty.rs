@@ -0,0 +1,80 @@
+use std::io;
+use crossterm::{
+    event::{DisableBracketedPaste, EnableBracketedPaste},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+pub fn enter() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste)
+}
+
+pub fn leave() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), DisableBracketedPaste, LeaveAlternateScreen, crossterm::cursor::Show)
+}
+
+// Raw mode turns Ctrl+Z into an ordinary key press, so stopping is done by
+// hand: give the shell its terminal back, stop, and take it again on `fg`.
+// SIGSTOP rather than SIGTSTP, which run_app catches and would land back here.
+// yt-dlp runs in its own processes and keeps downloading meanwhile
+#[cfg(unix)]
+pub fn suspend() -> io::Result<()> {
+    leave()?;
+    // SAFETY: raise only sends a signal to the calling process
+    unsafe {
+        libc::raise(libc::SIGSTOP);
+    }
+    enter()
+}
+
+#[cfg(not(unix))]
+pub fn suspend() -> io::Result<()> {
+    Ok(())
+}
+
+pub enum JobControl {
+    // `kill -TSTP` from outside
+    Stop,
+    // Resumed after a stop oxidlp didn't see coming, e.g. `kill -STOP`
+    Continue,
+}
+
+#[cfg(unix)]
+pub struct JobSignals {
+    stop: tokio::signal::unix::Signal,
+    resume: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl JobSignals {
+    pub fn new() -> io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+        Ok(Self {
+            stop: signal(SignalKind::from_raw(libc::SIGTSTP))?,
+            resume: signal(SignalKind::from_raw(libc::SIGCONT))?,
+        })
+    }
+
+    pub async fn recv(&mut self) -> JobControl {
+        tokio::select! {
+            _ = self.stop.recv() => JobControl::Stop,
+            _ = self.resume.recv() => JobControl::Continue,
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub struct JobSignals;
+
+#[cfg(not(unix))]
+impl JobSignals {
+    pub fn new() -> io::Result<Self> {
+        Ok(Self)
+    }
+
+    pub async fn recv(&mut self) -> JobControl {
+        std::future::pending().await
+    }
+}
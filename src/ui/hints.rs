@@ -0,0 +1,305 @@
+use crate::app::{App, QueueRow};
+use crate::events::JobStatus;
+
+// What is on screen, boiled down to what decides which keys are worth showing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overlay {
+    Health { blocked: bool },
+    Diagnosis { running: bool },
+    YtdlpUpdate { running: bool },
+    ConfirmQuit,
+    Help,
+    Dependents,
+    PlaylistChoice,
+    // Prompts that only take typing, Enter and Esc
+    TextPrompt,
+    Format,
+    Settings,
+    SettingsEditing,
+    SettingsReview,
+    Triage,
+    QueueFilter,
+    // Every other popup: a list to move through and pick from
+    Menu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Selection {
+    Group,
+    Fetching,
+    Pending,
+    Ready,
+    Queued,
+    Active,
+    Blocked,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AppContext {
+    pub overlay: Option<Overlay>,
+    pub input_mode: bool,
+    pub input_empty: bool,
+    pub selection: Option<Selection>,
+    pub has_queued: bool,
+    pub halted: bool,
+    // The selected job would be saved under the same name as an earlier one
+    pub name_clash: bool,
+    // Tab moves between the split queue's lists rather than to the input
+    pub split_queue: bool,
+    // The queue is narrowed to a filter's matches
+    pub filtered: bool,
+}
+
+impl AppContext {
+    // Mirrors the order handle_key checks popups in, so the hints belong to
+    // whatever is actually taking the keys
+    pub fn from_app(app: &App) -> Self {
+        let overlay = if let Some(screen) = &app.health {
+            Some(Overlay::Health { blocked: screen.report.as_ref().is_none_or(|r| r.is_blocked()) })
+        } else if let Some(screen) = &app.diagnosis {
+            Some(Overlay::Diagnosis { running: screen.report.is_none() })
+        } else if let Some(screen) = &app.ytdlp_update {
+            Some(Overlay::YtdlpUpdate { running: screen.result.is_none() })
+        } else if app.confirm_quit {
+            Some(Overlay::ConfirmQuit)
+        } else if app.show_help {
+            Some(Overlay::Help)
+        } else if app.default_format_prompt.is_some()
+            || app.large_downloads.is_some()
+            || app.completed_menu.is_some()
+            || app.conflict_prompt.is_some()
+            || app.halt_prompt.is_some()
+            || app.clash_prompt.is_some()
+        {
+            Some(Overlay::Menu)
+        } else if app.dependents_prompt.is_some() {
+            Some(Overlay::Dependents)
+        } else if app.remove_menu.is_some()
+            || app.channel_list.is_some()
+            || app.trash_view.is_some()
+            || app.remediation.is_some()
+            || app.import_picker.is_some()
+            || app.session_load.is_some()
+        {
+            Some(Overlay::Menu)
+        } else if app.playlist_choice.is_some() {
+            Some(Overlay::PlaylistChoice)
+        } else if app.import_prompt.is_some() || app.note_prompt.is_some() || app.section_prompt.is_some() || app.playlist_range.is_some()
+            || app.session_prompt.is_some()
+            || app.output_prompt.is_some()
+        {
+            Some(Overlay::TextPrompt)
+        } else if app.triage.is_some() {
+            Some(Overlay::Triage)
+        } else if app.format_popup.is_some() {
+            Some(Overlay::Format)
+        } else if let Some(settings) = &app.settings_popup {
+            Some(if settings.reviewing {
+                Overlay::SettingsReview
+            } else if settings.editing {
+                Overlay::SettingsEditing
+            } else {
+                Overlay::Settings
+            })
+        } else if app.group_menu.is_some() || app.command_popup.is_some() {
+            Some(Overlay::Menu)
+        } else if app.queue_filter.as_ref().is_some_and(|f| f.editing) {
+            Some(Overlay::QueueFilter)
+        } else {
+            None
+        };
+
+        let selection = match app.selected_row() {
+            Some(QueueRow::Group(_)) => Some(Selection::Group),
+            Some(QueueRow::Job(i)) => app.jobs.get(i).map(|job| match job.status {
+                JobStatus::ExpandingPlaylist { .. } | JobStatus::FetchingFormats => Selection::Fetching,
+                JobStatus::MetadataPending => Selection::Pending,
+                JobStatus::Ready => Selection::Ready,
+                JobStatus::Queued => Selection::Queued,
+                JobStatus::Starting | JobStatus::WaitingForSlot { .. } | JobStatus::Downloading { .. } => Selection::Active,
+                JobStatus::Blocked { .. } => Selection::Blocked,
+                JobStatus::Completed => Selection::Completed,
+                JobStatus::Failed(_) => Selection::Failed,
+                JobStatus::Cancelled => Selection::Cancelled,
+            }),
+            None => None,
+        };
+
+        Self {
+            overlay,
+            input_mode: app.input_mode,
+            input_empty: app.input_buffer.is_empty(),
+            selection,
+            has_queued: app.jobs.iter().any(|j| j.status == JobStatus::Queued || (j.status == JobStatus::Ready && j.selected_format.is_none())),
+            name_clash: app.selected_job().is_some_and(|j| app.name_clashes.contains_key(&j.id)),
+            halted: app.queue_halted.is_some() || app.power_paused.is_some(),
+            split_queue: app.split_queue,
+            filtered: app.queue_filter.is_some(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hint {
+    pub key: &'static str,
+    pub label: &'static str,
+}
+
+const fn hint(key: &'static str, label: &'static str) -> Hint {
+    Hint { key, label }
+}
+
+// Gap between two hints
+const SEPARATOR: usize = 2;
+const MAX_HINTS: usize = 4;
+
+impl Hint {
+    pub fn width(&self) -> usize {
+        self.key.chars().count() + 1 + self.label.chars().count()
+    }
+}
+
+type Applies = fn(&AppContext) -> bool;
+
+// Queue mode candidates, most relevant first; the first few that apply are shown
+const QUEUE_HINTS: &[(Applies, Hint)] = &[
+    (|c| c.halted, hint("P", "resume queue")),
+    (|c| c.selection == Some(Selection::Ready), hint("⏎", "pick format")),
+    (|c| c.selection == Some(Selection::Pending), hint("⏎", "fetch first")),
+    (|c| c.selection == Some(Selection::Blocked), hint("⏎", "resolve")),
+    (|c| c.selection == Some(Selection::Completed), hint("⏎", "open")),
+    (|c| c.selection == Some(Selection::Group), hint("⏎", "playlist")),
+    (|c| c.name_clash, hint("n", "name clash")),
+    (|c| c.selection == Some(Selection::Failed), hint("r", "fix")),
+    (|c| c.selection == Some(Selection::Failed), hint("e", "diagnose")),
+    (|c| c.selection == Some(Selection::Cancelled), hint("r", "resume")),
+    (|c| c.has_queued && !c.halted, hint("s", "start")),
+    (|c| matches!(c.selection, Some(Selection::Queued | Selection::Active)), hint("c", "cancel")),
+    (|c| c.filtered, hint("esc", "clear filter")),
+    (|c| c.selection.is_none(), hint("i", "add url")),
+    (|c| c.split_queue, hint("tab", "other list")),
+    (|_| true, hint("?", "help")),
+    (|_| true, hint("g", "settings")),
+    (|_| true, hint("q", "quit")),
+];
+
+// The most relevant keys right now, at most MAX_HINTS of them
+pub fn select(ctx: &AppContext) -> Vec<Hint> {
+    let fixed: &[Hint] = match ctx.overlay {
+        Some(Overlay::Health { blocked: true }) => &[hint("r", "check again"), hint("q", "quit")],
+        Some(Overlay::Health { blocked: false }) => &[hint("⏎", "continue"), hint("r", "check again")],
+        Some(Overlay::Diagnosis { running: true }) => &[hint("esc", "stop")],
+        Some(Overlay::Diagnosis { running: false }) => &[hint("y", "copy report"), hint("r", "run again"), hint("esc", "close")],
+        Some(Overlay::YtdlpUpdate { running: true }) => &[hint("esc", "hide, keeps running")],
+        Some(Overlay::YtdlpUpdate { running: false }) => &[hint("esc", "close")],
+        Some(Overlay::ConfirmQuit) => &[hint("y", "quit"), hint("n", "stay")],
+        Some(Overlay::Help) => &[hint("esc", "close")],
+        Some(Overlay::PlaylistChoice) => &[hint("v", "this video"), hint("p", "whole playlist"), hint("esc", "skip")],
+        Some(Overlay::Dependents) => &[hint("y", "run anyway"), hint("n", "cancel them"), hint("esc", "keep waiting")],
+        Some(Overlay::TextPrompt) => &[hint("⏎", "save"), hint("esc", "cancel")],
+        Some(Overlay::Format) => &[hint("⏎", "select"), hint("l", "expand"), hint("a", "audio"), hint("esc", "close")],
+        Some(Overlay::Settings) => &[hint("⏎", "edit"), hint("←→", "change"), hint("s", "save"), hint("esc", "close")],
+        Some(Overlay::SettingsEditing) => &[hint("⏎", "done")],
+        Some(Overlay::SettingsReview) => &[hint("y", "apply"), hint("n", "back")],
+        Some(Overlay::Triage) => &[hint("1", "1080p"), hint("2", "720p"), hint("a", "audio"), hint("s", "skip")],
+        Some(Overlay::QueueFilter) => &[hint("⏎", "keep filter"), hint("esc", "clear"), hint("'", "exact")],
+        Some(Overlay::Menu) => &[hint("j/k", "move"), hint("⏎", "choose"), hint("esc", "close")],
+        None if ctx.input_mode && !ctx.input_empty => &[hint("⏎", "add"), hint("tab", "queue")],
+        None if ctx.input_mode => &[hint("tab", "queue"), hint("esc", "back")],
+        None => {
+            return QUEUE_HINTS
+                .iter()
+                .filter(|(applies, _)| applies(ctx))
+                .map(|(_, hint)| *hint)
+                .take(MAX_HINTS)
+                .collect();
+        }
+    };
+    fixed.iter().copied().take(MAX_HINTS).collect()
+}
+
+// Drops the least relevant hints until the rest fit in `width` columns
+pub fn fit(mut hints: Vec<Hint>, width: usize) -> Vec<Hint> {
+    while !hints.is_empty() && total_width(&hints) > width {
+        hints.pop();
+    }
+    hints
+}
+
+fn total_width(hints: &[Hint]) -> usize {
+    hints.iter().map(Hint::width).sum::<usize>() + SEPARATOR * hints.len().saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUEUE: AppContext = AppContext {
+        overlay: None,
+        input_mode: false,
+        input_empty: true,
+        selection: None,
+        has_queued: false,
+        halted: false,
+        name_clash: false,
+        split_queue: false,
+        filtered: false,
+    };
+
+    fn keys(ctx: &AppContext) -> Vec<&'static str> {
+        select(ctx).iter().map(|h| h.key).collect()
+    }
+
+    #[test]
+    fn hints_follow_the_context() {
+        let cases: &[(&str, AppContext, &[&str])] = &[
+            ("empty queue", QUEUE, &["i", "?", "g", "q"]),
+            ("ready job", AppContext { selection: Some(Selection::Ready), ..QUEUE }, &["⏎", "?", "g", "q"]),
+            ("ready job, more queued", AppContext { selection: Some(Selection::Ready), has_queued: true, ..QUEUE }, &["⏎", "s", "?", "g"]),
+            ("queued job", AppContext { selection: Some(Selection::Queued), has_queued: true, ..QUEUE }, &["s", "c", "?", "g"]),
+            ("failed job", AppContext { selection: Some(Selection::Failed), ..QUEUE }, &["r", "e", "?", "g"]),
+            ("name clash", AppContext { selection: Some(Selection::Ready), name_clash: true, ..QUEUE }, &["⏎", "n", "?", "g"]),
+            ("halted", AppContext { selection: Some(Selection::Queued), has_queued: true, halted: true, ..QUEUE }, &["P", "c", "?", "g"]),
+            ("filtered split queue", AppContext { selection: Some(Selection::Completed), filtered: true, split_queue: true, ..QUEUE }, &["⏎", "esc", "tab", "?"]),
+            ("typing", AppContext { input_mode: true, input_empty: false, ..QUEUE }, &["⏎", "tab"]),
+            ("empty input", AppContext { input_mode: true, ..QUEUE }, &["tab", "esc"]),
+            // A popup takes the keys whatever is selected behind it
+            ("confirm quit", AppContext { overlay: Some(Overlay::ConfirmQuit), selection: Some(Selection::Ready), ..QUEUE }, &["y", "n"]),
+            ("menu over typing", AppContext { overlay: Some(Overlay::Menu), input_mode: true, input_empty: false, ..QUEUE }, &["j/k", "⏎", "esc"]),
+            ("blocked health", AppContext { overlay: Some(Overlay::Health { blocked: true }), ..QUEUE }, &["r", "q"]),
+            ("running diagnosis", AppContext { overlay: Some(Overlay::Diagnosis { running: true }), ..QUEUE }, &["esc"]),
+        ];
+        for (name, ctx, expected) in cases {
+            assert_eq!(keys(ctx), *expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn never_more_than_the_cap() {
+        let busy = AppContext {
+            selection: Some(Selection::Failed),
+            has_queued: true,
+            name_clash: true,
+            filtered: true,
+            split_queue: true,
+            ..QUEUE
+        };
+        assert_eq!(select(&busy).len(), MAX_HINTS);
+    }
+
+    #[test]
+    fn fitting_drops_the_least_relevant_first() {
+        let hints = select(&AppContext { selection: Some(Selection::Ready), has_queued: true, ..QUEUE });
+        // "⏎ pick format" 13, "s start" 7, "? help" 6, "g settings" 10, 2 between each
+        assert_eq!(total_width(&hints), 42);
+        let cases: &[(usize, &[&str])] = &[(100, &["⏎", "s", "?", "g"]), (42, &["⏎", "s", "?", "g"]), (41, &["⏎", "s", "?"]), (22, &["⏎", "s"]), (13, &["⏎"]), (12, &[]), (0, &[])];
+        for (width, expected) in cases {
+            let fitted = fit(hints.clone(), *width);
+            assert!(total_width(&fitted) <= *width, "width {width}");
+            assert_eq!(fitted.iter().map(|h| h.key).collect::<Vec<_>>(), *expected, "width {width}");
+        }
+    }
+}
@@ -1,12 +1,46 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
-use crate::app::App;
-use crate::events::AppEvent;
+use crate::app::{App, QueueRow};
+use crate::events::{AppEvent, ClashAction, CompletedAction, ConflictPromptState, HaltAction, JobId, RemoveFilter, JobStatus, SectionPromptState, SessionPromptState, OutputPromptState, RemediationStep, TriagePreset};
+use crate::session::LoadChoice;
 
 pub fn handle_key(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
     if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
         return Some(AppEvent::Quit);
     }
+    if key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        return Some(AppEvent::Suspend);
+    }
+
+    if key.code == KeyCode::F(12) {
+        return Some(AppEvent::ToggleFpsOverlay);
+    }
+
+    if let Some(screen) = &app.health {
+        let blocked = screen.report.as_ref().is_none_or(|r| r.is_blocked());
+        return match key.code {
+            KeyCode::Char('r') if screen.report.is_some() => Some(AppEvent::OpenHealthCheck),
+            KeyCode::Char('q') if blocked => Some(AppEvent::ConfirmQuit),
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseHealthCheck),
+            _ => None,
+        };
+    }
+
+    if let Some(screen) = &app.diagnosis {
+        return match key.code {
+            KeyCode::Char('y') if screen.report.is_some() => Some(AppEvent::CopyDiagnosis),
+            KeyCode::Char('r') if screen.report.is_some() => Some(AppEvent::DiagnoseJob(screen.id)),
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseDiagnosis),
+            _ => None,
+        };
+    }
+
+    if app.ytdlp_update.is_some() {
+        return match key.code {
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseYtdlpUpdate),
+            _ => None,
+        };
+    }
 
     if app.confirm_quit {
         return match key.code {
@@ -26,6 +60,111 @@ pub fn handle_key(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
         return None;
     }
 
+    if app.default_format_prompt.is_some() {
+        return match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => Some(AppEvent::ConfirmDefaultFormat),
+            KeyCode::Char('s') => Some(AppEvent::SkipDefaultFormat),
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => Some(AppEvent::CloseDefaultFormat),
+            _ => None,
+        };
+    }
+
+    if app.large_downloads.is_some() {
+        return match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => Some(AppEvent::ConfirmLargeDownloads),
+            KeyCode::Char('s') => Some(AppEvent::SkipLargeDownloads),
+            KeyCode::Char('f') => Some(AppEvent::DownsizeLargeDownload),
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => Some(AppEvent::CloseLargeDownloads),
+            _ => None,
+        };
+    }
+
+    if let Some(menu) = &app.completed_menu {
+        return handle_completed_menu(key, menu.job_id);
+    }
+
+    if let Some(prompt) = &app.conflict_prompt {
+        return handle_conflict_prompt(key, prompt.job_id);
+    }
+
+    if app.halt_prompt.is_some() {
+        return handle_halt_prompt(key);
+    }
+
+    if app.clash_prompt.is_some() {
+        return handle_clash_prompt(key);
+    }
+
+    if app.dependents_prompt.is_some() {
+        return match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => Some(AppEvent::RunDependentsAnyway),
+            KeyCode::Char('n') => Some(AppEvent::CancelDependents),
+            KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseDependentsPrompt),
+            _ => None,
+        };
+    }
+
+    if app.remove_menu.is_some() {
+        return handle_remove_menu(key);
+    }
+
+    if app.channel_list.is_some() {
+        return handle_channel_list(key);
+    }
+
+    if app.trash_view.is_some() {
+        return handle_trash_view(key);
+    }
+
+    if let Some(state) = &app.remediation {
+        return handle_remediation(key, state.step);
+    }
+
+    if app.import_picker.is_some() {
+        return handle_import_picker(key);
+    }
+
+    if app.playlist_choice.is_some() {
+        return match key.code {
+            KeyCode::Char('v') | KeyCode::Char('V') => Some(AppEvent::ChooseSingleVideo),
+            KeyCode::Char('p') | KeyCode::Char('P') => Some(AppEvent::ChooseWholePlaylist),
+            KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::SkipPlaylistChoice),
+            _ => None,
+        };
+    }
+
+    if app.import_prompt.is_some() {
+        return handle_import_prompt(key, app);
+    }
+
+    if app.note_prompt.is_some() {
+        return handle_note_prompt(key, app);
+    }
+
+    if app.section_prompt.is_some() {
+        return handle_section_prompt(key, app);
+    }
+
+    if app.output_prompt.is_some() {
+        return handle_output_prompt(key, app);
+    }
+
+    if app.playlist_range.is_some() {
+        return handle_playlist_range(key, app);
+    }
+
+    if app.session_prompt.is_some() {
+        return handle_session_prompt(key, app);
+    }
+
+    if app.session_load.is_some() {
+        return handle_session_load(key);
+    }
+
+    if app.triage.is_some() {
+        return handle_triage(key);
+    }
+
     if app.format_popup.is_some() {
         return handle_format_popup(key);
     }
@@ -34,6 +173,25 @@ pub fn handle_key(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
         return handle_settings_popup(key, app);
     }
 
+    if app.group_menu.is_some() {
+        return handle_group_menu(key);
+    }
+
+    if app.command_popup.is_some() {
+        return match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') | KeyCode::Char('!') => Some(AppEvent::CloseCommandPopup),
+            _ => None,
+        };
+    }
+
+    if app.queue_filter.as_ref().is_some_and(|f| f.editing) {
+        return handle_queue_filter(key, app);
+    }
+
+    // With the queue split, Tab moves between its lists; `i` still reaches the input
+    if key.code == KeyCode::Tab && app.split_queue && !app.input_mode {
+        return Some(AppEvent::SwitchQueuePane);
+    }
     if key.code == KeyCode::Tab {
         return Some(AppEvent::ToggleInputMode);
     }
@@ -49,15 +207,349 @@ fn handle_format_popup(key: KeyEvent) -> Option<AppEvent> {
     match key.code {
         KeyCode::Char('j') | KeyCode::Down => Some(AppEvent::FormatSelectNext),
         KeyCode::Char('k') | KeyCode::Up => Some(AppEvent::FormatSelectPrev),
-        KeyCode::Char('a') => Some(AppEvent::ToggleAudioOnly),
+        KeyCode::PageDown => Some(AppEvent::FormatPageDown),
+        KeyCode::PageUp => Some(AppEvent::FormatPageUp),
+        KeyCode::Home | KeyCode::Char('g') => Some(AppEvent::FormatSelectFirst),
+        KeyCode::End | KeyCode::Char('G') => Some(AppEvent::FormatSelectLast),
+        KeyCode::Char('l') | KeyCode::Right => Some(AppEvent::FormatExpandTier),
+        KeyCode::Char('h') | KeyCode::Left => Some(AppEvent::FormatCollapseTier),
+        KeyCode::Char('a') => Some(AppEvent::FormatToggleAudioTier),
+        KeyCode::Char('x') => Some(AppEvent::FormatToggleExtractAudio),
+        KeyCode::Char(']') => Some(AppEvent::FormatStepAudioQuality(true)),
+        KeyCode::Char('[') => Some(AppEvent::FormatStepAudioQuality(false)),
+        KeyCode::Char('c') => Some(AppEvent::FormatCycleContainer),
         KeyCode::Char('A') => Some(AppEvent::ToggleApplyToAll),
+        KeyCode::Char('*') => Some(AppEvent::FormatToggleShowAll),
+        KeyCode::Char('f') => Some(AppEvent::ToggleLiveFromStart),
         KeyCode::Enter => Some(AppEvent::ConfirmFormat),
         KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseFormatPopup),
         _ => None,
     }
 }
 
+fn handle_completed_menu(key: KeyEvent, job_id: JobId) -> Option<AppEvent> {
+    match key.code {
+        KeyCode::Char('j') | KeyCode::Down => Some(AppEvent::CompletedMenuNext),
+        KeyCode::Char('k') | KeyCode::Up => Some(AppEvent::CompletedMenuPrev),
+        KeyCode::Enter => Some(AppEvent::ConfirmCompletedAction),
+        KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseCompletedMenu),
+        KeyCode::Char(c) => CompletedAction::for_key(c).map(|action| AppEvent::RunCompletedAction(job_id, action)),
+        _ => None,
+    }
+}
+
+fn handle_conflict_prompt(key: KeyEvent, job_id: JobId) -> Option<AppEvent> {
+    match key.code {
+        KeyCode::Char('j') | KeyCode::Down => Some(AppEvent::ConflictPromptNext),
+        KeyCode::Char('k') | KeyCode::Up => Some(AppEvent::ConflictPromptPrev),
+        KeyCode::Enter => Some(AppEvent::ConfirmConflictChoice),
+        KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseConflictPrompt),
+        KeyCode::Char(c) => ConflictPromptState::for_key(c).map(|(policy, always)| AppEvent::ResolveConflict(job_id, policy, always)),
+        _ => None,
+    }
+}
+
+fn handle_remove_menu(key: KeyEvent) -> Option<AppEvent> {
+    match key.code {
+        KeyCode::Char('j') | KeyCode::Down => Some(AppEvent::RemoveMenuNext),
+        KeyCode::Char('k') | KeyCode::Up => Some(AppEvent::RemoveMenuPrev),
+        KeyCode::Enter => Some(AppEvent::ConfirmRemoveMenu),
+        KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseRemoveMenu),
+        KeyCode::Char(c) => RemoveFilter::for_key(c).map(AppEvent::RemoveByStatus),
+        _ => None,
+    }
+}
+
+fn handle_channel_list(key: KeyEvent) -> Option<AppEvent> {
+    match key.code {
+        KeyCode::Char('j') | KeyCode::Down => Some(AppEvent::ChannelListNext),
+        KeyCode::Char('k') | KeyCode::Up => Some(AppEvent::ChannelListPrev),
+        KeyCode::Char('d') | KeyCode::Delete => Some(AppEvent::ForgetChannel),
+        KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseChannelList),
+        _ => None,
+    }
+}
+
+fn handle_trash_view(key: KeyEvent) -> Option<AppEvent> {
+    match key.code {
+        KeyCode::Char('j') | KeyCode::Down => Some(AppEvent::TrashNext),
+        KeyCode::Char('k') | KeyCode::Up => Some(AppEvent::TrashPrev),
+        KeyCode::Char('r') | KeyCode::Enter => Some(AppEvent::RestoreTrashed),
+        KeyCode::Char('d') | KeyCode::Delete => Some(AppEvent::PurgeTrashed),
+        KeyCode::Char('D') => Some(AppEvent::EmptyTrash),
+        KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseTrash),
+        _ => None,
+    }
+}
+
+fn handle_clash_prompt(key: KeyEvent) -> Option<AppEvent> {
+    match key.code {
+        KeyCode::Char('j') | KeyCode::Down => Some(AppEvent::ClashPromptNext),
+        KeyCode::Char('k') | KeyCode::Up => Some(AppEvent::ClashPromptPrev),
+        KeyCode::Enter => Some(AppEvent::ConfirmClashAction),
+        KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseClashPrompt),
+        KeyCode::Char(c) => ClashAction::for_key(c).map(AppEvent::RunClashAction),
+        _ => None,
+    }
+}
+
+fn handle_halt_prompt(key: KeyEvent) -> Option<AppEvent> {
+    match key.code {
+        KeyCode::Char('j') | KeyCode::Down => Some(AppEvent::HaltPromptNext),
+        KeyCode::Char('k') | KeyCode::Up => Some(AppEvent::HaltPromptPrev),
+        KeyCode::Enter => Some(AppEvent::ConfirmHaltAction),
+        KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseHaltPrompt),
+        KeyCode::Char(c) => HaltAction::for_key(c).map(AppEvent::RunHaltAction),
+        _ => None,
+    }
+}
+
+fn handle_remediation(key: KeyEvent, step: RemediationStep) -> Option<AppEvent> {
+    if step == RemediationStep::Login {
+        return match key.code {
+            KeyCode::Tab | KeyCode::Down | KeyCode::Up => Some(AppEvent::RemediationSwitchField),
+            KeyCode::Enter => Some(AppEvent::RemediationConfirm),
+            KeyCode::Esc => Some(AppEvent::CloseRemediation),
+            KeyCode::Backspace => Some(AppEvent::RemediationBackspace),
+            KeyCode::Char(c) => Some(AppEvent::RemediationInput(c)),
+            _ => None,
+        };
+    }
+
+    match key.code {
+        KeyCode::Char('j') | KeyCode::Down => Some(AppEvent::RemediationNext),
+        KeyCode::Char('k') | KeyCode::Up => Some(AppEvent::RemediationPrev),
+        KeyCode::Enter => Some(AppEvent::RemediationConfirm),
+        KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseRemediation),
+        _ => None,
+    }
+}
+
+fn handle_import_picker(key: KeyEvent) -> Option<AppEvent> {
+    match key.code {
+        KeyCode::Char('j') | KeyCode::Down => Some(AppEvent::ImportPickerNext),
+        KeyCode::Char('k') | KeyCode::Up => Some(AppEvent::ImportPickerPrev),
+        KeyCode::Char(' ') => Some(AppEvent::ImportPickerToggle),
+        KeyCode::Char('a') => Some(AppEvent::ImportPickerToggleAll),
+        KeyCode::Char('1') => Some(AppEvent::ImportPickerPreset(Some(TriagePreset::Video1080))),
+        KeyCode::Char('2') => Some(AppEvent::ImportPickerPreset(Some(TriagePreset::Video720))),
+        KeyCode::Char('3') => Some(AppEvent::ImportPickerPreset(Some(TriagePreset::Audio))),
+        KeyCode::Char('0') => Some(AppEvent::ImportPickerPreset(None)),
+        KeyCode::Enter => Some(AppEvent::ConfirmImport),
+        KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseImportPicker),
+        _ => None,
+    }
+}
+
+fn handle_import_prompt(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
+    let path = app.import_prompt.as_mut()?;
+    match key.code {
+        KeyCode::Enter => {
+            let path = std::mem::take(path);
+            (!path.trim().is_empty()).then(|| AppEvent::ImportBookmarks(path.trim().into()))
+        }
+        KeyCode::Backspace => {
+            path.pop();
+            None
+        }
+        KeyCode::Esc => {
+            app.import_prompt = None;
+            None
+        }
+        KeyCode::Char(c) => {
+            path.push(c);
+            None
+        }
+        _ => None,
+    }
+}
+
+fn handle_note_prompt(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
+    let (id, note) = app.note_prompt.as_mut()?;
+    match key.code {
+        KeyCode::Enter => Some(AppEvent::SetNote { id: *id, note: std::mem::take(note) }),
+        KeyCode::Backspace => {
+            note.pop();
+            None
+        }
+        KeyCode::Esc => {
+            app.note_prompt = None;
+            None
+        }
+        KeyCode::Char(c) => {
+            push_input_char(note, c);
+            None
+        }
+        _ => None,
+    }
+}
+
+fn handle_section_prompt(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
+    let prompt = app.section_prompt.as_mut()?;
+    match key.code {
+        KeyCode::Enter => return Some(AppEvent::ConfirmSections),
+        KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => prompt.editing_end = !prompt.editing_end,
+        KeyCode::Backspace => {
+            prompt.field_mut().pop();
+        }
+        KeyCode::Esc => app.section_prompt = None,
+        KeyCode::Char(c) if c.is_ascii_digit() || c == ':' || c == '.' => prompt.field_mut().push(c),
+        _ => {}
+    }
+    None
+}
+
+fn handle_output_prompt(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
+    let prompt = app.output_prompt.as_mut()?;
+    match key.code {
+        KeyCode::Enter => return Some(AppEvent::ConfirmOutput),
+        KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => prompt.editing_template = !prompt.editing_template,
+        KeyCode::Backspace => {
+            prompt.field_mut().pop();
+        }
+        KeyCode::Esc => app.output_prompt = None,
+        KeyCode::Char(c) => prompt.field_mut().push(c),
+        _ => {}
+    }
+    None
+}
+
+fn handle_session_prompt(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
+    let prompt = app.session_prompt.as_mut()?;
+    match key.code {
+        KeyCode::Enter => {
+            let path = prompt.path.trim();
+            if path.is_empty() {
+                return None;
+            }
+            let path = path.into();
+            Some(if prompt.saving { AppEvent::SaveSession(path) } else { AppEvent::OpenSession(path) })
+        }
+        KeyCode::Backspace => {
+            prompt.path.pop();
+            None
+        }
+        KeyCode::Esc => {
+            app.session_prompt = None;
+            None
+        }
+        KeyCode::Char(c) => {
+            prompt.path.push(c);
+            None
+        }
+        _ => None,
+    }
+}
+
+fn handle_session_load(key: KeyEvent) -> Option<AppEvent> {
+    match key.code {
+        KeyCode::Char('j') | KeyCode::Down => Some(AppEvent::SessionLoadNext),
+        KeyCode::Char('k') | KeyCode::Up => Some(AppEvent::SessionLoadPrev),
+        KeyCode::Enter => Some(AppEvent::ConfirmSessionLoad),
+        KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseSessionLoad),
+        KeyCode::Char(c) => LoadChoice::for_key(c).map(AppEvent::LoadSession),
+        _ => None,
+    }
+}
+
+fn handle_playlist_range(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
+    let prompt = app.playlist_range.as_mut()?;
+    match key.code {
+        KeyCode::Enter => return Some(AppEvent::ConfirmPlaylistRange),
+        KeyCode::Esc => return Some(AppEvent::SkipPlaylistRange),
+        KeyCode::Backspace => {
+            prompt.input.pop();
+        }
+        KeyCode::Char(c) if !c.is_control() => prompt.input.push(c),
+        _ => {}
+    }
+    prompt.error = None;
+    None
+}
+
+fn handle_triage(key: KeyEvent) -> Option<AppEvent> {
+    match key.code {
+        KeyCode::Char('1') => Some(AppEvent::TriageAssign(TriagePreset::Video1080)),
+        KeyCode::Char('2') => Some(AppEvent::TriageAssign(TriagePreset::Video720)),
+        KeyCode::Char('a') => Some(AppEvent::TriageAssign(TriagePreset::Audio)),
+        KeyCode::Char('s') => Some(AppEvent::TriageSkip),
+        KeyCode::Char('d') => Some(AppEvent::TriageRemove),
+        KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseTriage),
+        _ => None,
+    }
+}
+
+fn handle_group_menu(key: KeyEvent) -> Option<AppEvent> {
+    match key.code {
+        KeyCode::Char('j') | KeyCode::Down => Some(AppEvent::GroupMenuNext),
+        KeyCode::Char('k') | KeyCode::Up => Some(AppEvent::GroupMenuPrev),
+        KeyCode::Enter => Some(AppEvent::ConfirmGroupAction),
+        KeyCode::Esc | KeyCode::Char('q') => Some(AppEvent::CloseGroupMenu),
+        _ => None,
+    }
+}
+
+// Pasted text goes through the same cleanup as typed characters, plus removal
+// of whole escape sequences that a terminal or web page may have carried along
+pub fn handle_paste(text: &str, app: &mut App) {
+    if !app.input_mode {
+        return;
+    }
+    let start = app.input_buffer.len();
+    for c in sanitize(text).chars() {
+        push_input_char(&mut app.input_buffer, c);
+    }
+    // A trailing newline from copying a whole line isn't worth flagging
+    app.input_sanitized = app.input_buffer[start..].trim() != text.trim();
+}
+
+// Whitespace of any kind collapses into single spaces and controls are dropped
+fn push_input_char(buffer: &mut String, c: char) {
+    if c.is_whitespace() {
+        if !buffer.is_empty() && !buffer.ends_with(' ') {
+            buffer.push(' ');
+        }
+    } else if !c.is_control() {
+        buffer.push(c);
+    }
+}
+
+// Strips CSI (`ESC [ ... final`), OSC (`ESC ] ... BEL` or `ESC ] ... ESC \`) and
+// two-character escapes, then every remaining C0 control other than whitespace
+fn sanitize(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            c if c.is_whitespace() => out.push(' '),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
 fn handle_input_mode(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
+    app.input_sanitized = false;
     match key.code {
         KeyCode::Enter => {
             if !app.input_buffer.is_empty() {
@@ -75,7 +567,7 @@ fn handle_input_mode(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
             None
         }
         KeyCode::Char(c) => {
-            app.input_buffer.push(c);
+            push_input_char(&mut app.input_buffer, c);
             None
         }
         _ => None,
@@ -83,24 +575,106 @@ fn handle_input_mode(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
 }
 
 fn handle_queue_mode(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        let saving = match key.code {
+            KeyCode::Char('s') => true,
+            KeyCode::Char('o') => false,
+            KeyCode::Char('u') => return Some(AppEvent::UpdateYtdlp),
+            _ => return None,
+        };
+        app.session_prompt = Some(SessionPromptState { saving, path: String::new() });
+        return None;
+    }
+
+    if let (KeyCode::Char(c), Some(job)) = (key.code, app.selected_job()) {
+        if job.status == JobStatus::Completed {
+            if let Some(action) = CompletedAction::for_key(c) {
+                return Some(AppEvent::RunCompletedAction(job.id, action));
+            }
+        }
+    }
+
     match key.code {
         KeyCode::Char('q') => Some(AppEvent::Quit),
         KeyCode::Char('?') => Some(AppEvent::ToggleHelp),
         KeyCode::Char('g') => Some(AppEvent::ToggleSettings),
         KeyCode::Char('j') | KeyCode::Down => Some(AppEvent::SelectNext),
         KeyCode::Char('k') | KeyCode::Up => Some(AppEvent::SelectPrev),
-        KeyCode::Enter => {
-            if let Some(job) = app.selected_job() {
-                if job.can_select_format() {
-                    return Some(AppEvent::OpenFormatPopup);
+        KeyCode::Enter => match app.selected_row()? {
+            QueueRow::Group(group_id) => Some(AppEvent::OpenGroupMenu(group_id)),
+            QueueRow::Job(_) => {
+                let job = app.selected_job()?;
+                if job.status == JobStatus::Completed {
+                    Some(AppEvent::OpenCompletedMenu(job.id))
+                } else if matches!(job.status, JobStatus::Blocked { .. }) {
+                    Some(AppEvent::OpenConflictPrompt(job.id))
+                } else {
+                    job.can_select_format().then_some(AppEvent::OpenFormatPopup)
                 }
             }
-            None
-        }
+        },
         KeyCode::Char('s') => Some(AppEvent::StartDownloads),
+        KeyCode::Char('T') => {
+            let group = match app.selected_row() {
+                Some(QueueRow::Group(group_id)) => Some(group_id),
+                _ => app.selected_job().and_then(|j| j.group),
+            };
+            Some(AppEvent::StartTriage(group))
+        }
+        KeyCode::Char('Y') => app.selected_job()?.record.as_ref().map(|_| AppEvent::CopyCommand),
+        KeyCode::Char('!') => app.selected_job()?.has_format().then_some(AppEvent::ShowCommand),
         KeyCode::Char('S') => Some(AppEvent::ToggleSysInfo),
+        KeyCode::Char('v') => Some(AppEvent::ToggleVerboseRows),
+        KeyCode::Char('|') => Some(AppEvent::ToggleQueueLayout),
+        KeyCode::Char('f') => Some(AppEvent::OpenQueueFilter),
+        KeyCode::Esc if app.queue_filter.is_some() => Some(AppEvent::ClearQueueFilter),
+        KeyCode::Char('w') => Some(AppEvent::ToggleWarnings),
+        KeyCode::Char('L') => Some(AppEvent::ToggleTimeline),
+        KeyCode::Char('>') => Some(AppEvent::LinkAfter),
+        KeyCode::Char('<') => app.selected_job()?.after.map(|_| AppEvent::UnlinkAfter),
+        KeyCode::Char('E') => app.selected_job()?.error_report().map(|_| AppEvent::CopyErrorReport),
+        KeyCode::Char('e') => app
+            .selected_job()
+            .filter(|j| matches!(j.status, JobStatus::Failed(_)))
+            .map(|j| AppEvent::DiagnoseJob(j.id)),
+        KeyCode::Char('N') => Some(AppEvent::ToggleDoNotDisturb),
+        KeyCode::Char('D') => Some(AppEvent::OpenRemoveMenu),
+        KeyCode::Char('u') => Some(AppEvent::UndoRemove),
+        KeyCode::Char('H') => Some(AppEvent::OpenHealthCheck),
+        KeyCode::Char('P') if app.queue_halted.is_some() => Some(AppEvent::OpenHaltPrompt),
+        KeyCode::Char('P') => app.power_paused.as_ref().map(|_| AppEvent::ResumePowerPause),
+        KeyCode::Char('b') => {
+            app.import_prompt = Some(String::new());
+            None
+        }
+        KeyCode::Char('n') => Some(AppEvent::OpenClashPrompt),
+        KeyCode::Char('o') => {
+            let job = app.selected_job().filter(|j| j.output_target.is_none() && !j.is_started())?;
+            app.output_prompt = Some(OutputPromptState::new(job));
+            None
+        }
+        KeyCode::Char('t') => {
+            let job = app.selected_job().filter(|j| matches!(j.status, JobStatus::Ready | JobStatus::Queued))?;
+            app.section_prompt = Some(SectionPromptState::new(job));
+            None
+        }
+        KeyCode::Char('#') => {
+            let job = app.selected_job()?;
+            app.note_prompt = Some((job.id, job.note.clone().unwrap_or_default()));
+            None
+        }
         KeyCode::Char('d') => app.selected_job().map(|j| AppEvent::RemoveJob(j.id)),
         KeyCode::Char('c') => app.selected_job().map(|j| AppEvent::CancelJob(j.id)),
+        KeyCode::Char('r') => app.selected_job().map(|j| match j.status {
+            JobStatus::Cancelled => AppEvent::RestartCancelled { id: j.id, resume: true },
+            _ => AppEvent::OpenRemediation(j.id),
+        }),
+        KeyCode::Char('R') => app
+            .selected_job()
+            .filter(|j| j.status == JobStatus::Cancelled)
+            .map(|j| AppEvent::RestartCancelled { id: j.id, resume: false }),
+        KeyCode::Char('M') => Some(AppEvent::RetryMembersOnly),
+        KeyCode::Char('U') => Some(AppEvent::SortByUploadDate),
         KeyCode::Char('i') | KeyCode::Char('/') => {
             app.input_mode = true;
             None
@@ -109,10 +683,33 @@ fn handle_queue_mode(key: KeyEvent, app: &mut App) -> Option<AppEvent> {
     }
 }
 
+// The queue follows every key press, so each one is sent on as the new text
+fn handle_queue_filter(key: KeyEvent, app: &App) -> Option<AppEvent> {
+    let mut query = app.queue_filter.as_ref()?.query.clone();
+    match key.code {
+        KeyCode::Enter | KeyCode::Down | KeyCode::Up => return Some(AppEvent::KeepQueueFilter),
+        KeyCode::Esc => return Some(AppEvent::ClearQueueFilter),
+        KeyCode::Backspace => {
+            query.pop();
+        }
+        KeyCode::Char(c) => push_input_char(&mut query, c),
+        _ => return None,
+    }
+    Some(AppEvent::SetQueueFilter(query))
+}
+
 fn handle_settings_popup(key: KeyEvent, app: &App) -> Option<AppEvent> {
     let settings = app.settings_popup.as_ref()?;
+
+    if settings.reviewing {
+        return match key.code {
+            KeyCode::Enter | KeyCode::Char('y') => Some(AppEvent::SaveSettings),
+            KeyCode::Esc | KeyCode::Char('n') => Some(AppEvent::CancelSettingsReview),
+            _ => None,
+        };
+    }
     
-    if settings.editing_path {
+    if settings.editing {
         return match key.code {
             KeyCode::Esc | KeyCode::Enter => Some(AppEvent::SettingsToggleEdit),
             KeyCode::Backspace => Some(AppEvent::SettingsBackspace),
@@ -127,14 +724,97 @@ fn handle_settings_popup(key: KeyEvent, app: &App) -> Option<AppEvent> {
         KeyCode::Char('l') | KeyCode::Right | KeyCode::Char('+') => Some(AppEvent::SettingsIncrement),
         KeyCode::Char('h') | KeyCode::Left | KeyCode::Char('-') => Some(AppEvent::SettingsDecrement),
         KeyCode::Enter => {
-            if settings.selected_field == 0 {
-                Some(AppEvent::SaveSettings)
+            if !settings.field().is_text() {
+                Some(AppEvent::ReviewSettings)
             } else {
                 Some(AppEvent::SettingsToggleEdit)
             }
         }
-        KeyCode::Char('s') => Some(AppEvent::SaveSettings),
+        KeyCode::Char('s') => Some(AppEvent::ReviewSettings),
+        KeyCode::Char('o') => Some(AppEvent::OpenLogFile),
+        KeyCode::Char('x') => Some(AppEvent::ClearOldLogs),
+        KeyCode::Char('c') => Some(AppEvent::OpenChannelList),
+        KeyCode::Char('t') => Some(AppEvent::OpenTrash),
+        KeyCode::Char('r') => Some(AppEvent::ReloadConfig),
         KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('g') => Some(AppEvent::CloseSettings),
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn sanitize_strips_what_pastes_carry_along() {
+        for (pasted, clean) in [
+            ("https://youtu.be/abc", "https://youtu.be/abc"),
+            // Bracketed paste markers a terminal failed to strip
+            ("\x1b[200~https://youtu.be/abc\x1b[201~", "https://youtu.be/abc"),
+            // Colour codes from copying out of another terminal
+            ("\x1b[1;32mhttps://youtu.be/abc\x1b[0m", "https://youtu.be/abc"),
+            // OSC 8 hyperlinks, ended by BEL and by ST
+            ("\x1b]8;;https://evil.example\x07https://youtu.be/abc\x1b]8;;\x07", "https://youtu.be/abc"),
+            ("\x1b]8;;https://evil.example\x1b\\https://youtu.be/abc\x1b]8;;\x1b\\", "https://youtu.be/abc"),
+            ("\x1bchttps://youtu.be/abc", "https://youtu.be/abc"),
+            ("https://youtu.be/a\x00b\x08c\x7f", "https://youtu.be/abc"),
+            ("https://youtu.be/abc\r\n", "https://youtu.be/abc  "),
+            ("one\ttwo\u{a0}three", "one two three"),
+            ("Vidéo ✓", "Vidéo ✓"),
+            // An unterminated sequence swallows the rest rather than leaking it
+            ("https://youtu.be/abc\x1b[12", "https://youtu.be/abc"),
+            ("https://youtu.be/abc\x1b]8;;never ends", "https://youtu.be/abc"),
+            ("\x1b", ""),
+        ] {
+            assert_eq!(sanitize(pasted), clean, "{:?}", pasted);
+        }
+    }
+
+    fn typing() -> App {
+        let (worker_tx, _worker_rx) = mpsc::channel(1);
+        let mut app = App::new(Config::default(), worker_tx);
+        app.input_mode = true;
+        app
+    }
+
+    #[test]
+    fn a_cleaned_paste_is_flagged_but_a_trailing_newline_is_not() {
+        let mut app = typing();
+        handle_paste("https://youtu.be/abc\n", &mut app);
+        assert_eq!(app.input_buffer, "https://youtu.be/abc ");
+        assert!(!app.input_sanitized);
+
+        let mut app = typing();
+        handle_paste("\x1b[31mhttps://youtu.be/abc\x1b[0m", &mut app);
+        assert_eq!(app.input_buffer, "https://youtu.be/abc");
+        assert!(app.input_sanitized);
+
+        let mut app = typing();
+        handle_paste("https://youtu.be/a\n\n  https://youtu.be/b", &mut app);
+        assert_eq!(app.input_buffer, "https://youtu.be/a https://youtu.be/b");
+    }
+
+    #[test]
+    fn tab_switches_lists_only_in_the_split_queue() {
+        let tab = KeyEvent::from(KeyCode::Tab);
+        let mut app = typing();
+        assert!(matches!(handle_key(tab, &mut app), Some(AppEvent::ToggleInputMode)));
+        app.input_mode = false;
+        assert!(matches!(handle_key(tab, &mut app), Some(AppEvent::ToggleInputMode)));
+        app.split_queue = true;
+        assert!(matches!(handle_key(tab, &mut app), Some(AppEvent::SwitchQueuePane)));
+        // Leaving the input still takes Tab
+        app.input_mode = true;
+        assert!(matches!(handle_key(tab, &mut app), Some(AppEvent::ToggleInputMode)));
+    }
+
+    #[test]
+    fn pastes_outside_the_input_are_ignored() {
+        let mut app = typing();
+        app.input_mode = false;
+        handle_paste("https://youtu.be/abc", &mut app);
+        assert!(app.input_buffer.is_empty());
+    }
+}
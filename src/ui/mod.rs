@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -7,9 +9,16 @@ use ratatui::{
 };
 use sysinfo::Pid;
 
-use crate::app::App;
-use crate::events::JobStatus;
+use crate::app::{App, QueuePane, QueueRow};
+use crate::config;
+use crate::events::{estimate_audio_size, ClashAction, Container, Conversion, DownloadPhase, CompletedAction, ConflictPromptState, DownloadError, FormatPopupState, GroupAction, GroupId, HaltAction, JobId, RemoveFilter, Job, JobStatus, MediaKind, LoginField, FormatRow, NoticeLevel, OutputTarget, OverwritePolicy, QualityTier, RemediationState, RemediationStep, SettingsField, SettingsState, COOKIE_BROWSERS};
+use crate::format;
+use crate::health::CheckStatus;
+use crate::preview::VideoPreview;
+use crate::session::LoadChoice;
+use crate::worker;
 
+pub mod hints;
 pub mod input;
 
 const CYAN: Color = Color::Rgb(80, 200, 200);
@@ -20,17 +29,23 @@ const MUTED: Color = Color::Rgb(100, 110, 120);
 const TEXT: Color = Color::Rgb(180, 190, 200);
 const BG: Color = Color::Rgb(30, 35, 40);
 
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
 pub fn render(f: &mut Frame, app: &App) {
+    let preview = app.preview.current().filter(|_| app.input_mode && !app.input_buffer.is_empty());
     let main_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3), 
+            Constraint::Length(if preview.is_some() { 4 } else { 3 }), 
             Constraint::Min(10),    
             Constraint::Length(3), 
         ])
         .split(f.area());
 
     render_input(f, app, main_chunks[0]);
+    if let Some(preview) = preview {
+        render_preview(f, preview, main_chunks[0]);
+    }
     
     let content_chunks = Layout::default()
         .direction(Direction::Horizontal)
@@ -47,7 +62,7 @@ pub fn render(f: &mut Frame, app: &App) {
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Min(8),
-                Constraint::Length(8),
+                Constraint::Length(9),
             ])
             .split(content_chunks[1]);
         render_details(f, app, right_chunks[0]);
@@ -66,6 +81,110 @@ pub fn render(f: &mut Frame, app: &App) {
         render_settings_popup(f, app);
     }
 
+    if app.group_menu.is_some() {
+        render_group_menu(f, app);
+    }
+
+    if app.command_popup.is_some() {
+        render_command_popup(f, app);
+    }
+
+    if app.triage.is_some() {
+        render_triage_popup(f, app);
+    }
+
+    if app.import_prompt.is_some() {
+        render_import_prompt(f, app);
+    }
+
+    if app.note_prompt.is_some() {
+        render_note_prompt(f, app);
+    }
+
+    if app.section_prompt.is_some() {
+        render_section_prompt(f, app);
+    }
+
+    if app.output_prompt.is_some() {
+        render_output_prompt(f, app);
+    }
+
+    if app.playlist_range.is_some() {
+        render_playlist_range(f, app);
+    }
+
+    if app.playlist_choice.is_some() {
+        render_playlist_choice(f, app);
+    }
+
+    if app.session_prompt.is_some() {
+        render_session_prompt(f, app);
+    }
+
+    if app.session_load.is_some() {
+        render_session_load(f, app);
+    }
+
+    if app.health.is_some() {
+        render_health(f, app);
+    }
+
+    if app.diagnosis.is_some() {
+        render_diagnosis(f, app);
+    }
+
+    if app.ytdlp_update.is_some() {
+        render_ytdlp_update(f, app);
+    }
+
+    if app.completed_menu.is_some() {
+        render_completed_menu(f, app);
+    }
+
+    if app.default_format_prompt.is_some() {
+        render_default_format_prompt(f, app);
+    }
+
+    if app.large_downloads.is_some() {
+        render_large_downloads(f, app);
+    }
+
+    if app.conflict_prompt.is_some() {
+        render_conflict_prompt(f, app);
+    }
+
+    if app.halt_prompt.is_some() {
+        render_halt_prompt(f, app);
+    }
+
+    if app.clash_prompt.is_some() {
+        render_clash_prompt(f, app);
+    }
+
+    if app.dependents_prompt.is_some() {
+        render_dependents_prompt(f, app);
+    }
+
+    if app.remove_menu.is_some() {
+        render_remove_menu(f, app);
+    }
+
+    if app.channel_list.is_some() {
+        render_channel_list(f, app);
+    }
+
+    if app.trash_view.is_some() {
+        render_trash_view(f, app);
+    }
+
+    if app.remediation.is_some() {
+        render_remediation_popup(f, app);
+    }
+
+    if app.import_picker.is_some() {
+        render_import_picker(f, app);
+    }
+
     if app.show_help {
         render_help_popup(f);
     }
@@ -73,18 +192,43 @@ pub fn render(f: &mut Frame, app: &App) {
     if app.confirm_quit {
         render_confirm_quit(f);
     }
+
+    if app.show_fps {
+        render_fps_overlay(f, app);
+    }
+}
+
+fn render_fps_overlay(f: &mut Frame, app: &App) {
+    let text = format!(" {} fps ", app.frame_counter.fps());
+    let area = f.area();
+    let width = (text.len() as u16).min(area.width);
+    let overlay = Rect::new(area.right().saturating_sub(width + 1), area.y, width, 1);
+    f.render_widget(Clear, overlay);
+    f.render_widget(Paragraph::new(text).style(Style::default().fg(BG).bg(YELLOW)), overlay);
+}
+
+// One line under the input box, so the link can be checked before Enter
+fn render_preview(f: &mut Frame, preview: &VideoPreview, area: Rect) {
+    if area.height < 4 {
+        return;
+    }
+    let line_area = Rect { y: area.y + 3, height: 1, ..area };
+    let mut spans = vec![Span::styled(" ▸ ", Style::default().fg(MUTED)), Span::styled(preview.title.clone(), Style::default().fg(TEXT))];
+    if !preview.channel.is_empty() {
+        spans.push(Span::styled(format!(" · {}", preview.channel), Style::default().fg(MUTED)));
+    }
+    f.render_widget(Paragraph::new(Line::from(spans)), line_area);
 }
 
 fn render_input(f: &mut Frame, app: &App, area: Rect) {
+    let area = Rect { height: area.height.min(3), ..area };
     let input_style = if app.input_mode {
         Style::default().fg(CYAN)
     } else {
         Style::default().fg(MUTED)
     };
 
-    const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
-    
-    let spinner_text = if app.loading_playlists > 0 {
+    let spinner_text = if app.loading_playlists() > 0 {
         let frame = SPINNER_FRAMES[app.spinner_frame % SPINNER_FRAMES.len()];
         format!(" {} parsing playlist...", frame)
     } else {
@@ -109,8 +253,10 @@ fn render_input(f: &mut Frame, app: &App, area: Rect) {
         input_style
     };
 
-    let title = if app.loading_playlists > 0 {
+    let title = if app.loading_playlists() > 0 {
         format!(" Input{} ", spinner_text)
+    } else if app.input_sanitized {
+        " Input · pasted text was cleaned up ".to_string()
     } else {
         " Input ".to_string()
     };
@@ -122,7 +268,7 @@ fn render_input(f: &mut Frame, app: &App, area: Rect) {
                 .borders(Borders::ALL)
                 .border_style(input_style)
                 .title(title)
-                .title_style(if app.loading_playlists > 0 { Style::default().fg(YELLOW) } else { input_style })
+                .title_style(if app.loading_playlists() > 0 || app.input_sanitized { Style::default().fg(YELLOW) } else { input_style })
         );
 
     f.render_widget(input, area);
@@ -133,69 +279,349 @@ fn render_input(f: &mut Frame, app: &App, area: Rect) {
     }
 }
 
+// Below this the size column would leave too little room for titles
+const VERBOSE_MIN_WIDTH: u16 = 60;
+
+// Below this the split queue shows only the focused list
+const SPLIT_MIN_HEIGHT: u16 = 14;
+
 fn render_queue(f: &mut Frame, app: &App, area: Rect) {
+    if !app.split_queue {
+        render_pane(f, app, area, QueuePane::Active, " Download Queue ".into());
+        return;
+    }
+
+    let finished = app.jobs.iter().filter(|j| j.is_finished()).count();
+    let active_title = format!(" Queue ({}) ", app.jobs.len() - finished);
+    let finished_title = format!(" Finished ({}) ", finished);
+    if area.height < SPLIT_MIN_HEIGHT {
+        let title = match app.pane {
+            QueuePane::Active => format!("{}· tab: finished ({}) ", active_title, finished),
+            QueuePane::Finished => format!("{}· tab: queue ({}) ", finished_title, app.jobs.len() - finished),
+        };
+        render_pane(f, app, area, app.pane, title);
+        return;
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+    render_pane(f, app, chunks[0], QueuePane::Active, active_title);
+    render_pane(f, app, chunks[1], QueuePane::Finished, finished_title);
+}
+
+// One list of the queue. Only the focused one shows the cursor and reports its
+// height back for scrolling; the other keeps its place from when it was left
+fn render_pane(f: &mut Frame, app: &App, area: Rect, pane: QueuePane, title: String) {
     let mut items: Vec<ListItem> = Vec::new();
+    let verbose = app.verbose_rows && area.width >= VERBOSE_MIN_WIDTH;
+    let focused = pane == app.pane;
+    let condensed = app.split_queue && pane == QueuePane::Finished;
+
+    let visible_height = area.height.saturating_sub(2) as usize;
+    let (selected_index, queue_offset) = if focused {
+        app.queue_height.set(visible_height);
+        (app.selected_index, app.queue_offset)
+    } else {
+        app.parked_cursor()
+    };
+    let rows = app.pane_rows(pane);
+    // Verbose rows with a note take a second line
+    let row_height = |row: &QueueRow| match row {
+        QueueRow::Job(index) if verbose && !condensed && app.jobs[*index].note.is_some() => 2,
+        _ => 1,
+    };
+    // The window may have shrunk, or rows above the selection grown, since the
+    // offset was last settled
+    let mut offset = queue_offset.max((selected_index + 1).saturating_sub(visible_height));
+    while offset < selected_index
+        && rows[offset..=selected_index.min(rows.len().saturating_sub(1))].iter().map(row_height).sum::<usize>() > visible_height
+    {
+        offset += 1;
+    }
+    let mut lines_left = visible_height;
+
+    for (i, row) in rows.iter().copied().enumerate().skip(offset) {
+        let height = row_height(&row);
+        if height > lines_left {
+            break;
+        }
+        lines_left -= height;
+        let is_selected = focused && i == selected_index && !app.input_mode;
+        let prefix = if is_selected { "> " } else { "  " };
 
-    for (i, job) in app.jobs.iter().enumerate() {
-        let is_selected = i == app.selected_index && !app.input_mode;
+        let job_index = match row {
+            QueueRow::Group(group_id) => {
+                items.push(ListItem::new(group_header_line(app, group_id, prefix, is_selected, area.width)));
+                continue;
+            }
+            QueueRow::Job(index) => index,
+        };
+        let job = &app.jobs[job_index];
+        if condensed {
+            items.push(ListItem::new(finished_line(job, prefix, is_selected, &app.filter_positions(job), area.width)));
+            continue;
+        }
 
+        let live = job.live && matches!(job.status, JobStatus::Ready | JobStatus::Queued | JobStatus::Downloading { .. });
         let (badge, badge_style): (String, Style) = match &job.status {
+            _ if live => ("[LIVE]".into(), Style::default().fg(RED).add_modifier(Modifier::BOLD)),
+            JobStatus::ExpandingPlaylist { found } => (format!("[{} found]", found), Style::default().fg(YELLOW)),
             JobStatus::FetchingFormats => ("[FETCHING]".into(), Style::default().fg(YELLOW)),
-            JobStatus::Ready { .. } => ("[READY]".into(), Style::default().fg(GREEN)),
+            JobStatus::MetadataPending => ("[◷]".into(), Style::default().fg(MUTED)),
+            JobStatus::Ready => ("[READY]".into(), Style::default().fg(GREEN)),
             JobStatus::Queued => ("[QUEUED]".into(), Style::default().fg(CYAN)),
+            JobStatus::Starting => ("[STARTING]".into(), Style::default().fg(CYAN)),
+            JobStatus::WaitingForSlot { .. } => ("[WAITING]".into(), Style::default().fg(MUTED)),
+            JobStatus::Downloading { phase: DownloadPhase::Recoding, .. } => {
+                let frame = SPINNER_FRAMES[app.spinner_frame % SPINNER_FRAMES.len()];
+                (format!("[{} RECODE]", frame), Style::default().fg(YELLOW))
+            }
             JobStatus::Downloading { percent, .. } => {
                 (format!("[{:.0}%]", percent), Style::default().fg(CYAN))
             }
+            JobStatus::Blocked { .. } => ("[EXISTS]".into(), Style::default().fg(YELLOW)),
+            JobStatus::Completed if job.fell_back() => ("[DONE*]".into(), Style::default().fg(YELLOW)),
             JobStatus::Completed => ("[DONE]".into(), Style::default().fg(GREEN)),
-            JobStatus::Failed(_) => ("[FAILED]".into(), Style::default().fg(RED)),
+            JobStatus::Failed(err) if err.kind.is_login_wall() => (err.kind.badge().into(), Style::default().fg(YELLOW)),
+            JobStatus::Failed(err) => (err.kind.badge().into(), Style::default().fg(RED)),
             JobStatus::Cancelled => ("[CANCELLED]".into(), Style::default().fg(MUTED)),
         };
 
-        let prefix = if is_selected { "> " } else { "  " };
+        let indent = if job.group.is_some() { "  " } else { "" };
+        let is_audio = job.media_kind() == MediaKind::Audio;
         let title_style = if is_selected {
             Style::default().fg(YELLOW)
+        } else if is_audio {
+            Style::default().fg(GREEN)
         } else {
             Style::default().fg(TEXT)
         };
 
-        let display_name = job.display_name();
-        let max_len = (area.width as usize).saturating_sub(badge.len() + 5);
-        let truncated: String = if display_name.len() > max_len {
-            format!("{}...", &display_name[..max_len.saturating_sub(3)])
+        let mut display_name: Cow<str> = if matches!(job.status, JobStatus::ExpandingPlaylist { .. }) {
+            Cow::Owned(format!("⟳ Loading playlist… {}", job.url))
+        } else if is_audio {
+            Cow::Owned(format!("♪ {}", job.display_name()))
         } else {
-            display_name.into()
+            Cow::Borrowed(job.display_name())
+        };
+        if job.sections.is_some() {
+            display_name = Cow::Owned(format!("✂ {}", display_name));
+        }
+        if app.dependency_mark == Some(job.id) {
+            display_name = Cow::Owned(format!("◆ {}", display_name));
+        }
+        if app.name_clashes.contains_key(&job.id) {
+            display_name = Cow::Owned(format!("{} ⚠ name clash", display_name));
+        }
+        if job.default_format && job.selected_format.is_none() {
+            display_name = Cow::Owned(format!("{} (default format)", display_name));
+        }
+        // The filter matched the bare title; the marks above shift it along
+        let marks = match display_name.find(job.display_name()) {
+            Some(at) if !matches!(job.status, JobStatus::ExpandingPlaylist { .. }) => {
+                let shift = display_name[..at].chars().count();
+                app.filter_positions(job).into_iter().map(|i| i + shift).collect()
+            }
+            _ => Vec::new(),
         };
 
-        let line = Line::from(vec![
-            Span::styled(prefix, title_style),
-            Span::styled(truncated, title_style),
-            Span::raw(" "),
-            Span::styled(badge, badge_style),
-        ]);
-        items.push(ListItem::new(line));
+        if verbose {
+            if let Some(position) = job.after.and_then(|id| app.jobs.iter().position(|j| j.id == id)) {
+                display_name = Cow::Owned(format!("{} ↳ after #{}", display_name, position + 1));
+            }
+            let width = area.width.saturating_sub(2) as usize;
+            let mut lines = vec![verbose_row(
+                [prefix, indent],
+                (&display_name, &marks),
+                (badge, badge_style),
+                size_column(job),
+                title_style,
+                width,
+            )];
+            if let Some(note) = &job.note {
+                let lead = format!("  {}", indent);
+                let note = truncate_chars(note, width.saturating_sub(lead.len()));
+                lines.push(Line::from(vec![Span::raw(lead), Span::styled(note, Style::default().fg(MUTED))]));
+            }
+            items.push(ListItem::new(lines));
+            continue;
+        }
+
+        let max_len = (area.width as usize).saturating_sub(badge.len() + indent.len() + 5);
+
+        let mut spans = vec![Span::styled(prefix, title_style), Span::raw(indent)];
+        spans.extend(highlighted(&display_name, max_len, &marks, title_style));
+        spans.extend([Span::raw(" "), Span::styled(badge, badge_style)]);
+        items.push(ListItem::new(Line::from(spans)));
+    }
+    if rows.is_empty() && app.filter_query().is_some() {
+        items.push(ListItem::new(Line::from(Span::styled("  No titles match the filter", Style::default().fg(MUTED)))));
     }
 
+    let color = if focused { CYAN } else { MUTED };
+    let title = match &app.queue_filter {
+        Some(filter) if focused => {
+            let cursor = if filter.editing { "▏" } else { "" };
+            format!("{}· filter: {}{} ({}) ", title, filter.query, cursor, rows.len())
+        }
+        _ => title,
+    };
     let queue = List::new(items).block(
         Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(CYAN))
-            .title(" Download Queue ")
-            .title_style(Style::default().fg(CYAN)),
+            .border_style(Style::default().fg(color))
+            .title(title)
+            .title_style(Style::default().fg(color)),
     );
 
     f.render_widget(queue, area);
 }
 
+// A finished item in the split layout: a mark, the title, and why it failed
+fn finished_line(job: &Job, prefix: &'static str, is_selected: bool, marks: &[usize], width: u16) -> Line<'static> {
+    let (mark, mark_style) = match &job.status {
+        JobStatus::Completed => ("✓", Style::default().fg(GREEN)),
+        JobStatus::Failed(_) => ("✗", Style::default().fg(RED)),
+        _ => ("–", Style::default().fg(MUTED)),
+    };
+    let reason = match &job.status {
+        JobStatus::Failed(err) => format!(" {}", err.kind.badge()),
+        _ => String::new(),
+    };
+    let title_style = if is_selected {
+        Style::default().fg(YELLOW)
+    } else if job.status == JobStatus::Completed {
+        Style::default().fg(TEXT)
+    } else {
+        Style::default().fg(MUTED)
+    };
+    let max_len = (width as usize).saturating_sub(reason.chars().count() + 6);
+    let mut spans = vec![Span::styled(prefix, title_style), Span::styled(format!("{} ", mark), mark_style)];
+    spans.extend(highlighted(job.display_name(), max_len, marks, title_style));
+    spans.push(Span::styled(reason, Style::default().fg(RED)));
+    Line::from(spans)
+}
+
+fn size_column(job: &Job) -> Option<String> {
+    match &job.status {
+        JobStatus::Completed => job.output_size.map(format::size),
+        JobStatus::Downloading { percent, total: Some(total), .. } => {
+            let done = (*total as f64 * (*percent as f64 / 100.0)) as u64;
+            Some(format!("{}/{}", format::size(done), format::size(*total)))
+        }
+        _ => None,
+    }
+}
+
+// Title, badge and a right-aligned size column laid out to exactly `width`
+// cells, shortening the title first
+fn verbose_row<'a>(
+    lead: [&'a str; 2],
+    (title, marks): (&str, &[usize]),
+    (badge, badge_style): (String, Style),
+    size: Option<String>,
+    title_style: Style,
+    width: usize,
+) -> Line<'a> {
+    let size = size.unwrap_or_default();
+    let lead_len: usize = lead.iter().map(|s| s.chars().count()).sum();
+    let fixed = lead_len + 1 + badge.chars().count() + 1 + size.chars().count();
+    let title = highlighted(title, width.saturating_sub(fixed), marks, title_style);
+    let used = fixed + title.iter().map(|span| span.content.chars().count()).sum::<usize>();
+    let padding = width.saturating_sub(used) + 1;
+
+    let mut spans = vec![Span::styled(lead[0], title_style), Span::raw(lead[1])];
+    spans.extend(title);
+    spans.extend([
+        Span::raw(" "),
+        Span::styled(badge, badge_style),
+        Span::raw(" ".repeat(padding)),
+        Span::styled(size, Style::default().fg(MUTED)),
+    ]);
+    Line::from(spans)
+}
+
+// `text` shortened like truncate_chars, with the characters at `marks` (char
+// indices into the full text, ascending) picked out. Marks that fall in the cut
+// part are dropped rather than landing on the ellipsis
+fn highlighted(text: &str, max: usize, marks: &[usize], style: Style) -> Vec<Span<'static>> {
+    let count = text.chars().count();
+    let (kept, ellipsis) = if count <= max { (count, "") } else { (max.saturating_sub(3), "...") };
+    let mark_style = style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_marked = false;
+    for (i, c) in text.chars().take(kept).enumerate() {
+        let marked = marks.binary_search(&i).is_ok();
+        if marked != run_marked && !run.is_empty() {
+            spans.push(Span::styled(std::mem::take(&mut run), if run_marked { mark_style } else { style }));
+        }
+        run_marked = marked;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(run, if run_marked { mark_style } else { style }));
+    }
+    if !ellipsis.is_empty() {
+        spans.push(Span::styled(ellipsis, style));
+    }
+    spans
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max.saturating_sub(3)).collect();
+    format!("{}...", kept)
+}
+
+fn group_header_line<'a>(app: &'a App, group_id: GroupId, prefix: &'a str, is_selected: bool, width: u16) -> Line<'a> {
+    let title = app.group(group_id).map_or("Playlist", |g| g.title.as_str());
+    let total = app.group_jobs(group_id).count();
+    let done = app.group_jobs(group_id).filter(|j| j.status == JobStatus::Completed).count();
+    let counter = format!("[{}/{}]", done, total);
+
+    let max_len = (width as usize).saturating_sub(counter.len() + 7);
+    let truncated: String = if title.len() > max_len {
+        format!("{}...", &title[..max_len.saturating_sub(3)])
+    } else {
+        title.into()
+    };
+
+    let style = if is_selected {
+        Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(CYAN).add_modifier(Modifier::BOLD)
+    };
+
+    Line::from(vec![
+        Span::styled(prefix, style),
+        Span::styled("▾ ", style),
+        Span::styled(truncated, style),
+        Span::raw(" "),
+        Span::styled(counter, Style::default().fg(MUTED)),
+    ])
+}
+
 fn render_details(f: &mut Frame, app: &App, area: Rect) {
     let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(CYAN))
-        .title(" Download Details ")
+        .title(if app.show_timeline { " Timeline (L details) " } else { " Download Details (L timeline) " })
         .title_style(Style::default().fg(CYAN));
 
     let inner = block.inner(area);
     f.render_widget(block, area);
 
+    if let Some(QueueRow::Group(group_id)) = app.selected_row() {
+        render_group_details(f, app, group_id, inner);
+        return;
+    }
+
     let Some(job) = app.selected_job() else {
         let empty = Paragraph::new("Select an item from the queue")
             .style(Style::default().fg(MUTED));
@@ -205,11 +631,39 @@ fn render_details(f: &mut Frame, app: &App, area: Rect) {
 
     let mut lines: Vec<Line> = Vec::new();
 
+    let position = app.selected_job_index().map_or(0, |i| i + 1);
+    let now = chrono::Local::now();
+    // Past a day a plain date reads better than "12d ago"
+    let added = if (now - job.added_at).num_days() >= 1 {
+        format!("on {}", format::date(job.added_at, &app.config.date_format))
+    } else {
+        format::age(job.added_at, now)
+    };
+    lines.push(Line::from(Span::styled(
+        format!("Item {} of {} · {} · added {}", position, app.jobs.len(), job.media_kind().label(), added),
+        Style::default().fg(MUTED),
+    )));
+
     let title = job.title.as_deref().unwrap_or(&job.url);
     let title_wrapped = textwrap_simple(title, inner.width as usize - 2);
     for line in title_wrapped {
         lines.push(Line::from(Span::styled(line, Style::default().fg(YELLOW))));
     }
+    if let Some(note) = &job.note {
+        for line in textwrap_simple(note, inner.width as usize - 2) {
+            lines.push(Line::from(Span::styled(line, Style::default().fg(MUTED).add_modifier(Modifier::ITALIC))));
+        }
+    }
+    if let Some((start, end)) = &job.sections {
+        lines.push(Line::from(Span::styled(format!("✂ Only {} – {}", start, end), Style::default().fg(CYAN))));
+    }
+    if let Some(list) = &job.playlist_skipped {
+        lines.push(Line::from(Span::styled(format!("Just this video, not its playlist {}", list), Style::default().fg(MUTED))));
+    }
+    if let Some((container, conversion)) = job.conversion() {
+        let how = if conversion == Conversion::Recode { "recoded, slow" } else { "remuxed" };
+        lines.push(Line::from(Span::styled(format!("Container: {} ({})", container.label().to_uppercase(), how), Style::default().fg(MUTED))));
+    }
     lines.push(Line::from(""));
 
     if job.title.is_some() {
@@ -223,17 +677,125 @@ fn render_details(f: &mut Frame, app: &App, area: Rect) {
         lines.push(Line::from(""));
     }
 
+    if app.show_timeline {
+        for line in job.timeline_lines() {
+            lines.push(Line::from(Span::styled(line, Style::default().fg(TEXT))));
+        }
+        if job.timeline.is_empty() {
+            lines.push(Line::from(Span::styled("Nothing recorded yet", Style::default().fg(MUTED))));
+        }
+        // Newest last, so keep the tail when it doesn't all fit
+        let skip = lines.len().saturating_sub(inner.height as usize);
+        f.render_widget(Paragraph::new(lines.split_off(skip)), inner);
+        return;
+    }
+
+    let facts: Vec<String> = [
+        job.metadata.duration.map(format::duration),
+        job.metadata.view_count.map(|n| format!("{} views", format::count(n))),
+        job.metadata.upload_date.as_deref().and_then(format::upload_date).map(|d| format!("uploaded {}", d)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if !facts.is_empty() {
+        lines.push(Line::from(Span::styled(facts.join(" · "), Style::default().fg(MUTED))));
+        lines.push(Line::from(""));
+    }
+
+    // The snapshot taken when the job first started, not the current settings
+    if let Some(target) = &job.output_target {
+        let label = if *target == OutputTarget::from_config(&app.config) {
+            "Saving to:"
+        } else {
+            "Saving to (settings changed since start):"
+        };
+        lines.push(Line::from(Span::styled(label, Style::default().fg(MUTED))));
+        let path = job.preview_path(&target.path_template().to_string_lossy(), &app.config);
+        for line in textwrap_simple(&path, inner.width as usize - 2) {
+            lines.push(Line::from(Span::styled(line, Style::default().fg(TEXT))));
+        }
+        lines.push(Line::from(""));
+    } else if matches!(job.status, JobStatus::Ready | JobStatus::Queued) {
+        if let Some(earlier) = app.name_clashes.get(&job.id).and_then(|id| app.jobs.iter().position(|j| j.id == *id)) {
+            lines.push(Line::from(Span::styled(
+                format!("⚠ Same file name as item {}; n to resolve", earlier + 1),
+                Style::default().fg(YELLOW),
+            )));
+        }
+        let label = if job.output_dir.is_some() || job.output_template.is_some() {
+            "Will save to (this item only, o to change):"
+        } else {
+            "Will save to (o to change):"
+        };
+        lines.push(Line::from(Span::styled(label, Style::default().fg(MUTED))));
+        let target = job.planned_target(&app.config);
+        let path = job.preview_path(&target.path_template().to_string_lossy(), &app.config);
+        for line in textwrap_simple(&path, inner.width as usize - 2) {
+            lines.push(Line::from(Span::styled(line, Style::default().fg(TEXT))));
+        }
+        lines.push(Line::from(""));
+    }
+
+    if let Some((position, other)) = job.linked_to.and_then(|id| app.jobs.iter().enumerate().find(|(_, j)| j.id == id)) {
+        lines.push(Line::from(Span::styled(
+            format!("⇄ Same video as item {} ({})", position + 1, other.display_name()),
+            Style::default().fg(CYAN),
+        )));
+        lines.push(Line::from(Span::styled("Shares its yt-dlp run instead of starting another", Style::default().fg(MUTED))));
+        lines.push(Line::from(""));
+    }
+
+    if job.from_channel && job.can_select_format() {
+        if let Some(fmt) = &job.selected_format {
+            let channel = job.metadata.channel.as_deref().unwrap_or("this channel");
+            let choice = if job.extract_audio || fmt.is_audio_only() {
+                "audio".to_string()
+            } else {
+                format!("{} {}", fmt.display_resolution(), fmt.ext)
+            };
+            lines.push(Line::from(Span::styled(format!("Remembered from channel {}: {}", channel, choice), Style::default().fg(CYAN))));
+            if job.status == JobStatus::Ready {
+                lines.push(Line::from(Span::styled("Enter opens it pre-selected to confirm or change", Style::default().fg(MUTED))));
+            }
+            lines.push(Line::from(""));
+        }
+    }
+
     match &job.status {
+        JobStatus::ExpandingPlaylist { found } => {
+            let elapsed = (chrono::Local::now() - job.added_at).num_seconds().max(0);
+            let loading = match &job.playlist_items {
+                Some(items) => format!("Loading playlist items {}...", items),
+                None => "Loading playlist...".to_string(),
+            };
+            lines.push(Line::from(Span::styled(loading, Style::default().fg(YELLOW))));
+            lines.push(Line::from(Span::styled(
+                format!("{} videos found · {} elapsed", found, format::duration(elapsed as f64)),
+                Style::default().fg(MUTED),
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("Press 'c' to stop loading", Style::default().fg(MUTED))));
+        }
         JobStatus::FetchingFormats => {
             lines.push(Line::from(Span::styled("Fetching formats...", Style::default().fg(YELLOW))));
         }
-        JobStatus::Ready { formats } => {
+        JobStatus::MetadataPending => {
+            lines.push(Line::from(Span::styled("Waiting for formats and details", Style::default().fg(MUTED))));
+            lines.push(Line::from(Span::styled(
+                "Playlist entries are fetched a few at a time; the queue is usable meanwhile",
+                Style::default().fg(MUTED),
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("Press Enter to fetch this one next", Style::default().fg(MUTED))));
+        }
+        JobStatus::Ready => {
             lines.push(Line::from(Span::styled("Formats Available:", Style::default().fg(MUTED))));
             lines.push(Line::from(Span::styled("─".repeat(inner.width as usize - 2), Style::default().fg(MUTED))));
             
             let mut video_count = 0;
             let mut audio_count = 0;
-            for fmt in formats.iter() {
+            for fmt in job.formats.iter() {
                 if video_count < 3 && fmt.is_video() {
                     let info = format!(
                         "▶ {} · {} · {}",
@@ -258,6 +820,12 @@ fn render_details(f: &mut Frame, app: &App, area: Rect) {
             lines.push(Line::from(""));
             lines.push(Line::from(Span::styled("Press Enter to select format", Style::default().fg(MUTED))));
         }
+        JobStatus::Queued if job.selected_format.is_none() && job.default_format => {
+            lines.push(Line::from(Span::styled("Selected Format: (default format)", Style::default().fg(MUTED))));
+            let spec = job.start_request(&app.config).map(|r| r.format_spec).unwrap_or_default();
+            lines.push(Line::from(Span::styled(format!("▶ {}", spec), Style::default().fg(CYAN))));
+            lines.push(Line::from(Span::styled("default_quality in the config; Enter picks one of its own", Style::default().fg(MUTED))));
+        }
         JobStatus::Queued => {
             if let Some(fmt) = &job.selected_format {
                 lines.push(Line::from(Span::styled("Selected Format:", Style::default().fg(MUTED))));
@@ -268,12 +836,49 @@ fn render_details(f: &mut Frame, app: &App, area: Rect) {
                     fmt.display_bitrate()
                 );
                 lines.push(Line::from(Span::styled(info, Style::default().fg(CYAN))));
+                if job.extract_audio {
+                    lines.push(Line::from(Span::styled(
+                        format!("♪ Extract audio ({} {})", app.config.audio_format, job.audio_quality(&app.config)),
+                        Style::default().fg(GREEN),
+                    )));
+                    if let (Some(source), Some(audio)) = (job.source_size(), job.estimated_audio_size(&app.config)) {
+                        lines.push(Line::from(Span::styled(
+                            format!("source {} → ≈ {} {}", format::size(source), format::size(audio), app.config.audio_format),
+                            Style::default().fg(MUTED),
+                        )));
+                    }
+                }
+                if let Some(reason) = app.start_diagnosis(job) {
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(Span::styled(reason, Style::default().fg(YELLOW))));
+                }
                 lines.push(Line::from(""));
                 lines.push(Line::from(Span::styled("Press 's' to start download", Style::default().fg(MUTED))));
                 lines.push(Line::from(Span::styled("Press Enter to change format", Style::default().fg(MUTED))));
+                lines.push(Line::from(Span::styled("Press '!' to show the yt-dlp command", Style::default().fg(MUTED))));
             }
         }
-        JobStatus::Downloading { percent, speed, eta, .. } => {
+        // Nothing to measure a live recording against, so it shows how long it has run
+        JobStatus::Downloading { speed, speed_bps, .. } if job.live => {
+            lines.push(Line::from(Span::styled("● Recording live", Style::default().fg(RED).add_modifier(Modifier::BOLD))));
+            lines.push(Line::from(Span::styled(
+                format!("{} recorded · {}", format::duration(job.speed.active_secs), format::speed(*speed_bps, speed)),
+                Style::default().fg(TEXT),
+            )));
+            let from = if job.live_from_start { "from the start of the stream" } else { "from when it started" };
+            lines.push(Line::from(Span::styled(format!("Recording {}", from), Style::default().fg(MUTED))));
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("Press 'c' to stop and keep the recording", Style::default().fg(MUTED))));
+        }
+        // ffmpeg prints no progress through yt-dlp, so a full bar would look stuck
+        JobStatus::Downloading { phase: DownloadPhase::Recoding, .. } => {
+            let frame = SPINNER_FRAMES[app.spinner_frame % SPINNER_FRAMES.len()];
+            let target = job.container.label().to_uppercase();
+            lines.push(Line::from(Span::styled(format!("{} Recoding to {}…", frame, target), Style::default().fg(YELLOW))));
+            lines.push(Line::from(Span::styled("Downloaded; ffmpeg is re-encoding the streams", Style::default().fg(TEXT))));
+            lines.push(Line::from(Span::styled("This can take longer than the download itself", Style::default().fg(MUTED))));
+        }
+        JobStatus::Downloading { percent, speed, speed_bps, eta, .. } => {
             lines.push(Line::from(Span::styled("Downloading...", Style::default().fg(CYAN))));
             
             let bar_width = (inner.width as usize).saturating_sub(2);
@@ -281,7 +886,7 @@ fn render_details(f: &mut Frame, app: &App, area: Rect) {
             lines.push(Line::from(Span::styled(bar, Style::default().fg(CYAN))));
             
             lines.push(Line::from(Span::styled(
-                format!("{:.1}% · {} · ETA {}", percent, speed, eta),
+                format!("{:.1}% · {} · ETA {}", percent, format::speed(*speed_bps, speed), eta),
                 Style::default().fg(TEXT)
             )));
         }
@@ -294,16 +899,148 @@ fn render_details(f: &mut Frame, app: &App, area: Rect) {
                     Style::default().fg(MUTED)
                 )));
             }
+            if let Some(speed) = job.speed.summary() {
+                lines.push(Line::from(Span::styled(speed, Style::default().fg(TEXT))));
+            }
+            if let Some(image) = &job.thumbnail_path {
+                lines.push(Line::from(Span::styled(
+                    format!("Thumbnail: {}", image.to_string_lossy()),
+                    Style::default().fg(MUTED),
+                )));
+            }
+            for file in &job.sidecars {
+                lines.push(Line::from(Span::styled(
+                    format!("Also: {}", file.to_string_lossy()),
+                    Style::default().fg(MUTED),
+                )));
+            }
+            if let Some(issue) = &job.thumbnail_issue {
+                lines.push(Line::from(Span::styled(
+                    format!("! Thumbnail not embedded: {}", issue),
+                    Style::default().fg(YELLOW),
+                )));
+            }
+            if let Some(record) = &job.record {
+                lines.push(Line::from(""));
+                lines.push(Line::from(vec![
+                    Span::styled("Format:  ", Style::default().fg(MUTED)),
+                    Span::styled(record.format_spec.as_str(), Style::default().fg(TEXT)),
+                ]));
+                for option in &record.options {
+                    lines.push(Line::from(vec![
+                        Span::styled("Option:  ", Style::default().fg(MUTED)),
+                        Span::styled(option.as_str(), Style::default().fg(TEXT)),
+                    ]));
+                }
+                lines.push(Line::from(vec![
+                    Span::styled("yt-dlp:  ", Style::default().fg(MUTED)),
+                    Span::styled(record.ytdlp_version.as_str(), Style::default().fg(TEXT)),
+                ]));
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled("Press 'Y' to copy as yt-dlp command", Style::default().fg(MUTED))));
+            }
         }
         JobStatus::Failed(err) => {
-            lines.push(Line::from(Span::styled("✗ Download Failed", Style::default().fg(RED))));
-            let err_wrapped = textwrap_simple(err, inner.width as usize - 2);
+            lines.push(Line::from(Span::styled(format!("✗ Download Failed: {}", err.kind.label()), Style::default().fg(RED))));
+            let err_wrapped = textwrap_simple(&err.message, inner.width as usize - 2);
             for line in err_wrapped.into_iter().take(3) {
                 lines.push(Line::from(Span::styled(line, Style::default().fg(RED))));
             }
+            if err.kind == DownloadError::MembersOnly {
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(
+                    "Members-only: needs cookies from an account subscribed to this channel",
+                    Style::default().fg(YELLOW),
+                )));
+                lines.push(Line::from(Span::styled("Press 'r' to choose a browser to take cookies from", Style::default().fg(MUTED))));
+                let count = app.members_only_jobs().count();
+                if app.config.cookies_from_browser.is_some() && count > 0 {
+                    lines.push(Line::from(Span::styled(
+                        format!("Press 'M' to retry all {} members-only item(s)", count),
+                        Style::default().fg(MUTED),
+                    )));
+                }
+            } else if err.kind.is_login_wall() {
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled("Press 'r' for sign-in options", Style::default().fg(MUTED))));
+            } else if let (DownloadError::GeoBlocked, Some(country)) = (err.kind, &app.config.geo_bypass_country) {
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(
+                    format!("Still blocked posing as {}; try another geo_bypass_country", country.to_uppercase()),
+                    Style::default().fg(YELLOW),
+                )));
+            } else if let Some(hint) = err.kind.hint() {
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(hint, Style::default().fg(YELLOW))));
+            }
+            if err.kind.is_transient() {
+                lines.push(Line::from(Span::styled("Press 'r' to retry", Style::default().fg(MUTED))));
+            }
+            if worker::wants_cookies(&err.message) && app.config.cookies_file.is_none() {
+                lines.push(Line::from(Span::styled("Hint: configure a cookies file in settings (g)", Style::default().fg(YELLOW))));
+            }
+            lines.push(Line::from(Span::styled("Press 'E' to copy an error report, 'e' to diagnose", Style::default().fg(MUTED))));
+        }
+        JobStatus::Starting => {
+            lines.push(Line::from(Span::styled("Starting download...", Style::default().fg(CYAN))));
+            if let Some(reason) = app.start_diagnosis(job) {
+                lines.push(Line::from(Span::styled(reason, Style::default().fg(MUTED))));
+            }
+        }
+        JobStatus::WaitingForSlot { position } => {
+            let reason = app.start_diagnosis(job).unwrap_or_default();
+            lines.push(Line::from(Span::styled(reason, Style::default().fg(CYAN))));
+            lines.push(Line::from(Span::styled(format!("{} in line", ordinal(*position)), Style::default().fg(MUTED))));
+        }
+        JobStatus::Blocked { path } => {
+            lines.push(Line::from(Span::styled("File already exists:", Style::default().fg(YELLOW))));
+            for line in textwrap_simple(&path.display().to_string(), inner.width as usize - 2) {
+                lines.push(Line::from(Span::styled(line, Style::default().fg(TEXT))));
+            }
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("Press Enter to choose what to do", Style::default().fg(MUTED))));
         }
         JobStatus::Cancelled => {
-            lines.push(Line::from(Span::styled("Download Cancelled", Style::default().fg(MUTED))));
+            match &job.partial {
+                Some(partial) => {
+                    let mut text = format!("Cancelled at {:.0}%", partial.percent);
+                    if let Some(bytes) = partial.downloaded {
+                        text.push_str(&format!(" ({} downloaded)", format::size(bytes)));
+                    }
+                    lines.push(Line::from(Span::styled(text, Style::default().fg(MUTED))));
+                }
+                None => lines.push(Line::from(Span::styled("Download Cancelled", Style::default().fg(MUTED)))),
+            }
+            if job.has_format() {
+                lines.push(Line::from(""));
+                if job.partial.is_some() {
+                    lines.push(Line::from(Span::styled("Press 'r' to resume where it stopped", Style::default().fg(MUTED))));
+                    lines.push(Line::from(Span::styled("Press 'R' to start over", Style::default().fg(MUTED))));
+                } else {
+                    lines.push(Line::from(Span::styled("Press 'r' to start it again", Style::default().fg(MUTED))));
+                }
+            }
+        }
+    }
+
+    let shows_warnings = matches!(job.status, JobStatus::Downloading { .. } | JobStatus::Completed);
+    if shows_warnings && !job.warnings.is_empty() {
+        lines.push(Line::from(""));
+        let count = job.warnings.len();
+        let toggle = if app.show_warnings { "w hide" } else { "w show" };
+        lines.push(Line::from(vec![
+            Span::styled(format!("⚠ {} warning{}", count, if count == 1 { "" } else { "s" }), Style::default().fg(YELLOW)),
+            Span::styled(format!("  ({})", toggle), Style::default().fg(MUTED)),
+        ]));
+        if job.fell_back() {
+            lines.push(Line::from(Span::styled("May not be the format that was selected", Style::default().fg(YELLOW))));
+        }
+        if app.show_warnings {
+            for warning in &job.warnings {
+                for line in textwrap_simple(warning, inner.width as usize - 2) {
+                    lines.push(Line::from(Span::styled(line, Style::default().fg(MUTED))));
+                }
+            }
         }
     }
 
@@ -311,6 +1048,45 @@ fn render_details(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(details, inner);
 }
 
+// The output path with what is already known about the job filled in
+fn render_group_details(f: &mut Frame, app: &App, group_id: GroupId, area: Rect) {
+    let Some(group) = app.group(group_id) else { return };
+
+    let mut lines: Vec<Line> = Vec::new();
+    for line in textwrap_simple(&group.title, (area.width as usize).saturating_sub(2)) {
+        lines.push(Line::from(Span::styled(line, Style::default().fg(YELLOW))));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("URL:", Style::default().fg(MUTED))));
+    lines.push(Line::from(Span::styled(group.url.as_str(), Style::default().fg(TEXT))));
+    lines.push(Line::from(""));
+
+    let count = |pred: fn(&JobStatus) -> bool| app.group_jobs(group_id).filter(|j| pred(&j.status)).count();
+    let summary = [
+        ("Ready", count(|s| *s == JobStatus::Ready), GREEN),
+        ("Queued", count(|s| *s == JobStatus::Queued), CYAN),
+        ("Downloading", count(|s| matches!(s, JobStatus::Downloading { .. })), CYAN),
+        ("Completed", count(|s| *s == JobStatus::Completed), GREEN),
+        ("Failed", count(|s| matches!(s, JobStatus::Failed(_))), RED),
+    ];
+    lines.push(Line::from(Span::styled(
+        format!("{} items", app.group_jobs(group_id).count()),
+        Style::default().fg(TEXT),
+    )));
+    for (label, n, color) in summary {
+        if n > 0 {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<12}", label), Style::default().fg(MUTED)),
+                Span::styled(n.to_string(), Style::default().fg(color)),
+            ]));
+        }
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Press Enter for playlist actions", Style::default().fg(MUTED))));
+
+    f.render_widget(Paragraph::new(lines), area);
+}
+
 fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
     let counts = app.status_counts();
 
@@ -321,181 +1097,1386 @@ fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
         Span::styled("  ", Style::default()),
     ];
     
-    if app.loading_playlists > 0 {
+    if app.loading_playlists() > 0 {
         spans.push(Span::styled("⟳ parsing ", Style::default().fg(YELLOW)));
     }
     
+    if let Some((limit, max)) = app.throttled_limit {
+        spans.push(Span::styled(
+            format!("⇣{}/{} reduced due to throttling ", limit, max),
+            Style::default().fg(YELLOW),
+        ));
+    }
+    
     if counts.fetching > 0 {
         spans.push(Span::styled(format!("↻{} ", counts.fetching), Style::default().fg(YELLOW)));
     }
     if counts.ready > 0 {
         spans.push(Span::styled(format!("●{} ", counts.ready), Style::default().fg(GREEN)));
     }
-    if counts.queued > 0 {
-        spans.push(Span::styled(format!("◇{} ", counts.queued), Style::default().fg(CYAN)));
+    if counts.queued > 0 {
+        spans.push(Span::styled(format!("◇{} ", counts.queued), Style::default().fg(CYAN)));
+    }
+    if counts.active > 0 {
+        spans.push(Span::styled(format!("▼{} ", counts.active), Style::default().fg(CYAN).add_modifier(Modifier::BOLD)));
+    }
+    if counts.completed > 0 {
+        spans.push(Span::styled(format!("✓{} ", counts.completed), Style::default().fg(GREEN)));
+    }
+    if counts.failed > 0 {
+        spans.push(Span::styled(format!("✗{}", counts.failed), Style::default().fg(RED)));
+    }
+    if let Some(policy) = app.conflict_always {
+        spans.push(Span::styled(
+            format!(" existing files: always {}", policy.label().to_lowercase()),
+            Style::default().fg(YELLOW),
+        ));
+    }
+    if app.queue_halted.is_some() {
+        spans.push(Span::styled(" ⏸ queue paused (P)", Style::default().fg(RED).add_modifier(Modifier::BOLD)));
+    } else if let Some(reason) = &app.power_paused {
+        spans.push(Span::styled(format!(" ⏸ paused: {} (P resumes)", reason), Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)));
+    }
+    // Shown from 90% full, so a runaway import is noticed before links are dropped
+    if let Some(max) = app.config.max_queue_size().filter(|max| app.jobs.len() * 10 >= max * 9) {
+        let color = if app.jobs.len() >= max { RED } else { YELLOW };
+        spans.push(Span::styled(format!(" queue {}/{}", app.jobs.len(), max), Style::default().fg(color)));
+    }
+    if let Some(cap) = app.config.rate_limit_bytes() {
+        spans.push(Span::styled(format!(" ⇣{}", format::rate(cap)), Style::default().fg(MUTED)));
+    }
+    if app.config.notifications && app.config.do_not_disturb {
+        spans.push(Span::styled(" DND", Style::default().fg(MUTED)));
+    }
+    if app.verbose_rows && counts.completed > 0 {
+        spans.push(Span::styled(format!(" Σ {}", format::size(app.completed_size())), Style::default().fg(MUTED)));
+    }
+    
+    spans.push(Span::styled("  │  ", Style::default().fg(MUTED)));
+
+    if let Some(notice) = &app.notice {
+        let color = match notice.level {
+            NoticeLevel::Info => TEXT,
+            NoticeLevel::Error => RED,
+        };
+        spans.push(Span::styled(notice.text.as_str(), Style::default().fg(color)));
+        spans.push(Span::styled("  │  ", Style::default().fg(MUTED)));
+    }
+
+    // Whatever room the counters and notice leave, inside the borders
+    let used: usize = spans.iter().map(|s| s.width()).sum();
+    let room = (area.width as usize).saturating_sub(2 + used);
+    for (i, hint) in hints::fit(hints::select(&hints::AppContext::from_app(app)), room).into_iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw("  "));
+        }
+        spans.push(Span::styled(hint.key, Style::default().fg(CYAN)));
+        spans.push(Span::styled(format!(" {}", hint.label), Style::default().fg(MUTED)));
+    }
+
+    let status = Line::from(spans);
+
+    let bar = Paragraph::new(status).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(MUTED)),
+    );
+
+    f.render_widget(bar, area);
+}
+
+fn render_format_popup(f: &mut Frame, app: &App) {
+    let Some(popup) = &app.format_popup else { return };
+    let Some(job) = app.jobs.get(popup.job_index) else { return };
+
+    let area = centered_rect(60, 65, f.area());
+    f.render_widget(Clear, area);
+
+    let block = popup_block(if popup.group.is_some() { " Select Format for Playlist " } else { " Select Format " });
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2), 
+            Constraint::Length(3), 
+            Constraint::Min(5),    
+            Constraint::Length(1),
+            Constraint::Length(2), 
+        ])
+        .split(inner);
+
+    let title = Paragraph::new(job.display_name())
+        .style(Style::default().fg(YELLOW).bg(BG));
+    f.render_widget(title, chunks[0]);
+
+    let in_audio = popup.selected_row().is_some_and(|r| r.tier() == QualityTier::Audio);
+    let video_style = if !in_audio {
+        Style::default().fg(CYAN).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(MUTED)
+    };
+    let audio_style = if in_audio {
+        Style::default().fg(GREEN).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(MUTED)
+    };
+    let apply_style = if popup.apply_to_all {
+        Style::default().fg(GREEN).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().fg(MUTED)
+    };
+
+    let apply_text = if popup.apply_to_all {
+        "[✓ Apply All] (will apply to all ready items)"
+    } else {
+        "[ Apply All] (press A to apply to all ready items)"
+    };
+
+    let mut tabs = vec![
+        Span::styled("[Video]", video_style),
+        Span::raw(" "),
+        Span::styled("[Audio]", audio_style),
+    ];
+    if job.live {
+        let (text, style) = if popup.live_from_start {
+            ("[✓ From start] (f to record from now)", Style::default().fg(RED).add_modifier(Modifier::BOLD))
+        } else {
+            ("[ From now] (f to record from the start)", Style::default().fg(RED))
+        };
+        tabs.extend([Span::raw("   "), Span::styled(text, style)]);
+    }
+    let toggles = Paragraph::new(vec![
+        Line::from(tabs),
+        Line::from(Span::styled(apply_text, apply_style)),
+        audio_line(app, popup, job),
+    ]).style(Style::default().bg(BG));
+    f.render_widget(toggles, chunks[1]);
+
+    let rows = popup.rows();
+    let visible_height = chunks[2].height as usize;
+    popup.viewport_height.set(visible_height);
+    let scroll_offset = popup.scroll_offset.min(rows.len().saturating_sub(visible_height));
+
+    let mut format_items: Vec<ListItem> = Vec::new();
+    for (i, row) in rows.iter().enumerate().skip(scroll_offset).take(visible_height) {
+        let is_sel = i == popup.selected;
+        let prefix = if is_sel { "▶ " } else { "  " };
+        let fmt = &popup.formats[row.format_index()];
+
+        let spans = match *row {
+            FormatRow::Tier { tier, count, expanded, .. } => {
+                let style = if is_sel {
+                    Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+                } else if tier == QualityTier::Audio {
+                    Style::default().fg(GREEN).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(CYAN).add_modifier(Modifier::BOLD)
+                };
+                let marker = if expanded { "▾" } else { "▸" };
+                let more = match count {
+                    1 => String::new(),
+                    n => format!("  +{} more", n - 1),
+                };
+                vec![
+                    Span::styled(prefix, style),
+                    Span::styled(format!("{} {:<6}", marker, tier.label()), style),
+                    Span::styled(
+                        format!(
+                            "{:<5} {} · {} · {} · {}",
+                            fmt.stream_tag(),
+                            fmt.ext.to_uppercase(),
+                            fmt.display_resolution(),
+                            fmt.display_bitrate(),
+                            fmt.display_size()
+                        ),
+                        if is_sel { style } else { Style::default().fg(TEXT) },
+                    ),
+                    Span::styled(more, Style::default().fg(MUTED)),
+                ]
+            }
+            FormatRow::Variant { .. } => {
+                let style = if is_sel {
+                    Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(TEXT)
+                };
+                vec![
+                    Span::styled(prefix, style),
+                    Span::styled(
+                        format!(
+                            "    {:<5} {} · {} · {} · {} · {}",
+                            fmt.stream_tag(),
+                            fmt.ext.to_uppercase(),
+                            fmt.display_codec(),
+                            fmt.display_resolution(),
+                            fmt.display_bitrate(),
+                            fmt.display_size()
+                        ),
+                        style,
+                    ),
+                ]
+            }
+        };
+        format_items.push(ListItem::new(Line::from(spans)));
+    }
+
+    let list = List::new(format_items).style(Style::default().bg(BG));
+    f.render_widget(list, chunks[2]);
+    f.render_widget(Paragraph::new(container_line(popup)).style(Style::default().bg(BG)), chunks[3]);
+
+    // Above the rest: a merge without ffmpeg fails only once the download is done
+    let note = if popup.formats.iter().any(|f| f.needs_merge()) && app.ffmpeg_missing {
+        Line::from(Span::styled("[V] video-only formats need ffmpeg to merge, and it is missing", Style::default().fg(YELLOW)))
+    } else if popup.hidden() > 0 {
+        Line::from(Span::styled(
+            format!("showing {} of {} — press * to show all", popup.variants - popup.hidden(), popup.variants),
+            Style::default().fg(YELLOW),
+        ))
+    } else if popup.beyond_cap > 0 {
+        Line::from(Span::styled("showing all — press * to shorten", Style::default().fg(MUTED)))
+    } else if popup.formats.iter().any(|f| f.needs_merge()) {
+        Line::from(Span::styled("[V] video-only formats will be merged with best audio", Style::default().fg(MUTED)))
+    } else {
+        Line::from("")
+    };
+
+    let hints = Paragraph::new(vec![note, Line::from(vec![
+        Span::styled("enter ", Style::default().fg(MUTED)),
+        Span::styled("select", Style::default().fg(TEXT)),
+        Span::raw("  "),
+        Span::styled("l/h ", Style::default().fg(MUTED)),
+        Span::styled("variants", Style::default().fg(TEXT)),
+        Span::raw("  "),
+        Span::styled("a ", Style::default().fg(MUTED)),
+        Span::styled("audio", Style::default().fg(TEXT)),
+        Span::raw("  "),
+        Span::styled("x ", Style::default().fg(MUTED)),
+        Span::styled("extract", Style::default().fg(TEXT)),
+        Span::raw("  "),
+        Span::styled("c ", Style::default().fg(MUTED)),
+        Span::styled("container", Style::default().fg(TEXT)),
+        Span::raw("  "),
+        Span::styled("A ", Style::default().fg(MUTED)),
+        Span::styled("all", Style::default().fg(TEXT)),
+        Span::raw("  "),
+        Span::styled("esc ", Style::default().fg(MUTED)),
+        Span::styled("cancel", Style::default().fg(TEXT)),
+    ])]).style(Style::default().bg(BG));
+    f.render_widget(hints, chunks[4]);
+}
+
+// Whether the chosen container means a quick remux or a slow recode of the selected format
+fn container_line(popup: &FormatPopupState) -> Line<'static> {
+    let choices = Container::ALL.iter().map(|c| c.label()).collect::<Vec<_>>().join(" / ");
+    let mut spans = vec![Span::styled("Container: ", Style::default().fg(MUTED))];
+    if popup.extract_audio {
+        spans.push(Span::styled(format!("{} (audio extraction makes its own file)", popup.container.label()), Style::default().fg(MUTED)));
+        return Line::from(spans);
+    }
+    let conversion = popup.selected_format().and_then(|f| popup.container.conversion(f, &popup.formats));
+    let (what, style) = match conversion {
+        None => ("as downloaded", Style::default().fg(TEXT)),
+        Some(Conversion::Remux) => ("remux, streams copied as they are", Style::default().fg(GREEN)),
+        Some(Conversion::Recode) => ("recode, slow: the codecs don't fit", Style::default().fg(YELLOW)),
+    };
+    spans.extend([
+        Span::styled(popup.container.label(), style.add_modifier(Modifier::BOLD)),
+        Span::styled(format!(" · {}", what), style),
+        Span::styled(format!("  (c: {})", choices), Style::default().fg(MUTED)),
+    ]);
+    Line::from(spans)
+}
+
+// Extraction and its quality, with the size the converted file should come to
+fn audio_line(app: &App, popup: &FormatPopupState, job: &Job) -> Line<'static> {
+    if !popup.extract_audio {
+        return Line::from(Span::styled("[ Extract audio] (x to convert to audio)", Style::default().fg(MUTED)));
+    }
+    let mut spans = vec![
+        Span::styled(format!("[✓ Extract {}]", app.config.audio_format), Style::default().fg(GREEN).add_modifier(Modifier::BOLD)),
+        Span::styled("  quality ", Style::default().fg(MUTED)),
+        Span::styled(popup.audio_quality.clone(), Style::default().fg(GREEN).add_modifier(Modifier::BOLD)),
+        Span::styled(" ([/] 0 best…9 worst or kbps)", Style::default().fg(MUTED)),
+    ];
+    // Lossless targets fall back to the source's own bitrate
+    let duration = job.metadata.duration;
+    let source_kbps = popup.selected_format().and_then(|f| f.tbr);
+    let estimate = duration.and_then(|secs| {
+        estimate_audio_size(secs, &app.config.audio_format, &popup.audio_quality).or_else(|| source_kbps.map(|kbps| (kbps * 1000.0 / 8.0 * secs) as u64))
+    });
+    if let Some(size) = estimate {
+        spans.push(Span::styled(format!("  ≈ {}", format::size(size)), Style::default().fg(TEXT)));
+    }
+    Line::from(spans)
+}
+
+fn render_group_menu(f: &mut Frame, app: &App) {
+    let Some(menu) = &app.group_menu else { return };
+
+    let area = centered_rect(45, 35, f.area());
+    f.render_widget(Clear, area);
+
+    let title = app.group(menu.group_id).map_or("Playlist", |g| g.title.as_str());
+    let mut lines = vec![
+        Line::from(Span::styled(title, Style::default().fg(YELLOW))),
+        Line::from(""),
+    ];
+
+    for (i, action) in GroupAction::ALL.iter().enumerate() {
+        let is_sel = i == menu.selected;
+        let style = if is_sel {
+            Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(TEXT)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(if is_sel { "▶ " } else { "  " }, style),
+            Span::styled(action.label(), style),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Skips items already downloading or completed",
+        Style::default().fg(MUTED),
+    )));
+
+    let popup = Paragraph::new(lines).block(popup_block(" Playlist Actions "));
+    f.render_widget(popup, area);
+}
+
+fn render_completed_menu(f: &mut Frame, app: &App) {
+    let Some(menu) = &app.completed_menu else { return };
+    let Some(job) = app.jobs.iter().find(|j| j.id == menu.job_id) else { return };
+
+    let area = centered_rect(45, 40, f.area());
+    f.render_widget(Clear, area);
+
+    let mut lines = vec![
+        Line::from(Span::styled(job.display_name(), Style::default().fg(YELLOW))),
+        Line::from(""),
+    ];
+
+    for (i, action) in CompletedAction::ALL.iter().enumerate() {
+        let is_sel = i == menu.selected;
+        let style = if !action.is_available(job) {
+            Style::default().fg(MUTED)
+        } else if is_sel {
+            Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(TEXT)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(if is_sel { "▶ " } else { "  " }, style),
+            Span::styled(format!("{:<20}", action.label()), style),
+            Span::styled(action.key().to_string(), Style::default().fg(CYAN)),
+        ]));
+    }
+
+    let popup = Paragraph::new(lines).block(popup_block(" Download Actions "));
+    f.render_widget(popup, area);
+}
+
+fn render_conflict_prompt(f: &mut Frame, app: &App) {
+    let Some(prompt) = &app.conflict_prompt else { return };
+    let Some(job) = app.jobs.iter().find(|j| j.id == prompt.job_id) else { return };
+
+    let area = centered_rect(60, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let width = area.width.saturating_sub(4) as usize;
+    let mut lines = vec![
+        Line::from(Span::styled(job.display_name(), Style::default().fg(YELLOW))),
+        Line::from(""),
+        Line::from(Span::styled("Already exists:", Style::default().fg(MUTED))),
+    ];
+    for line in textwrap_simple(&prompt.path.display().to_string(), width) {
+        lines.push(Line::from(Span::styled(line, Style::default().fg(TEXT))));
+    }
+    lines.push(Line::from(""));
+
+    for (i, (policy, always)) in ConflictPromptState::CHOICES.iter().enumerate() {
+        let is_sel = i == prompt.selected;
+        let style = if is_sel {
+            Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(TEXT)
+        };
+        let (label, key) = match (policy, always) {
+            (OverwritePolicy::Overwrite, false) => ("Overwrite", 'o'),
+            (OverwritePolicy::KeepBoth, false) => ("Keep both", 'b'),
+            (OverwritePolicy::Skip, false) => ("Skip", 's'),
+            (OverwritePolicy::Overwrite, true) => ("Always overwrite this session", 'O'),
+            (OverwritePolicy::KeepBoth, true) => ("Always keep both this session", 'B'),
+            _ => ("Always skip this session", 'S'),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(if is_sel { "▶ " } else { "  " }, style),
+            Span::styled(format!("{:<32}", label), style),
+            Span::styled(key.to_string(), Style::default().fg(CYAN)),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("esc decide later", Style::default().fg(MUTED))));
+
+    let popup = Paragraph::new(lines).block(popup_block(" File Exists "));
+    f.render_widget(popup, area);
+}
+
+fn render_remove_menu(f: &mut Frame, app: &App) {
+    let Some(menu) = &app.remove_menu else { return };
+
+    let area = centered_rect(45, 40, f.area());
+    f.render_widget(Clear, area);
+
+    let mut lines = vec![
+        Line::from(Span::styled("Remove from the queue:", Style::default().fg(YELLOW))),
+        Line::from(""),
+    ];
+    for (i, filter) in RemoveFilter::ALL.iter().enumerate() {
+        let count = app.count_matching(*filter);
+        let is_sel = i == menu.selected;
+        let style = if count == 0 {
+            Style::default().fg(MUTED)
+        } else if is_sel {
+            Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(TEXT)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(if is_sel { "▶ " } else { "  " }, style),
+            Span::styled(format!("{:<24}", format!("All {} ({})", filter.label(), count)), style),
+            Span::styled(filter.key().to_string(), Style::default().fg(CYAN)),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("u undoes a removal afterwards", Style::default().fg(MUTED))));
+
+    let popup = Paragraph::new(lines).block(popup_block(" Remove "));
+    f.render_widget(popup, area);
+}
+
+fn render_channel_list(f: &mut Frame, app: &App) {
+    let Some(list) = &app.channel_list else { return };
+
+    let area = centered_rect(60, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let entries = app.channels.entries();
+    let mut lines = Vec::new();
+    if entries.is_empty() {
+        lines.push(Line::from(Span::styled("No channels yet. Confirming a format remembers it for the video's channel.", Style::default().fg(MUTED))));
+    }
+    // Header and footer take four rows; keep the selection in view
+    let visible = area.height.saturating_sub(5).max(1) as usize;
+    let start = list.selected.saturating_sub(visible - 1);
+    let width = area.width.saturating_sub(4) as usize;
+    for (i, (_, choice)) in entries.iter().enumerate().skip(start).take(visible) {
+        let is_sel = i == list.selected;
+        let style = if is_sel {
+            Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(TEXT)
+        };
+        let label = choice.label();
+        let name_width = width.saturating_sub(label.chars().count() + 4);
+        lines.push(Line::from(vec![
+            Span::styled(if is_sel { "▶ " } else { "  " }, style),
+            Span::styled(format!("{:<w$}", truncate_chars(&choice.name, name_width), w = name_width), style),
+            Span::raw(" "),
+            Span::styled(label, Style::default().fg(CYAN)),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("d forget  esc close", Style::default().fg(MUTED))));
+
+    let popup = Paragraph::new(lines).block(popup_block(" Remembered Channels "));
+    f.render_widget(popup, area);
+}
+
+fn render_trash_view(f: &mut Frame, app: &App) {
+    let Some(view) = &app.trash_view else { return };
+
+    let area = centered_rect(60, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let mut lines = Vec::new();
+    if app.trash.is_empty() {
+        lines.push(Line::from(Span::styled("The trash is empty. Removed items wait here until purged or the app exits.", Style::default().fg(MUTED))));
+    }
+    // Header and footer take four rows; keep the selection in view
+    let visible = area.height.saturating_sub(5).max(1) as usize;
+    let start = view.selected.saturating_sub(visible - 1);
+    let width = area.width.saturating_sub(4) as usize;
+    for (i, entry) in app.trash.iter().rev().enumerate().skip(start).take(visible) {
+        let is_sel = i == view.selected;
+        let style = if is_sel {
+            Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(TEXT)
+        };
+        let stage = entry.job.status.stage();
+        let name_width = width.saturating_sub(stage.chars().count() + 4);
+        lines.push(Line::from(vec![
+            Span::styled(if is_sel { "▶ " } else { "  " }, style),
+            Span::styled(format!("{:<w$}", truncate_chars(entry.job.display_name(), name_width), w = name_width), style),
+            Span::raw(" "),
+            Span::styled(stage, Style::default().fg(MUTED)),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("r restore  d purge  D empty trash  esc close", Style::default().fg(MUTED))));
+
+    let title = format!(" Trash ({}) ", app.trash.len());
+    let popup = Paragraph::new(lines).block(popup_block(&title));
+    f.render_widget(popup, area);
+}
+
+fn render_halt_prompt(f: &mut Frame, app: &App) {
+    let Some(prompt) = &app.halt_prompt else { return };
+
+    let area = centered_rect(60, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let width = area.width.saturating_sub(4) as usize;
+    let mut lines = vec![
+        Line::from(Span::styled("Downloads are failing with:", Style::default().fg(RED))),
+    ];
+    for line in textwrap_simple(&prompt.reason, width).into_iter().take(4) {
+        lines.push(Line::from(Span::styled(line, Style::default().fg(TEXT))));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "The queue is paused. This usually means yt-dlp needs an update.",
+        Style::default().fg(MUTED),
+    )));
+    lines.push(Line::from(""));
+
+    for (i, action) in HaltAction::ALL.iter().enumerate() {
+        let is_sel = i == prompt.selected;
+        let style = if is_sel {
+            Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(TEXT)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(if is_sel { "▶ " } else { "  " }, style),
+            Span::styled(format!("{:<32}", action.label()), style),
+            Span::styled(action.key().to_string(), Style::default().fg(CYAN)),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("esc keep paused (P reopens this)", Style::default().fg(MUTED))));
+
+    let popup = Paragraph::new(lines).block(popup_block(" Queue Paused "));
+    f.render_widget(popup, area);
+}
+
+fn render_clash_prompt(f: &mut Frame, app: &App) {
+    let Some(prompt) = &app.clash_prompt else { return };
+    let Some(job) = app.jobs.iter().find(|j| j.id == prompt.job_id) else { return };
+    let earlier = app.jobs.iter().position(|j| j.id == prompt.earlier);
+
+    let area = centered_rect(60, 40, f.area());
+    f.render_widget(Clear, area);
+
+    let width = area.width.saturating_sub(4) as usize;
+    let mut lines = vec![Line::from(Span::styled(
+        format!("This item and item {} would both be saved as:", earlier.map_or(0, |i| i + 1)),
+        Style::default().fg(TEXT),
+    ))];
+    for line in textwrap_simple(&job.planned_path(&app.config), width).into_iter().take(3) {
+        lines.push(Line::from(Span::styled(line, Style::default().fg(YELLOW))));
+    }
+    lines.push(Line::from(""));
+
+    for (i, action) in ClashAction::ALL.iter().enumerate() {
+        let is_sel = i == prompt.selected;
+        let style = if is_sel {
+            Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(TEXT)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(if is_sel { "▶ " } else { "  " }, style),
+            Span::styled(format!("{:<32}", action.label()), style),
+            Span::styled(action.key().to_string(), Style::default().fg(CYAN)),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("esc decide later; a number is added when it starts", Style::default().fg(MUTED))));
+
+    let popup = Paragraph::new(lines).block(popup_block(" Name Clash "));
+    f.render_widget(popup, area);
+}
+
+fn render_dependents_prompt(f: &mut Frame, app: &App) {
+    let Some(prompt) = &app.dependents_prompt else { return };
+
+    let area = centered_rect(60, 40, f.area());
+    f.render_widget(Clear, area);
+
+    let name = |id: JobId| app.jobs.iter().find(|j| j.id == id).map_or("", |j| j.display_name());
+    let width = area.width.saturating_sub(6) as usize;
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} {}. These were waiting for it:", truncate_chars(name(prompt.prerequisite), width), prompt.outcome),
+            Style::default().fg(YELLOW),
+        )),
+        Line::from(""),
+    ];
+    for &id in &prompt.dependents {
+        lines.push(Line::from(Span::styled(format!("  {}", truncate_chars(name(id), width)), Style::default().fg(TEXT))));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("y ", Style::default().fg(CYAN)),
+        Span::styled("run them anyway  ", Style::default().fg(TEXT)),
+        Span::styled("n ", Style::default().fg(CYAN)),
+        Span::styled("cancel them  ", Style::default().fg(TEXT)),
+        Span::styled("esc ", Style::default().fg(CYAN)),
+        Span::styled("keep waiting (a retry that succeeds releases them)", Style::default().fg(TEXT)),
+    ]));
+
+    let popup = Paragraph::new(lines)
+        .wrap(ratatui::widgets::Wrap { trim: false })
+        .block(popup_block(" Waiting Downloads "));
+    f.render_widget(popup, area);
+}
+
+fn render_health(f: &mut Frame, app: &App) {
+    let Some(screen) = &app.health else { return };
+
+    let area = centered_rect(70, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let Some(report) = &screen.report else {
+        let popup = Paragraph::new(Line::from(Span::styled("Checking yt-dlp, ffmpeg, output directory and config…", Style::default().fg(MUTED))))
+            .block(popup_block(" Health Check "));
+        f.render_widget(popup, area);
+        return;
+    };
+
+    let width = area.width.saturating_sub(6) as usize;
+    let mut lines = Vec::new();
+    for check in &report.checks {
+        let (mark, color) = match check.status {
+            CheckStatus::Ok => ("✓", GREEN),
+            CheckStatus::Warning => ("!", YELLOW),
+            CheckStatus::Blocking => ("✗", RED),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{} ", mark), Style::default().fg(color).add_modifier(Modifier::BOLD)),
+            Span::styled(format!("{:<18}", check.name), Style::default().fg(TEXT)),
+            Span::styled(truncate_chars(&check.detail, width.saturating_sub(18)), Style::default().fg(if check.status == CheckStatus::Ok { MUTED } else { color })),
+        ]));
+        if let Some(hint) = &check.hint {
+            for line in textwrap_simple(hint, width) {
+                lines.push(Line::from(Span::styled(format!("  {}", line), Style::default().fg(MUTED))));
+            }
+        }
+    }
+    lines.push(Line::from(""));
+    let hint = if report.is_blocked() {
+        "oxidlp can't download anything until this is fixed.  r check again  q quit"
+    } else {
+        "enter continue  r check again  (H reopens this)"
+    };
+    lines.push(Line::from(Span::styled(hint, Style::default().fg(MUTED))));
+
+    let popup = Paragraph::new(lines).block(popup_block(" Health Check "));
+    f.render_widget(popup, area);
+}
+
+fn render_diagnosis(f: &mut Frame, app: &App) {
+    let Some(screen) = &app.diagnosis else { return };
+
+    let area = centered_rect(75, 70, f.area());
+    f.render_widget(Clear, area);
+
+    let Some(report) = &screen.report else {
+        let popup = Paragraph::new(Line::from(Span::styled(
+            "Checking yt-dlp, the network, a plain fetch of the video and ffmpeg…",
+            Style::default().fg(MUTED),
+        )))
+        .block(popup_block(" Diagnose "));
+        f.render_widget(popup, area);
+        return;
+    };
+
+    let width = area.width.saturating_sub(6) as usize;
+    let mut lines = Vec::new();
+    for line in textwrap_simple(&format!("{}: {}", report.failure.kind.label(), report.failure.message.trim()), width).into_iter().take(2) {
+        lines.push(Line::from(Span::styled(line, Style::default().fg(RED))));
+    }
+    lines.push(Line::from(""));
+    for step in &report.steps {
+        let (mark, color) = match step.status {
+            CheckStatus::Ok => ("✓", GREEN),
+            CheckStatus::Warning => ("!", YELLOW),
+            CheckStatus::Blocking => ("✗", RED),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{} ", mark), Style::default().fg(color).add_modifier(Modifier::BOLD)),
+            Span::styled(format!("{:<14}", step.name), Style::default().fg(TEXT)),
+            Span::styled(truncate_chars(&step.detail, width.saturating_sub(16)), Style::default().fg(if step.status == CheckStatus::Ok { MUTED } else { color })),
+        ]));
+    }
+    if !report.output.is_empty() {
+        lines.push(Line::from(""));
+        for line in &report.output {
+            lines.push(Line::from(Span::styled(truncate_chars(line, width), Style::default().fg(MUTED))));
+        }
+    }
+    lines.push(Line::from(""));
+    for line in textwrap_simple(&format!("Likely cause: {}", report.verdict), width) {
+        lines.push(Line::from(Span::styled(line, Style::default().fg(CYAN).add_modifier(Modifier::BOLD))));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("y copy report  r run again  esc close", Style::default().fg(MUTED))));
+
+    let popup = Paragraph::new(lines).block(popup_block(" Diagnose "));
+    f.render_widget(popup, area);
+}
+
+fn render_ytdlp_update(f: &mut Frame, app: &App) {
+    let Some(screen) = &app.ytdlp_update else { return };
+
+    let area = centered_rect(70, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let width = area.width.saturating_sub(4) as usize;
+    // Command, a gap, the result and version lines and the key line around the output
+    let room = area.height.saturating_sub(9) as usize;
+    let mut lines = vec![
+        Line::from(Span::styled(truncate_chars(&format!("$ {}", screen.command), width), Style::default().fg(CYAN))),
+        Line::from(""),
+    ];
+    let skipped = screen.output.len().saturating_sub(room);
+    for line in &screen.output[skipped..] {
+        lines.push(Line::from(Span::styled(truncate_chars(line, width), Style::default().fg(MUTED))));
+    }
+    lines.push(Line::from(""));
+    match &screen.result {
+        None => {
+            let frame = SPINNER_FRAMES[app.spinner_frame % SPINNER_FRAMES.len()];
+            lines.push(Line::from(Span::styled(format!("{} Updating; new downloads wait until it's done", frame), Style::default().fg(YELLOW))));
+        }
+        Some((true, _)) => {
+            let version = screen.version.as_deref().map_or_else(|| "checking the version…".to_string(), |v| format!("now {}", v));
+            lines.push(Line::from(Span::styled(format!("✓ yt-dlp updated, {}", version), Style::default().fg(GREEN).add_modifier(Modifier::BOLD))));
+        }
+        Some((false, message)) => {
+            lines.push(Line::from(Span::styled(truncate_chars(&format!("✗ {}", message), width), Style::default().fg(RED).add_modifier(Modifier::BOLD))));
+            if app.config.ytdlp_update_command.is_empty() {
+                lines.push(Line::from(Span::styled(
+                    "Installed with pip? Set ytdlp_update_command = [\"pip\", \"install\", \"-U\", \"yt-dlp\"]",
+                    Style::default().fg(MUTED),
+                )));
+            }
+        }
+    }
+    lines.push(Line::from(""));
+    let keys = if screen.result.is_none() { "esc hide (the update carries on)" } else { "esc close" };
+    lines.push(Line::from(Span::styled(keys, Style::default().fg(MUTED))));
+
+    let popup = Paragraph::new(lines).block(popup_block(" Update yt-dlp "));
+    f.render_widget(popup, area);
+}
+
+fn render_default_format_prompt(f: &mut Frame, app: &App) {
+    let Some(state) = &app.default_format_prompt else { return };
+
+    let area = centered_rect(60, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let queued = app.jobs.iter().filter(|j| state.group.is_none_or(|g| j.group == Some(g)) && j.status == JobStatus::Queued).count();
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} item(s) have no format selected. Start them with the default format?", state.jobs.len()),
+            Style::default().fg(YELLOW),
+        )),
+        Line::from(vec![
+            Span::styled("  ", Style::default()),
+            Span::styled(app.config.default_format(), Style::default().fg(CYAN)),
+        ]),
+        Line::from(""),
+    ];
+
+    let width = area.width.saturating_sub(6) as usize;
+    let shown = (area.height as usize).saturating_sub(10);
+    for id in state.jobs.iter().take(shown) {
+        let name = app.jobs.iter().find(|j| j.id == *id).map_or("", |j| j.display_name());
+        lines.push(Line::from(Span::styled(format!("  {}", truncate_chars(name, width)), Style::default().fg(TEXT))));
+    }
+    if state.jobs.len() > shown {
+        lines.push(Line::from(Span::styled(format!("  … and {} more", state.jobs.len() - shown), Style::default().fg(MUTED))));
+    }
+
+    lines.push(Line::from(""));
+    if queued > 0 {
+        lines.push(Line::from(Span::styled(format!("{} item(s) with a selected format start either way", queued), Style::default().fg(MUTED))));
+    }
+    lines.push(Line::from(vec![
+        Span::styled("y ", Style::default().fg(CYAN)),
+        Span::styled("use the default  ", Style::default().fg(TEXT)),
+        Span::styled("s ", Style::default().fg(CYAN)),
+        Span::styled("start only the selected  ", Style::default().fg(TEXT)),
+        Span::styled("esc ", Style::default().fg(CYAN)),
+        Span::styled("cancel", Style::default().fg(TEXT)),
+    ]));
+
+    let popup = Paragraph::new(lines)
+        .wrap(ratatui::widgets::Wrap { trim: false })
+        .block(popup_block(" Default Format "));
+    f.render_widget(popup, area);
+}
+
+fn render_large_downloads(f: &mut Frame, app: &App) {
+    let Some(state) = &app.large_downloads else { return };
+
+    let area = centered_rect(60, 50, f.area());
+    f.render_widget(Clear, area);
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} download(s) are larger than {}:", state.jobs.len(), format::size(app.config.large_download_threshold().unwrap_or(0))),
+            Style::default().fg(YELLOW),
+        )),
+        Line::from(""),
+    ];
+
+    let width = area.width.saturating_sub(16) as usize;
+    for (id, size) in &state.jobs {
+        let job = app.jobs.iter().find(|j| j.id == *id);
+        let name = job.map_or("", |j| j.display_name());
+        // Guessed from what the default format would likely pick
+        let size = if job.is_some_and(|j| j.selected_format.is_none()) { format!("≈{}", format::size(*size)) } else { format::size(*size) };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:>10}  ", size), Style::default().fg(CYAN)),
+            Span::styled(truncate_chars(name, width), Style::default().fg(TEXT)),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(format!("Total ≈ {}", format::size(state.total())), Style::default().fg(MUTED))));
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("y ", Style::default().fg(CYAN)),
+        Span::styled("start all  ", Style::default().fg(TEXT)),
+        Span::styled("s ", Style::default().fg(CYAN)),
+        Span::styled("skip these  ", Style::default().fg(TEXT)),
+        Span::styled("f ", Style::default().fg(CYAN)),
+        Span::styled("pick a smaller format  ", Style::default().fg(TEXT)),
+        Span::styled("esc ", Style::default().fg(CYAN)),
+        Span::styled("cancel", Style::default().fg(TEXT)),
+    ]));
+
+    let popup = Paragraph::new(lines)
+        .wrap(ratatui::widgets::Wrap { trim: false })
+        .block(popup_block(" Large Downloads "));
+    f.render_widget(popup, area);
+}
+
+fn render_triage_popup(f: &mut Frame, app: &App) {
+    let Some(triage) = &app.triage else { return };
+
+    let area = centered_rect(60, 40, f.area());
+    f.render_widget(Clear, area);
+
+    let key = |k: &'static str| Span::styled(k, Style::default().fg(CYAN));
+    let label = |l: &'static str| Span::styled(l, Style::default().fg(TEXT));
+
+    let mut lines = Vec::new();
+    match triage.current.and_then(|id| app.jobs.iter().find(|j| j.id == id)) {
+        Some(job) => {
+            lines.push(Line::from(Span::styled(job.display_name(), Style::default().fg(YELLOW).add_modifier(Modifier::BOLD))));
+            lines.push(Line::from(""));
+            let duration = job.metadata.duration.map_or("unknown".into(), format::duration);
+            lines.push(Line::from(vec![
+                Span::styled("Duration:  ", Style::default().fg(MUTED)),
+                Span::styled(duration, Style::default().fg(TEXT)),
+            ]));
+            lines.push(Line::from(vec![
+                Span::styled("Thumbnail: ", Style::default().fg(MUTED)),
+                Span::styled(job.metadata.thumbnail.as_deref().unwrap_or("none"), Style::default().fg(TEXT)),
+            ]));
+        }
+        None => {
+            lines.push(Line::from(Span::styled("Waiting for formats...", Style::default().fg(YELLOW))));
+        }
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!("{} assigned · {} remaining", triage.assigned, triage.remaining(&app.jobs)),
+        Style::default().fg(MUTED),
+    )));
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        key("[1]"), label(" 1080p  "),
+        key("[2]"), label(" 720p  "),
+        key("[a]"), label(" Audio"),
+    ]));
+    lines.push(Line::from(vec![
+        key("[s]"), label(" Skip  "),
+        key("[d]"), label(" Remove  "),
+        Span::styled("[Esc]", Style::default().fg(MUTED)), label(" Done"),
+    ]));
+
+    let popup = Paragraph::new(lines)
+        .wrap(ratatui::widgets::Wrap { trim: false })
+        .block(popup_block(" Triage "));
+    f.render_widget(popup, area);
+}
+
+fn render_remediation_popup(f: &mut Frame, app: &App) {
+    let Some(state) = &app.remediation else { return };
+
+    let area = centered_rect(50, 45, f.area());
+    f.render_widget(Clear, area);
+
+    let title = app.jobs.iter().find(|j| j.id == state.job_id).map_or("", |j| j.display_name());
+    let mut lines = vec![
+        Line::from(Span::styled(title, Style::default().fg(YELLOW))),
+        Line::from(Span::styled("This video needs a signed-in session", Style::default().fg(MUTED))),
+        Line::from(""),
+    ];
+
+    let option_line = |i: usize, label: &str| {
+        let is_sel = i == state.selected;
+        let style = if is_sel {
+            Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(TEXT)
+        };
+        Line::from(vec![
+            Span::styled(if is_sel { "▶ " } else { "  " }, style),
+            Span::styled(label.to_string(), style),
+        ])
+    };
+
+    match state.step {
+        RemediationStep::Menu => {
+            lines.extend(RemediationState::OPTIONS.iter().enumerate().map(|(i, o)| option_line(i, o)));
+        }
+        RemediationStep::Browser => {
+            lines.push(Line::from(Span::styled("Read cookies from:", Style::default().fg(MUTED))));
+            lines.extend(COOKIE_BROWSERS.iter().enumerate().map(|(i, b)| option_line(i, b)));
+        }
+        RemediationStep::Login => {
+            let field_style = |field: LoginField| {
+                if state.field == field {
+                    Style::default().fg(GREEN).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(TEXT)
+                }
+            };
+            lines.push(Line::from(vec![
+                Span::styled("Username: ", Style::default().fg(MUTED)),
+                Span::styled(state.username.as_str(), field_style(LoginField::Username)),
+            ]));
+            lines.push(Line::from(vec![
+                Span::styled("Password: ", Style::default().fg(MUTED)),
+                Span::styled("*".repeat(state.password.0.chars().count()), field_style(LoginField::Password)),
+            ]));
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                "Used for this job only and never saved",
+                Style::default().fg(MUTED),
+            )));
+        }
+    }
+
+    lines.push(Line::from(""));
+    let hint = match state.step {
+        RemediationStep::Login => "tab switch field  enter retry  esc cancel",
+        _ => "enter choose  esc cancel",
+    };
+    lines.push(Line::from(Span::styled(hint, Style::default().fg(MUTED))));
+
+    let popup = Paragraph::new(lines).block(popup_block(" Sign-in Required "));
+    f.render_widget(popup, area);
+}
+
+fn render_import_prompt(f: &mut Frame, app: &App) {
+    let Some(path) = &app.import_prompt else { return };
+
+    let area = centered_rect(60, 20, f.area());
+    f.render_widget(Clear, area);
+
+    let popup = Paragraph::new(vec![
+        Line::from(Span::styled("Path to a bookmarks HTML export:", Style::default().fg(MUTED))),
+        Line::from(vec![
+            Span::styled(path.as_str(), Style::default().fg(GREEN)),
+            Span::styled("│", Style::default().fg(GREEN)),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled("enter import  esc cancel", Style::default().fg(MUTED))),
+    ])
+    .block(popup_block(" Import Bookmarks "));
+
+    f.render_widget(popup, area);
+}
+
+fn render_note_prompt(f: &mut Frame, app: &App) {
+    let Some((id, note)) = &app.note_prompt else { return };
+    let name = app.jobs.iter().find(|j| j.id == *id).map_or("", |j| j.display_name());
+
+    let area = centered_rect(60, 20, f.area());
+    f.render_widget(Clear, area);
+
+    let width = area.width.saturating_sub(4) as usize;
+    let popup = Paragraph::new(vec![
+        Line::from(Span::styled(truncate_chars(name, width), Style::default().fg(MUTED))),
+        Line::from(vec![
+            Span::styled(note.as_str(), Style::default().fg(GREEN)),
+            Span::styled("│", Style::default().fg(GREEN)),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled("enter save (empty clears)  esc cancel", Style::default().fg(MUTED))),
+    ])
+    .block(popup_block(" Note "));
+
+    f.render_widget(popup, area);
+}
+
+fn render_session_prompt(f: &mut Frame, app: &App) {
+    let Some(prompt) = &app.session_prompt else { return };
+
+    let area = centered_rect(60, 20, f.area());
+    f.render_widget(Clear, area);
+
+    let (title, question) = if prompt.saving {
+        (" Save Session As ", "File to write the queue and download settings to:")
+    } else {
+        (" Open Session ", "Session file to load:")
+    };
+    let popup = Paragraph::new(vec![
+        Line::from(Span::styled(question, Style::default().fg(TEXT))),
+        Line::from(vec![
+            Span::styled(prompt.path.as_str(), Style::default().fg(GREEN)),
+            Span::styled("│", Style::default().fg(GREEN)),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled("enter confirm  esc cancel", Style::default().fg(MUTED))),
+    ])
+    .block(popup_block(title));
+
+    f.render_widget(popup, area);
+}
+
+fn render_session_load(f: &mut Frame, app: &App) {
+    let Some(prompt) = &app.session_load else { return };
+
+    let area = centered_rect(60, 40, f.area());
+    f.render_widget(Clear, area);
+
+    let width = area.width.saturating_sub(4) as usize;
+    let saved_at = format::date(prompt.session.saved_at, &app.config.date_format);
+    let mut lines = vec![
+        Line::from(Span::styled(truncate_chars(&prompt.path.display().to_string(), width), Style::default().fg(MUTED))),
+        Line::from(Span::styled(
+            format!("{} items, saved {}. The queue already has {}.", prompt.session.jobs.len(), saved_at, app.jobs.len()),
+            Style::default().fg(TEXT),
+        )),
+        Line::from(""),
+    ];
+    for (i, choice) in LoadChoice::ALL.iter().enumerate() {
+        let is_sel = i == prompt.selected;
+        let style = if is_sel {
+            Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(TEXT)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(if is_sel { "▶ " } else { "  " }, style),
+            Span::styled(format!("{:<40}", choice.label()), style),
+            Span::styled(choice.key().to_string(), Style::default().fg(CYAN)),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Items already in the queue are skipped when adding", Style::default().fg(MUTED))));
+    lines.push(Line::from(Span::styled("esc cancel", Style::default().fg(MUTED))));
+
+    let popup = Paragraph::new(lines).block(popup_block(" Open Session "));
+    f.render_widget(popup, area);
+}
+
+fn render_playlist_range(f: &mut Frame, app: &App) {
+    let Some(prompt) = &app.playlist_range else { return };
+    let Some(url) = prompt.urls.front() else { return };
+
+    let area = centered_rect(60, 30, f.area());
+    f.render_widget(Clear, area);
+
+    let width = area.width.saturating_sub(4) as usize;
+    let mut lines = vec![
+        Line::from(Span::styled(truncate_chars(url, width), Style::default().fg(MUTED))),
+        Line::from(Span::styled("Which items? e.g. 1-25, 10,12,30-40 or 50- (blank for all)", Style::default().fg(TEXT))),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(prompt.input.as_str(), Style::default().fg(GREEN)),
+            Span::styled("│", Style::default().fg(GREEN)),
+        ]),
+        Line::from(""),
+    ];
+    if let Some(error) = &prompt.error {
+        lines.push(Line::from(Span::styled(error.as_str(), Style::default().fg(RED))));
     }
-    if counts.active > 0 {
-        spans.push(Span::styled(format!("▼{} ", counts.active), Style::default().fg(CYAN).add_modifier(Modifier::BOLD)));
+    if prompt.urls.len() > 1 {
+        lines.push(Line::from(Span::styled(format!("{} more playlists after this one", prompt.urls.len() - 1), Style::default().fg(MUTED))));
     }
-    if counts.completed > 0 {
-        spans.push(Span::styled(format!("✓{} ", counts.completed), Style::default().fg(GREEN)));
+    lines.push(Line::from(Span::styled("enter load  esc skip this playlist", Style::default().fg(MUTED))));
+
+    let popup = Paragraph::new(lines).block(popup_block(" Load Playlist "));
+    f.render_widget(popup, area);
+}
+
+fn render_playlist_choice(f: &mut Frame, app: &App) {
+    let Some(prompt) = &app.playlist_choice else { return };
+    let Some(url) = prompt.urls.front() else { return };
+
+    let area = centered_rect(60, 25, f.area());
+    f.render_widget(Clear, area);
+
+    let width = area.width.saturating_sub(4) as usize;
+    let mut lines = vec![Line::from(Span::styled(truncate_chars(url, width), Style::default().fg(MUTED)))];
+    for line in textwrap_simple("This video is part of a playlist. Download just this video or the entire playlist (size unknown)?", width) {
+        lines.push(Line::from(Span::styled(line, Style::default().fg(TEXT))));
     }
-    if counts.failed > 0 {
-        spans.push(Span::styled(format!("✗{}", counts.failed), Style::default().fg(RED)));
+    lines.extend([
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("[v] ", Style::default().fg(YELLOW)),
+            Span::styled("This video  ", Style::default().fg(TEXT)),
+            Span::styled("[p] ", Style::default().fg(YELLOW)),
+            Span::styled("Whole playlist", Style::default().fg(TEXT)),
+        ]),
+        Line::from(""),
+    ]);
+    if prompt.urls.len() > 1 {
+        lines.push(Line::from(Span::styled(format!("{} more links after this one", prompt.urls.len() - 1), Style::default().fg(MUTED))));
     }
-    
-    spans.push(Span::styled("  │  ", Style::default().fg(MUTED)));
-    spans.push(Span::styled("?", Style::default().fg(CYAN)));
-    spans.push(Span::styled(" help  ", Style::default().fg(MUTED)));
-    spans.push(Span::styled("g", Style::default().fg(CYAN)));
-    spans.push(Span::styled(" settings  ", Style::default().fg(MUTED)));
-    spans.push(Span::styled("q", Style::default().fg(CYAN)));
-    spans.push(Span::styled(" quit", Style::default().fg(MUTED)));
+    lines.push(Line::from(Span::styled("esc skip this link", Style::default().fg(MUTED))));
 
-    let status = Line::from(spans);
+    let popup = Paragraph::new(lines).block(popup_block(" Video or Playlist? "));
+    f.render_widget(popup, area);
+}
 
-    let bar = Paragraph::new(status).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(MUTED)),
-    );
+fn render_output_prompt(f: &mut Frame, app: &App) {
+    let Some(prompt) = &app.output_prompt else { return };
+    let job = app.jobs.iter().find(|j| j.id == prompt.job_id);
 
-    f.render_widget(bar, area);
+    let area = centered_rect(70, 35, f.area());
+    f.render_widget(Clear, area);
+
+    let width = area.width.saturating_sub(4) as usize;
+    let field = |label: &'static str, value: &str, fallback: String, active: bool| {
+        let style = if active { Style::default().fg(GREEN) } else { Style::default().fg(TEXT) };
+        let mut spans = vec![Span::styled(label, Style::default().fg(MUTED)), Span::styled(value.to_string(), style)];
+        if active {
+            spans.push(Span::styled("│", style));
+        }
+        if value.is_empty() {
+            spans.push(Span::styled(format!(" {}", fallback), Style::default().fg(MUTED)));
+        }
+        Line::from(spans)
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(truncate_chars(job.map_or("", |j| j.display_name()), width), Style::default().fg(MUTED))),
+        Line::from(""),
+        field("Directory  ", &prompt.dir, format!("(settings: {})", app.config.output_dir.display()), !prompt.editing_template),
+        field("Template   ", &prompt.template, format!("(settings: {})", app.config.output_template), prompt.editing_template),
+        Line::from(""),
+    ];
+    if let Some(error) = &prompt.error {
+        lines.push(Line::from(Span::styled(error.as_str(), Style::default().fg(RED))));
+    }
+    lines.push(Line::from(Span::styled("Applies to this item only; empty follows the settings", Style::default().fg(MUTED))));
+    lines.push(Line::from(Span::styled("tab switch  enter save  esc cancel", Style::default().fg(MUTED))));
+
+    let popup = Paragraph::new(lines).block(popup_block(" Save This Item To "));
+    f.render_widget(popup, area);
 }
 
-fn render_format_popup(f: &mut Frame, app: &App) {
-    let Some(popup) = &app.format_popup else { return };
-    let Some(job) = app.jobs.get(popup.job_index) else { return };
+fn render_section_prompt(f: &mut Frame, app: &App) {
+    let Some(prompt) = &app.section_prompt else { return };
+    let job = app.jobs.iter().find(|j| j.id == prompt.job_id);
 
-    let area = centered_rect(60, 65, f.area());
+    let area = centered_rect(60, 30, f.area());
     f.render_widget(Clear, area);
 
-    let block = popup_block(" Select Format ");
-    let inner = block.inner(area);
-    f.render_widget(block, area);
-
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(2), 
-            Constraint::Length(2), 
-            Constraint::Min(5),    
-            Constraint::Length(2), 
+    let width = area.width.saturating_sub(4) as usize;
+    let field = |label: &'static str, value: &str, active: bool| {
+        let style = if active { Style::default().fg(GREEN) } else { Style::default().fg(TEXT) };
+        Line::from(vec![
+            Span::styled(label, Style::default().fg(MUTED)),
+            Span::styled(value.to_string(), style),
+            Span::styled(if active { "│" } else { "" }, style),
         ])
-        .split(inner);
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(truncate_chars(job.map_or("", |j| j.display_name()), width), Style::default().fg(MUTED))),
+    ];
+    if let Some(duration) = job.and_then(|j| j.metadata.duration) {
+        lines.push(Line::from(Span::styled(format!("Length {}", format::duration(duration)), Style::default().fg(MUTED))));
+    }
+    lines.push(Line::from(""));
+    lines.push(field("Start  ", &prompt.start, !prompt.editing_end));
+    lines.push(field("End    ", &prompt.end, prompt.editing_end));
+    lines.push(Line::from(""));
+    if let Some(error) = &prompt.error {
+        lines.push(Line::from(Span::styled(error.as_str(), Style::default().fg(RED))));
+    }
+    lines.push(Line::from(Span::styled("tab switch  enter save (both empty clears)  esc cancel", Style::default().fg(MUTED))));
 
-    let title = Paragraph::new(job.display_name())
-        .style(Style::default().fg(YELLOW).bg(BG));
-    f.render_widget(title, chunks[0]);
+    let popup = Paragraph::new(lines).block(popup_block(" Download Part of the Video "));
+    f.render_widget(popup, area);
+}
 
-    let video_style = if !popup.audio_only {
-        Style::default().fg(CYAN).add_modifier(Modifier::BOLD)
-    } else {
-        Style::default().fg(MUTED)
-    };
-    let audio_style = if popup.audio_only {
-        Style::default().fg(GREEN).add_modifier(Modifier::BOLD)
-    } else {
-        Style::default().fg(MUTED)
-    };
-    let apply_style = if popup.apply_to_all {
-        Style::default().fg(GREEN).add_modifier(Modifier::BOLD)
-    } else {
-        Style::default().fg(MUTED)
-    };
+fn render_import_picker(f: &mut Frame, app: &App) {
+    let Some(picker) = &app.import_picker else { return };
 
-    let apply_text = if popup.apply_to_all {
-        "[✓ Apply All] (will apply to all ready items)"
-    } else {
-        "[ Apply All] (press A to apply to all ready items)"
-    };
+    let area = centered_rect(70, 70, f.area());
+    f.render_widget(Clear, area);
 
-    let toggles = Paragraph::new(vec![
-        Line::from(vec![
-            Span::styled("[Video]", video_style),
-            Span::raw(" "),
-            Span::styled("[Audio]", audio_style),
-        ]),
-        Line::from(Span::styled(apply_text, apply_style)),
-    ]).style(Style::default().bg(BG));
-    f.render_widget(toggles, chunks[1]);
+    // Header and footer take four rows; keep the selection in view
+    let visible = area.height.saturating_sub(6).max(1) as usize;
+    let start = picker.selected.saturating_sub(visible - 1);
 
-    let filtered = popup.filtered_formats();
-    let visible_height = chunks[2].height as usize;
-    let scroll_offset = popup.scroll_offset.min(filtered.len().saturating_sub(visible_height));
+    let preset = match picker.preset {
+        Some(preset) => format!(" · queue as {}", preset.label()),
+        None => String::new(),
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} of {} links selected{}", picker.chosen_count(), picker.bookmarks.len(), preset),
+            Style::default().fg(YELLOW),
+        )),
+        Line::from(""),
+    ];
 
-    let mut format_items: Vec<ListItem> = Vec::new();
-    for (i, fmt) in filtered.iter().enumerate().skip(scroll_offset).take(visible_height) {
-        let is_sel = i == popup.selected;
-        let prefix = if is_sel { "▶ " } else { "  " };
+    for (i, bookmark) in picker.bookmarks.iter().enumerate().skip(start).take(visible) {
+        let is_sel = i == picker.selected;
         let style = if is_sel {
             Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
         } else {
             Style::default().fg(TEXT)
         };
-
-        let info = format!(
-            "{} · {} · {} · {}",
-            fmt.ext.to_uppercase(),
-            fmt.display_resolution(),
-            fmt.display_bitrate(),
-            fmt.display_size()
-        );
-        format_items.push(ListItem::new(Line::from(vec![
-            Span::styled(prefix, style),
-            Span::styled(info, style),
-        ])));
+        let check = if picker.chosen[i] { "[x] " } else { "[ ] " };
+        lines.push(Line::from(vec![
+            Span::styled(if is_sel { "▶ " } else { "  " }, style),
+            Span::styled(check, Style::default().fg(GREEN)),
+            Span::styled(bookmark.title.as_deref().unwrap_or(&bookmark.url), style),
+        ]));
     }
 
-    let list = List::new(format_items).style(Style::default().bg(BG));
-    f.render_widget(list, chunks[2]);
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "space toggle  a all/none  1 1080p  2 720p  3 audio  0 no preset  enter add  esc cancel",
+        Style::default().fg(MUTED),
+    )));
 
-    let hints = Paragraph::new(Line::from(vec![
-        Span::styled("enter ", Style::default().fg(MUTED)),
-        Span::styled("select", Style::default().fg(TEXT)),
-        Span::raw("  "),
-        Span::styled("a ", Style::default().fg(MUTED)),
-        Span::styled("audio", Style::default().fg(TEXT)),
-        Span::raw("  "),
-        Span::styled("A ", Style::default().fg(MUTED)),
-        Span::styled("all", Style::default().fg(TEXT)),
-        Span::raw("  "),
-        Span::styled("esc ", Style::default().fg(MUTED)),
-        Span::styled("cancel", Style::default().fg(TEXT)),
-    ])).style(Style::default().bg(BG));
-    f.render_widget(hints, chunks[3]);
+    let popup = Paragraph::new(lines).block(popup_block(" Import Bookmarks "));
+    f.render_widget(popup, area);
+}
+
+fn render_command_popup(f: &mut Frame, app: &App) {
+    let Some(command) = &app.command_popup else { return };
+
+    let area = centered_rect(70, 40, f.area());
+    f.render_widget(Clear, area);
+
+    let popup = Paragraph::new(vec![
+        Line::from(Span::styled(command.as_str(), Style::default().fg(TEXT))),
+        Line::from(""),
+        Line::from(Span::styled("esc close", Style::default().fg(MUTED))),
+    ])
+    .wrap(ratatui::widgets::Wrap { trim: false })
+    .block(popup_block(" yt-dlp Command "));
+
+    f.render_widget(popup, area);
 }
 
 fn render_help_popup(f: &mut Frame) {
-    let area = centered_rect(60, 70, f.area());
+    let area = centered_rect(60, 80, f.area());
     f.render_widget(Clear, area);
 
-    let help_text = vec![
+    let mut help_text = vec![
         Line::from(Span::styled("━━━ oxidlp Help ━━━", Style::default().fg(CYAN).add_modifier(Modifier::BOLD))),
         Line::from(""),
         Line::from(Span::styled("Navigation", Style::default().fg(CYAN))),
         Line::from(vec![Span::styled("  Tab     ", Style::default().fg(YELLOW)), Span::styled("Switch between input and queue", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  i       ", Style::default().fg(YELLOW)), Span::styled("Type a URL (Tab switches lists when split)", Style::default().fg(TEXT))]),
         Line::from(vec![Span::styled("  j / ↓   ", Style::default().fg(YELLOW)), Span::styled("Move down in queue/formats", Style::default().fg(TEXT))]),
         Line::from(vec![Span::styled("  k / ↑   ", Style::default().fg(YELLOW)), Span::styled("Move up in queue/formats", Style::default().fg(TEXT))]),
         Line::from(""),
         Line::from(Span::styled("Queue Actions", Style::default().fg(CYAN))),
         Line::from(vec![Span::styled("  Enter   ", Style::default().fg(YELLOW)), Span::styled("Open format selector (on ready item)", Style::default().fg(TEXT))]),
-        Line::from(vec![Span::styled("  s       ", Style::default().fg(YELLOW)), Span::styled("Start all queued downloads", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  Enter   ", Style::default().fg(YELLOW)), Span::styled("Playlist actions (on playlist header)", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  T       ", Style::default().fg(YELLOW)), Span::styled("Triage: pick formats job by job", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  s       ", Style::default().fg(YELLOW)), Span::styled("Start all queued downloads (asks before using the default format)", Style::default().fg(TEXT))]),
         Line::from(vec![Span::styled("  d       ", Style::default().fg(YELLOW)), Span::styled("Remove selected item from queue", Style::default().fg(TEXT))]),
         Line::from(vec![Span::styled("  c       ", Style::default().fg(YELLOW)), Span::styled("Cancel active download", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  > / <   ", Style::default().fg(YELLOW)), Span::styled("Mark, then run an item after the marked one / unlink", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  r / M   ", Style::default().fg(YELLOW)), Span::styled("Sign-in options / retry members-only items", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  r / R   ", Style::default().fg(YELLOW)), Span::styled("Resume / start over (cancelled item)", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  P       ", Style::default().fg(YELLOW)), Span::styled("Options for a paused queue / resume a battery pause", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  D / u   ", Style::default().fg(YELLOW)), Span::styled("Remove by status / restore the last removal from the trash", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  N       ", Style::default().fg(YELLOW)), Span::styled("Toggle do not disturb (desktop notifications)", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  U       ", Style::default().fg(YELLOW)), Span::styled("Sort by upload date (again to reverse)", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  !       ", Style::default().fg(YELLOW)), Span::styled("Show the yt-dlp command (queued item)", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  Y       ", Style::default().fg(YELLOW)), Span::styled("Copy as yt-dlp command (completed item)", Style::default().fg(TEXT))]),
+        Line::from(""),
+        Line::from(Span::styled("Completed Items", Style::default().fg(CYAN))),
+        Line::from(vec![Span::styled("  Enter   ", Style::default().fg(YELLOW)), Span::styled("Open the actions menu", Style::default().fg(TEXT))]),
+    ];
+    help_text.extend(CompletedAction::ALL.iter().map(|action| {
+        Line::from(vec![
+            Span::styled(format!("  {:<8}", action.key()), Style::default().fg(YELLOW)),
+            Span::styled(action.label(), Style::default().fg(TEXT)),
+        ])
+    }));
+    help_text.extend([
         Line::from(""),
         Line::from(Span::styled("Format Selection", Style::default().fg(CYAN))),
-        Line::from(vec![Span::styled("  a       ", Style::default().fg(YELLOW)), Span::styled("Toggle video/audio only formats", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  l / →   ", Style::default().fg(YELLOW)), Span::styled("Show every format in a quality tier", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  h / ←   ", Style::default().fg(YELLOW)), Span::styled("Collapse the tier", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  a       ", Style::default().fg(YELLOW)), Span::styled("Jump to/from the audio tier", Style::default().fg(TEXT))]),
         Line::from(vec![Span::styled("  A       ", Style::default().fg(YELLOW)), Span::styled("Apply format to ALL ready items", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  PgUp/Dn ", Style::default().fg(YELLOW)), Span::styled("Move a page; Home/End jump to the ends", Style::default().fg(TEXT))]),
         Line::from(vec![Span::styled("  Enter   ", Style::default().fg(YELLOW)), Span::styled("Confirm selection", Style::default().fg(TEXT))]),
         Line::from(vec![Span::styled("  Esc     ", Style::default().fg(YELLOW)), Span::styled("Close without selecting", Style::default().fg(TEXT))]),
         Line::from(""),
         Line::from(Span::styled("General", Style::default().fg(CYAN))),
         Line::from(vec![Span::styled("  g       ", Style::default().fg(YELLOW)), Span::styled("Open settings", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  g t     ", Style::default().fg(YELLOW)), Span::styled("Trash: restore or purge removed items", Style::default().fg(TEXT))]),
         Line::from(vec![Span::styled("  S       ", Style::default().fg(YELLOW)), Span::styled("Toggle system info panel", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  v       ", Style::default().fg(YELLOW)), Span::styled("Toggle size column in the queue", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  f       ", Style::default().fg(YELLOW)), Span::styled("Filter by title, fuzzy; ' for exact text", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  |       ", Style::default().fg(YELLOW)), Span::styled("Split finished items into their own list", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  w       ", Style::default().fg(YELLOW)), Span::styled("Show or hide yt-dlp warnings in the details", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  L       ", Style::default().fg(YELLOW)), Span::styled("Switch the details between info and timeline", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  E       ", Style::default().fg(YELLOW)), Span::styled("Copy an error report for a failed item", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  e       ", Style::default().fg(YELLOW)), Span::styled("Diagnose a failed item", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  b       ", Style::default().fg(YELLOW)), Span::styled("Import links from a bookmarks export", Style::default().fg(TEXT))]),
         Line::from(vec![Span::styled("  ?       ", Style::default().fg(YELLOW)), Span::styled("Toggle this help", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  H       ", Style::default().fg(YELLOW)), Span::styled("Check yt-dlp, ffmpeg, output directory and config", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  #       ", Style::default().fg(YELLOW)), Span::styled("Add or edit a note on the item", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  Ctrl+S  ", Style::default().fg(YELLOW)), Span::styled("Save the session (queue and settings) to a file", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  Ctrl+O  ", Style::default().fg(YELLOW)), Span::styled("Open a saved session", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  n       ", Style::default().fg(YELLOW)), Span::styled("Resolve a name clash: number the file or rename it", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  o       ", Style::default().fg(YELLOW)), Span::styled("Set this item's own output directory and template", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  t       ", Style::default().fg(YELLOW)), Span::styled("Download only part of the video (start and end time)", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  Ctrl+U  ", Style::default().fg(YELLOW)), Span::styled("Update yt-dlp", Style::default().fg(TEXT))]),
+        Line::from(vec![Span::styled("  Ctrl+Z  ", Style::default().fg(YELLOW)), Span::styled("Suspend to the shell (fg resumes)", Style::default().fg(TEXT))]),
         Line::from(vec![Span::styled("  q       ", Style::default().fg(YELLOW)), Span::styled("Quit application", Style::default().fg(TEXT))]),
-    ];
+        Line::from(""),
+        Line::from(Span::styled(format!("Log file: {}", config::current_log_file().display()), Style::default().fg(MUTED))),
+    ]);
 
     let help = Paragraph::new(help_text)
         .block(popup_block(" Help "))
@@ -574,8 +2555,6 @@ fn render_sysinfo(f: &mut Frame, app: &App, area: Rect) {
         (p.cpu_usage(), p.memory())
     });
     
-    let rss_mb = rss as f64 / (1024.0 * 1024.0);
-    
     let mut lines = vec![
         Line::from(vec![
             Span::styled("CPU  ", Style::default().fg(MUTED)),
@@ -583,9 +2562,20 @@ fn render_sysinfo(f: &mut Frame, app: &App, area: Rect) {
         ]),
         Line::from(vec![
             Span::styled("RSS  ", Style::default().fg(MUTED)),
-            Span::styled(format!("{:.1} MB", rss_mb), Style::default().fg(GREEN)),
+            Span::styled(format::size(rss), Style::default().fg(GREEN)),
         ]),
     ];
+
+    let children: Vec<_> = app.ytdlp_pids().filter_map(|pid| app.sysinfo.process(pid)).collect();
+    if !children.is_empty() {
+        let child_cpu: f32 = children.iter().map(|p| p.cpu_usage()).sum();
+        let child_memory = children.iter().map(|p| p.memory()).sum::<u64>();
+        lines.push(Line::from(vec![
+            Span::styled("YTDL ", Style::default().fg(MUTED)),
+            Span::styled(format!("{:.1}% · {}", child_cpu, format::size(child_memory)), Style::default().fg(CYAN)),
+            Span::styled(format!(" ({} proc)", children.len()), Style::default().fg(MUTED)),
+        ]));
+    }
     
     if let Some((percent, speed, eta)) = app.aggregate_progress() {
         // Count how many are downloading
@@ -601,13 +2591,22 @@ fn render_sysinfo(f: &mut Frame, app: &App, area: Rect) {
         let bar_width = (area.width as usize).saturating_sub(4);
         let bar = progress_bar(bar_width, percent);
         lines.push(Line::from(Span::styled(bar, Style::default().fg(CYAN))));
+        let speed_color = if app.rate_cap.is_exceeded() { RED } else { CYAN };
         lines.push(Line::from(vec![
             Span::styled(format!("{:.0}%", percent), Style::default().fg(TEXT)),
             Span::styled(" · ", Style::default().fg(MUTED)),
-            Span::styled(speed, Style::default().fg(CYAN)),
+            Span::styled(speed, Style::default().fg(speed_color)),
             Span::styled(" · ETA ", Style::default().fg(MUTED)),
             Span::styled(eta, Style::default().fg(TEXT)),
         ]));
+        if let Some(cap) = app.config.rate_limit_bytes() {
+            let label = if app.rate_cap.is_exceeded() { " exceeded" } else { "" };
+            lines.push(Line::from(vec![
+                Span::styled("CAP  ", Style::default().fg(MUTED)),
+                Span::styled(format!("{} per download", format::rate(cap)), Style::default().fg(TEXT)),
+                Span::styled(label, Style::default().fg(RED)),
+            ]));
+        }
     }
     
     let block = Block::default()
@@ -655,8 +2654,13 @@ fn render_confirm_quit(f: &mut Frame) {
 fn render_settings_popup(f: &mut Frame, app: &App) {
     let Some(settings) = &app.settings_popup else { return };
     
-    let area = centered_rect(55, 35, f.area());
+    let area = centered_rect(60, 75, f.area());
     f.render_widget(Clear, area);
+
+    if settings.reviewing {
+        render_settings_review(f, app, settings, area);
+        return;
+    }
     
     let concurrent_style = if settings.selected_field == 0 {
         Style::default().fg(CYAN).add_modifier(Modifier::BOLD)
@@ -664,17 +2668,18 @@ fn render_settings_popup(f: &mut Frame, app: &App) {
         Style::default().fg(TEXT)
     };
     
-    let path_style = if settings.selected_field == 1 {
-        if settings.editing_path {
+    let text_style = |field: SettingsField| {
+        if settings.field() != field {
+            Style::default().fg(TEXT)
+        } else if settings.editing {
             Style::default().fg(GREEN).add_modifier(Modifier::BOLD)
         } else {
             Style::default().fg(CYAN).add_modifier(Modifier::BOLD)
         }
-    } else {
-        Style::default().fg(TEXT)
     };
+    let cursor = |field: SettingsField| if settings.editing && settings.field() == field { "│" } else { "" };
     
-    let text = vec![
+    let mut text = vec![
         Line::from(""),
         Line::from(vec![
             Span::styled("  Concurrent Downloads: ", Style::default().fg(MUTED)),
@@ -682,16 +2687,94 @@ fn render_settings_popup(f: &mut Frame, app: &App) {
             Span::styled(format!("{}", settings.concurrent_downloads), concurrent_style),
             Span::styled(" ►", Style::default().fg(if settings.selected_field == 0 { CYAN } else { MUTED })),
         ]),
+        Line::from(vec![
+            Span::styled("  Rate Limit: ", Style::default().fg(MUTED)),
+            Span::styled("◄ ", Style::default().fg(if settings.field() == SettingsField::RateLimit { CYAN } else { MUTED })),
+            Span::styled(
+                if settings.rate_limit.is_empty() { "off".to_string() } else { format!("{}/s", settings.rate_limit) },
+                text_style(SettingsField::RateLimit),
+            ),
+            Span::styled(" ►", Style::default().fg(if settings.field() == SettingsField::RateLimit { CYAN } else { MUTED })),
+            Span::styled(
+                if settings.field() == SettingsField::RateLimit { "  per download, applies to downloads started after saving" } else { "" },
+                Style::default().fg(MUTED),
+            ),
+        ]),
         Line::from(""),
         Line::from(vec![
             Span::styled("  Download Location: ", Style::default().fg(MUTED)),
         ]),
         Line::from(vec![
             Span::styled("  ", Style::default()),
-            Span::styled(&settings.output_dir, path_style),
-            Span::styled(if settings.editing_path { "│" } else { "" }, Style::default().fg(GREEN)),
+            Span::styled(&settings.output_dir, text_style(SettingsField::OutputDir)),
+            Span::styled(cursor(SettingsField::OutputDir), Style::default().fg(GREEN)),
+        ]),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("  yt-dlp Command: ", Style::default().fg(MUTED)),
+        ]),
+        Line::from(vec![
+            Span::styled("  ", Style::default()),
+            Span::styled(&settings.ytdlp_command, text_style(SettingsField::YtdlpCommand)),
+            Span::styled(cursor(SettingsField::YtdlpCommand), Style::default().fg(GREEN)),
+        ]),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("  Cookies File: ", Style::default().fg(MUTED)),
+        ]),
+        Line::from(vec![
+            Span::styled("  ", Style::default()),
+            if settings.cookies_file.is_empty() && !(settings.editing && settings.field() == SettingsField::CookiesFile) {
+                Span::styled("(none)", text_style(SettingsField::CookiesFile))
+            } else {
+                Span::styled(&settings.cookies_file, text_style(SettingsField::CookiesFile))
+            },
+            Span::styled(cursor(SettingsField::CookiesFile), Style::default().fg(GREEN)),
+        ]),
+        Line::from(vec![
+            Span::styled("  Cookies From Browser: ", Style::default().fg(MUTED)),
+            Span::styled("◄ ", Style::default().fg(if settings.field() == SettingsField::CookiesBrowser { CYAN } else { MUTED })),
+            Span::styled(
+                if settings.cookies_from_browser.is_empty() { "(none)" } else { settings.cookies_from_browser.as_str() },
+                text_style(SettingsField::CookiesBrowser),
+            ),
+            Span::styled(" ►", Style::default().fg(if settings.field() == SettingsField::CookiesBrowser { CYAN } else { MUTED })),
+        ]),
+        Line::from(vec![
+            Span::styled("  Username: ", Style::default().fg(MUTED)),
+            if settings.username.is_empty() && !(settings.editing && settings.field() == SettingsField::Username) {
+                Span::styled("(none)", text_style(SettingsField::Username))
+            } else {
+                Span::styled(&settings.username, text_style(SettingsField::Username))
+            },
+            Span::styled(cursor(SettingsField::Username), Style::default().fg(GREEN)),
+        ]),
+        Line::from(vec![
+            Span::styled("  Password: ", Style::default().fg(MUTED)),
+            if settings.password.0.is_empty() && !(settings.editing && settings.field() == SettingsField::Password) {
+                Span::styled("(none)", text_style(SettingsField::Password))
+            } else {
+                Span::styled("*".repeat(settings.password.0.chars().count()), text_style(SettingsField::Password))
+            },
+            Span::styled(cursor(SettingsField::Password), Style::default().fg(GREEN)),
+        ]),
+        Line::from(vec![
+            Span::styled("  Use .netrc: ", Style::default().fg(MUTED)),
+            Span::styled(if settings.use_netrc { "[x] on" } else { "[ ] off" }, text_style(SettingsField::UseNetrc)),
+            Span::styled(
+                if settings.field() == SettingsField::UseNetrc && !settings.username.trim().is_empty() { "  the username above wins" } else { "" },
+                Style::default().fg(MUTED),
+            ),
         ]),
         Line::from(""),
+        Line::from(vec![
+            Span::styled("  Embed Subtitles: ", Style::default().fg(MUTED)),
+            Span::styled(if settings.embed_subs { "[x] on" } else { "[ ] off" }, text_style(SettingsField::EmbedSubs)),
+        ]),
+        Line::from(vec![
+            Span::styled("  Embed Metadata & Chapters: ", Style::default().fg(MUTED)),
+            Span::styled(if settings.embed_metadata { "[x] on" } else { "[ ] off" }, text_style(SettingsField::EmbedMetadata)),
+        ]),
         Line::from(""),
         Line::from(vec![
             Span::styled("  ", Style::default()),
@@ -705,12 +2788,284 @@ fn render_settings_popup(f: &mut Frame, app: &App) {
             Span::styled("[←/→]", Style::default().fg(MUTED)),
             Span::styled(" Adjust  ", Style::default().fg(TEXT)),
             Span::styled("[Enter]", Style::default().fg(MUTED)),
-            Span::styled(" Edit path", Style::default().fg(TEXT)),
+            Span::styled(" Edit field", Style::default().fg(TEXT)),
         ]),
+        Line::from(vec![
+            Span::styled("  ", Style::default()),
+            Span::styled("[o]", Style::default().fg(MUTED)),
+            Span::styled(" Open log  ", Style::default().fg(TEXT)),
+            Span::styled("[x]", Style::default().fg(MUTED)),
+            Span::styled(format!(" Clear logs older than {} days", app.config.log_retention_days), Style::default().fg(TEXT)),
+        ]),
+        Line::from(vec![
+            Span::styled("  ", Style::default()),
+            Span::styled("[c]", Style::default().fg(MUTED)),
+            Span::styled(format!(" Remembered channel formats ({})  ", app.channels.len()), Style::default().fg(TEXT)),
+            Span::styled("[t]", Style::default().fg(MUTED)),
+            Span::styled(format!(" Trash ({})  ", app.trash.len()), Style::default().fg(TEXT)),
+            Span::styled("[r]", Style::default().fg(MUTED)),
+            Span::styled(" Reload config.toml", Style::default().fg(TEXT)),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(format!("  Log: {}", config::current_log_file().display()), Style::default().fg(MUTED))),
     ];
+    // Only set in the config file, but they change every run, so they're shown while in effect
+    for (label, args) in [("Extra download args", &app.config.extra_args), ("Extra fetch args", &app.config.extra_fetch_args)] {
+        if !args.is_empty() {
+            text.push(Line::from(vec![
+                Span::styled(format!("  {}: ", label), Style::default().fg(MUTED)),
+                Span::styled(worker::shell_join(args), Style::default().fg(YELLOW)),
+            ]));
+        }
+    }
+    if let Some(downloader) = &app.config.external_downloader {
+        text.push(Line::from(vec![
+            Span::styled("  Downloader: ", Style::default().fg(MUTED)),
+            Span::styled(format!("{} {}", downloader, worker::shell_join(&app.config.external_downloader_args)).trim_end().to_string(), Style::default().fg(YELLOW)),
+        ]));
+    }
+    // Read-only; they're set in the [http_headers] table of the config file
+    for (name, value) in app.config.sorted_headers() {
+        let value = if config::is_secret_key(name) { "********" } else { value.as_str() };
+        text.push(Line::from(vec![
+            Span::styled(format!("  Header {}: ", name), Style::default().fg(MUTED)),
+            Span::styled(value.to_string(), Style::default().fg(YELLOW)),
+        ]));
+    }
     
     let popup = Paragraph::new(text)
         .block(popup_block(" Settings "));
     
     f.render_widget(popup, area);
 }
+
+fn render_settings_review(f: &mut Frame, app: &App, settings: &SettingsState, area: Rect) {
+    let width = area.width.saturating_sub(8) as usize;
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled("  Save these changes?", Style::default().fg(YELLOW))),
+        Line::from(""),
+    ];
+
+    for change in settings.changes(&app.config) {
+        lines.push(Line::from(Span::styled(format!("  {}", change.field.label()), Style::default().fg(MUTED))));
+        // Short values fit on one line; long paths get a line each
+        if change.old.chars().count() + change.new.chars().count() + 3 <= width {
+            lines.push(Line::from(vec![
+                Span::styled(format!("    {}", change.old), Style::default().fg(RED)),
+                Span::styled(" → ", Style::default().fg(MUTED)),
+                Span::styled(change.new, Style::default().fg(GREEN)),
+            ]));
+        } else {
+            lines.push(Line::from(Span::styled(format!("    {}", change.old), Style::default().fg(RED))));
+            lines.push(Line::from(vec![
+                Span::styled("  → ", Style::default().fg(MUTED)),
+                Span::styled(change.new, Style::default().fg(GREEN)),
+            ]));
+        }
+        lines.push(Line::from(""));
+    }
+
+    lines.push(Line::from(vec![
+        Span::styled("  ", Style::default()),
+        Span::styled("[Enter]", Style::default().fg(CYAN)),
+        Span::styled(" Apply  ", Style::default().fg(TEXT)),
+        Span::styled("[Esc]", Style::default().fg(MUTED)),
+        Span::styled(" Back to editing", Style::default().fg(TEXT)),
+    ]));
+
+    let popup = Paragraph::new(lines).block(popup_block(" Review Settings "));
+    f.render_widget(popup, area);
+}
+
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::events::{AppEvent, WorkerEvent};
+    use crate::testing::format;
+    use ratatui::{backend::TestBackend, Terminal};
+    use tokio::sync::mpsc;
+
+    fn queue(jobs: usize) -> (App, mpsc::Receiver<crate::events::WorkerCommand>) {
+        let (worker_tx, worker_rx) = mpsc::channel(256);
+        let mut app = App::new(Config::default(), worker_tx);
+        app.input_mode = false;
+        app.jobs.push(Job::playlist_placeholder("https://www.youtube.com/playlist?list=PLabove", None));
+        app.jobs.extend((0..jobs).map(|i| Job { title: Some(format!("Job {:02}", i)), status: JobStatus::Ready, ..Job::new(format!("https://example.com/v/{}", i)) }));
+        (app, worker_rx)
+    }
+
+    // The screen line the cursor is on and the text on it
+    fn cursor(terminal: &mut Terminal<TestBackend>, app: &App) -> (u16, String) {
+        terminal.draw(|f| render(f, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let width = buffer.area.width;
+        let lines: Vec<String> = (0..buffer.area.height).map(|y| (0..width).map(|x| buffer[(x, y)].symbol()).collect()).collect();
+        match lines.iter().position(|line| line.contains("> ")) {
+            Some(y) => (y as u16, lines[y].clone()),
+            None => panic!("no cursor on\n{}", lines.join("\n")),
+        }
+    }
+
+    fn title(line: &str) -> &str {
+        let start = line.find("Job ").unwrap();
+        &line[start..start + 6]
+    }
+
+    fn screen(terminal: &mut Terminal<TestBackend>, app: &App) -> Vec<String> {
+        terminal.draw(|f| render(f, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height).map(|y| (0..buffer.area.width).map(|x| buffer[(x, y)].symbol()).collect()).collect()
+    }
+
+    fn runs(spans: &[Span]) -> Vec<(String, bool)> {
+        spans.iter().map(|s| (s.content.to_string(), s.style.add_modifier.contains(Modifier::UNDERLINED))).collect()
+    }
+
+    #[test]
+    fn highlights_follow_chars_through_truncation() {
+        let style = Style::default();
+        let text = "日本語のタイトル — Never Gonna Give You Up";
+        let marks: Vec<usize> = [0, 1, 11, 12, 13, 14, 15, 32].into();
+        let owned = |parts: &[(&str, bool)]| parts.iter().map(|(t, m)| (t.to_string(), *m)).collect::<Vec<_>>();
+
+        assert_eq!(
+            runs(&highlighted(text, 100, &marks, style)),
+            owned(&[("日本", true), ("語のタイトル — ", false), ("Never", true), (" Gonna Give You ", false), ("U", true), ("p", false)])
+        );
+        // Marks in the cut part are dropped, not drawn on the ellipsis
+        assert_eq!(runs(&highlighted(text, 18, &marks, style)), owned(&[("日本", true), ("語のタイトル — ", false), ("Neve", true), ("...", false)]));
+        assert_eq!(runs(&highlighted(text, 3, &marks, style)), owned(&[("...", false)]));
+        assert_eq!(runs(&highlighted("", 10, &[], style)), owned(&[]));
+    }
+
+    #[test]
+    fn a_filtered_queue_underlines_what_matched() {
+        let (mut app, _worker_rx) = queue(3);
+        app.jobs[2].title = Some("Ça va — Never Gonna Give You Up".into());
+        app.queue_filter = Some(crate::events::QueueFilterState { query: "gonna".into(), editing: false });
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        terminal.draw(|f| render(f, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let underlined: String = (0..buffer.area.height)
+            .flat_map(|y| (0..buffer.area.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| buffer[(x, y)].modifier.contains(Modifier::UNDERLINED))
+            .map(|(x, y)| buffer[(x, y)].symbol().to_string())
+            .collect();
+        assert_eq!(underlined, "Gonna");
+    }
+
+    #[test]
+    fn the_status_bar_counts_the_queue_near_its_cap() {
+        let (mut app, _worker_rx) = queue(8);
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        app.config.max_queue_size = 11;
+        assert!(!screen(&mut terminal, &app).iter().any(|l| l.contains(" queue 9/11")));
+        app.config.max_queue_size = 10;
+        assert!(screen(&mut terminal, &app).iter().any(|l| l.contains(" queue 9/10")));
+        app.config.max_queue_size = 0;
+        assert!(!screen(&mut terminal, &app).iter().any(|l| l.contains(" queue 9/")));
+    }
+
+    #[test]
+    fn a_huge_format_list_only_draws_what_fits() {
+        let (mut app, _worker_rx) = queue(1);
+        let formats: Vec<crate::events::Format> = (0..500)
+            .map(|i| format(&format!(r#"{{"format_id":"a{}","ext":"webm","vcodec":"none","acodec":"opus","tbr":{}}}"#, i, 1000 - i)))
+            .collect();
+        let mut popup = FormatPopupState::new(1, formats.into());
+        popup.expand_selected();
+        app.format_popup = Some(popup);
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+
+        let lines = screen(&mut terminal, &app);
+        assert!(lines.iter().any(|l| l.contains("showing 20 of 500 — press * to show all")), "{}", lines.join("\n"));
+        let drawn = |lines: &[String]| lines.iter().filter(|l| l.contains("WEBM ·")).count();
+        let height = app.format_popup.as_ref().unwrap().viewport_height.get();
+        assert_eq!(drawn(&lines), height.min(1 + 20));
+
+        app.handle_event(AppEvent::FormatToggleShowAll);
+        app.handle_event(AppEvent::FormatSelectLast);
+        let lines = screen(&mut terminal, &app);
+        assert!(lines.iter().any(|l| l.contains("showing all — press * to shorten")));
+        assert_eq!(drawn(&lines), height);
+        assert!(lines.iter().any(|l| l.contains("▶ ")));
+    }
+
+    #[test]
+    fn the_format_popup_warns_when_a_merge_has_no_ffmpeg() {
+        let (mut app, _worker_rx) = queue(1);
+        let formats: Vec<crate::events::Format> = [r#"{"format_id":"137","ext":"mp4","vcodec":"avc1","acodec":"none","height":1080}"#, r#"{"format_id":"18","ext":"mp4","vcodec":"avc1","acodec":"mp4a","height":360}"#]
+            .into_iter()
+            .map(format)
+            .collect();
+        app.format_popup = Some(FormatPopupState::new(1, formats.into()));
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+
+        let lines = screen(&mut terminal, &app);
+        assert!(lines.iter().any(|l| l.contains("[V] video-only formats will be merged with best audio")), "{}", lines.join("\n"));
+        app.ffmpeg_missing = true;
+        let lines = screen(&mut terminal, &app);
+        assert!(lines.iter().any(|l| l.contains("[V] video-only formats need ffmpeg to merge, and it is missing")), "{}", lines.join("\n"));
+    }
+
+    #[test]
+    fn a_burst_of_insertions_leaves_the_cursor_in_place() {
+        let (mut app, _worker_rx) = queue(40);
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        cursor(&mut terminal, &app);
+        for _ in 0..20 {
+            app.handle_event(AppEvent::SelectNext);
+        }
+        let (row, line) = cursor(&mut terminal, &app);
+        assert_eq!(title(&line), "Job 19");
+        let selected = app.selected_job().map(|j| j.id);
+
+        // The playlist above the cursor opens out while links keep landing below
+        let id = app.jobs[0].id;
+        let urls = (0..8).map(|i| (format!("https://www.youtube.com/watch?v=above{}", i), Some(format!("Above {}", i)))).collect();
+        app.handle_event(AppEvent::Worker(WorkerEvent::PlaylistExpanded { id, url: "https://www.youtube.com/playlist?list=PLabove".into(), title: Some("Above".into()), urls }));
+        assert_eq!(cursor(&mut terminal, &app), (row, line.clone()));
+        assert_eq!(app.queue_offset, 8, "the list scrolled by the rows added above");
+        for i in 0..5 {
+            app.handle_event(AppEvent::AddUrl(format!("https://example.com/v/below{}", i)));
+            assert_eq!(cursor(&mut terminal, &app), (row, line.clone()));
+        }
+        assert_eq!(app.selected_job().map(|j| j.id), selected);
+
+        // The next key press acts on the job after the one under the cursor
+        app.handle_event(AppEvent::SelectNext);
+        let (next_row, next_line) = cursor(&mut terminal, &app);
+        assert_eq!(title(&next_line), "Job 20");
+        assert!(next_row >= row);
+    }
+
+    #[test]
+    fn insertions_below_do_not_scroll() {
+        let (mut app, _worker_rx) = queue(10);
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        cursor(&mut terminal, &app);
+        for _ in 0..3 {
+            app.handle_event(AppEvent::SelectNext);
+        }
+        let before = cursor(&mut terminal, &app);
+        let offset = app.queue_offset;
+        for i in 0..30 {
+            app.handle_event(AppEvent::AddUrl(format!("https://example.com/v/below{}", i)));
+        }
+        assert_eq!(cursor(&mut terminal, &app), before);
+        assert_eq!(app.queue_offset, offset);
+    }
+}
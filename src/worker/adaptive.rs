@@ -0,0 +1,153 @@
+use crate::core::DownloadError;
+
+// Consecutive throttle failures before a permit is withheld
+const THROTTLE_THRESHOLD: usize = 2;
+// Successful completions needed to give one permit back
+const RECOVERY_SUCCESSES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Adjustment {
+    Reduced,
+    Restored,
+    Unchanged,
+}
+
+#[derive(Debug)]
+pub struct AdaptiveConcurrency {
+    max: usize,
+    effective: usize,
+    consecutive_throttles: usize,
+    successes: usize,
+}
+
+impl AdaptiveConcurrency {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            effective: max,
+            consecutive_throttles: 0,
+            successes: 0,
+        }
+    }
+
+    pub fn effective(&self) -> usize {
+        self.effective
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    pub fn is_reduced(&self) -> bool {
+        self.effective < self.max
+    }
+
+    pub fn on_failure(&mut self, kind: DownloadError) -> Adjustment {
+        self.successes = 0;
+        if kind != DownloadError::Throttled {
+            self.consecutive_throttles = 0;
+            return Adjustment::Unchanged;
+        }
+
+        self.consecutive_throttles += 1;
+        if self.consecutive_throttles >= THROTTLE_THRESHOLD && self.effective > 1 {
+            self.consecutive_throttles = 0;
+            self.effective -= 1;
+            return Adjustment::Reduced;
+        }
+        Adjustment::Unchanged
+    }
+
+    pub fn on_success(&mut self) -> Adjustment {
+        self.consecutive_throttles = 0;
+        if !self.is_reduced() {
+            return Adjustment::Unchanged;
+        }
+
+        self.successes += 1;
+        if self.successes >= RECOVERY_SUCCESSES {
+            self.successes = 0;
+            self.effective += 1;
+            return Adjustment::Restored;
+        }
+        Adjustment::Unchanged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throttled(limiter: &mut AdaptiveConcurrency) -> Adjustment {
+        limiter.on_failure(DownloadError::Throttled)
+    }
+
+    #[test]
+    fn two_throttles_in_a_row_withhold_a_permit() {
+        let mut limiter = AdaptiveConcurrency::new(3);
+        assert_eq!(throttled(&mut limiter), Adjustment::Unchanged);
+        assert_eq!(throttled(&mut limiter), Adjustment::Reduced);
+        assert_eq!(limiter.effective(), 2);
+        assert!(limiter.is_reduced());
+
+        // The count starts over after each reduction
+        assert_eq!(throttled(&mut limiter), Adjustment::Unchanged);
+        assert_eq!(throttled(&mut limiter), Adjustment::Reduced);
+        assert_eq!(limiter.effective(), 1);
+    }
+
+    #[test]
+    fn never_drops_below_one() {
+        let mut limiter = AdaptiveConcurrency::new(1);
+        for _ in 0..5 {
+            assert_eq!(throttled(&mut limiter), Adjustment::Unchanged);
+        }
+        assert_eq!(limiter.effective(), 1);
+        assert!(!limiter.is_reduced());
+    }
+
+    #[test]
+    fn other_failures_and_successes_break_a_throttle_streak() {
+        let mut limiter = AdaptiveConcurrency::new(3);
+        throttled(&mut limiter);
+        assert_eq!(limiter.on_failure(DownloadError::NotFound), Adjustment::Unchanged);
+        assert_eq!(throttled(&mut limiter), Adjustment::Unchanged);
+
+        limiter.on_success();
+        assert_eq!(throttled(&mut limiter), Adjustment::Unchanged);
+        assert_eq!(limiter.effective(), 3);
+    }
+
+    #[test]
+    fn three_successes_give_a_permit_back() {
+        let mut limiter = AdaptiveConcurrency::new(3);
+        throttled(&mut limiter);
+        throttled(&mut limiter);
+        throttled(&mut limiter);
+        throttled(&mut limiter);
+        assert_eq!(limiter.effective(), 1);
+
+        let steps: Vec<Adjustment> = (0..6).map(|_| limiter.on_success()).collect();
+        use Adjustment::*;
+        assert_eq!(steps, vec![Unchanged, Unchanged, Restored, Unchanged, Unchanged, Restored]);
+        assert_eq!(limiter.effective(), 3);
+        assert!(!limiter.is_reduced());
+
+        // Already at the limit, so nothing more to give back
+        assert_eq!(limiter.on_success(), Adjustment::Unchanged);
+        assert_eq!(limiter.effective(), limiter.max());
+    }
+
+    #[test]
+    fn a_failure_resets_recovery() {
+        let mut limiter = AdaptiveConcurrency::new(2);
+        throttled(&mut limiter);
+        throttled(&mut limiter);
+        limiter.on_success();
+        limiter.on_success();
+        limiter.on_failure(DownloadError::NotFound);
+        assert_eq!(limiter.on_success(), Adjustment::Unchanged);
+        assert_eq!(limiter.on_success(), Adjustment::Unchanged);
+        assert_eq!(limiter.on_success(), Adjustment::Restored);
+    }
+}
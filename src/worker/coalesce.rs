@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+
+use crate::core::{short_id, video_id, DownloadRequest, JobId, WorkerEvent};
+
+// Two share links for one video fetch the same formats; YouTube ids catch the
+// different link forms, anything else has to match exactly
+pub fn fetch_key(url: &str) -> String {
+    let url = url.trim();
+    video_id(url).map(|id| format!("youtube:{}", id)).unwrap_or_else(|| url.to_string())
+}
+
+// Downloads only share a run when they'd write the very same file
+pub fn download_key(request: &DownloadRequest) -> String {
+    let output = request.output.as_ref().map(|o| o.path_template().to_string_lossy().into_owned()).unwrap_or_default();
+    let sections = request.sections.as_ref().map(|(start, end)| format!("{}-{}", start, end)).unwrap_or_default();
+    let container = request.convert.map(|(container, _)| container.label()).unwrap_or_default();
+    format!("{}|{}|{}|{}|{}|{}", fetch_key(&request.url), request.format_spec, request.extract_audio, output, sections, container)
+}
+
+struct Run {
+    // The job the run was started for; the runner reports everything under its id
+    leader: JobId,
+    // Everyone still interested, the leader included unless it was cancelled
+    members: Vec<JobId>,
+}
+
+// yt-dlp runs shared by several jobs, by fetch_key or download_key
+#[derive(Clone, Default)]
+pub struct SharedRuns {
+    runs: Arc<Mutex<HashMap<String, Run>>>,
+}
+
+impl SharedRuns {
+    // Attaches `id` to a run already under way for `key`; returns whose run it is
+    pub async fn join(&self, key: &str, id: JobId) -> Option<JobId> {
+        let mut runs = self.runs.lock().await;
+        let run = runs.get_mut(key)?;
+        if !run.members.contains(&id) {
+            run.members.push(id);
+        }
+        tracing::info!("{} shares the run of {}", short_id(id), short_id(run.leader));
+        Some(run.leader)
+    }
+
+    // Registers a run for `leader`. Its events go through the returned sender,
+    // which hands a copy to every member until the run ends
+    pub async fn start(&self, key: String, leader: JobId, event_tx: mpsc::Sender<WorkerEvent>) -> mpsc::Sender<WorkerEvent> {
+        self.runs.lock().await.insert(key.clone(), Run { leader, members: vec![leader] });
+
+        let (tx, mut rx) = mpsc::channel::<WorkerEvent>(32);
+        let runs = self.runs.clone();
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                let members = {
+                    let mut runs = runs.lock().await;
+                    let members = match runs.get(&key) {
+                        Some(run) if run.leader == leader && event.job_id() == Some(leader) => run.members.clone(),
+                        _ => vec![],
+                    };
+                    // Anyone asking after this point needs a run of their own
+                    if !members.is_empty() && event.ends_run() {
+                        runs.remove(&key);
+                    }
+                    members
+                };
+                if members.is_empty() {
+                    let _ = event_tx.send(event).await;
+                    continue;
+                }
+                for id in members {
+                    let _ = event_tx.send(event.for_job(id)).await;
+                }
+            }
+            // Cancelled runs end without a final event
+            let mut runs = runs.lock().await;
+            if runs.get(&key).is_some_and(|run| run.leader == leader) {
+                runs.remove(&key);
+            }
+        });
+        tx
+    }
+
+    // Detaches `id` from its run and says whose run to stop: None while others
+    // still share it, the run's leader once `id` was the last one on it (the
+    // runner and the queues know the run by that id), or `id` itself when it
+    // isn't on a run here
+    pub async fn leave(&self, id: JobId) -> Option<JobId> {
+        let mut runs = self.runs.lock().await;
+        let Some((key, run)) = runs.iter_mut().find(|(_, run)| run.members.contains(&id)) else {
+            return Some(id);
+        };
+        run.members.retain(|&m| m != id);
+        if !run.members.is_empty() {
+            return None;
+        }
+        let leader = run.leader;
+        // Nobody may join a run that is about to be stopped
+        let key = key.clone();
+        runs.remove(&key);
+        Some(leader)
+    }
+
+    // Jobs riding along on `leader`'s run
+    pub async fn followers(&self, leader: JobId) -> Vec<JobId> {
+        let runs = self.runs.lock().await;
+        runs.values()
+            .find(|run| run.leader == leader)
+            .map(|run| run.members.iter().copied().filter(|&m| m != leader).collect())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,194 @@
+use crate::core::DownloadError;
+
+const MEMBERS_ONLY_MARKERS: &[&str] = &[
+    "members-only",
+    "members only",
+    "join this channel to get access",
+    "available to this channel's members",
+    "requires a youtube premium",
+    "premium members",
+];
+
+const THROTTLED_MARKERS: &[&str] = &["http error 429", "too many requests", "rate-limited"];
+
+const AGE_MARKERS: &[&str] = &[
+    "sign in to confirm your age",
+    "age-restricted",
+    "age restricted",
+    "inappropriate for some users",
+];
+
+const LOGIN_WALL_MARKERS: &[&str] = &[
+    "sign in to confirm",
+    "login required",
+    "requires authentication",
+    "use --cookies",
+    "--username",
+];
+
+const PRIVATE_MARKERS: &[&str] = &["private video", "video is private"];
+
+const GEO_MARKERS: &[&str] = &[
+    "not available in your country",
+    "available in your country",
+    "geo restriction",
+    "geo-restricted",
+    "blocked it in your country",
+];
+
+const NOT_FOUND_MARKERS: &[&str] = &[
+    "http error 404",
+    "video unavailable",
+    "video has been removed",
+    "no longer available",
+    "does not exist",
+    "has been terminated",
+];
+
+const DISK_FULL_MARKERS: &[&str] = &["no space left on device", "errno 28", "disk full", "not enough space"];
+
+const FFMPEG_MARKERS: &[&str] = &["ffmpeg is not installed", "ffmpeg not found", "--ffmpeg-location"];
+
+const NETWORK_MARKERS: &[&str] = &[
+    "unable to download webpage",
+    "connection reset",
+    "connection refused",
+    "connection aborted",
+    "timed out",
+    "name or service not known",
+    "temporary failure in name resolution",
+    "getaddrinfo failed",
+    "network is unreachable",
+    "remote end closed connection",
+];
+
+// The text of a yt-dlp `WARNING:` line
+pub fn warning_text(line: &str) -> Option<&str> {
+    line.strip_prefix("WARNING:").map(str::trim).filter(|w| !w.is_empty())
+}
+
+// Only yt-dlp's wording for a format that isn't there; other warnings fall back
+// too, such as "Falling back on generic information extractor"
+const FORMAT_FALLBACK_MARKERS: &[&str] = &["requested format is not available", "requested format not available"];
+
+// The file may not be in the quality that was picked, e.g. "Requested format
+// is not available, falling back to best"
+pub fn is_format_fallback(warning: &str) -> bool {
+    let lower = warning.to_lowercase();
+    FORMAT_FALLBACK_MARKERS.iter().any(|m| lower.contains(m))
+}
+
+// "Sign in to confirm your age" and "Sign in to confirm you're not a bot";
+// a cookies file from a signed-in browser gets past both
+pub fn wants_cookies(message: &str) -> bool {
+    message.to_lowercase().contains("sign in to confirm")
+}
+
+// Checked in order: the specific reasons come before "Video unavailable", which
+// yt-dlp puts in front of most of them, and before the network markers, which
+// any of them can trail
+pub fn classify(message: &str) -> DownloadError {
+    const ORDER: &[(&[&str], DownloadError)] = &[
+        (THROTTLED_MARKERS, DownloadError::Throttled),
+        (MEMBERS_ONLY_MARKERS, DownloadError::MembersOnly),
+        (AGE_MARKERS, DownloadError::AgeRestricted),
+        // yt-dlp tells private videos to use --cookies too
+        (PRIVATE_MARKERS, DownloadError::Private),
+        (LOGIN_WALL_MARKERS, DownloadError::LoginRequired),
+        (GEO_MARKERS, DownloadError::GeoBlocked),
+        (DISK_FULL_MARKERS, DownloadError::DiskFull),
+        (FFMPEG_MARKERS, DownloadError::FfmpegMissing),
+        (NOT_FOUND_MARKERS, DownloadError::NotFound),
+        (NETWORK_MARKERS, DownloadError::Network),
+    ];
+    let lower = message.to_lowercase();
+    ORDER
+        .iter()
+        .find(|(markers, _)| markers.iter().any(|m| lower.contains(m)))
+        .map_or(DownloadError::Unknown, |(_, kind)| *kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warnings_are_read_off_their_lines() {
+        assert_eq!(warning_text("WARNING: [youtube] dQw4w9WgXcQ: nsig extraction failed"), Some("[youtube] dQw4w9WgXcQ: nsig extraction failed"));
+        assert_eq!(warning_text("WARNING:   "), None);
+        assert_eq!(warning_text("[download]  42.0% of 10.00MiB"), None);
+        assert_eq!(warning_text("ERROR: [youtube] dQw4w9WgXcQ: Video unavailable"), None);
+    }
+
+    #[test]
+    fn only_a_missing_format_is_a_format_fallback() {
+        // Captured from yt-dlp runs
+        let fallbacks = [
+            "WARNING: [youtube] dQw4w9WgXcQ: Requested format is not available, falling back to best",
+            "WARNING: Requested format is not available. Use --list-formats for a list of available formats",
+            "WARNING: [vimeo] 76979871: Requested format not available, trying best",
+        ];
+        let others = [
+            "WARNING: Falling back on generic information extractor",
+            "WARNING: [generic] Falling back on generic information extractor.",
+            "WARNING: [youtube] dQw4w9WgXcQ: nsig extraction failed: Some formats may be missing",
+            "WARNING: Requested formats are incompatible for merge and will be merged into mkv",
+            "WARNING: [youtube] Unable to download webpage, falling back to the API",
+        ];
+        for line in fallbacks {
+            assert!(warning_text(line).is_some_and(is_format_fallback), "{}", line);
+        }
+        for line in others {
+            assert!(!warning_text(line).is_some_and(is_format_fallback), "{}", line);
+        }
+    }
+
+    #[test]
+    fn sample_errors_map_to_their_kind() {
+        // Captured from yt-dlp runs
+        let samples = [
+            ("ERROR: [youtube] dQw4w9WgXcQ: Video unavailable. This video has been removed by the uploader", DownloadError::NotFound),
+            ("ERROR: [youtube] dQw4w9WgXcQ: Video unavailable. This video is no longer available because the YouTube account associated with this video has been terminated.", DownloadError::NotFound),
+            ("ERROR: unable to download video data: HTTP Error 404: Not Found", DownloadError::NotFound),
+            ("ERROR: [youtube] dQw4w9WgXcQ: Private video. Sign in if you've been granted access to this video. Use --cookies-from-browser or --cookies for the authentication.", DownloadError::Private),
+            ("ERROR: [vimeo] 76979871: This video is private", DownloadError::Private),
+            ("ERROR: [youtube] dQw4w9WgXcQ: Video unavailable. The uploader has not made this video available in your country", DownloadError::GeoBlocked),
+            ("ERROR: [BBC] p0abc123: This video is not available from your location due to geo restriction", DownloadError::GeoBlocked),
+            ("ERROR: [youtube] dQw4w9WgXcQ: Sign in to confirm your age. This video may be inappropriate for some users. Use --cookies-from-browser or --cookies for the authentication.", DownloadError::AgeRestricted),
+            ("ERROR: [youtube] dQw4w9WgXcQ: Sign in to confirm you’re not a bot. Use --cookies-from-browser or --cookies for the authentication.", DownloadError::LoginRequired),
+            ("ERROR: [instagram] C1abcDEF: Requested content is not available, rate-limit reached or login required. Use --cookies, --cookies-from-browser, --username and --password", DownloadError::LoginRequired),
+            ("ERROR: [youtube] dQw4w9WgXcQ: Join this channel to get access to members-only content like this video, and other exclusive perks.", DownloadError::MembersOnly),
+            ("ERROR: [youtube] dQw4w9WgXcQ: This video is only available to Music Premium members", DownloadError::MembersOnly),
+            ("ERROR: [youtube] dQw4w9WgXcQ: Unable to download API page: HTTP Error 429: Too Many Requests (caused by <HTTPError 429: Too Many Requests>)", DownloadError::Throttled),
+            ("ERROR: [youtube] dQw4w9WgXcQ: Unable to download webpage: <urlopen error [Errno -3] Temporary failure in name resolution> (caused by TransportError)", DownloadError::Network),
+            ("ERROR: [download] Got error: HTTPSConnectionPool(host='rr3---sn-q4fl6n7s.googlevideo.com', port=443): Read timed out.", DownloadError::Network),
+            ("ERROR: unable to download video data: ('Connection aborted.', ConnectionResetError(104, 'Connection reset by peer'))", DownloadError::Network),
+            ("ERROR: unable to write data: [Errno 28] No space left on device", DownloadError::DiskFull),
+            ("ERROR: You have requested merging of multiple formats but ffmpeg is not installed. Aborting due to --abort-on-error", DownloadError::FfmpegMissing),
+            ("ERROR: Postprocessing: ffprobe and ffmpeg not found. Please install or provide the path using --ffmpeg-location", DownloadError::FfmpegMissing),
+            ("ERROR: [generic] Unsupported URL: https://example.com/page", DownloadError::Unknown),
+            ("", DownloadError::Unknown),
+        ];
+        for (stderr, kind) in samples {
+            assert_eq!(classify(stderr), kind, "{}", stderr);
+        }
+
+        // Every kind but Cancelled, which the worker sets itself, comes out of some sample
+        let all = [
+            DownloadError::NotFound,
+            DownloadError::Private,
+            DownloadError::GeoBlocked,
+            DownloadError::AgeRestricted,
+            DownloadError::LoginRequired,
+            DownloadError::MembersOnly,
+            DownloadError::Throttled,
+            DownloadError::Network,
+            DownloadError::DiskFull,
+            DownloadError::FfmpegMissing,
+            DownloadError::Unknown,
+        ];
+        for kind in all {
+            assert!(samples.iter().any(|(_, k)| *k == kind), "no sample for {:?}", kind);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use crate::core::DownloadError;
+
+// Failures in a row, all quick and all alike, before the queue is halted
+const THRESHOLD: usize = 5;
+// A failure this soon after the start never really got going
+const IMMEDIATE: Duration = Duration::from_secs(15);
+
+// Spots yt-dlp itself being broken, e.g. after a site change, when every job
+// fails straight away with the same error, so the queue can stop instead of
+// failing through every remaining item
+#[derive(Debug, Default)]
+pub struct FailureStreak {
+    signature: Option<String>,
+    count: usize,
+}
+
+impl FailureStreak {
+    // Returns the shared error once the streak reaches the threshold
+    pub fn record(&mut self, failure: Option<(DownloadError, &str)>, elapsed: Duration) -> Option<String> {
+        let signature = match failure {
+            // Sign-in walls are about the video, not about yt-dlp
+            Some((kind, _)) if kind.is_login_wall() => None,
+            Some((_, message)) if elapsed < IMMEDIATE => Some(signature(message)),
+            _ => None,
+        };
+        let Some(signature) = signature else {
+            self.reset();
+            return None;
+        };
+
+        if self.signature.as_ref() == Some(&signature) {
+            self.count += 1;
+        } else {
+            self.signature = Some(signature);
+            self.count = 1;
+        }
+        if self.count < THRESHOLD {
+            return None;
+        }
+        self.count = 0;
+        self.signature.clone()
+    }
+
+    pub fn reset(&mut self) {
+        self.signature = None;
+        self.count = 0;
+    }
+}
+
+// "ERROR: [youtube] dQw4w9WgXcQ: Unable to extract ..." differs per video only
+// in the id, so that prefix is dropped before comparing
+fn signature(message: &str) -> String {
+    let line = message.lines().find(|l| l.starts_with("ERROR:")).unwrap_or(message).trim();
+    let line = line.strip_prefix("ERROR:").unwrap_or(line).trim_start();
+    // Only a line with an extractor tag has an id after it
+    let Some((_, line)) = line.strip_prefix('[').and_then(|rest| rest.split_once(']')) else {
+        return line.to_string();
+    };
+    let line = line.trim_start();
+    match line.split_once(": ") {
+        Some((id, rest)) if !id.contains(' ') => rest.to_string(),
+        _ => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUICK: Duration = Duration::from_secs(2);
+
+    fn broken(id: &str) -> String {
+        format!("ERROR: [youtube] {}: Unable to extract uploader id; please report this issue", id)
+    }
+
+    fn fail(streak: &mut FailureStreak, message: &str, elapsed: Duration) -> Option<String> {
+        streak.record(Some((DownloadError::Unknown, message)), elapsed)
+    }
+
+    #[test]
+    fn the_same_quick_failure_five_times_halts() {
+        let mut streak = FailureStreak::default();
+        for id in ["aaaaaaaaaaa", "bbbbbbbbbbb", "ccccccccccc", "ddddddddddd"] {
+            assert_eq!(fail(&mut streak, &broken(id), QUICK), None);
+        }
+        assert_eq!(fail(&mut streak, &broken("eeeeeeeeeee"), QUICK).as_deref(), Some("Unable to extract uploader id; please report this issue"));
+
+        // A halt starts the count over
+        assert_eq!(fail(&mut streak, &broken("fffffffffff"), QUICK), None);
+    }
+
+    #[test]
+    fn anything_else_breaks_the_streak() {
+        let breaks: [(Option<(DownloadError, &str)>, Duration); 4] = [
+            (None, QUICK),
+            (Some((DownloadError::Unknown, "ERROR: [youtube] xxxxxxxxxxx: Unable to extract uploader id; please report this issue")), IMMEDIATE),
+            (Some((DownloadError::LoginRequired, "ERROR: [youtube] xxxxxxxxxxx: Sign in to confirm your age")), QUICK),
+            (Some((DownloadError::Unknown, "ERROR: [youtube] xxxxxxxxxxx: HTTP Error 500")), QUICK),
+        ];
+        for (failure, elapsed) in breaks {
+            let mut streak = FailureStreak::default();
+            for id in ["aaaaaaaaaaa", "bbbbbbbbbbb", "ccccccccccc", "ddddddddddd"] {
+                fail(&mut streak, &broken(id), QUICK);
+            }
+            let interrupted = streak.record(failure, elapsed);
+            assert_eq!(interrupted, None, "{:?}", failure);
+            assert_eq!(fail(&mut streak, &broken("eeeeeeeeeee"), QUICK), None, "{:?} after {:?}", failure, elapsed);
+        }
+    }
+
+    #[test]
+    fn signatures_ignore_the_video_id() {
+        for (message, expected) in [
+            (broken("dQw4w9WgXcQ").as_str(), "Unable to extract uploader id; please report this issue"),
+            ("WARNING: something\nERROR: [vimeo] 76979871: Unable to download JSON metadata", "Unable to download JSON metadata"),
+            ("ERROR: Unable to download webpage: timed out", "Unable to download webpage: timed out"),
+            ("ERROR: [generic] Unsupported URL: https://example.com", "Unsupported URL: https://example.com"),
+            ("yt-dlp: command not found", "yt-dlp: command not found"),
+        ] {
+            assert_eq!(signature(message), expected, "{:?}", message);
+        }
+    }
+}
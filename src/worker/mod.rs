@@ -1,139 +1,589 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
-use tokio::sync::mpsc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
 use tokio_util::sync::CancellationToken;
 
 use crate::config::Config;
-use crate::events::{AppEvent, JobId, WorkerCommand};
+use crate::core::{short_id, Credentials, DownloadError, Failure, WorkerEvent, DownloadRequest, JobId, WorkerCommand};
 
+mod adaptive;
+mod coalesce;
+mod errors;
+mod halt;
+mod process;
+mod runner;
+mod titles;
 mod ytdlp;
 
+pub use errors::{classify, is_format_fallback, wants_cookies};
+pub use runner::{Ytdlp, YtdlpRunner};
+pub use titles::{oembed, OEmbed};
+pub use ytdlp::{command_line, download_args, runnable_command_line, shell_join, standalone_args, PlaylistEntries};
+
+use adaptive::{AdaptiveConcurrency, Adjustment};
+use coalesce::SharedRuns;
+use halt::FailureStreak;
+use titles::TitleRequest;
+
+// Background format fetches for playlist entries running at once
+const HYDRATE_SLOTS: usize = 3;
+
 type ActiveJobsMap = HashMap<JobId, CancellationToken>;
+type Outcome = Result<(), DownloadError>;
+// Sent whenever a download releases its slot; None for cancelled jobs, which
+// say nothing about the server
+type Finished = Option<Attempt>;
+
+struct Attempt {
+    outcome: Outcome,
+    // yt-dlp's error, empty on success
+    message: String,
+    elapsed: Duration,
+}
 
-pub struct WorkerPool {
+pub struct WorkerPool<R: YtdlpRunner = Ytdlp> {
+    runner: Arc<R>,
     config: Arc<Config>,
+    // Set by watch_config; each new config applies to the runs started after it
+    config_rx: Option<watch::Receiver<Arc<Config>>>,
     command_rx: mpsc::Receiver<WorkerCommand>,
-    event_tx: mpsc::Sender<AppEvent>,
+    event_tx: mpsc::Sender<WorkerEvent>,
     active_jobs: Arc<Mutex<ActiveJobsMap>>,
+    semaphore: Arc<Semaphore>,
+    pending: VecDeque<DownloadRequest>,
+    // Playlist entries waiting for a background format fetch, and those fetching
+    hydrate_queue: VecDeque<(JobId, String)>,
+    hydrating: HashSet<JobId>,
+    hydrated_tx: mpsc::UnboundedSender<JobId>,
+    hydrated_rx: mpsc::UnboundedReceiver<JobId>,
+    // Jobs whose dependents may start
+    released: HashSet<JobId>,
+    adaptive: Option<AdaptiveConcurrency>,
+    finished_tx: mpsc::UnboundedSender<Finished>,
+    finished_rx: mpsc::UnboundedReceiver<Finished>,
+    title_tx: mpsc::UnboundedSender<TitleRequest>,
+    title_rx: Option<mpsc::UnboundedReceiver<TitleRequest>>,
+    title_cancel: CancellationToken,
+    // Fetches and downloads several jobs are waiting on at once
+    shared: SharedRuns,
+    // The sender each registered download reports through, until it is spawned
+    run_tx: HashMap<JobId, mpsc::Sender<WorkerEvent>>,
+    streak: FailureStreak,
+    // Set after a run of identical instant failures; pending jobs stay put until resumed
+    halted: bool,
+    // While halted: one job may go as a probe, and whether it is still running
+    probe_requested: bool,
+    probing: bool,
+    // Held by the app, e.g. while on battery; running downloads are left alone
+    paused: bool,
+    // No download starts while yt-dlp is being replaced; updated_rx says when it's done
+    updating: bool,
+    updated_tx: mpsc::UnboundedSender<()>,
+    updated_rx: mpsc::UnboundedReceiver<()>,
+}
+
+// The next config from watch_config; never resolves without one
+async fn config_changed(config_rx: &mut Option<watch::Receiver<Arc<Config>>>) -> Option<Arc<Config>> {
+    let Some(rx) = config_rx.as_mut() else {
+        return std::future::pending().await;
+    };
+    if rx.changed().await.is_err() {
+        // The sender is gone, so this config is the last one
+        *config_rx = None;
+        return None;
+    }
+    Some(rx.borrow_and_update().clone())
 }
 
 impl WorkerPool {
     pub fn new(
         config: Arc<Config>,
         command_rx: mpsc::Receiver<WorkerCommand>,
-        event_tx: mpsc::Sender<AppEvent>,
+        event_tx: mpsc::Sender<WorkerEvent>,
     ) -> Self {
+        Self::with_runner(Ytdlp, config, command_rx, event_tx)
+    }
+}
+
+impl<R: YtdlpRunner> WorkerPool<R> {
+    pub fn with_runner(
+        runner: R,
+        config: Arc<Config>,
+        command_rx: mpsc::Receiver<WorkerCommand>,
+        event_tx: mpsc::Sender<WorkerEvent>,
+    ) -> Self {
+        let max = config.max_concurrent_downloads;
+        let adaptive = config.adaptive_concurrency.then(|| AdaptiveConcurrency::new(max));
+        let (finished_tx, finished_rx) = mpsc::unbounded_channel();
+        let (title_tx, title_rx) = mpsc::unbounded_channel();
+        let (hydrated_tx, hydrated_rx) = mpsc::unbounded_channel();
+        let (updated_tx, updated_rx) = mpsc::unbounded_channel();
         Self {
+            runner: Arc::new(runner),
             config,
+            config_rx: None,
             command_rx,
             event_tx,
             active_jobs: Arc::new(Mutex::new(HashMap::new())),
+            semaphore: Arc::new(Semaphore::new(max)),
+            pending: VecDeque::new(),
+            hydrate_queue: VecDeque::new(),
+            hydrating: HashSet::new(),
+            hydrated_tx,
+            hydrated_rx,
+            released: HashSet::new(),
+            adaptive,
+            finished_tx,
+            finished_rx,
+            title_tx,
+            title_rx: Some(title_rx),
+            title_cancel: CancellationToken::new(),
+            shared: SharedRuns::default(),
+            run_tx: HashMap::new(),
+            streak: FailureStreak::default(),
+            halted: false,
+            probe_requested: false,
+            probing: false,
+            paused: false,
+            updating: false,
+            updated_tx,
+            updated_rx,
         }
     }
 
+    // Follows a config that changes while running, instead of UpdateConfig
+    pub fn watch_config(mut self, config_rx: watch::Receiver<Arc<Config>>) -> Self {
+        self.config = config_rx.borrow().clone();
+        self.config_rx = Some(config_rx);
+        self
+    }
+
     pub async fn run(mut self) {
-        let semaphore = Arc::new(tokio::sync::Semaphore::new(
-            self.config.max_concurrent_downloads,
-        ));
-
-        while let Some(cmd) = self.command_rx.recv().await {
-            match cmd {
-                WorkerCommand::FetchFormats { job_id, url } => {
-                    let event_tx = self.event_tx.clone();
-                    tokio::spawn(async move {
-                        if let Err(e) = ytdlp::fetch_formats(job_id, &url, event_tx.clone()).await {
-                            let _ = event_tx
-                                .send(AppEvent::JobFailed {
-                                    id: job_id,
-                                    error: e.to_string(),
-                                })
-                                .await;
+        if let Some(title_rx) = self.title_rx.take() {
+            tokio::spawn(titles::run_resolver(title_rx, self.event_tx.clone(), self.title_cancel.clone()));
+        }
+
+        loop {
+            tokio::select! {
+                cmd = self.command_rx.recv() => {
+                    let Some(cmd) = cmd else { break };
+                    // A job started right after a settings change must not race the change
+                    if let Some(config_rx) = &mut self.config_rx {
+                        if config_rx.has_changed().unwrap_or(false) {
+                            self.config = config_rx.borrow_and_update().clone();
                         }
-                    });
+                    }
+                    if !self.handle_command(cmd).await {
+                        break;
+                    }
+                }
+                Some(finished) = self.finished_rx.recv() => {
+                    if let Some(attempt) = finished {
+                        self.record_outcome(attempt.outcome).await;
+                        self.check_streak(attempt).await;
+                    }
+                    self.dispatch_pending().await;
                 }
+                Some(id) = self.hydrated_rx.recv() => {
+                    self.hydrating.remove(&id);
+                    self.dispatch_hydration().await;
+                }
+                Some(()) = self.updated_rx.recv() => {
+                    self.updating = false;
+                    self.dispatch_pending().await;
+                }
+                Some(config) = config_changed(&mut self.config_rx) => {
+                    self.config = config;
+                }
+            }
+        }
+    }
 
-                WorkerCommand::FetchPlaylist { url } => {
-                    let event_tx = self.event_tx.clone();
-                    tokio::spawn(async move {
-                        if let Ok(urls) = ytdlp::fetch_playlist(&url).await {
-                            let _ = event_tx.send(AppEvent::PlaylistExpanded { urls }).await;
-                        }
-                    });
+    // False when the job joined a fetch already running for the same URL. A
+    // background fetch reports on hydrated_tx when it ends, freeing its slot
+    async fn spawn_fetch(&mut self, job_id: JobId, url: String, credentials: Option<Credentials>, background: bool) -> bool {
+        // A sign-in retry needs its own run with the credentials
+        let key = coalesce::fetch_key(&url);
+        if credentials.is_none() {
+            if let Some(leader) = self.shared.join(&key, job_id).await {
+                let _ = self.event_tx.send(WorkerEvent::Coalesced { id: job_id, with: leader }).await;
+                return false;
+            }
+        }
+
+        // Tracked like a download so CancelJob on a fetching job stops it
+        let cancel_token = CancellationToken::new();
+        self.active_jobs.lock().await.insert(job_id, cancel_token.clone());
+
+        let event_tx = self.shared.start(key, job_id, self.event_tx.clone()).await;
+        let config = self.config.clone();
+        let active_jobs = self.active_jobs.clone();
+        let runner = self.runner.clone();
+        let hydrated_tx = background.then(|| self.hydrated_tx.clone());
+        tokio::spawn(async move {
+            let result = runner
+                .fetch_formats(job_id, &url, credentials.as_ref(), &config, event_tx.clone(), cancel_token.clone())
+                .await;
+            active_jobs.lock().await.remove(&job_id);
+            if let Some(hydrated_tx) = hydrated_tx {
+                let _ = hydrated_tx.send(job_id);
+            }
+            if cancel_token.is_cancelled() {
+                return;
+            }
+            if let Err(e) = result {
+                let _ = event_tx
+                    .send(WorkerEvent::JobFailed {
+                        id: job_id,
+                        error: Failure::new(e.to_string()),
+                    })
+                    .await;
+            }
+        });
+        true
+    }
+
+    // Playlist entries fetch their formats a few at a time so a long playlist
+    // doesn't start hundreds of yt-dlp processes at once
+    async fn dispatch_hydration(&mut self) {
+        while self.hydrating.len() < HYDRATE_SLOTS {
+            let Some((job_id, url)) = self.hydrate_queue.pop_front() else {
+                break;
+            };
+            if self.spawn_fetch(job_id, url, None, true).await {
+                self.hydrating.insert(job_id);
+            }
+        }
+    }
+
+    // Returns false once the pool should shut down
+    async fn handle_command(&mut self, cmd: WorkerCommand) -> bool {
+        match cmd {
+            WorkerCommand::FetchFormats { job_id, url, credentials } => {
+                self.spawn_fetch(job_id, url, credentials, false).await;
+            }
+
+            WorkerCommand::HydrateFormats(entries) => {
+                self.hydrate_queue.extend(entries);
+                self.dispatch_hydration().await;
+            }
+
+            WorkerCommand::PromoteFetch { job_id, url } => {
+                // Already fetching; its formats are on the way
+                if self.hydrating.contains(&job_id) {
+                    return true;
                 }
+                let entry = match self.hydrate_queue.iter().position(|(id, _)| *id == job_id) {
+                    Some(pos) => self.hydrate_queue.remove(pos),
+                    None => Some((job_id, url)),
+                };
+                if let Some(entry) = entry {
+                    self.hydrate_queue.push_front(entry);
+                }
+                self.dispatch_hydration().await;
+            }
 
-                WorkerCommand::StartJob { job_id, url, format_id } => {
-                    let permit = semaphore.clone().acquire_owned().await;
-                    if permit.is_err() {
-                        continue;
-                    }
-                    let permit = permit.unwrap();
+            WorkerCommand::FetchPlaylist { job_id, url, items } => {
+                // Tracked like a download so CancelJob on the placeholder stops it
+                let cancel_token = CancellationToken::new();
+                self.active_jobs.lock().await.insert(job_id, cancel_token.clone());
+
+                let event_tx = self.event_tx.clone();
+                let config = self.config.clone();
+                let active_jobs = self.active_jobs.clone();
+                let runner = self.runner.clone();
+                tokio::spawn(async move {
+                    let result = runner.fetch_playlist(job_id, &url, items.as_deref(), &config, event_tx.clone(), cancel_token.clone()).await;
+                    active_jobs.lock().await.remove(&job_id);
+                    let event = match result {
+                        Ok((title, urls)) => WorkerEvent::PlaylistExpanded { id: job_id, url, title, urls },
+                        Err(_) if cancel_token.is_cancelled() => return,
+                        Err(e) => WorkerEvent::PlaylistFailed { id: job_id, url, error: e.to_string() },
+                    };
+                    let _ = event_tx.send(event).await;
+                });
+            }
 
-                    let cancel_token = CancellationToken::new();
-                    {
-                        let mut jobs = self.active_jobs.lock().await;
-                        jobs.insert(job_id, cancel_token.clone());
+            WorkerCommand::ResolveTitle { job_id, url } => {
+                let config = self.config.clone();
+                let _ = self.title_tx.send(TitleRequest { job_id, url, config });
+            }
+
+            WorkerCommand::StartJob(request) => {
+                let job_id = request.job_id;
+                if let Some(leader) = self.shared.join(&coalesce::download_key(&request), job_id).await {
+                    if leader == job_id {
+                        tracing::warn!("Ignoring duplicate StartJob for {}", short_id(job_id));
+                        return true;
                     }
+                    let _ = self.event_tx.send(WorkerEvent::StartAccepted { id: job_id }).await;
+                    // A run still waiting for a slot reports the follower in SlotQueueChanged
+                    if self.pending.iter().any(|r| r.job_id == leader) {
+                        self.report_waiting().await;
+                    } else {
+                        let _ = self.event_tx.send(WorkerEvent::JobStarted { id: job_id }).await;
+                    }
+                    let _ = self.event_tx.send(WorkerEvent::Coalesced { id: job_id, with: leader }).await;
+                    return true;
+                }
+                let is_pending = self.pending.iter().any(|r| r.job_id == job_id);
+                if is_pending || self.active_jobs.lock().await.contains_key(&job_id) {
+                    tracing::warn!("Ignoring duplicate StartJob for {}", short_id(job_id));
+                    return true;
+                }
+                if self.waits_on_itself(&request) {
+                    let error = Failure::new("Dependency cycle: this item ends up waiting on itself");
+                    let _ = self.event_tx.send(WorkerEvent::JobFailed { id: job_id, error }).await;
+                    return true;
+                }
+                let _ = self.event_tx.send(WorkerEvent::StartAccepted { id: job_id }).await;
 
-                    let event_tx = self.event_tx.clone();
-                    let config = self.config.clone();
-                    let active_jobs = self.active_jobs.clone();
-
-                    tokio::spawn(async move {
-                        let _permit = permit;
-
-                        let _ = event_tx.send(AppEvent::JobStarted { id: job_id }).await;
-
-                        let result = ytdlp::download(
-                            job_id,
-                            &url,
-                            &format_id,
-                            &config,
-                            event_tx.clone(),
-                            cancel_token,
-                        )
-                        .await;
-
-                        match result {
-                            Ok(path) => {
-                                let _ = event_tx
-                                    .send(AppEvent::JobCompleted { id: job_id, path })
-                                    .await;
-                            }
-                            Err(e) => {
-                                let _ = event_tx
-                                    .send(AppEvent::JobFailed {
-                                        id: job_id,
-                                        error: e.to_string(),
-                                    })
-                                    .await;
-                            }
-                        }
+                // Registered while still pending so a second StartJob joins it
+                let run_tx = self.shared.start(coalesce::download_key(&request), job_id, self.event_tx.clone()).await;
+                self.run_tx.insert(job_id, run_tx);
+                self.pending.push_back(request);
+                self.dispatch_pending().await;
+            }
 
-                        let mut jobs = active_jobs.lock().await;
-                        jobs.remove(&job_id);
-                    });
+            WorkerCommand::CancelJob(id) => {
+                // Others still want the file or formats, so the run carries on without this job
+                let Some(id) = self.shared.leave(id).await else {
+                    self.report_waiting().await;
+                    return true;
+                };
+                if let Some(pos) = self.hydrate_queue.iter().position(|(job_id, _)| *job_id == id) {
+                    self.hydrate_queue.remove(pos);
+                } else if let Some(pos) = self.pending.iter().position(|r| r.job_id == id) {
+                    self.pending.remove(pos);
+                    self.run_tx.remove(&id);
+                    self.report_waiting().await;
+                } else if let Some(token) = self.active_jobs.lock().await.get(&id) {
+                    token.cancel();
                 }
+            }
 
-                WorkerCommand::CancelJob(id) => {
-                    let jobs = self.active_jobs.lock().await;
-                    if let Some(token) = jobs.get(&id) {
-                        token.cancel();
-                    }
+            WorkerCommand::ResumeQueue => {
+                tracing::info!("Queue resumed");
+                self.halted = false;
+                self.probe_requested = false;
+                self.streak.reset();
+                self.dispatch_pending().await;
+            }
+
+            WorkerCommand::ProbeQueue => {
+                if self.halted && !self.probing {
+                    self.probe_requested = true;
+                    self.dispatch_pending().await;
                 }
+            }
 
-                WorkerCommand::UpdateConcurrent(count) => {
-                    tracing::info!("Concurrent downloads setting updated to {}. Takes effect on next app restart.", count);
+            WorkerCommand::SetPaused(paused) => {
+                self.paused = paused;
+                if !paused {
+                    self.dispatch_pending().await;
                 }
+            }
 
-                WorkerCommand::Shutdown => {
-                    let jobs = self.active_jobs.lock().await;
-                    for token in jobs.values() {
-                        token.cancel();
-                    }
-                    break;
+            WorkerCommand::UpdateYtdlp => {
+                if self.updating {
+                    tracing::warn!("Ignoring UpdateYtdlp, an update is already running");
+                    return true;
                 }
+                self.updating = true;
+                let event_tx = self.event_tx.clone();
+                let updated_tx = self.updated_tx.clone();
+                let config = self.config.clone();
+                tokio::spawn(async move {
+                    let event = match ytdlp::self_update(&config, &event_tx).await {
+                        Ok(message) => WorkerEvent::YtdlpUpdated { success: true, message },
+                        Err(e) => WorkerEvent::YtdlpUpdated { success: false, message: e.to_string() },
+                    };
+                    let _ = event_tx.send(event).await;
+                    let _ = updated_tx.send(());
+                });
+            }
+
+            WorkerCommand::UpdateConfig(config) => {
+                self.config = Arc::new(*config);
+            }
+
+            WorkerCommand::ReleaseDependents(id) => {
+                self.released.insert(id);
+                self.dispatch_pending().await;
+            }
+
+            WorkerCommand::UpdateConcurrent(count) => {
+                tracing::info!("Concurrent downloads setting updated to {}. Takes effect on next app restart.", count);
+            }
+
+            WorkerCommand::Shutdown => {
+                self.pending.clear();
+                self.hydrate_queue.clear();
+                self.run_tx.clear();
+                self.title_cancel.cancel();
+                let jobs = self.active_jobs.lock().await;
+                for token in jobs.values() {
+                    token.cancel();
+                }
+                return false;
+            }
+        }
+        true
+    }
+
+    // Start as many waiting jobs as there are free slots. Jobs never wait on the
+    // semaphore inside the command loop, so cancels are handled while queued
+    async fn dispatch_pending(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        // Jobs waiting on another are passed over, not in the way of those behind them
+        while let Some(pos) = self.pending.iter().position(|r| r.after.is_none_or(|id| self.released.contains(&id))) {
+            if self.paused || self.updating || (self.halted && !self.probe_requested) {
+                break;
+            }
+            let Ok(permit) = self.semaphore.clone().try_acquire_owned() else {
+                break;
+            };
+            if self.halted {
+                self.probe_requested = false;
+                self.probing = true;
+            }
+            if let Some(request) = self.pending.remove(pos) {
+                self.spawn_download(request, permit).await;
             }
         }
+        self.report_waiting().await;
+    }
+
+    // Follows the chain of prerequisites through the queue; the app refuses such
+    // links, so this only guards against a queue file edited by hand
+    fn waits_on_itself(&self, request: &DownloadRequest) -> bool {
+        let mut next = request.after;
+        for _ in 0..=self.pending.len() {
+            match next {
+                Some(id) if id == request.job_id => return true,
+                Some(id) => next = self.pending.iter().find(|r| r.job_id == id).and_then(|r| r.after),
+                None => return false,
+            }
+        }
+        false
+    }
+
+    async fn report_waiting(&self) {
+        let mut waiting = Vec::new();
+        for request in &self.pending {
+            waiting.push(request.job_id);
+            waiting.extend(self.shared.followers(request.job_id).await);
+        }
+        let _ = self.event_tx.send(WorkerEvent::SlotQueueChanged { waiting }).await;
+    }
+
+    async fn spawn_download(&mut self, mut request: DownloadRequest, permit: OwnedSemaphorePermit) {
+        let job_id = request.job_id;
+        let cancel_token = CancellationToken::new();
+        self.active_jobs.lock().await.insert(job_id, cancel_token.clone());
+
+        let event_tx = self.run_tx.remove(&job_id).unwrap_or_else(|| self.event_tx.clone());
+        let config = self.config.clone();
+        let active_jobs = self.active_jobs.clone();
+        let finished_tx = self.finished_tx.clone();
+        let runner = self.runner.clone();
+
+        tokio::spawn(async move {
+            let started = Instant::now();
+            let _ = event_tx.send(WorkerEvent::JobStarted { id: job_id }).await;
+
+            if let Some(path) = runner.resolve_conflict(&mut request, &config, &cancel_token).await {
+                drop(permit);
+                active_jobs.lock().await.remove(&job_id);
+                let _ = finished_tx.send(None);
+                let _ = event_tx.send(WorkerEvent::ConflictDetected { id: job_id, path }).await;
+                return;
+            }
+
+            let result = runner.download(&request, &config, event_tx.clone(), cancel_token.clone()).await;
+
+            let attempt = match &result {
+                Ok(_) => Some(Attempt { outcome: Ok(()), message: String::new(), elapsed: started.elapsed() }),
+                Err(e) if !cancel_token.is_cancelled() => {
+                    let message = e.to_string();
+                    Some(Attempt { outcome: Err(errors::classify(&message)), message, elapsed: started.elapsed() })
+                }
+                Err(_) => None,
+            };
+
+            // Release the slot before announcing it so the next job can take it
+            drop(permit);
+            active_jobs.lock().await.remove(&job_id);
+            let _ = finished_tx.send(attempt);
+
+            let event = match result {
+                Ok(path) => WorkerEvent::JobCompleted { id: job_id, path },
+                Err(e) if cancel_token.is_cancelled() => WorkerEvent::JobFailed { id: job_id, error: Failure::cancelled(e.to_string()) },
+                Err(e) => WorkerEvent::JobFailed { id: job_id, error: Failure::new(e.to_string()) },
+            };
+            let _ = event_tx.send(event).await;
+        });
+    }
+
+    async fn check_streak(&mut self, attempt: Attempt) {
+        if self.probing {
+            self.probing = false;
+            if attempt.outcome.is_ok() {
+                tracing::info!("Probe download succeeded, resuming the queue");
+                self.halted = false;
+                self.streak.reset();
+                let _ = self.event_tx.send(WorkerEvent::QueueResumed).await;
+            } else {
+                let _ = self.event_tx.send(WorkerEvent::QueueHalted { reason: attempt.message }).await;
+            }
+            return;
+        }
+
+        let failure = attempt.outcome.err().map(|kind| (kind, attempt.message.as_str()));
+        if let Some(reason) = self.streak.record(failure, attempt.elapsed) {
+            if !self.halted {
+                tracing::warn!("Halting the queue, downloads keep failing with: {}", reason);
+                self.halted = true;
+                let _ = self.event_tx.send(WorkerEvent::QueueHalted { reason }).await;
+            }
+        }
+    }
+
+    async fn record_outcome(&mut self, outcome: Outcome) {
+        let Some(adaptive) = &mut self.adaptive else {
+            return;
+        };
+
+        let adjustment = match outcome {
+            Ok(()) => adaptive.on_success(),
+            Err(kind) => adaptive.on_failure(kind),
+        };
+
+        match adjustment {
+            Adjustment::Reduced => {
+                // Withhold a permit: it is taken (possibly once a running job finishes) and never returned
+                let semaphore = self.semaphore.clone();
+                tokio::spawn(async move {
+                    if let Ok(permit) = semaphore.acquire_owned().await {
+                        permit.forget();
+                    }
+                });
+            }
+            Adjustment::Restored => self.semaphore.add_permits(1),
+            Adjustment::Unchanged => return,
+        }
+
+        tracing::info!("Adaptive concurrency limit now {}/{}", adaptive.effective(), adaptive.max());
+        let _ = self
+            .event_tx
+            .send(WorkerEvent::ConcurrencyChanged {
+                limit: adaptive.effective(),
+                max: adaptive.max(),
+            })
+            .await;
     }
 }
@@ -0,0 +1,285 @@
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tokio::process::Child;
+
+// yt-dlp spawns ffmpeg (and external downloaders) as its own children. Killing
+// only the yt-dlp process leaves those running, so take down the tree.
+#[cfg(windows)]
+pub async fn kill_tree(child: &mut Child) -> std::io::Result<()> {
+    if let Some(pid) = child.id() {
+        let status = tokio::process::Command::new("taskkill")
+            .args(["/PID", &pid.to_string(), "/T", "/F"])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .await;
+        if matches!(status, Ok(s) if s.success()) {
+            let _ = child.wait().await;
+            return Ok(());
+        }
+        tracing::warn!("taskkill failed for pid {}, falling back to killing yt-dlp only", pid);
+    }
+    child.kill().await
+}
+
+// Leads a process group of its own, so the group can be signalled as a whole.
+// Only for the processes kill_tree and interrupt are used on
+#[cfg(unix)]
+pub fn own_group(command: &mut tokio::process::Command) -> &mut tokio::process::Command {
+    command.process_group(0)
+}
+
+#[cfg(windows)]
+pub fn own_group(command: &mut tokio::process::Command) -> &mut tokio::process::Command {
+    command
+}
+
+// Sends `signal` to the whole group spawned with own_group, yt-dlp and ffmpeg alike
+#[cfg(unix)]
+fn signal_group(child: &Child, signal: libc::c_int) -> bool {
+    let Some(pid) = child.id().and_then(|pid| libc::pid_t::try_from(pid).ok()) else {
+        return false;
+    };
+    // SAFETY: kill only sends a signal; the group is the one we spawned
+    unsafe { libc::kill(-pid, signal) == 0 }
+}
+
+#[cfg(unix)]
+pub async fn kill_tree(child: &mut Child) -> std::io::Result<()> {
+    if signal_group(child, libc::SIGKILL) {
+        child.wait().await?;
+        return Ok(());
+    }
+    child.kill().await
+}
+
+// Asks yt-dlp to stop as on Ctrl+C, which reaches its ffmpeg too, so they can
+// finish off what they have written. False where that can't be done and the
+// process has to be killed instead
+#[cfg(unix)]
+pub async fn interrupt(child: &mut Child) -> bool {
+    signal_group(child, libc::SIGINT)
+}
+
+#[cfg(windows)]
+pub async fn interrupt(_child: &mut Child) -> bool {
+    false
+}
+
+// The `--print after_move:filepath` line is the only untagged output that names a
+// real file; checking the path itself works for both `/` and `C:\` style outputs
+pub fn is_output_path_line(line: &str) -> bool {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('[') {
+        return false;
+    }
+    let path = Path::new(line);
+    path.is_absolute() || is_windows_absolute(line) || path.exists()
+}
+
+// `C:\…`, `C:/…` or `\\server\share`, whatever platform the check runs on
+fn is_windows_absolute(line: &str) -> bool {
+    match line.as_bytes() {
+        [drive, b':', b'\\' | b'/', ..] => drive.is_ascii_alphabetic(),
+        [b'\\', b'\\', ..] => true,
+        _ => false,
+    }
+}
+
+// Lines from yt-dlp's post-processors, e.g. `[Metadata] Adding metadata to "…"`.
+// Only the leading tag counts: titles in Destination lines can contain anything
+pub fn is_postprocessor_line(line: &str) -> bool {
+    const TAGS: [&str; 9] = [
+        "[Merger]",
+        "[ffmpeg]",
+        "[ExtractAudio]",
+        "[Metadata]",
+        "[EmbedSubtitle]",
+        "[EmbedThumbnail]",
+        "[FixupM3u8]",
+        "[VideoRemuxer]",
+        "[VideoConvertor]",
+    ];
+    let line = line.trim_start();
+    TAGS.iter().any(|tag| line.starts_with(tag))
+}
+
+// `[VideoConvertor] Converting video from webm to mp4; Destination: …`, the
+// start of a --recode-video run, which prints nothing more until it's done
+pub fn is_recoding_line(line: &str) -> bool {
+    line.trim_start().starts_with("[VideoConvertor] Converting")
+}
+
+// The file a tagged progress or postprocessor line is working on. Only used to
+// salvage a download whose thumbnail couldn't be embedded: yt-dlp stops before
+// the after_move print then, though the media file itself is complete
+pub fn destination(line: &str) -> Option<PathBuf> {
+    let line = line.trim();
+    if let Some(path) = line.strip_prefix("[download] Destination: ").or_else(|| line.strip_prefix("[ExtractAudio] Destination: ")) {
+        return Some(PathBuf::from(path));
+    }
+    if line.starts_with("[Merger]") || line.starts_with("[EmbedThumbnail]") {
+        let (start, end) = (line.find('"')?, line.rfind('"')?);
+        return (end > start + 1).then(|| PathBuf::from(&line[start + 1..end]));
+    }
+    None
+}
+
+// A file written beside the media, e.g. `[info] Writing video metadata as JSON
+// to: …`. These come before the after_move print and start with a tag, so they
+// never get mistaken for the output path. Thumbnails are left to
+// finish_thumbnail since they may be converted after this line
+pub fn sidecar(line: &str) -> Option<PathBuf> {
+    let rest = line.trim().strip_prefix("[info] Writing ")?;
+    let (what, path) = rest.split_once(" to: ")?;
+    if what.contains("thumbnail") {
+        return None;
+    }
+    (!path.is_empty()).then(|| PathBuf::from(path))
+}
+
+// Line reader for yt-dlp's output. Titles and paths from some locales contain
+// bytes that aren't valid UTF-8, which makes `Lines::next_line` error out and
+// stop the read loop; here they're replaced instead so parsing carries on.
+pub struct LossyLines<R> {
+    reader: R,
+    buf: Vec<u8>,
+}
+
+impl<R: AsyncBufRead + Unpin> LossyLines<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, buf: Vec::new() }
+    }
+
+    // Cancel safe for use in select!: a partly read line stays in `buf` and is
+    // completed by the next call
+    pub async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        let read = self.reader.read_until(b'\n', &mut self.buf).await?;
+        if read == 0 && self.buf.is_empty() {
+            return Ok(None);
+        }
+        let mut line = std::mem::take(&mut self.buf);
+        if line.last() == Some(&b'\n') {
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_path_lines() {
+        for (line, expected) in [
+            ("/home/me/Videos/clip.mp4", true),
+            ("  /home/me/Videos/clip.mp4\r", true),
+            ("C:\\Users\\me\\Videos\\clip.mp4", true),
+            ("d:/Videos/clip.mp4", true),
+            ("\\\\nas\\media\\clip.mp4", true),
+            ("[download] Destination: /home/me/Videos/clip.mp4", false),
+            ("[Merger] Merging formats into \"C:\\Videos\\clip.mp4\"", false),
+            ("", false),
+            ("Deleting original file clip.f137.mp4", false),
+            ("C:", false),
+            ("1:\\clip.mp4", false),
+        ] {
+            assert_eq!(is_output_path_line(line), expected, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn metadata_embedding_output_is_a_postprocessor_step() {
+        let lines = [
+            "[Metadata] Adding metadata to \"/home/me/Videos/AC/DC - Live at 1/2 price.mp4\"",
+            "[Metadata] Adding metadata to \"C:\\Videos\\clip.webm\"",
+            "[Metadata] There isn't any metadata to add",
+        ];
+        for line in lines {
+            assert!(is_postprocessor_line(line), "{:?}", line);
+            assert!(!is_output_path_line(line), "{:?}", line);
+            assert!(!is_recoding_line(line), "{:?}", line);
+            assert_eq!(sidecar(line), None, "{:?}", line);
+            assert_eq!(destination(line), None, "{:?}", line);
+        }
+        // The after_move print that follows is still the output path
+        assert!(!is_postprocessor_line("/home/me/Videos/AC/DC - Live at 1/2 price.mp4"));
+        assert!(is_output_path_line("/home/me/Videos/AC/DC - Live at 1/2 price.mp4"));
+    }
+
+    #[test]
+    fn destinations_with_windows_paths() {
+        assert_eq!(destination("[download] Destination: C:\\Videos\\a b.webm"), Some(PathBuf::from("C:\\Videos\\a b.webm")));
+        assert_eq!(destination("[Merger] Merging formats into \"C:\\Videos\\a \"b\".mkv\""), Some(PathBuf::from("C:\\Videos\\a \"b\".mkv")));
+        assert_eq!(destination("[Merger] Merging formats into \"\""), None);
+    }
+
+    // The shell's sleep holds stdout open, so it only closes once the whole group is gone
+    #[cfg(unix)]
+    async fn group_closes_after(stop: impl AsyncFnOnce(&mut Child)) {
+        use tokio::io::AsyncReadExt;
+        let mut command = tokio::process::Command::new("sh");
+        command.args(["-c", "echo ready; sleep 30; echo done"]).stdout(std::process::Stdio::piped()).kill_on_drop(true);
+        let mut child = own_group(&mut command).spawn().unwrap();
+        let mut stdout = child.stdout.take().unwrap();
+        let mut ready = [0u8; 6];
+        stdout.read_exact(&mut ready).await.unwrap();
+
+        stop(&mut child).await;
+        let mut rest = Vec::new();
+        let closed = tokio::time::timeout(std::time::Duration::from_secs(5), stdout.read_to_end(&mut rest)).await;
+        assert!(closed.is_ok(), "the sleep outlived its parent");
+        let _ = child.wait().await;
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn killing_takes_down_the_whole_group() {
+        group_closes_after(async |child: &mut Child| kill_tree(child).await.unwrap()).await;
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn interrupting_reaches_the_whole_group() {
+        group_closes_after(async |child: &mut Child| assert!(interrupt(child).await)).await;
+    }
+
+    #[tokio::test]
+    async fn lossy_lines_survive_mixed_encodings() {
+        let bytes: &[u8] = b"[download] Destination: /v/caf\xc3\xa9.mp4\n/v/caf\xe9.mp4\r\n\xff\xfe\ntail";
+        let mut lines = LossyLines::new(bytes);
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("[download] Destination: /v/café.mp4"));
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("/v/caf\u{fffd}.mp4"));
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("\u{fffd}\u{fffd}"));
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("tail"));
+        assert_eq!(lines.next_line().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn a_line_split_across_reads_comes_out_whole() {
+        // A one-byte buffer splits the é between reads
+        let bytes: &[u8] = b"caf\xc3\xa9\nend";
+        let mut lines = LossyLines::new(tokio::io::BufReader::with_capacity(1, bytes));
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("café"));
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("end"));
+        assert_eq!(lines.next_line().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn a_cancelled_read_keeps_the_partial_line() {
+        use tokio::io::AsyncWriteExt;
+
+        let (mut writer, reader) = tokio::io::duplex(64);
+        let mut lines = LossyLines::new(tokio::io::BufReader::new(reader));
+        writer.write_all(b"[download]  42.0%").await.unwrap();
+        let cut_short = tokio::time::timeout(std::time::Duration::from_millis(20), lines.next_line()).await;
+        assert!(cut_short.is_err());
+
+        writer.write_all(b" of 10MiB\n").await.unwrap();
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("[download]  42.0% of 10MiB"));
+    }
+}
@@ -0,0 +1,102 @@
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use color_eyre::Result;
+
+use crate::config::Config;
+use crate::core::{Credentials, DownloadRequest, JobId, WorkerEvent};
+use super::ytdlp::{self, PlaylistEntries};
+
+// Every yt-dlp run the pool makes goes through this, so the pool can be driven
+// by something other than the real binary. Progress and other intermediate
+// events go to `event_tx`; the result is what the pool reports at the end.
+// Each call must stop promptly once `cancel` fires
+pub trait YtdlpRunner: Send + Sync + 'static {
+    // Sends FormatsReady, or JobFailed when yt-dlp itself reports an error
+    fn fetch_formats(
+        &self,
+        job_id: JobId,
+        url: &str,
+        credentials: Option<&Credentials>,
+        config: &Config,
+        event_tx: mpsc::Sender<WorkerEvent>,
+        cancel: CancellationToken,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn fetch_playlist(
+        &self,
+        job_id: JobId,
+        url: &str,
+        items: Option<&str>,
+        config: &Config,
+        event_tx: mpsc::Sender<WorkerEvent>,
+        cancel: CancellationToken,
+    ) -> impl Future<Output = Result<(Option<String>, PlaylistEntries)>> + Send;
+
+    // Some(path) when the destination exists and the user has to be asked first
+    fn resolve_conflict(
+        &self,
+        request: &mut DownloadRequest,
+        config: &Config,
+        cancel: &CancellationToken,
+    ) -> impl Future<Output = Option<PathBuf>> + Send;
+
+    fn download(
+        &self,
+        request: &DownloadRequest,
+        config: &Arc<Config>,
+        event_tx: mpsc::Sender<WorkerEvent>,
+        cancel: CancellationToken,
+    ) -> impl Future<Output = Result<PathBuf>> + Send;
+}
+
+// The real thing: spawns the configured yt-dlp command
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ytdlp;
+
+impl YtdlpRunner for Ytdlp {
+    fn fetch_formats(
+        &self,
+        job_id: JobId,
+        url: &str,
+        credentials: Option<&Credentials>,
+        config: &Config,
+        event_tx: mpsc::Sender<WorkerEvent>,
+        cancel: CancellationToken,
+    ) -> impl Future<Output = Result<()>> + Send {
+        ytdlp::fetch_formats(job_id, url, credentials, config, event_tx, cancel)
+    }
+
+    fn fetch_playlist(
+        &self,
+        job_id: JobId,
+        url: &str,
+        items: Option<&str>,
+        config: &Config,
+        event_tx: mpsc::Sender<WorkerEvent>,
+        cancel: CancellationToken,
+    ) -> impl Future<Output = Result<(Option<String>, PlaylistEntries)>> + Send {
+        ytdlp::fetch_playlist(job_id, url, items, config, event_tx, cancel)
+    }
+
+    fn resolve_conflict(
+        &self,
+        request: &mut DownloadRequest,
+        config: &Config,
+        cancel: &CancellationToken,
+    ) -> impl Future<Output = Option<PathBuf>> + Send {
+        ytdlp::resolve_conflict(request, config, cancel)
+    }
+
+    fn download(
+        &self,
+        request: &DownloadRequest,
+        config: &Arc<Config>,
+        event_tx: mpsc::Sender<WorkerEvent>,
+        cancel: CancellationToken,
+    ) -> impl Future<Output = Result<PathBuf>> + Send {
+        ytdlp::download(request, config, event_tx, cancel)
+    }
+}
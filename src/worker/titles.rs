@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use std::time::Duration;
+use color_eyre::Result;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::Config;
+use crate::core::{WorkerEvent, JobId};
+
+// Titles are cosmetic, so lookups run one at a time with a pause in between
+// and never compete with downloads for yt-dlp processes
+const MIN_INTERVAL: Duration = Duration::from_millis(750);
+
+pub struct TitleRequest {
+    pub job_id: JobId,
+    pub url: String,
+    pub config: Arc<Config>,
+}
+
+pub async fn run_resolver(
+    mut request_rx: mpsc::UnboundedReceiver<TitleRequest>,
+    event_tx: mpsc::Sender<WorkerEvent>,
+    cancel: CancellationToken,
+) {
+    loop {
+        let request = tokio::select! {
+            _ = cancel.cancelled() => break,
+            request = request_rx.recv() => match request {
+                Some(request) => request,
+                None => break,
+            },
+        };
+
+        let result = tokio::select! {
+            _ = cancel.cancelled() => break,
+            result = resolve(&request.url, &request.config) => result,
+        };
+        match result {
+            Ok(title) => {
+                let _ = event_tx.send(WorkerEvent::TitleResolved { id: request.job_id, title }).await;
+            }
+            Err(e) => tracing::debug!("Could not resolve title for {}: {}", request.url, e),
+        }
+
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = tokio::time::sleep(MIN_INTERVAL) => {}
+        }
+    }
+}
+
+async fn resolve(url: &str, config: &Config) -> Result<String> {
+    if is_youtube(url) {
+        match oembed_title(url).await {
+            Ok(title) => return Ok(title),
+            Err(e) => tracing::debug!("oEmbed lookup failed, falling back to yt-dlp: {}", e),
+        }
+    }
+    ytdlp_title(url, config).await
+}
+
+fn is_youtube(url: &str) -> bool {
+    url.contains("youtube.com/watch") || url.contains("youtu.be/") || url.contains("youtube.com/shorts/")
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OEmbed {
+    pub title: String,
+    #[serde(default)]
+    pub author_name: String,
+}
+
+async fn oembed_title(url: &str) -> Result<String> {
+    Ok(oembed(url).await?.title)
+}
+
+// One small HTTP request instead of a whole yt-dlp process; YouTube only
+pub async fn oembed(url: &str) -> Result<OEmbed> {
+    let url = url.to_string();
+    let response: OEmbed = tokio::task::spawn_blocking(move || {
+        ureq::get("https://www.youtube.com/oembed")
+            .query("url", &url)
+            .query("format", "json")
+            .timeout(Duration::from_secs(10))
+            .call()?
+            .into_json::<OEmbed>()
+            .map_err(color_eyre::eyre::Error::from)
+    })
+    .await??;
+    Ok(response)
+}
+
+async fn ytdlp_title(url: &str, config: &Config) -> Result<String> {
+    let output = config
+        .ytdlp_command()
+        .args(["--print", "title", "--skip-download", "--no-playlist", "--no-warnings"])
+        .args(super::ytdlp::network_args(config))
+        .arg(url)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        color_eyre::eyre::bail!("yt-dlp exited with code: {:?}", output.status.code());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.lines().map(str::trim).find(|l| !l.is_empty()) {
+        Some(title) => Ok(title.to_string()),
+        None => color_eyre::eyre::bail!("yt-dlp printed no title"),
+    }
+}
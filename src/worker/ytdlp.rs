@@ -1,41 +1,56 @@
+use std::borrow::Cow;
 use std::path::PathBuf;
 use std::process::Stdio;
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
+use std::time::Duration;
+use tokio::io::BufReader;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 use color_eyre::Result;
 use serde::Deserialize;
 
-use crate::config::Config;
-use crate::events::{AppEvent, DownloadPhase, Format, JobId};
+use crate::config::{self, Config};
+use super::errors;
+use super::process::{self, LossyLines};
+use crate::core::{short_id, WorkerEvent, Conversion, Credentials, Failure, DownloadPhase, DownloadRequest, Format, JobId, Metadata, OutputTarget, OverwritePolicy};
 
 #[derive(Debug, Deserialize)]
 struct VideoInfo {
     title: String,
     formats: Vec<Format>,
+    #[serde(flatten)]
+    metadata: Metadata,
 }
 
+#[tracing::instrument(skip_all, fields(job = %short_id(job_id), title = tracing::field::Empty))]
 pub async fn fetch_formats(
     job_id: JobId,
     url: &str,
-    event_tx: mpsc::Sender<AppEvent>,
+    credentials: Option<&Credentials>,
+    config: &Config,
+    event_tx: mpsc::Sender<WorkerEvent>,
+    cancel: CancellationToken,
 ) -> Result<()> {
-    let output = Command::new("yt-dlp")
-        .arg("--dump-json")
-        .arg("--no-download")
-        .arg("--no-warnings")
-        .arg(url)
-        .output()
-        .await?;
+    check_cookies(config)?;
+    check_cwd(config)?;
+    let mut args: Vec<String> = ["--dump-json", "--no-download", "--no-warnings"].map(String::from).to_vec();
+    args.extend(auth_args(config, credentials));
+    args.extend(network_args(config));
+    args.extend(config.extra_fetch_args.iter().cloned());
+    args.push(url.to_string());
+    tracing::debug!("Running {}", command_line(config, &args));
+    // Dropping the output future on cancel kills yt-dlp
+    let output = tokio::select! {
+        _ = cancel.cancelled() => color_eyre::eyre::bail!("Format fetch cancelled"),
+        output = config.ytdlp_command().args(&args).kill_on_drop(true).output() => output?,
+    };
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         let _ = event_tx
-            .send(AppEvent::JobFailed {
+            .send(WorkerEvent::JobFailed {
                 id: job_id,
-                error: stderr.to_string(),
+                error: Failure::new(stderr),
             })
             .await;
         return Ok(());
@@ -43,22 +58,25 @@ pub async fn fetch_formats(
 
     let json_str = String::from_utf8_lossy(&output.stdout);
     let info: VideoInfo = serde_json::from_str(&json_str)?;
+    tracing::Span::current().record("title", info.title.as_str());
 
     // Filter to useful formats (video with audio, or standalone video/audio)
     let formats: Vec<Format> = info
         .formats
         .into_iter()
         .filter(|f| {
-            // Keep formats that have video
-            f.is_video() && f.height.is_some()
+            // Keep formats that have video, plus audio-only streams for extraction
+            (f.is_video() && f.height.is_some()) || f.is_audio_only()
         })
         .collect();
+    tracing::debug!("Fetched {} usable formats", formats.len());
 
     let _ = event_tx
-        .send(AppEvent::FormatsReady {
+        .send(WorkerEvent::FormatsReady {
             id: job_id,
             title: info.title,
             formats,
+            metadata: info.metadata,
         })
         .await;
 
@@ -69,83 +87,547 @@ pub async fn fetch_formats(
 struct PlaylistEntry {
     url: String,
     title: Option<String>,
+    #[serde(default)]
+    playlist_title: Option<String>,
 }
 
+// (video URL, title) in playlist order
+pub type PlaylistEntries = Vec<(String, Option<String>)>;
 
-pub async fn fetch_playlist(url: &str) -> Result<Vec<(String, Option<String>)>> {
-    let output = Command::new("yt-dlp")
-        .arg("--flat-playlist")
-        .arg("--dump-json")
-        .arg("--no-warnings")
-        .arg(url)
-        .output()
-        .await?;
+// Entries are counted as they stream in so the placeholder row can show progress
+// on long playlists
+#[tracing::instrument(skip(config, event_tx, cancel), fields(job = %short_id(job_id)))]
+pub async fn fetch_playlist(
+    job_id: JobId,
+    url: &str,
+    items: Option<&str>,
+    config: &Config,
+    event_tx: mpsc::Sender<WorkerEvent>,
+    cancel: CancellationToken,
+) -> Result<(Option<String>, PlaylistEntries)> {
+    // Progress is throttled so a playlist with thousands of entries doesn't flood the UI
+    const PROGRESS_EVERY: usize = 10;
 
-    if !output.status.success() {
-        color_eyre::eyre::bail!("Failed to fetch playlist");
+    check_cwd(config)?;
+    let mut args = vec!["--flat-playlist".to_string(), "--dump-json".into(), "--no-warnings".into()];
+    if let Some(items) = items {
+        args.extend(["--playlist-items".into(), items.to_string()]);
     }
+    args.extend(network_args(config));
+    args.push(url.to_string());
+    tracing::debug!("Running {}", command_line(config, &args));
+    let mut command = config.ytdlp_command();
+    let mut child = process::own_group(&mut command)
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
 
-    let json_str = String::from_utf8_lossy(&output.stdout);
-    
+    let stdout = child.stdout.take().expect("stdout not captured");
+    let mut lines = LossyLines::new(BufReader::new(stdout));
+    let stderr = child.stderr.take().expect("stderr not captured");
+    let stderr_task = tokio::spawn(collect_stderr(stderr, None));
     let mut urls = Vec::new();
-    for line in json_str.lines() {
-        if let Ok(entry) = serde_json::from_str::<PlaylistEntry>(line) {
+    let mut playlist_title = None;
+
+    loop {
+        let line = tokio::select! {
+            _ = cancel.cancelled() => {
+                process::kill_tree(&mut child).await?;
+                color_eyre::eyre::bail!("Playlist loading cancelled");
+            }
+            line = lines.next_line() => line?,
+        };
+        let Some(line) = line else { break };
+
+        if let Ok(entry) = serde_json::from_str::<PlaylistEntry>(&line) {
+            if playlist_title.is_none() {
+                playlist_title = entry.playlist_title;
+            }
             let video_url = if entry.url.starts_with("http") {
                 entry.url
             } else {
                 format!("https://www.youtube.com/watch?v={}", entry.url)
             };
             urls.push((video_url, entry.title));
+            if urls.len() % PROGRESS_EVERY == 1 {
+                let _ = event_tx.send(WorkerEvent::PlaylistProgress { id: job_id, found: urls.len() }).await;
+            }
+        }
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        let stderr_lines = stderr_task.await.unwrap_or_default();
+        match stderr_lines.iter().rev().find(|l| l.starts_with("ERROR:")) {
+            Some(error) => color_eyre::eyre::bail!("{}", error),
+            None => color_eyre::eyre::bail!("yt-dlp exited with code: {:?}", status.code()),
+        }
+    }
+
+    Ok((playlist_title, urls))
+}
+
+// `-U` only works for the standalone binary; pip and distro installs print why
+// not and exit non-zero, and that line becomes the error. Every line is sent
+// on as YtdlpUpdateOutput while it runs; pip can take a while
+pub async fn self_update(config: &Config, event_tx: &mpsc::Sender<WorkerEvent>) -> Result<String> {
+    let mut child = config
+        .ytdlp_update_command()
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdout = LossyLines::new(BufReader::new(child.stdout.take().expect("stdout not captured")));
+    let mut stderr = LossyLines::new(BufReader::new(child.stderr.take().expect("stderr not captured")));
+    let (mut stdout_done, mut stderr_done) = (false, false);
+    let mut last = String::new();
+    loop {
+        let line = tokio::select! {
+            line = stdout.next_line(), if !stdout_done => line?.or_else(|| {
+                stdout_done = true;
+                None
+            }),
+            line = stderr.next_line(), if !stderr_done => line?.or_else(|| {
+                stderr_done = true;
+                None
+            }),
+            else => break,
+        };
+        if let Some(line) = line.filter(|l| !l.trim().is_empty()) {
+            last = line.trim().to_string();
+            let _ = event_tx.send(WorkerEvent::YtdlpUpdateOutput { line }).await;
         }
     }
-    
-    Ok(urls)
+    if !child.wait().await?.success() {
+        color_eyre::eyre::bail!("{}", last);
+    }
+    Ok(last)
 }
 
+// The full argument vector for a download; kept free of side effects so the
+// dry-run output is exactly what gets executed
+pub fn download_args(request: &DownloadRequest, config: &Config) -> Vec<String> {
+    let mut args: Vec<String> = vec!["--newline".into(), "--progress".into(), "--no-colors".into()];
+    args.extend(media_args(request, config));
+    args.extend(["--print".into(), "after_move:filepath".into()]);
+    args.push(request.url.clone());
+    args
+}
+
+// What a user would type to reproduce the download by hand, without the
+// flags oxidlp only needs for following progress
+pub fn standalone_args(request: &DownloadRequest, config: &Config) -> Vec<String> {
+    let mut args = media_args(request, config);
+    args.push(request.url.clone());
+    args
+}
+
+fn media_args(request: &DownloadRequest, config: &Config) -> Vec<String> {
+    let output_template = request
+        .output
+        .clone()
+        .unwrap_or_else(|| OutputTarget::from_config(config))
+        .path_template();
+
+    let mut args: Vec<String> = vec![
+        "-f".into(),
+        request.format_spec.clone(),
+        "-o".into(),
+        output_template.to_string_lossy().into_owned(),
+    ];
+
+    args.extend(auth_args(config, request.credentials.as_ref()));
+    args.extend(network_args(config));
+
+    // Cutting between keyframes would leave a few seconds of frozen or missing picture
+    if let Some((start, end)) = &request.sections {
+        args.extend(["--download-sections".into(), format!("*{}-{}", start, end), "--force-keyframes-at-cuts".into()]);
+    }
+
+    if request.live_from_start {
+        args.push("--live-from-start".into());
+    }
+
+    if request.no_playlist {
+        args.push("--no-playlist".into());
+    }
+
+    if request.on_conflict.unwrap_or(config.overwrite_policy) == OverwritePolicy::Overwrite {
+        args.push("--force-overwrites".into());
+    }
+
+    match request.resume {
+        Some(true) => args.push("--continue".into()),
+        Some(false) => args.push("--no-continue".into()),
+        None => {}
+    }
+
+    if let Some(limit) = &config.rate_limit {
+        args.extend(["--limit-rate".into(), limit.clone()]);
+    }
+
+    if config.embed_subs {
+        args.push(if request.subs_embeddable { "--embed-subs" } else { "--write-subs" }.into());
+    }
+
+    if config.embed_metadata {
+        args.extend(["--embed-metadata".into(), "--embed-chapters".into()]);
+    }
+    if config.embed_thumbnail {
+        args.push("--embed-thumbnail".into());
+    }
+    if config.write_thumbnail {
+        args.push("--write-thumbnail".into());
+    }
+    if config.write_info_json {
+        args.push("--write-info-json".into());
+    }
+    if config.write_description {
+        args.push("--write-description".into());
+    }
+
+    if request.extract_audio {
+        args.extend([
+            "-x".into(),
+            "--audio-format".into(),
+            config.audio_format.clone(),
+            "--audio-quality".into(),
+            request.audio_quality.clone().unwrap_or_else(|| config.audio_quality.clone()),
+        ]);
+    }
+
+    // Audio extraction makes its own file, so a container only applies to video
+    if let Some((container, conversion)) = request.convert.filter(|_| !request.extract_audio) {
+        let flag = match conversion {
+            Conversion::Remux => "--remux-video",
+            Conversion::Recode => "--recode-video",
+        };
+        args.extend([flag.into(), container.label().into()]);
+    }
+
+    if let Some(downloader) = &config.external_downloader {
+        args.extend(["--downloader".into(), downloader.clone()]);
+        // yt-dlp splits these again the way a shell would
+        if !config.external_downloader_args.is_empty() {
+            args.extend(["--downloader-args".into(), format!("{}:{}", downloader, shell_join(&config.external_downloader_args))]);
+        }
+    }
+
+    // Last, so they can override anything above
+    args.extend(config.extra_args.iter().cloned());
+
+    args
+}
+
+// yt-dlp answers a missing cookies file or an unknown browser with a Python traceback
+fn check_cookies(config: &Config) -> Result<()> {
+    if let Some(error) = config.cookie_browser_error() {
+        color_eyre::eyre::bail!("{}", error);
+    }
+    match &config.cookies_file {
+        Some(file) if config.cookies_from_browser.is_none() && !file.is_file() => {
+            color_eyre::eyre::bail!("Cookies file not found: {} (change it in settings, g)", file.display())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_cwd(config: &Config) -> Result<()> {
+    match config.ytdlp_cwd_error() {
+        Some(error) => color_eyre::eyre::bail!("{}", error),
+        None => Ok(()),
+    }
+}
+
+fn auth_args(config: &Config, credentials: Option<&Credentials>) -> Vec<String> {
+    let mut args = Vec::new();
+    match (&config.cookies_from_browser, &config.cookies_file) {
+        (Some(browser), file) => {
+            if let Some(file) = file {
+                tracing::warn!("Both cookies_from_browser and cookies_file are set; using {} and ignoring {}", browser, file.display());
+            }
+            args.extend(["--cookies-from-browser".into(), browser.clone()]);
+        }
+        (None, Some(file)) => args.extend(["--cookies".into(), file.to_string_lossy().into_owned()]),
+        (None, None) => {}
+    }
+    match credentials.cloned().or_else(|| config.credentials()) {
+        Some(credentials) => args.extend([
+            "--username".into(),
+            credentials.username,
+            "--password".into(),
+            credentials.password.0,
+        ]),
+        None if config.use_netrc => args.push("--netrc".into()),
+        None => {}
+    }
+    args
+}
+
+// yt-dlp's options that take a password, which may also come in through extra_args
+const PASSWORD_OPTIONS: &[&str] = &["--password", "-p", "--video-password", "--ap-password"];
+
+fn masked_password(arg: &str) -> Option<String> {
+    let (option, _) = arg.split_once('=')?;
+    (option.starts_with("--") && PASSWORD_OPTIONS.contains(&option)).then(|| format!("{}=********", option))
+}
+
+// Where requests appear to come from, for every kind of yt-dlp run
+pub(super) fn network_args(config: &Config) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(country) = &config.geo_bypass_country {
+        args.extend(["--geo-bypass-country".into(), country.to_uppercase()]);
+    }
+    if let Some(address) = &config.source_address {
+        args.extend(["--source-address".into(), address.clone()]);
+    }
+    for (name, value) in config.sorted_headers() {
+        args.extend(["--add-header".into(), format!("{}:{}", name, value)]);
+    }
+    args
+}
+
+// For display, logs and dry runs; the password and secret-looking environment
+// variables are masked
+pub fn command_line(config: &Config, args: &[String]) -> String {
+    render_command(config, args, false).0
+}
+
+// For copying: the same command with the secrets left out rather than masked,
+// so it runs as pasted and yt-dlp asks for the password itself. Also says
+// what was left out, for the user to add back
+pub fn runnable_command_line(config: &Config, args: &[String]) -> (String, Vec<String>) {
+    render_command(config, args, true)
+}
+
+fn render_command(config: &Config, args: &[String], leave_out: bool) -> (String, Vec<String>) {
+    let (program, program_args) = config.ytdlp_program();
+    let mut left_out = Vec::new();
+    let mut words: Vec<Cow<str>> = Vec::new();
+    let mut all = std::iter::once(program).chain(program_args.iter().map(String::as_str)).chain(args.iter().map(String::as_str));
+    while let Some(arg) = all.next() {
+        let option = arg.split_once('=').map_or(arg, |(option, _)| option);
+        if PASSWORD_OPTIONS.contains(&arg) || masked_password(arg).is_some() {
+            if leave_out {
+                if PASSWORD_OPTIONS.contains(&arg) {
+                    all.next();
+                }
+                left_out.push(option.to_string());
+            } else if PASSWORD_OPTIONS.contains(&arg) {
+                words.push(arg.into());
+                if all.next().is_some() {
+                    words.push("********".into());
+                }
+            } else {
+                words.push(masked_password(arg).unwrap_or_default().into());
+            }
+            continue;
+        }
+        if arg == "--add-header" {
+            match all.next() {
+                Some(header) => match header.split_once(':') {
+                    Some((name, _)) if config::is_secret_key(name) && leave_out => left_out.push(format!("the {} header", name)),
+                    Some((name, _)) if config::is_secret_key(name) => words.extend([arg.into(), format!("{}:********", name).into()]),
+                    _ => words.extend([arg.into(), header.into()]),
+                },
+                None => words.push(arg.into()),
+            }
+            continue;
+        }
+        words.push(arg.into());
+    }
+    let command = words.iter().map(|word| shell_quote(word)).collect::<Vec<_>>().join(" ");
+
+    let mut env: Vec<_> = config.ytdlp_env.iter().collect();
+    env.sort();
+    let mut prefix = String::new();
+    for (key, value) in env {
+        match config::is_secret_key(key) {
+            true if leave_out => left_out.push(key.clone()),
+            true => prefix.push_str(&format!("{}={} ", key, shell_quote("********"))),
+            false => prefix.push_str(&format!("{}={} ", key, shell_quote(value))),
+        }
+    }
+    let line = match &config.ytdlp_cwd {
+        Some(dir) => format!("cd {} && {}{}", shell_quote(&dir.to_string_lossy()), prefix, command),
+        None => format!("{}{}", prefix, command),
+    };
+    (line, left_out)
+}
+
+pub fn shell_join(args: &[String]) -> String {
+    args.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" ")
+}
+
+fn shell_quote(arg: &str) -> std::borrow::Cow<'_, str> {
+    let is_safe = !arg.is_empty()
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=+%@,".contains(c));
+    if is_safe {
+        std::borrow::Cow::Borrowed(arg)
+    } else {
+        std::borrow::Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''")))
+    }
+}
+
+// `ask` and `keep_both` need the destination before anything is downloaded, so
+// yt-dlp renders the template in a dry pass first. Returns the existing file
+// when the job has to wait for the user; for `keep_both` the request is
+// pointed at a free suffixed name instead. If the dry pass fails the download
+// goes ahead and reports the real error itself.
+pub async fn resolve_conflict(request: &mut DownloadRequest, config: &Config, cancel: &CancellationToken) -> Option<PathBuf> {
+    let policy = request.on_conflict.unwrap_or(config.overwrite_policy);
+    if !matches!(policy, OverwritePolicy::Ask | OverwritePolicy::KeepBoth) {
+        return None;
+    }
+
+    let path = tokio::select! {
+        _ = cancel.cancelled() => return None,
+        path = predict_path(request, config) => match path {
+            Ok(path) => path,
+            Err(e) => {
+                tracing::warn!("Could not work out the output file: {}", e);
+                return None;
+            }
+        },
+    };
+    if !path.exists() {
+        return None;
+    }
+
+    if policy == OverwritePolicy::Ask {
+        return Some(path);
+    }
+    tracing::info!("{} exists, keeping both", path.display());
+    request.output = Some(suffixed_target(&path));
+    None
+}
+
+async fn predict_path(request: &DownloadRequest, config: &Config) -> Result<PathBuf> {
+    let mut args = media_args(request, config);
+    args.extend(["--skip-download", "--no-warnings", "--print", "filename"].map(String::from));
+    args.push(request.url.clone());
+    tracing::debug!("Running {}", command_line(config, &args));
+
+    let output = config.ytdlp_command().args(&args).kill_on_drop(true).output().await?;
+    if !output.status.success() {
+        color_eyre::eyre::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let Some(line) = stdout.lines().map(str::trim).rfind(|l| !l.is_empty()) else {
+        color_eyre::eyre::bail!("yt-dlp printed no filename");
+    };
+
+    // The printed name is from before post-processing, which changes the extension
+    let path = PathBuf::from(line);
+    if request.extract_audio && config.audio_format != "best" {
+        return Ok(path.with_extension(&config.audio_format));
+    }
+    Ok(path)
+}
+
+// "name (1).%(ext)s" with the first number not taken; the stem is escaped so
+// yt-dlp reads it literally
+fn suffixed_target(existing: &std::path::Path) -> OutputTarget {
+    let dir = existing.parent().map(PathBuf::from).unwrap_or_default();
+    let stem = existing.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = existing.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+
+    let n = (1..)
+        .find(|n| !dir.join(format!("{} ({}){}", stem, n, ext)).exists())
+        .unwrap_or(1);
+    OutputTarget {
+        template: format!("{} ({}).%(ext)s", stem.replace('%', "%%"), n),
+        dir,
+    }
+}
+
+// How long a stopped live recording gets to merge and close its file
+const LIVE_STOP_GRACE: Duration = Duration::from_secs(30);
+
+#[tracing::instrument(skip_all, fields(job = %short_id(request.job_id), title = request.title.as_deref().unwrap_or_default()))]
 pub async fn download(
-    job_id: JobId,
-    url: &str,
-    format_id: &str,
+    request: &DownloadRequest,
     config: &Arc<Config>,
-    event_tx: mpsc::Sender<AppEvent>,
+    event_tx: mpsc::Sender<WorkerEvent>,
     cancel: CancellationToken,
 ) -> Result<PathBuf> {
-    let output_template = config.output_dir.join(&config.output_template);
-
-    let mut child = Command::new("yt-dlp")
-        .arg("--newline")
-        .arg("--progress")
-        .arg("--no-colors")
-        .arg("-f")
-        .arg(format!("{}+bestaudio/best", format_id))
-        .arg("-o")
-        .arg(output_template.to_string_lossy().as_ref())
-        .arg("--print")
-        .arg("after_move:filepath")
-        .arg(url)
+    let job_id = request.job_id;
+    check_cookies(config)?;
+    check_cwd(config)?;
+    let args = download_args(request, config);
+    tracing::debug!("Spawning {}", command_line(config, &args));
+    let mut command = config.ytdlp_command();
+    let mut child = process::own_group(&mut command)
+        .args(&args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()?;
 
+    if let Some(pid) = child.id() {
+        let _ = event_tx.send(WorkerEvent::JobSpawned { id: job_id, pid }).await;
+    }
+
     let stdout = child.stdout.take().expect("stdout not captured");
-    let mut reader = BufReader::new(stdout).lines();
+    let mut reader = LossyLines::new(BufReader::new(stdout));
+    let stderr = child.stderr.take().expect("stderr not captured");
+    let stderr_task = tokio::spawn(collect_stderr(stderr, Some((job_id, event_tx.clone()))));
 
     let mut final_path: Option<PathBuf> = None;
-    let mut current_phase = DownloadPhase::Video;
+    // The last file yt-dlp named, and whether it was embedding the thumbnail into it
+    let mut last_destination: Option<PathBuf> = None;
+    let mut embedding_thumbnail = false;
+    // Extra files yt-dlp announced next to the media, e.g. the info JSON
+    let mut sidecars: Vec<PathBuf> = Vec::new();
+    // Progressive formats are a single stream with nothing to merge
+    let merging = request.format_spec.contains('+');
+    // A live recording being wound up on cancel rather than thrown away, and
+    // when to give up waiting for yt-dlp to finish the file
+    let mut stopping = false;
+    let mut stop_deadline: Option<tokio::time::Instant> = None;
+    let mut current_phase = if merging { DownloadPhase::Video } else { DownloadPhase::Single };
 
     loop {
         tokio::select! {
-            _ = cancel.cancelled() => {
-                child.kill().await?;
-                color_eyre::eyre::bail!("Download cancelled");
+            _ = cancel.cancelled(), if !stopping => {
+                if !request.live {
+                    process::kill_tree(&mut child).await?;
+                    color_eyre::eyre::bail!("Download cancelled");
+                }
+                // yt-dlp closes off the file on an interrupt; the output keeps being read until it exits
+                tracing::info!("Stopping the live recording");
+                stopping = true;
+                if process::interrupt(&mut child).await {
+                    stop_deadline = Some(tokio::time::Instant::now() + LIVE_STOP_GRACE);
+                } else {
+                    process::kill_tree(&mut child).await?;
+                }
+            }
+            _ = tokio::time::sleep_until(stop_deadline.unwrap_or_else(tokio::time::Instant::now)), if stop_deadline.is_some() => {
+                tracing::warn!("yt-dlp didn't stop within {}s, killing it", LIVE_STOP_GRACE.as_secs());
+                stop_deadline = None;
+                process::kill_tree(&mut child).await?;
             }
             result = reader.next_line() => {
                 match result {
                     Ok(Some(line_content)) => {
                         // Detect phase changes from yt-dlp output
-                        if line_content.contains("[Merger]") || line_content.contains("[ffmpeg]") {
+                        if process::is_recoding_line(&line_content) {
+                            current_phase = DownloadPhase::Recoding;
+                            // No progress lines follow, so the change is reported on its own
+                            let _ = event_tx.send(WorkerEvent::JobProgress {
+                                id: job_id,
+                                percent: 100.0,
+                                speed: "--".into(),
+                                speed_bps: None,
+                                eta: "--".into(),
+                                phase: current_phase,
+                                total: None,
+                            }).await;
+                        } else if process::is_postprocessor_line(&line_content) {
                             current_phase = DownloadPhase::Merging;
-                        } else if line_content.contains("[download] Destination:") {
+                        } else if merging && line_content.contains("[download] Destination:") {
                             if line_content.contains(".f") && (line_content.contains("audio") || line_content.contains(".m4a") || line_content.contains(".webm")) {
                                 current_phase = DownloadPhase::Audio;
                             } else {
@@ -154,15 +636,22 @@ pub async fn download(
                         }
                         
                         if let Some(progress) = parse_progress(&line_content) {
-                            let _ = event_tx.send(AppEvent::JobProgress {
+                            let _ = event_tx.send(WorkerEvent::JobProgress {
                                 id: job_id,
                                 percent: progress.percent,
+                                speed_bps: parse_size(progress.speed.trim_end_matches("/s")),
                                 speed: progress.speed,
                                 eta: progress.eta,
                                 phase: current_phase,
+                                total: progress.total,
                             }).await;
-                        } else if !line_content.starts_with('[') && line_content.contains('/') {
+                        } else if process::is_output_path_line(&line_content) {
                             final_path = Some(PathBuf::from(line_content.trim()));
+                        } else if let Some(path) = process::sidecar(&line_content) {
+                            sidecars.push(path);
+                        } else if let Some(path) = process::destination(&line_content) {
+                            embedding_thumbnail = line_content.trim_start().starts_with("[EmbedThumbnail]");
+                            last_destination = Some(path);
                         }
                     }
                     Ok(None) => break,
@@ -176,12 +665,79 @@ pub async fn download(
     }
 
     let status = child.wait().await?;
+    let stderr_lines = stderr_task.await.unwrap_or_default();
+
+    // Whatever was recorded is the result, finished or still a part file
+    if stopping {
+        let recorded = final_path.or(last_destination).and_then(|path| {
+            let part = PathBuf::from(format!("{}.part", path.display()));
+            [path, part].into_iter().find(|p| p.exists())
+        });
+        return recorded.ok_or_else(|| color_eyre::eyre::eyre!("Recording stopped before anything was saved"));
+    }
 
     if !status.success() {
-        color_eyre::eyre::bail!("yt-dlp exited with code: {:?}", status.code());
+        let error = stderr_lines.iter().rev().find(|l| l.starts_with("ERROR:"));
+        // A container the thumbnail can't go into, or a missing mutagen or
+        // AtomicParsley, fails the whole run after the media is already saved
+        if let Some(path) = last_destination.filter(|p| embedding_thumbnail && p.exists()) {
+            let reason = error.map(|e| e.trim_start_matches("ERROR:").trim().to_string()).unwrap_or_default();
+            tracing::warn!("Thumbnail not embedded into {}: {}", path.display(), reason);
+            let _ = event_tx.send(WorkerEvent::ThumbnailNotEmbedded { id: job_id, reason }).await;
+            return finish_thumbnail(request, config, &event_tx, path).await;
+        }
+        match error {
+            Some(error) => color_eyre::eyre::bail!("{}", error),
+            None => color_eyre::eyre::bail!("yt-dlp exited with code: {:?}", status.code()),
+        }
     }
 
-    final_path.ok_or_else(|| color_eyre::eyre::eyre!("Could not determine output file path"))
+    let path = final_path.ok_or_else(|| color_eyre::eyre::eyre!("Could not determine output file path"))?;
+    // Subtitles embedded into the file are deleted afterwards, so only what's left counts
+    sidecars.retain(|p| p.exists() && *p != path);
+    sidecars.dedup();
+    if !sidecars.is_empty() {
+        let _ = event_tx.send(WorkerEvent::SidecarsSaved { id: job_id, paths: sidecars }).await;
+    }
+    if config.embed_thumbnail {
+        // e.g. "Skipping embedding the thumbnail because the file is missing"
+        if let Some(warning) = stderr_lines.iter().find(|l| l.starts_with("WARNING:") && l.to_lowercase().contains("thumbnail")) {
+            let reason = warning.trim_start_matches("WARNING:").trim().to_string();
+            let _ = event_tx.send(WorkerEvent::ThumbnailNotEmbedded { id: job_id, reason }).await;
+        }
+    }
+    finish_thumbnail(request, config, &event_tx, path).await
+}
+
+// yt-dlp may convert the image for embedding, so the kept copy is found by
+// looking beside the media file rather than trusting the name it announced
+async fn finish_thumbnail(request: &DownloadRequest, config: &Config, event_tx: &mpsc::Sender<WorkerEvent>, path: PathBuf) -> Result<PathBuf> {
+    if config.write_thumbnail {
+        let image = ["jpg", "webp", "png", "jpeg"].iter().map(|ext| path.with_extension(ext)).find(|p| p.exists());
+        if let Some(image) = image {
+            let _ = event_tx.send(WorkerEvent::ThumbnailSaved { id: request.job_id, path: image }).await;
+        }
+    }
+    Ok(path)
+}
+
+// Keeps the tail of stderr so failures can report yt-dlp's own error message.
+// With a job to report to, warnings are passed on as they come
+async fn collect_stderr(stderr: tokio::process::ChildStderr, warnings: Option<(JobId, mpsc::Sender<WorkerEvent>)>) -> Vec<String> {
+    const MAX_LINES: usize = 20;
+
+    let mut lines = LossyLines::new(BufReader::new(stderr));
+    let mut tail = std::collections::VecDeque::with_capacity(MAX_LINES);
+    while let Ok(Some(line)) = lines.next_line().await {
+        if let (Some((id, event_tx)), Some(warning)) = (&warnings, errors::warning_text(&line)) {
+            let _ = event_tx.send(WorkerEvent::JobWarning { id: *id, warning: warning.to_string() }).await;
+        }
+        if tail.len() == MAX_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+    tail.into()
 }
 
 #[derive(Debug)]
@@ -189,19 +745,26 @@ struct Progress {
     percent: f32,
     speed: String,
     eta: String,
+    total: Option<u64>,
 }
 
 fn parse_progress(line: &str) -> Option<Progress> {
-    if !line.contains("[download]") || !line.contains('%') {
+    if let Some(progress) = parse_aria2_progress(line) {
+        return Some(progress);
+    }
+    if !line.contains("[download]") {
         return None;
     }
+    if !line.contains('%') {
+        return parse_live_progress(line);
+    }
 
+    // Fragmented downloads, such as sections, may only know how many fragments are done
     let percent = line
         .split_whitespace()
-        .find(|s| s.ends_with('%'))?
-        .trim_end_matches('%')
-        .parse::<f32>()
-        .ok()?;
+        .find(|s| s.ends_with('%'))
+        .and_then(|s| s.trim_end_matches('%').parse::<f32>().ok())
+        .or_else(|| fragment_percent(line))?;
 
     let speed = line
         .split_whitespace()
@@ -219,5 +782,162 @@ fn parse_progress(line: &str) -> Option<Progress> {
         "--".to_string()
     };
 
-    Some(Progress { percent, speed, eta })
+    // "of ~ 120.50MiB" while the size is still an estimate
+    let total = line
+        .split_whitespace()
+        .skip_while(|s| *s != "of")
+        .find(|s| *s != "of" && *s != "~")
+        .and_then(parse_size);
+
+    Some(Progress { percent, speed, eta, total })
+}
+
+// aria2c's summary line, passed through by yt-dlp:
+// "[#2089b0 400.0KiB/33.2MiB(1%) CN:1 DL:115.7KiB ETA:4m51s]"
+fn parse_aria2_progress(line: &str) -> Option<Progress> {
+    let (_, rest) = line.split_once("[#")?;
+    let body = rest.split_once(']')?.0;
+    let sizes = body.split_whitespace().find(|s| s.ends_with("%)"))?;
+    let sizes = sizes.strip_prefix("SIZE:").unwrap_or(sizes);
+    let (sizes, percent) = sizes.split_once('(')?;
+    let percent = percent.trim_end_matches("%)").parse::<f32>().ok()?;
+    let total = sizes.split_once('/').and_then(|(_, total)| parse_size(total));
+
+    let field = |name: &str| body.split_whitespace().find_map(|s| s.strip_prefix(name)).map(str::to_string);
+    let speed = field("DL:").or_else(|| field("SPD:")).map_or_else(|| "--".to_string(), |s| format!("{}/s", s));
+    let eta = field("ETA:").unwrap_or_else(|| "--".to_string());
+    Some(Progress { percent, speed, eta, total })
+}
+
+// Live streams have no total, so no percent: "[download]   25.10MiB at  1.20MiB/s (00:01:23)"
+fn parse_live_progress(line: &str) -> Option<Progress> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let at = words.windows(3).position(|w| w[1] == "at" && parse_size(w[0]).is_some() && w[2].ends_with("/s"))?;
+    Some(Progress { percent: 0.0, speed: words[at + 2].to_string(), eta: "--".into(), total: None })
+}
+
+// "(frag 12/80)"
+fn fragment_percent(line: &str) -> Option<f32> {
+    let (_, rest) = line.split_once("(frag ")?;
+    let (done, total) = rest.split_once(')')?.0.split_once('/')?;
+    let (done, total) = (done.trim().parse::<f32>().ok()?, total.trim().parse::<f32>().ok()?);
+    (total > 0.0).then(|| (done / total * 100.0).min(100.0))
+}
+
+fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim_start_matches('~');
+    let split = text.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let multiplier = match unit {
+        "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        _ => return None,
+    };
+    Some((number * multiplier) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Job;
+
+    fn config(output_dir: &str) -> Config {
+        Config { output_dir: output_dir.into(), output_template: "%(title)s.%(ext)s".into(), ..Config::default() }
+    }
+
+    fn request(url: &str) -> DownloadRequest {
+        Job::new(url).request_with_format("137+140".into())
+    }
+
+    #[test]
+    fn quotes_only_what_the_shell_would_split() {
+        for (arg, quoted) in [
+            ("137+140", "137+140"),
+            ("/home/me/Videos", "/home/me/Videos"),
+            ("", "''"),
+            ("My Videos", "'My Videos'"),
+            ("it's", r"'it'\''s'"),
+            ("$HOME", "'$HOME'"),
+            ("a;rm -rf x", "'a;rm -rf x'"),
+            ("Vidéos", "'Vidéos'"),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=5", "'https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=5'"),
+        ] {
+            assert_eq!(shell_quote(arg), quoted, "{:?}", arg);
+        }
+    }
+
+    #[test]
+    fn the_copied_command_escapes_awkward_paths() {
+        let config = config("/home/me/it's my \"stuff\"");
+        let command = command_line(&config, &standalone_args(&request("https://example.com/v/1"), &config));
+        assert_eq!(
+            command,
+            r#"yt-dlp -f 137+140 -o '/home/me/it'\''s my "stuff"/%(title)s.%(ext)s' https://example.com/v/1"#
+        );
+    }
+
+    #[test]
+    fn the_copied_command_leaves_out_progress_flags() {
+        let config = config("/downloads");
+        let request = request("https://example.com/v/1");
+        let standalone = standalone_args(&request, &config);
+        let full = download_args(&request, &config);
+        for flag in ["--newline", "--progress", "--no-colors", "--print"] {
+            assert!(!standalone.iter().any(|a| a == flag), "{} in {:?}", flag, standalone);
+            assert!(full.iter().any(|a| a == flag), "{} missing from {:?}", flag, full);
+        }
+        assert_eq!(standalone.last(), full.last());
+    }
+
+    #[test]
+    fn embedding_metadata_adds_both_flags() {
+        let request = request("https://example.com/v/1");
+        let mut config = config("/downloads");
+        assert!(!download_args(&request, &config).iter().any(|a| a.starts_with("--embed-")));
+        config.embed_metadata = true;
+        let args = download_args(&request, &config);
+        for flag in ["--embed-metadata", "--embed-chapters"] {
+            assert!(args.iter().any(|a| a == flag), "{} missing from {:?}", flag, args);
+        }
+    }
+
+    #[test]
+    fn metadata_lines_are_not_progress() {
+        assert!(parse_progress("[Metadata] Adding metadata to \"/v/50% off - 1.2MiB/s.mp4\"").is_none());
+        assert!(parse_progress("[Metadata] There isn't any metadata to add").is_none());
+    }
+
+    #[test]
+    fn the_shown_command_masks_secrets() {
+        let mut config = config("/downloads");
+        config.ytdlp_env.insert("API_TOKEN".into(), "hunter2".into());
+        config.ytdlp_cwd = Some("/work dir".into());
+        let args: Vec<String> = ["--username", "me", "--password", "hunter2", "--video-password=hunter2", "--add-header", "Authorization:Bearer hunter2", "--add-header", "Referer:x"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        let command = command_line(&config, &args);
+        assert!(!command.contains("hunter2"), "{}", command);
+        assert_eq!(
+            command,
+            "cd '/work dir' && API_TOKEN='********' yt-dlp --username me --password '********' '--video-password=********' --add-header 'Authorization:********' --add-header Referer:x"
+        );
+
+        // Copied, the secrets are left out so it runs as pasted, and named
+        let (command, left_out) = runnable_command_line(&config, &args);
+        assert_eq!(command, "cd '/work dir' && yt-dlp --username me --add-header Referer:x");
+        assert_eq!(left_out, ["--password", "--video-password", "the Authorization header", "API_TOKEN"]);
+
+        config.ytdlp_env.clear();
+        config.ytdlp_cwd = None;
+        let (command, left_out) = runnable_command_line(&config, &["--netrc".to_string(), "-f".into(), "22".into()]);
+        assert_eq!(command, "yt-dlp --netrc -f 22");
+        assert!(left_out.is_empty());
+    }
 }
@@ -0,0 +1,143 @@
+// Jobs for the same video sharing one yt-dlp run, against the scripted runner
+mod support;
+
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use oxidlp::core::{Config, DownloadError, Job, WorkerCommand, WorkerEvent};
+use support::{FakeRunner, Pool, Step};
+
+const WATCH: &str = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+const SHORT: &str = "https://youtu.be/dQw4w9WgXcQ";
+
+fn start(job: &Job) -> WorkerCommand {
+    WorkerCommand::StartJob(job.request_with_format("best".into()))
+}
+
+fn fetch(job: &Job) -> WorkerCommand {
+    WorkerCommand::FetchFormats { job_id: job.id, url: job.url.clone(), credentials: None }
+}
+
+#[tokio::test]
+async fn fetches_for_one_video_share_a_run() {
+    let mut pool = Pool::start(FakeRunner::default().fetch(WATCH, vec![Step::Gate]), Config::default());
+    let (leader, follower) = (Job::new(WATCH), Job::new(SHORT));
+
+    pool.send(fetch(&leader)).await;
+    pool.stats.wait_running(1).await;
+    pool.send(fetch(&follower)).await;
+    let event = pool.expect("the follower joining", |e| matches!(e, WorkerEvent::Coalesced { id, .. } if *id == follower.id)).await;
+    assert!(matches!(event, WorkerEvent::Coalesced { with, .. } if with == leader.id));
+
+    pool.stats.release(1);
+    for job in [&leader, &follower] {
+        pool.expect("formats for both", |e| matches!(e, WorkerEvent::FormatsReady { id, .. } if *id == job.id)).await;
+    }
+    assert_eq!(pool.stats.fetches.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn downloads_of_the_same_file_share_a_run() {
+    let mut pool = Pool::start(FakeRunner::default().download(WATCH, vec![Step::Progress(40.0), Step::Gate]), Config::default());
+    let (leader, follower) = (Job::new(WATCH), Job::new(WATCH));
+
+    pool.send(start(&leader)).await;
+    pool.stats.wait_running(1).await;
+    pool.send(start(&follower)).await;
+    pool.expect("the follower joining", |e| matches!(e, WorkerEvent::Coalesced { id, with } if *id == follower.id && *with == leader.id)).await;
+
+    pool.stats.release(1);
+    for job in [&leader, &follower] {
+        pool.expect("both to complete", |e| matches!(e, WorkerEvent::JobCompleted { id, .. } if *id == job.id)).await;
+    }
+    assert_eq!(pool.stats.downloads.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn cancelling_the_follower_leaves_the_run_going() {
+    let mut pool = Pool::start(FakeRunner::default().download(WATCH, vec![Step::Gate]), Config::default());
+    let (leader, follower) = (Job::new(WATCH), Job::new(WATCH));
+
+    pool.send(start(&leader)).await;
+    pool.stats.wait_running(1).await;
+    pool.send(start(&follower)).await;
+    pool.expect("the follower joining", |e| matches!(e, WorkerEvent::Coalesced { id, .. } if *id == follower.id)).await;
+    pool.send(WorkerCommand::CancelJob(follower.id)).await;
+
+    // The command is handled before the release reaches the run
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(pool.stats.running.load(Ordering::SeqCst), 1);
+    pool.stats.release(1);
+    pool.expect("the leader to complete", |e| matches!(e, WorkerEvent::JobCompleted { id, .. } if *id == leader.id)).await;
+    assert!(pool.events_for(follower.id, Duration::from_millis(100)).await.is_empty());
+}
+
+#[tokio::test]
+async fn cancelling_the_leader_then_the_last_follower_stops_the_download() {
+    let mut pool = Pool::start(FakeRunner::default().download(WATCH, vec![Step::Gate]), Config::default());
+    let (leader, follower) = (Job::new(WATCH), Job::new(WATCH));
+
+    pool.send(start(&leader)).await;
+    pool.stats.wait_running(1).await;
+    pool.send(start(&follower)).await;
+    pool.expect("the follower joining", |e| matches!(e, WorkerEvent::Coalesced { id, .. } if *id == follower.id)).await;
+
+    pool.send(WorkerCommand::CancelJob(leader.id)).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(pool.stats.running.load(Ordering::SeqCst), 1, "the follower still wants the file");
+
+    pool.send(WorkerCommand::CancelJob(follower.id)).await;
+    pool.stats.wait_running(0).await;
+    pool.stats.release(1);
+    let late = pool.events_for(follower.id, Duration::from_millis(100)).await;
+    assert!(!late.iter().any(|e| matches!(e, WorkerEvent::JobCompleted { .. })), "{:?}", late);
+}
+
+#[tokio::test]
+async fn cancelling_the_leader_then_the_last_follower_stops_the_fetch() {
+    let mut pool = Pool::start(FakeRunner::default().fetch(WATCH, vec![Step::Gate]), Config::default());
+    let (leader, follower) = (Job::new(WATCH), Job::new(SHORT));
+
+    pool.send(fetch(&leader)).await;
+    pool.stats.wait_running(1).await;
+    pool.send(fetch(&follower)).await;
+    pool.expect("the follower joining", |e| matches!(e, WorkerEvent::Coalesced { id, .. } if *id == follower.id)).await;
+
+    pool.send(WorkerCommand::CancelJob(leader.id)).await;
+    pool.send(WorkerCommand::CancelJob(follower.id)).await;
+    pool.stats.wait_running(0).await;
+    pool.stats.release(1);
+    let late = pool.events_for(follower.id, Duration::from_millis(100)).await;
+    assert!(!late.iter().any(|e| matches!(e, WorkerEvent::FormatsReady { .. })), "{:?}", late);
+}
+
+#[tokio::test]
+async fn a_waiting_run_is_dropped_once_everyone_left() {
+    let config = Config { max_concurrent_downloads: 1, ..Config::default() };
+    let busy = "https://example.com/v/busy";
+    let runner = FakeRunner::default().download(busy, vec![Step::Gate]);
+    let mut pool = Pool::start(runner, config);
+    let blocker = Job::new(busy);
+    let (leader, follower) = (Job::new(WATCH), Job::new(WATCH));
+
+    pool.send(start(&blocker)).await;
+    pool.stats.wait_running(1).await;
+    pool.send(start(&leader)).await;
+    pool.send(start(&follower)).await;
+    pool.expect("the follower joining", |e| matches!(e, WorkerEvent::Coalesced { id, .. } if *id == follower.id)).await;
+
+    pool.send(WorkerCommand::CancelJob(leader.id)).await;
+    pool.send(WorkerCommand::CancelJob(follower.id)).await;
+    pool.expect("an empty slot queue", |e| matches!(e, WorkerEvent::SlotQueueChanged { waiting } if waiting.is_empty())).await;
+
+    pool.stats.release(1);
+    pool.expect("the blocker to complete", |e| matches!(e, WorkerEvent::JobCompleted { id, .. } if *id == blocker.id)).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(pool.stats.downloads.load(Ordering::SeqCst), 1, "the cancelled run never started");
+
+    // A new job for the video gets a run of its own rather than joining the dropped one
+    let again = Job::new(WATCH);
+    pool.send(start(&again)).await;
+    let event = pool.expect("the new job to finish", |e| matches!(e, WorkerEvent::JobCompleted { id, .. } | WorkerEvent::JobFailed { id, .. } if *id == again.id)).await;
+    assert!(!matches!(event, WorkerEvent::JobFailed { ref error, .. } if error.kind == DownloadError::Cancelled), "{:?}", event);
+}
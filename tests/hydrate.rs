@@ -0,0 +1,60 @@
+// Playlist entries fetching their formats in the background, a few at a time
+mod support;
+
+use std::sync::atomic::Ordering;
+
+use oxidlp::core::{Config, JobId, WorkerCommand, WorkerEvent};
+use support::{FakeRunner, Pool, Step};
+
+fn entries(count: usize) -> Vec<(JobId, String)> {
+    (0..count).map(|i| (JobId::new_v4(), format!("https://example.com/v/entry{}", i))).collect()
+}
+
+#[tokio::test]
+async fn promoting_an_entry_the_worker_never_got_fetches_it() {
+    let mut pool = Pool::start(FakeRunner::default(), Config::default());
+    let (job_id, url) = entries(1).remove(0);
+
+    pool.send(WorkerCommand::PromoteFetch { job_id, url }).await;
+
+    pool.expect("the entry's formats", |e| matches!(e, WorkerEvent::FormatsReady { id, .. } if *id == job_id)).await;
+    assert_eq!(pool.stats.fetches.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn a_promoted_entry_takes_the_next_free_slot() {
+    let entries = entries(6);
+    // The last entry is unscripted, so it reports back the moment it runs
+    let runner = entries[..5].iter().fold(FakeRunner::default(), |runner, (_, url)| runner.fetch(url, vec![Step::Gate]));
+    let mut pool = Pool::start(runner, Config::default());
+
+    pool.send(WorkerCommand::HydrateFormats(entries.clone())).await;
+    pool.stats.wait_running(3).await;
+    let (promoted, url) = entries[5].clone();
+    pool.send(WorkerCommand::PromoteFetch { job_id: promoted, url }).await;
+
+    pool.stats.release(1);
+    let first = pool.expect("a gated entry to finish", |e| matches!(e, WorkerEvent::FormatsReady { .. })).await;
+    assert!(entries[..3].iter().any(|(id, _)| first.job_id() == Some(*id)), "{:?}", first);
+    let next = pool.expect("the next formats", |e| matches!(e, WorkerEvent::FormatsReady { .. })).await;
+    assert_eq!(next.job_id(), Some(promoted));
+
+    pool.stats.wait_running(3).await;
+    assert_eq!(pool.stats.peak.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn promoting_a_running_entry_does_not_fetch_it_twice() {
+    let entries = entries(1);
+    let runner = FakeRunner::default().fetch(&entries[0].1, vec![Step::Gate]);
+    let mut pool = Pool::start(runner, Config::default());
+    let (job_id, url) = entries[0].clone();
+
+    pool.send(WorkerCommand::HydrateFormats(entries)).await;
+    pool.stats.wait_running(1).await;
+    pool.send(WorkerCommand::PromoteFetch { job_id, url }).await;
+
+    pool.stats.release(1);
+    pool.expect("the entry's formats", |e| matches!(e, WorkerEvent::FormatsReady { id, .. } if *id == job_id)).await;
+    assert_eq!(pool.stats.fetches.load(Ordering::SeqCst), 1);
+}
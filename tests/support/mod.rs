@@ -0,0 +1,242 @@
+// A scripted stand-in for yt-dlp, so the worker pool can be driven without a
+// network or the binary. Each URL gets its own steps; anything unscripted
+// succeeds straight away
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use color_eyre::Result;
+use oxidlp::core::{Config, Credentials, DownloadPhase, DownloadRequest, JobId, Metadata, WorkerCommand, WorkerEvent, WorkerPool};
+use oxidlp::worker::{PlaylistEntries, YtdlpRunner};
+use tokio::sync::{mpsc, watch, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+const EVENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub enum Step {
+    Progress(f32),
+    // Waits for one permit from `Stats::release`
+    Gate,
+    // Ends the run with yt-dlp's error
+    Fail(String),
+}
+
+pub struct Stats {
+    pub fetches: AtomicUsize,
+    pub downloads: AtomicUsize,
+    pub running: AtomicUsize,
+    pub peak: AtomicUsize,
+    // The output_dir each download was started with
+    pub output_dirs: Mutex<Vec<PathBuf>>,
+    gate: Semaphore,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            fetches: AtomicUsize::new(0),
+            downloads: AtomicUsize::new(0),
+            running: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            output_dirs: Mutex::new(Vec::new()),
+            gate: Semaphore::new(0),
+        }
+    }
+}
+
+impl Stats {
+    pub fn release(&self, runs: usize) {
+        self.gate.add_permits(runs);
+    }
+
+    pub async fn wait_running(&self, count: usize) {
+        tokio::time::timeout(EVENT_TIMEOUT, async {
+            while self.running.load(Ordering::SeqCst) != count {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("never had {} runs going, {} are", count, self.running.load(Ordering::SeqCst)));
+    }
+}
+
+#[derive(Default)]
+pub struct FakeRunner {
+    fetches: HashMap<String, Vec<Step>>,
+    playlists: HashMap<String, Vec<Step>>,
+    downloads: HashMap<String, Vec<Step>>,
+    pub stats: Arc<Stats>,
+}
+
+impl FakeRunner {
+    pub fn fetch(mut self, url: &str, steps: Vec<Step>) -> Self {
+        self.fetches.insert(url.into(), steps);
+        self
+    }
+
+    pub fn playlist(mut self, url: &str, steps: Vec<Step>) -> Self {
+        self.playlists.insert(url.into(), steps);
+        self
+    }
+
+    pub fn download(mut self, url: &str, steps: Vec<Step>) -> Self {
+        self.downloads.insert(url.into(), steps);
+        self
+    }
+
+    async fn play(&self, id: JobId, steps: &[Step], event_tx: &mpsc::Sender<WorkerEvent>, cancel: &CancellationToken) -> Result<()> {
+        let running = self.stats.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.stats.peak.fetch_max(running, Ordering::SeqCst);
+        let result = tokio::select! {
+            _ = cancel.cancelled() => Err(color_eyre::eyre::eyre!("Download cancelled")),
+            result = self.steps(id, steps, event_tx) => result,
+        };
+        self.stats.running.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+
+    async fn steps(&self, id: JobId, steps: &[Step], event_tx: &mpsc::Sender<WorkerEvent>) -> Result<()> {
+        for step in steps {
+            match step {
+                Step::Progress(percent) => {
+                    let event = WorkerEvent::JobProgress {
+                        id,
+                        percent: *percent,
+                        speed: "1.00MiB/s".into(),
+                        speed_bps: Some(1 << 20),
+                        eta: "00:01".into(),
+                        phase: DownloadPhase::Single,
+                        total: Some(10 << 20),
+                    };
+                    let _ = event_tx.send(event).await;
+                }
+                Step::Gate => self.stats.gate.acquire().await?.forget(),
+                Step::Fail(message) => color_eyre::eyre::bail!("{}", message),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl YtdlpRunner for FakeRunner {
+    async fn fetch_formats(
+        &self,
+        job_id: JobId,
+        url: &str,
+        _credentials: Option<&Credentials>,
+        _config: &Config,
+        event_tx: mpsc::Sender<WorkerEvent>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        self.stats.fetches.fetch_add(1, Ordering::SeqCst);
+        let steps = self.fetches.get(url).cloned().unwrap_or_default();
+        self.play(job_id, &steps, &event_tx, &cancel).await?;
+        let event = WorkerEvent::FormatsReady { id: job_id, title: format!("Title of {}", url), formats: Vec::new(), metadata: Metadata::default() };
+        let _ = event_tx.send(event).await;
+        Ok(())
+    }
+
+    async fn fetch_playlist(
+        &self,
+        job_id: JobId,
+        url: &str,
+        _items: Option<&str>,
+        _config: &Config,
+        event_tx: mpsc::Sender<WorkerEvent>,
+        cancel: CancellationToken,
+    ) -> Result<(Option<String>, PlaylistEntries)> {
+        let steps = self.playlists.get(url).cloned().unwrap_or_default();
+        self.play(job_id, &steps, &event_tx, &cancel).await?;
+        Ok((None, Vec::new()))
+    }
+
+    async fn resolve_conflict(&self, _request: &mut DownloadRequest, _config: &Config, _cancel: &CancellationToken) -> Option<PathBuf> {
+        None
+    }
+
+    async fn download(
+        &self,
+        request: &DownloadRequest,
+        config: &Arc<Config>,
+        event_tx: mpsc::Sender<WorkerEvent>,
+        cancel: CancellationToken,
+    ) -> Result<PathBuf> {
+        self.stats.downloads.fetch_add(1, Ordering::SeqCst);
+        self.stats.output_dirs.lock().unwrap().push(config.output_dir.clone());
+        let steps = self.downloads.get(&request.url).cloned().unwrap_or_default();
+        self.play(request.job_id, &steps, &event_tx, &cancel).await?;
+        Ok(PathBuf::from(format!("/downloads/{}.mp4", request.url.rsplit('/').next().unwrap_or("video"))))
+    }
+}
+
+pub struct Pool {
+    pub commands: mpsc::Sender<WorkerCommand>,
+    pub events: mpsc::Receiver<WorkerEvent>,
+    pub stats: Arc<Stats>,
+}
+
+impl Pool {
+    pub fn start(runner: FakeRunner, config: Config) -> Self {
+        Self::spawn(runner, Arc::new(config), None)
+    }
+
+    // Follows the config on `current`, as the app's config task publishes it
+    pub fn watching(runner: FakeRunner, current: watch::Receiver<Arc<Config>>) -> Self {
+        let config = current.borrow().clone();
+        Self::spawn(runner, config, Some(current))
+    }
+
+    fn spawn(runner: FakeRunner, config: Arc<Config>, current: Option<watch::Receiver<Arc<Config>>>) -> Self {
+        let stats = runner.stats.clone();
+        let (commands, command_rx) = mpsc::channel(32);
+        // Roomy enough that an unread event never holds the pool up
+        let (event_tx, events) = mpsc::channel(1024);
+        let mut pool = WorkerPool::with_runner(runner, config, command_rx, event_tx);
+        if let Some(current) = current {
+            pool = pool.watch_config(current);
+        }
+        tokio::spawn(pool.run());
+        Self { commands, events, stats }
+    }
+
+    pub async fn send(&self, command: WorkerCommand) {
+        self.commands.send(command).await.expect("worker pool stopped");
+    }
+
+    // Skips everything else until an event matches
+    pub async fn expect(&mut self, what: &str, matches: impl Fn(&WorkerEvent) -> bool) -> WorkerEvent {
+        let found = tokio::time::timeout(EVENT_TIMEOUT, async {
+            while let Some(event) = self.events.recv().await {
+                if matches(&event) {
+                    return Some(event);
+                }
+            }
+            None
+        })
+        .await;
+        match found {
+            Ok(Some(event)) => event,
+            Ok(None) => panic!("worker pool stopped before {}", what),
+            Err(_) => panic!("no {} within {:?}", what, EVENT_TIMEOUT),
+        }
+    }
+
+    // Everything that arrives for `id` within `wait`
+    pub async fn events_for(&mut self, id: JobId, wait: Duration) -> Vec<WorkerEvent> {
+        let mut found = Vec::new();
+        let _ = tokio::time::timeout(wait, async {
+            while let Some(event) = self.events.recv().await {
+                if event.job_id() == Some(id) {
+                    found.push(event);
+                }
+            }
+        })
+        .await;
+        found
+    }
+}
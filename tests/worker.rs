@@ -0,0 +1,210 @@
+// The worker pool end to end, against the scripted runner in support/
+mod support;
+
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use oxidlp::core::{Config, DownloadError, Job, WorkerCommand, WorkerEvent};
+use support::{FakeRunner, Pool, Step};
+use tokio::sync::watch;
+
+fn config(concurrent: usize) -> Config {
+    Config { max_concurrent_downloads: concurrent, ..Config::default() }
+}
+
+fn job(url: &str) -> Job {
+    Job::new(url)
+}
+
+#[tokio::test]
+async fn download_reports_every_stage_in_order() {
+    let url = "https://example.com/v/lifecycle";
+    let runner = FakeRunner::default().download(url, vec![Step::Progress(10.0), Step::Progress(55.5), Step::Progress(100.0)]);
+    let mut pool = Pool::start(runner, config(2));
+    let job = job(url);
+
+    pool.send(WorkerCommand::StartJob(job.request_with_format("best".into()))).await;
+
+    let mut seen = Vec::new();
+    loop {
+        let event = pool.expect("an event for the job", |e| e.job_id() == Some(job.id)).await;
+        let done = matches!(event, WorkerEvent::JobCompleted { .. } | WorkerEvent::JobFailed { .. });
+        seen.push(event);
+        if done {
+            break;
+        }
+    }
+
+    assert!(matches!(seen[0], WorkerEvent::StartAccepted { .. }), "{:?}", seen);
+    assert!(matches!(seen[1], WorkerEvent::JobStarted { .. }), "{:?}", seen);
+    let percents: Vec<f32> = seen
+        .iter()
+        .filter_map(|e| match e {
+            WorkerEvent::JobProgress { percent, .. } => Some(*percent),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(percents, vec![10.0, 55.5, 100.0]);
+    match seen.last() {
+        Some(WorkerEvent::JobCompleted { path, .. }) => assert_eq!(*path, PathBuf::from("/downloads/lifecycle.mp4")),
+        other => panic!("expected JobCompleted, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn cancel_mid_progress_stops_the_run() {
+    let url = "https://example.com/v/cancelled";
+    let runner = FakeRunner::default().download(url, vec![Step::Progress(20.0), Step::Gate, Step::Progress(100.0)]);
+    let mut pool = Pool::start(runner, config(2));
+    let job = job(url);
+
+    pool.send(WorkerCommand::StartJob(job.request_with_format("best".into()))).await;
+    pool.expect("the first progress", |e| matches!(e, WorkerEvent::JobProgress { id, .. } if *id == job.id)).await;
+    pool.send(WorkerCommand::CancelJob(job.id)).await;
+
+    let event = pool.expect("the end of the job", |e| {
+        matches!(e, WorkerEvent::JobCompleted { id, .. } | WorkerEvent::JobFailed { id, .. } | WorkerEvent::JobProgress { id, .. } if *id == job.id)
+    }).await;
+    match event {
+        WorkerEvent::JobFailed { error, .. } => assert_eq!(error.kind, DownloadError::Cancelled),
+        other => panic!("expected a cancelled JobFailed, got {:?}", other),
+    }
+    pool.stats.wait_running(0).await;
+    // The gate would have let the rest of the script through; nothing may come after
+    pool.stats.release(1);
+    assert!(pool.events_for(job.id, Duration::from_millis(100)).await.is_empty());
+}
+
+#[tokio::test]
+async fn failure_is_classified_from_ytdlps_error() {
+    let url = "https://example.com/v/gone";
+    let error = "ERROR: [generic] gone: Unable to download webpage: HTTP Error 404: Not Found";
+    let runner = FakeRunner::default().download(url, vec![Step::Progress(5.0), Step::Fail(error.into())]);
+    let mut pool = Pool::start(runner, config(2));
+    let job = job(url);
+
+    pool.send(WorkerCommand::StartJob(job.request_with_format("best".into()))).await;
+
+    let event = pool.expect("the failure", |e| matches!(e, WorkerEvent::JobFailed { id, .. } | WorkerEvent::JobCompleted { id, .. } if *id == job.id)).await;
+    match event {
+        WorkerEvent::JobFailed { error: failure, .. } => {
+            assert_eq!(failure.kind, DownloadError::NotFound);
+            assert!(failure.message.contains("HTTP Error 404"), "{}", failure.message);
+        }
+        other => panic!("expected JobFailed, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn never_runs_more_than_the_concurrency_limit() {
+    let urls: Vec<String> = (0..5).map(|i| format!("https://example.com/v/slot{}", i)).collect();
+    let runner = urls.iter().fold(FakeRunner::default(), |runner, url| runner.download(url, vec![Step::Gate]));
+    let mut pool = Pool::start(runner, config(2));
+    let jobs: Vec<Job> = urls.iter().map(|url| job(url)).collect();
+
+    for job in &jobs {
+        pool.send(WorkerCommand::StartJob(job.request_with_format("best".into()))).await;
+    }
+    pool.stats.wait_running(2).await;
+    let waiting = pool.expect("the slot queue", |e| matches!(e, WorkerEvent::SlotQueueChanged { waiting } if waiting.len() == 3)).await;
+    if let WorkerEvent::SlotQueueChanged { waiting } = waiting {
+        assert_eq!(waiting, jobs[2..].iter().map(|j| j.id).collect::<Vec<_>>());
+    }
+    // Give a third download every chance to sneak in
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(pool.stats.running.load(Ordering::SeqCst), 2);
+
+    pool.stats.release(jobs.len());
+    for job in &jobs {
+        pool.expect("every job to complete", |e| matches!(e, WorkerEvent::JobCompleted { id, .. } if *id == job.id)).await;
+    }
+    assert_eq!(pool.stats.peak.load(Ordering::SeqCst), 2);
+    assert_eq!(pool.stats.downloads.load(Ordering::SeqCst), jobs.len());
+}
+
+#[tokio::test]
+async fn jobs_started_after_a_settings_change_use_the_new_output_dir() {
+    let (current, current_rx) = watch::channel(Arc::new(Config { output_dir: "/old".into(), ..Config::default() }));
+    let mut pool = Pool::watching(FakeRunner::default(), current_rx);
+    let (before, after) = (job("https://example.com/v/before"), job("https://example.com/v/after"));
+
+    pool.send(WorkerCommand::StartJob(before.request_with_format("best".into()))).await;
+    pool.expect("the first job", |e| matches!(e, WorkerEvent::JobCompleted { id, .. } if *id == before.id)).await;
+
+    current.send_replace(Arc::new(Config { output_dir: "/new".into(), ..Config::default() }));
+    pool.send(WorkerCommand::StartJob(after.request_with_format("best".into()))).await;
+    pool.expect("the second job", |e| matches!(e, WorkerEvent::JobCompleted { id, .. } if *id == after.id)).await;
+
+    let dirs = pool.stats.output_dirs.lock().unwrap().clone();
+    assert_eq!(dirs, vec![PathBuf::from("/old"), PathBuf::from("/new")]);
+}
+
+#[tokio::test]
+async fn a_repeated_start_for_a_running_job_is_ignored() {
+    let url = "https://example.com/v/twice";
+    let mut pool = Pool::start(FakeRunner::default().download(url, vec![Step::Gate]), config(2));
+    let job = job(url);
+
+    pool.send(WorkerCommand::StartJob(job.request_with_format("best".into()))).await;
+    pool.stats.wait_running(1).await;
+    pool.send(WorkerCommand::StartJob(job.request_with_format("best".into()))).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(pool.stats.running.load(Ordering::SeqCst), 1);
+
+    pool.stats.release(2);
+    pool.expect("the job to complete", |e| matches!(e, WorkerEvent::JobCompleted { id, .. } if *id == job.id)).await;
+    let late = pool.events_for(job.id, Duration::from_millis(100)).await;
+    assert!(!late.iter().any(|e| matches!(e, WorkerEvent::JobCompleted { .. })), "{:?}", late);
+    assert_eq!(pool.stats.downloads.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn a_failed_playlist_expansion_is_reported() {
+    let url = "https://www.youtube.com/playlist?list=PLtypo";
+    let error = "ERROR: [youtube:tab] PLtypo: The playlist does not exist.";
+    let mut pool = Pool::start(FakeRunner::default().playlist(url, vec![Step::Fail(error.into())]), config(2));
+    let placeholder = Job::playlist_placeholder(url, None);
+
+    pool.send(placeholder.fetch_playlist_command()).await;
+    let event = pool.expect("the expansion to end", |e| e.job_id() == Some(placeholder.id)).await;
+    match event {
+        WorkerEvent::PlaylistFailed { url: failed, error: message, .. } => {
+            assert_eq!(failed, url);
+            assert!(message.contains("does not exist"), "{}", message);
+        }
+        other => panic!("expected PlaylistFailed, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn a_cancelled_playlist_expansion_reports_nothing() {
+    let url = "https://www.youtube.com/playlist?list=PLslow";
+    let mut pool = Pool::start(FakeRunner::default().playlist(url, vec![Step::Gate]), config(2));
+    let placeholder = Job::playlist_placeholder(url, None);
+
+    pool.send(placeholder.fetch_playlist_command()).await;
+    pool.stats.wait_running(1).await;
+    pool.send(WorkerCommand::CancelJob(placeholder.id)).await;
+    pool.stats.wait_running(0).await;
+    let late = pool.events_for(placeholder.id, Duration::from_millis(100)).await;
+    assert!(!late.iter().any(|e| matches!(e, WorkerEvent::PlaylistFailed { .. } | WorkerEvent::PlaylistExpanded { .. })), "{:?}", late);
+}
+
+#[tokio::test]
+async fn cancelling_a_format_fetch_stops_it() {
+    let url = "https://example.com/v/slow-fetch";
+    let mut pool = Pool::start(FakeRunner::default().fetch(url, vec![Step::Gate]), config(2));
+    let job = job(url);
+
+    pool.send(WorkerCommand::FetchFormats { job_id: job.id, url: url.into(), credentials: None }).await;
+    pool.stats.wait_running(1).await;
+    pool.send(WorkerCommand::CancelJob(job.id)).await;
+    pool.stats.wait_running(0).await;
+
+    // Releasing the gate would let a still-running fetch report its formats
+    pool.stats.release(1);
+    let late = pool.events_for(job.id, Duration::from_millis(100)).await;
+    assert!(!late.iter().any(|e| matches!(e, WorkerEvent::FormatsReady { .. })), "{:?}", late);
+}